@@ -0,0 +1,247 @@
+use crate::board::CheckerBoard;
+use crate::board_position::BoardPosition;
+use crate::board_ui_factory::BoardUiFactory;
+use crate::engine::{Engine, PrincipalVariation, Score};
+use crate::notation::San;
+use crate::screen_layout::PanelPlacement;
+use bevy::prelude::{
+    default, BackgroundColor, BuildChildren, ButtonInput, Color, Commands, Component, Gizmos,
+    KeyCode, NodeBundle, PositionType, Query, Res, ResMut, Resource, Style, Text, TextBundle,
+    TextStyle, Val, Visibility, With, Without,
+};
+use bevy::tasks::{block_on, poll_once, AsyncComputeTaskPool, Task};
+
+#[derive(Resource)]
+pub struct Analysis {
+    enabled: bool,
+    max_depth: u8,
+    lines: usize,
+    depth: u8,
+    variations: Vec<PrincipalVariation>,
+    descriptions: Vec<String>,
+    task: Option<Task<Vec<PrincipalVariation>>>,
+}
+
+impl Analysis {
+    pub fn new(max_depth: u8, lines: usize) -> Self {
+        Self {
+            enabled: false,
+            max_depth,
+            lines,
+            depth: 0,
+            variations: vec![],
+            descriptions: vec![],
+            task: None,
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn toggle(&mut self) {
+        self.enabled = !self.enabled;
+        self.restart();
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        if self.enabled != enabled {
+            self.toggle();
+        }
+    }
+
+    pub fn restart(&mut self) {
+        self.depth = 0;
+        self.variations.clear();
+        self.descriptions.clear();
+        self.task = None;
+    }
+
+    /// In analysis mode either side may move, so hand the turn over when
+    /// the piece being moved is not the side to move.
+    pub fn prepare_move(&self, board: &mut CheckerBoard, from: &BoardPosition, to: &BoardPosition) {
+        if !self.enabled {
+            return;
+        }
+        if let Some(piece) = board.piece_at(from) {
+            if piece.color() != board.active_turn() && board.get_possible_moves(from).contains(to) {
+                board.pass_turn();
+            }
+        }
+    }
+}
+
+impl Default for Analysis {
+    fn default() -> Self {
+        Self::new(4, 3)
+    }
+}
+
+#[derive(Component)]
+pub struct AnalysisPanel;
+
+#[derive(Component)]
+pub struct EvaluationBar;
+
+#[derive(Component)]
+pub struct AnalysisLines;
+
+pub fn spawn_analysis_panel(mut commands: Commands) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    left: Val::Px(336.),
+                    top: Val::Px(72.),
+                    width: Val::Px(20.),
+                    height: Val::Px(576.),
+                    ..default()
+                },
+                background_color: BackgroundColor(Color::BLACK),
+                visibility: Visibility::Hidden,
+                ..default()
+            },
+            AnalysisPanel,
+            PanelPlacement::new(336., 72.).narrow(578., 64.),
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                NodeBundle {
+                    style: Style {
+                        position_type: PositionType::Absolute,
+                        bottom: Val::Px(0.),
+                        width: Val::Percent(100.),
+                        height: Val::Percent(50.),
+                        ..default()
+                    },
+                    background_color: BackgroundColor(Color::WHITE),
+                    ..default()
+                },
+                EvaluationBar,
+            ));
+        });
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font_size: 18.,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            left: Val::Px(930.),
+            top: Val::Px(72.),
+            ..default()
+        }),
+        AnalysisLines,
+        PanelPlacement::new(930., 72.).narrow(8., 720.),
+    ));
+}
+
+pub fn toggle_analysis(keys: Res<ButtonInput<KeyCode>>, mut analysis: ResMut<Analysis>) {
+    if keys.just_pressed(KeyCode::KeyA) {
+        analysis.toggle();
+    }
+}
+
+pub fn start_analysis(mut analysis: ResMut<Analysis>) {
+    analysis.set_enabled(true);
+}
+
+pub fn stop_analysis(mut analysis: ResMut<Analysis>) {
+    analysis.set_enabled(false);
+}
+
+pub fn restart_analysis(mut analysis: ResMut<Analysis>) {
+    analysis.restart();
+}
+
+/// Deepens the search one ply at a time in the background until `max_depth`.
+pub fn run_analysis(board_ui_factory: Res<BoardUiFactory>, mut analysis: ResMut<Analysis>) {
+    if !analysis.enabled {
+        return;
+    }
+    if let Some(task) = analysis.task.as_mut() {
+        if let Some(variations) = block_on(poll_once(task)) {
+            analysis.descriptions = variations
+                .iter()
+                .map(|line| {
+                    let moves = San::from_line(&board_ui_factory.board, &line.moves);
+                    format!("{} {}", line.score, moves.join(" "))
+                })
+                .collect();
+            analysis.variations = variations;
+            analysis.depth += 1;
+            analysis.task = None;
+        }
+        return;
+    }
+    if analysis.depth >= analysis.max_depth {
+        return;
+    }
+    let board = board_ui_factory.board.clone();
+    let engine = Engine::new(analysis.depth + 1);
+    let lines = analysis.lines;
+    analysis.task =
+        Some(AsyncComputeTaskPool::get().spawn(async move { engine.analyse(&board, lines) }));
+}
+
+pub fn update_analysis_panel(
+    analysis: Res<Analysis>,
+    mut panel_query: Query<&mut Visibility, With<AnalysisPanel>>,
+    mut bar_query: Query<&mut Style, With<EvaluationBar>>,
+    mut lines_query: Query<&mut Text, (With<AnalysisLines>, Without<AnalysisPanel>)>,
+) {
+    for mut visibility in panel_query.iter_mut() {
+        *visibility = if analysis.enabled {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+    }
+    let white_share = analysis
+        .variations
+        .first()
+        .map(|line| white_share(&line.score))
+        .unwrap_or(0.5);
+    for mut style in bar_query.iter_mut() {
+        style.height = Val::Percent(white_share * 100.);
+    }
+    let lines = if analysis.enabled {
+        analysis.descriptions.join("\n")
+    } else {
+        String::new()
+    };
+    for mut text in lines_query.iter_mut() {
+        text.sections[0].value = lines.clone();
+    }
+}
+
+pub fn draw_best_move_arrow(
+    analysis: Res<Analysis>,
+    board_ui_factory: Res<BoardUiFactory>,
+    mut gizmos: Gizmos,
+) {
+    if !analysis.enabled {
+        return;
+    }
+    if let Some(best_move) = analysis.variations.first().and_then(|l| l.moves.first()) {
+        let from = board_ui_factory.get_pos_transform(best_move.from());
+        let to = board_ui_factory.get_pos_transform(best_move.to());
+        gizmos.arrow_2d(
+            from.translation.truncate(),
+            to.translation.truncate(),
+            Color::srgb(0.2, 0.6, 1.),
+        );
+    }
+}
+
+fn white_share(score: &Score) -> f32 {
+    match score {
+        Score::Centipawns(centipawns) => 1. / (1. + (-*centipawns as f32 / 400.).exp()),
+        Score::Mate(moves) if *moves > 0 => 1.,
+        Score::Mate(_) => 0.,
+    }
+}
@@ -0,0 +1,157 @@
+use crate::board_ui_factory::PieceSprites;
+use bevy::color::Alpha;
+use bevy::prelude::{
+    Commands, Component, DespawnRecursiveExt, Entity, Handle, Image, Query, Res, Sprite,
+    TextureAtlas, Time, Timer, TimerMode, Transform, Vec3,
+};
+
+const MOVE_SECONDS: f32 = 0.18;
+const CAPTURE_SECONDS: f32 = 0.25;
+const PROMOTION_SECONDS: f32 = 0.3;
+/// Moving pieces are drawn above the resting ones.
+const MOVING_Z: f32 = 3.;
+
+/// Slides a piece from wherever it is to `to`.
+#[derive(Component)]
+pub struct MoveAnimation {
+    from: Option<Vec3>,
+    to: Vec3,
+    timer: Timer,
+}
+
+impl MoveAnimation {
+    pub fn new(to: Vec3) -> Self {
+        Self {
+            from: None,
+            to,
+            timer: Timer::from_seconds(MOVE_SECONDS, TimerMode::Once),
+        }
+    }
+
+    /// Ends the slide where `transform` now is, for pieces put on their
+    /// square before it ended.
+    pub fn retarget(&mut self, transform: &Transform) {
+        self.from = Some(transform.translation);
+        self.to.x = transform.translation.x;
+        self.to.y = transform.translation.y;
+    }
+}
+
+/// Fades and shrinks a captured piece, then despawns it.
+#[derive(Component)]
+pub struct CaptureAnimation {
+    timer: Timer,
+}
+
+impl CaptureAnimation {
+    /// Lets the piece vanish on the next frame.
+    pub fn finish(&mut self) {
+        let duration = self.timer.duration();
+        self.timer.set_elapsed(duration);
+    }
+}
+
+impl Default for CaptureAnimation {
+    fn default() -> Self {
+        Self {
+            timer: Timer::from_seconds(CAPTURE_SECONDS, TimerMode::Once),
+        }
+    }
+}
+
+/// Shrinks a promoting pawn away and grows it back with the sprite at `index`.
+#[derive(Component)]
+pub struct PromotionAnimation {
+    index: usize,
+    timer: Timer,
+}
+
+impl PromotionAnimation {
+    pub fn new(index: usize) -> Self {
+        Self {
+            index,
+            timer: Timer::from_seconds(PROMOTION_SECONDS, TimerMode::Once),
+        }
+    }
+}
+
+pub fn animate_moves(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut query: Query<(Entity, &mut MoveAnimation, &mut Transform)>,
+) {
+    for (entity, mut animation, mut transform) in query.iter_mut() {
+        let from = *animation.from.get_or_insert(transform.translation);
+        animation.timer.tick(time.delta());
+        if animation.timer.finished() {
+            transform.translation = animation.to;
+            commands.entity(entity).remove::<MoveAnimation>();
+            continue;
+        }
+        let mut translation = from.lerp(animation.to, ease(animation.timer.fraction()));
+        translation.z = MOVING_Z;
+        transform.translation = translation;
+    }
+}
+
+pub fn animate_captures(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut query: Query<(Entity, &mut CaptureAnimation, &mut Sprite, &mut Transform)>,
+) {
+    for (entity, mut animation, mut sprite, mut transform) in query.iter_mut() {
+        animation.timer.tick(time.delta());
+        if animation.timer.finished() {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+        let t = ease(animation.timer.fraction());
+        sprite.color.set_alpha(1. - t);
+        transform.scale = Vec3::splat(1. - t * 0.5);
+    }
+}
+
+pub fn animate_promotions(
+    mut commands: Commands,
+    time: Res<Time>,
+    piece_sprites: Res<PieceSprites>,
+    mut query: Query<(
+        Entity,
+        &mut PromotionAnimation,
+        &mut Handle<Image>,
+        &mut TextureAtlas,
+        &mut Transform,
+    )>,
+) {
+    for (entity, mut animation, mut texture, mut atlas, mut transform) in query.iter_mut() {
+        animation.timer.tick(time.delta());
+        let t = animation.timer.fraction();
+        if t >= 0.5 {
+            *texture = piece_sprites.texture(animation.index);
+            *atlas = piece_sprites.atlas(animation.index);
+        }
+        if animation.timer.finished() {
+            transform.scale = Vec3::ONE;
+            commands.entity(entity).remove::<PromotionAnimation>();
+            continue;
+        }
+        transform.scale = Vec3::splat((1. - 2. * t).abs());
+    }
+}
+
+fn ease(t: f32) -> f32 {
+    t * t * (3. - 2. * t)
+}
+
+#[cfg(test)]
+mod animation_tests {
+    use crate::animation::ease;
+
+    #[test]
+    fn easing_starts_and_ends_in_place() {
+        assert_eq!(ease(0.), 0.);
+        assert_eq!(ease(0.5), 0.5);
+        assert_eq!(ease(1.), 1.);
+        assert!(ease(0.1) < 0.1);
+    }
+}
@@ -0,0 +1,250 @@
+use crate::board::CheckerBoard;
+use crate::board_move::BoardMove;
+use crate::board_position::BoardPosition;
+use crate::game_history::GameHistory;
+use crate::game_over::GameOutcome;
+use crate::notation::San;
+use crate::pieces::color::PieceColor;
+use crate::pieces::piece_type::PieceType;
+use crate::screen_layout::PanelPlacement;
+use bevy::a11y::accesskit::{Live, NodeBuilder, Role};
+use bevy::a11y::AccessibilityNode;
+use bevy::prelude::{
+    default, Commands, Component, Local, PositionType, Query, Res, ResMut, Resource, Style, Text,
+    TextBundle, TextSection, TextStyle, Val, With,
+};
+use std::collections::VecDeque;
+
+/// How many announcements stay on screen.
+const SHOWN: usize = 3;
+
+fn color_name(color: &PieceColor) -> &'static str {
+    match color {
+        PieceColor::White => "White",
+        PieceColor::Black => "Black",
+    }
+}
+
+fn piece_name(piece_type: &PieceType) -> &'static str {
+    match piece_type {
+        PieceType::Pawn => "pawn",
+        PieceType::Knight => "knight",
+        PieceType::Bishop => "bishop",
+        PieceType::Rook => "rook",
+        PieceType::Queen => "queen",
+        PieceType::King => "king",
+    }
+}
+
+/// Reads `board_move` out in words, as in "White knight to f3, check".
+pub fn describe_move(board: &CheckerBoard, board_move: &BoardMove) -> String {
+    let from = board_move.from();
+    let to = board_move.to();
+    let color = board
+        .piece_at(from)
+        .map(|piece| piece.color().clone())
+        .unwrap_or_else(|| board.active_turn().clone());
+    let piece_type = board_move.piece_type();
+    let mut text = if piece_type == &PieceType::King && from.x().abs_diff(to.x()) == 2 {
+        let side = if to.x() > from.x() {
+            "kingside"
+        } else {
+            "queenside"
+        };
+        format!("{} castles {}", color_name(&color), side)
+    } else {
+        let mover = format!("{} {}", color_name(&color), piece_name(piece_type));
+        match board.piece_at(to) {
+            Some(taken) => format!(
+                "{} takes {} on {}",
+                mover,
+                piece_name(taken.piece_type()),
+                to
+            ),
+            None if piece_type == &PieceType::Pawn && from.x() != to.x() => {
+                format!("{} takes pawn en passant on {}", mover, to)
+            }
+            None => format!("{} to {}", mover, to),
+        }
+    };
+    if board_move.is_promotion() {
+        text.push_str(", promotes to queen");
+    }
+    let san = San::from_move(board, board_move);
+    if san.ends_with('#') {
+        text.push_str(", checkmate");
+    } else if san.ends_with('+') {
+        text.push_str(", check");
+    }
+    text
+}
+
+/// Reads out a square and what stands on it, as in "e4, white pawn".
+pub fn describe_square(board: &CheckerBoard, pos: &BoardPosition) -> String {
+    match board.piece_at(pos) {
+        Some(piece) => format!(
+            "{}, {} {}",
+            pos,
+            color_name(piece.color()).to_lowercase(),
+            piece_name(piece.piece_type())
+        ),
+        None => format!("{}, empty", pos),
+    }
+}
+
+/// What happened on the board in words, newest last, for players who
+/// cannot follow the pieces on screen.
+#[derive(Resource, Default)]
+pub struct Announcements {
+    lines: VecDeque<String>,
+}
+
+impl Announcements {
+    pub fn push(&mut self, line: String) {
+        if self.lines.len() == SHOWN {
+            self.lines.pop_front();
+        }
+        self.lines.push_back(line);
+    }
+
+    pub fn latest(&self) -> Option<&String> {
+        self.lines.back()
+    }
+
+    pub fn lines(&self) -> impl Iterator<Item = &String> {
+        self.lines.iter()
+    }
+}
+
+/// The announcements on screen, also exposed as a live region so screen
+/// readers speak each new line. The first section holds the move being
+/// typed.
+#[derive(Component)]
+pub struct AnnouncementLog;
+
+pub fn spawn_announcement_log(mut commands: Commands) {
+    let style = TextStyle {
+        font_size: 18.,
+        ..default()
+    };
+    commands.spawn((
+        TextBundle::from_sections([
+            TextSection::new("", style.clone()),
+            TextSection::new("", style),
+        ])
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            left: Val::Px(8.),
+            top: Val::Px(652.),
+            ..default()
+        }),
+        AccessibilityNode(NodeBuilder::new(Role::Log)),
+        AnnouncementLog,
+        PanelPlacement::new(8., 652.).narrow(330., 830.),
+    ));
+}
+
+/// Announces each move as it lands in the history, and new games.
+pub fn announce_moves(
+    history: Res<GameHistory>,
+    mut announced: Local<usize>,
+    mut announcements: ResMut<Announcements>,
+) {
+    let len = history.len();
+    if len == *announced + 1 {
+        let board = &history.positions()[len - 1];
+        announcements.push(describe_move(board, &history.moves()[len - 1]));
+    } else if len == 0 && *announced > 0 {
+        let turn = history.current().active_turn();
+        announcements.push(format!("New game, {} to move", color_name(turn)));
+    }
+    *announced = len;
+}
+
+pub fn announce_game_over(outcome: Res<GameOutcome>, mut announcements: ResMut<Announcements>) {
+    announcements.push(format!("Game over, {} {}", outcome.result, outcome.reason));
+}
+
+pub fn update_announcement_log(
+    announcements: Res<Announcements>,
+    mut query: Query<(&mut Text, &mut AccessibilityNode), With<AnnouncementLog>>,
+) {
+    let log = announcements
+        .lines()
+        .cloned()
+        .collect::<Vec<String>>()
+        .join("\n");
+    for (mut text, mut node) in query.iter_mut() {
+        text.sections[1].value = log.clone();
+        let mut builder = NodeBuilder::new(Role::Log);
+        builder.set_live(Live::Polite);
+        if let Some(latest) = announcements.latest() {
+            builder.set_name(latest.as_str());
+        }
+        node.0 = builder;
+    }
+}
+
+#[cfg(test)]
+mod announcements_tests {
+    use crate::announcements::{describe_move, describe_square, Announcements};
+    use crate::board::CheckerBoard;
+    use crate::board_move::BoardMove;
+    use crate::board_pos;
+    use crate::pieces::piece_type::PieceType;
+    use std::str::FromStr;
+
+    #[test]
+    fn it_reads_out_a_quiet_move() {
+        let board = CheckerBoard::default();
+        let board_move = BoardMove::new(PieceType::Knight, board_pos!("g1"), board_pos!("f3"));
+        assert_eq!(describe_move(&board, &board_move), "White knight to f3");
+    }
+
+    #[test]
+    fn it_reads_out_captures_and_checks() {
+        let board = CheckerBoard::from_fen(
+            "rnbqkbnr/ppp2ppp/8/3pp3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 0 3",
+        )
+        .unwrap();
+        let board_move = BoardMove::new(PieceType::Pawn, board_pos!("e4"), board_pos!("d5"));
+        assert_eq!(
+            describe_move(&board, &board_move),
+            "White pawn takes pawn on d5"
+        );
+        let board_move = BoardMove::new(PieceType::Bishop, board_pos!("f1"), board_pos!("b5"));
+        assert_eq!(
+            describe_move(&board, &board_move),
+            "White bishop to b5, check"
+        );
+    }
+
+    #[test]
+    fn it_reads_out_castling_and_mate() {
+        let board = CheckerBoard::from_fen("6k1/5ppp/8/8/8/8/8/R3K2R w KQ - 0 1").unwrap();
+        let board_move = BoardMove::new(PieceType::King, board_pos!("e1"), board_pos!("g1"));
+        assert_eq!(describe_move(&board, &board_move), "White castles kingside");
+        let board_move = BoardMove::new(PieceType::Rook, board_pos!("a1"), board_pos!("a8"));
+        assert_eq!(
+            describe_move(&board, &board_move),
+            "White rook to a8, checkmate"
+        );
+    }
+
+    #[test]
+    fn it_reads_out_squares() {
+        let board = CheckerBoard::default();
+        assert_eq!(describe_square(&board, &board_pos!("e2")), "e2, white pawn");
+        assert_eq!(describe_square(&board, &board_pos!("e4")), "e4, empty");
+    }
+
+    #[test]
+    fn only_the_latest_lines_are_kept() {
+        let mut announcements = Announcements::default();
+        for line in ["one", "two", "three", "four"] {
+            announcements.push(line.to_string());
+        }
+        assert_eq!(announcements.lines().count(), 3);
+        assert_eq!(announcements.latest(), Some(&"four".to_string()));
+    }
+}
@@ -0,0 +1,328 @@
+use crate::clock::Clock;
+use crate::game_history::GameHistory;
+use crate::game_result::GameResult;
+use crate::game_setup::{AppState, GameSetup, Opponent, StartGame, TimeControl};
+use crate::pgn::{Pgn, PgnError};
+use crate::pieces::color::PieceColor;
+use crate::storage;
+use bevy::log::warn;
+use bevy::prelude::{AppExit, EventReader, EventWriter, Res, ResMut, Resource, State};
+use std::fmt::Display;
+use std::time::Duration;
+use thiserror::Error;
+
+/// The name of the saved game, as a file in the data directory or as a key
+/// in the browser's local storage.
+pub const AUTOSAVE_NAME: &str = "autosave.txt";
+
+#[derive(Error, Debug)]
+pub enum AutosaveError {
+    #[error(transparent)]
+    Pgn(#[from] PgnError),
+    #[error("Invalid saved game: {0}")]
+    Invalid(String),
+}
+
+/// A game against the computer or between two people at this screen, as it
+/// was left. It is written as `key=value` lines for the setup and the clock,
+/// then an empty line and the moves as PGN.
+#[derive(Clone)]
+pub struct SavedGame {
+    pub setup: GameSetup,
+    pub history: GameHistory,
+    /// The time left for white and black in timed games.
+    pub clock: Option<[Duration; 2]>,
+}
+
+impl SavedGame {
+    /// Whether a game with this setup can be saved and picked up again here.
+    /// Online, correspondence and puzzle games are kept elsewhere.
+    pub fn can_save(setup: &GameSetup) -> bool {
+        matches!(setup.opponent, Opponent::Human | Opponent::Computer)
+    }
+
+    pub fn parse(text: &str) -> Result<Self, AutosaveError> {
+        let invalid = |line: &str| AutosaveError::Invalid(line.to_string());
+        let (head, pgn) = text.split_once("\n\n").ok_or_else(|| invalid(text))?;
+        let mut setup = GameSetup::default();
+        let mut clock = None;
+        for line in head.lines() {
+            let (key, value) = line.split_once('=').ok_or_else(|| invalid(line))?;
+            match key {
+                "opponent" => {
+                    setup.opponent = match value {
+                        "human" => Opponent::Human,
+                        "computer" => Opponent::Computer,
+                        _ => return Err(invalid(line)),
+                    }
+                }
+                "color" => {
+                    setup.player_color = match value {
+                        "white" => PieceColor::White,
+                        "black" => PieceColor::Black,
+                        _ => return Err(invalid(line)),
+                    }
+                }
+                "level" => setup.level = value.parse().map_err(|_| invalid(line))?,
+                "time" => {
+                    setup.time_control = match value.split_once('+') {
+                        Some((minutes, increment)) => Some(TimeControl::new(
+                            minutes.parse().map_err(|_| invalid(line))?,
+                            increment.parse().map_err(|_| invalid(line))?,
+                        )),
+                        None => None,
+                    }
+                }
+                "clock" => {
+                    let millis: Vec<u64> = value
+                        .split_whitespace()
+                        .map(str::parse)
+                        .collect::<Result<_, _>>()
+                        .map_err(|_| invalid(line))?;
+                    let [white, black] = millis[..] else {
+                        return Err(invalid(line));
+                    };
+                    clock = Some([white, black].map(Duration::from_millis));
+                }
+                "white_name" => setup.white_name = value.to_string(),
+                "black_name" => setup.black_name = value.to_string(),
+                "auto_flip" => setup.auto_flip = value.parse().map_err(|_| invalid(line))?,
+                "pass_device" => setup.pass_device = value.parse().map_err(|_| invalid(line))?,
+                _ => {}
+            }
+        }
+        Ok(Self {
+            setup,
+            history: Pgn::parse(pgn)?,
+            clock,
+        })
+    }
+
+    /// Takes over the choices the game was played with, keeping the rest.
+    pub fn restore_setup(&self, setup: &mut GameSetup) {
+        *setup = GameSetup {
+            opponent: self.setup.opponent,
+            player_color: self.setup.player_color.clone(),
+            level: self.setup.level,
+            time_control: self.setup.time_control,
+            white_name: self.setup.white_name.clone(),
+            black_name: self.setup.black_name.clone(),
+            auto_flip: self.setup.auto_flip,
+            pass_device: self.setup.pass_device,
+            watching: None,
+            ..setup.clone()
+        };
+    }
+}
+
+impl Display for SavedGame {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let setup = &self.setup;
+        let opponent = match setup.opponent {
+            Opponent::Computer => "computer",
+            _ => "human",
+        };
+        let color = match setup.player_color {
+            PieceColor::White => "white",
+            PieceColor::Black => "black",
+        };
+        writeln!(f, "opponent={}", opponent)?;
+        writeln!(f, "color={}", color)?;
+        writeln!(f, "level={}", setup.level)?;
+        if let Some(time_control) = setup.time_control {
+            writeln!(f, "time={}", time_control)?;
+        }
+        if let Some([white, black]) = self.clock {
+            writeln!(f, "clock={} {}", white.as_millis(), black.as_millis())?;
+        }
+        // Names stay on a single line, whatever was typed.
+        let name = |name: &str| name.replace(['\n', '\r'], " ");
+        writeln!(f, "white_name={}", name(&setup.white_name))?;
+        writeln!(f, "black_name={}", name(&setup.black_name))?;
+        writeln!(f, "auto_flip={}", setup.auto_flip)?;
+        writeln!(f, "pass_device={}", setup.pass_device)?;
+        writeln!(f)?;
+        write!(f, "{}", Pgn::new(&self.history))
+    }
+}
+
+/// The game to continue from the title screen, kept up to date as it is
+/// played.
+#[derive(Resource, Default)]
+pub struct Autosave {
+    pub saved: Option<SavedGame>,
+    /// The time left in a game being continued, put back once it started.
+    pending_clock: Option<[Duration; 2]>,
+}
+
+impl Autosave {
+    pub fn load() -> Self {
+        let saved = storage::read(AUTOSAVE_NAME).and_then(|text| match SavedGame::parse(&text) {
+            Ok(saved) => Some(saved),
+            Err(error) => {
+                warn!("Could not read the saved game: {}", error);
+                None
+            }
+        });
+        Self {
+            saved,
+            pending_clock: None,
+        }
+    }
+
+    fn store(&mut self, saved: SavedGame) {
+        if let Err(error) = storage::write(AUTOSAVE_NAME, &saved.to_string()) {
+            warn!("Could not save the game: {}", error);
+        }
+        self.saved = Some(saved);
+    }
+
+    fn clear(&mut self) {
+        if self.saved.take().is_some() {
+            storage::remove(AUTOSAVE_NAME);
+        }
+    }
+
+    /// Starts the saved game again, as it was left.
+    pub fn resume(&mut self, setup: &mut GameSetup, start_game: &mut EventWriter<StartGame>) {
+        let Some(saved) = self.saved.as_ref() else {
+            return;
+        };
+        saved.restore_setup(setup);
+        self.pending_clock = saved.clock;
+        start_game.send(StartGame(saved.history.clone()));
+    }
+}
+
+fn save(autosave: &mut Autosave, setup: &GameSetup, history: &GameHistory, clock: &Clock) {
+    if !SavedGame::can_save(setup) {
+        return;
+    }
+    if GameResult::from_board(history.current()) != GameResult::Ongoing {
+        return autosave.clear();
+    }
+    let clock = clock
+        .remaining(&PieceColor::White)
+        .zip(clock.remaining(&PieceColor::Black))
+        .map(|(white, black)| [white, black]);
+    autosave.store(SavedGame {
+        setup: setup.clone(),
+        history: history.clone(),
+        clock,
+    });
+}
+
+/// Saves the game after every move, which is all a browser tab that is
+/// closed leaves time for.
+pub fn autosave_game(
+    mut autosave: ResMut<Autosave>,
+    setup: Res<GameSetup>,
+    history: Res<GameHistory>,
+    clock: Res<Clock>,
+) {
+    save(&mut autosave, &setup, &history, &clock);
+}
+
+/// Saves the game once more on the way out, with the time left right now.
+pub fn autosave_on_exit(
+    mut exits: EventReader<AppExit>,
+    mut autosave: ResMut<Autosave>,
+    setup: Res<GameSetup>,
+    history: Res<GameHistory>,
+    clock: Res<Clock>,
+    state: Res<State<AppState>>,
+) {
+    if exits.read().last().is_none() {
+        return;
+    }
+    if matches!(state.get(), AppState::Playing | AppState::Handover) {
+        save(&mut autosave, &setup, &history, &clock);
+    }
+}
+
+/// A finished game has nothing left to continue.
+pub fn clear_autosave(mut autosave: ResMut<Autosave>, setup: Res<GameSetup>) {
+    if SavedGame::can_save(&setup) {
+        autosave.clear();
+    }
+}
+
+/// Puts back the time left once the continued game has started.
+pub fn restore_autosave_clock(
+    mut autosave: ResMut<Autosave>,
+    history: Res<GameHistory>,
+    mut clock: ResMut<Clock>,
+) {
+    if let Some(remaining) = autosave.pending_clock.take() {
+        clock.resume(remaining, history.len());
+    }
+}
+
+#[cfg(test)]
+mod autosave_tests {
+    use crate::autosave::SavedGame;
+    use crate::board::CheckerBoard;
+    use crate::board_pos;
+    use crate::game_history::GameHistory;
+    use crate::game_setup::{GameSetup, Opponent, TimeControl};
+    use crate::pieces::color::PieceColor;
+    use std::str::FromStr;
+    use std::time::Duration;
+
+    #[test]
+    fn saved_games_round_trip() {
+        let mut board = CheckerBoard::from_fen("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1").unwrap();
+        board.move_piece(&board_pos!("e2"), &board_pos!("e4"));
+        let start = CheckerBoard::from_fen("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1").unwrap();
+        let saved = SavedGame {
+            setup: GameSetup {
+                opponent: Opponent::Computer,
+                player_color: PieceColor::Black,
+                level: 4,
+                time_control: Some(TimeControl::new(5, 3)),
+                black_name: "Anna".to_string(),
+                ..Default::default()
+            },
+            history: GameHistory::replay(&start, board.get_moves()),
+            clock: Some([Duration::from_millis(281_250), Duration::from_secs(300)]),
+        };
+        let read = SavedGame::parse(&saved.to_string()).unwrap();
+        assert_eq!(read.setup.opponent, Opponent::Computer);
+        assert_eq!(read.setup.player_color, PieceColor::Black);
+        assert_eq!(read.setup.level, 4);
+        assert_eq!(read.setup.time_control, Some(TimeControl::new(5, 3)));
+        assert_eq!(read.setup.black_name, "Anna");
+        assert_eq!(read.clock, saved.clock);
+        assert_eq!(read.history.sans(), &vec!["e4"]);
+        assert_eq!(read.history.current().to_fen(), board.to_fen());
+    }
+
+    #[test]
+    fn continuing_keeps_choices_the_game_does_not_set() {
+        let saved = SavedGame {
+            setup: GameSetup {
+                opponent: Opponent::Computer,
+                ..Default::default()
+            },
+            history: GameHistory::default(),
+            clock: None,
+        };
+        let mut setup = GameSetup {
+            days_per_move: 7,
+            ..Default::default()
+        };
+        saved.restore_setup(&mut setup);
+        assert_eq!(setup.opponent, Opponent::Computer);
+        assert_eq!(setup.days_per_move, 7);
+    }
+
+    #[test]
+    fn only_local_games_are_saved() {
+        let mut setup = GameSetup::default();
+        assert!(SavedGame::can_save(&setup));
+        setup.opponent = Opponent::Online;
+        assert!(!SavedGame::can_save(&setup));
+        assert!(SavedGame::parse("opponent=online\n\n*").is_err());
+        assert!(SavedGame::parse("no moves").is_err());
+    }
+}
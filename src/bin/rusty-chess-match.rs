@@ -0,0 +1,157 @@
+use rusty_chess::engine_match::{
+    run_match, Adjudication, EngineSpec, Hypothesis, MatchConfig, MatchError, MatchGame, Opening,
+    Sprt, Tally, TimeControl,
+};
+use std::str::FromStr;
+
+const USAGE: &str = "Usage: rusty-chess-match [options]
+
+  --engine <spec>          builtin:<depth> or uci:<command>, given twice
+  --games <n>              games to play, 100 by default
+  --tc <base+inc>          seconds per game and per move, 10+0.1 by default
+  --openings <file>        FEN, EPD or move lines, each played with both colours
+  --concurrency <n>        games played at once, 1 by default
+  --sprt <elo0,elo1>       stop once the games tell the two apart
+  --alpha <p> --beta <p>   the SPRT error rates, 0.05 by default
+  --resign <cp,moves>      adjudicate a loss, 1000,3 by default
+  --draw <cp,moves,ply>    adjudicate a draw, 10,8,80 by default
+  --max-plies <n>          draw a game this long, 400 by default
+
+Pawns only promote to queens: an engine that underpromotes loses the game.";
+
+fn parse<T: FromStr>(option: &str, value: &str) -> Result<T, MatchError> {
+    value
+        .parse()
+        .map_err(|_| MatchError::Invalid(format!("{} {}", option, value)))
+}
+
+/// Reads comma separated numbers, as many as `count`.
+fn parse_list<T: FromStr>(option: &str, value: &str, count: usize) -> Result<Vec<T>, MatchError> {
+    let values: Vec<T> = value
+        .split(',')
+        .map(|word| parse(option, word))
+        .collect::<Result<_, _>>()?;
+    match values.len() == count {
+        true => Ok(values),
+        false => Err(MatchError::Invalid(format!("{} {}", option, value))),
+    }
+}
+
+fn parse_args(args: &[String]) -> Result<MatchConfig, MatchError> {
+    let mut engines = vec![];
+    let mut config = MatchConfig {
+        engines: [
+            EngineSpec::BuiltIn { depth: 3 },
+            EngineSpec::BuiltIn { depth: 3 },
+        ],
+        games: 100,
+        openings: vec![],
+        time_control: TimeControl::from_str("10+0.1")?,
+        adjudication: Adjudication::default(),
+        concurrency: 1,
+        sprt: None,
+    };
+    let mut bounds = None;
+    let (mut alpha, mut beta) = (0.05, 0.05);
+    for pair in args.chunks(2) {
+        let [option, value] = pair else {
+            return Err(MatchError::Invalid(format!("{} needs a value", pair[0])));
+        };
+        let option = option.as_str();
+        match option {
+            "--engine" => engines.push(EngineSpec::from_str(value)?),
+            "--games" => config.games = parse(option, value)?,
+            "--tc" => config.time_control = TimeControl::from_str(value)?,
+            "--openings" => {
+                config.openings = Opening::parse_suite(&std::fs::read_to_string(value)?)?
+            }
+            "--concurrency" => config.concurrency = parse(option, value)?,
+            "--sprt" => bounds = Some(parse_list::<f64>(option, value, 2)?),
+            "--alpha" => alpha = parse(option, value)?,
+            "--beta" => beta = parse(option, value)?,
+            "--resign" => {
+                let values = parse_list::<String>(option, value, 2)?;
+                config.adjudication.resign_score = parse(option, &values[0])?;
+                config.adjudication.resign_moves = parse(option, &values[1])?;
+            }
+            "--draw" => {
+                let values = parse_list::<String>(option, value, 3)?;
+                config.adjudication.draw_score = parse(option, &values[0])?;
+                config.adjudication.draw_moves = parse(option, &values[1])?;
+                config.adjudication.draw_after = parse(option, &values[2])?;
+            }
+            "--max-plies" => config.adjudication.max_plies = parse(option, value)?,
+            _ => return Err(MatchError::Invalid(option.to_string())),
+        }
+    }
+    config.engines = engines
+        .try_into()
+        .map_err(|_| MatchError::Invalid("--engine must be given twice".to_string()))?;
+    config.sprt = bounds.map(|bounds| Sprt {
+        elo0: bounds[0],
+        elo1: bounds[1],
+        alpha,
+        beta,
+    });
+    Ok(config)
+}
+
+/// Plays two engines against each other and reports how much stronger the
+/// first one is.
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let config = match parse_args(&args) {
+        Ok(config) => config,
+        Err(error) => {
+            eprintln!("{}\n\n{}", error, USAGE);
+            std::process::exit(2);
+        }
+    };
+    let [first, second] = &config.engines;
+    println!(
+        "{} vs {}, {} games at {}",
+        first, second, config.games, config.time_control
+    );
+    let report = |game: &MatchGame, tally: &Tally| {
+        let (white, black) = match game.first_is_white {
+            true => (first, second),
+            false => (second, first),
+        };
+        let mut line = format!(
+            "Game {} {} - {}: {} {} ({} plies)  {}",
+            game.number + 1,
+            white,
+            black,
+            game.record.result,
+            game.record.reason,
+            game.record.moves.len(),
+            tally
+        );
+        if let Some(sprt) = &config.sprt {
+            let (lower, upper) = sprt.bounds();
+            line.push_str(&format!(
+                "  LLR {:.2} ({:.2}, {:.2})",
+                sprt.llr(tally),
+                lower,
+                upper
+            ));
+        }
+        println!("{}", line);
+    };
+    match run_match(&config, report) {
+        Ok((tally, decision)) => {
+            println!("\n{} games: {}", tally.games(), tally);
+            match decision {
+                Some(Hypothesis::Alternative) => {
+                    println!("SPRT: H1 accepted, {} is stronger", first)
+                }
+                Some(Hypothesis::Null) => println!("SPRT: H0 accepted, {} is not stronger", first),
+                None => {}
+            }
+        }
+        Err(error) => {
+            eprintln!("{}", error);
+            std::process::exit(1);
+        }
+    }
+}
@@ -0,0 +1,37 @@
+use bevy::log::tracing_subscriber;
+use rusty_chess::protocol::{DEFAULT_PORT, DEFAULT_WEBSOCKET_PORT};
+use rusty_chess::server::serve;
+use std::net::TcpListener;
+
+fn bind(address: &str) -> TcpListener {
+    match TcpListener::bind(address) {
+        Ok(listener) => listener,
+        Err(error) => {
+            eprintln!("Could not listen on {}: {}", address, error);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Runs the game server on the address given as the first argument, and
+/// for browsers on the WebSocket address given as the second, or on every
+/// interface at the default ports.
+fn main() {
+    tracing_subscriber::fmt()
+        .with_writer(std::io::stderr)
+        .init();
+    let mut args = std::env::args().skip(1);
+    let address = args
+        .next()
+        .unwrap_or_else(|| format!("0.0.0.0:{}", DEFAULT_PORT));
+    let websocket_address = args
+        .next()
+        .unwrap_or_else(|| format!("0.0.0.0:{}", DEFAULT_WEBSOCKET_PORT));
+    let listener = bind(&address);
+    let websocket = bind(&websocket_address);
+    println!(
+        "Listening on {}, and for WebSocket connections on {}",
+        address, websocket_address
+    );
+    serve(listener, Some(websocket));
+}
@@ -0,0 +1,103 @@
+use rusty_chess::tournament::{format_points, parse_result, Kind, Tournament, TOURNAMENT_PATH};
+
+const USAGE: &str = "Usage: rusty-chess-tournament <command>
+
+  new <name> <round-robin|swiss> [rounds]  start a tournament
+  add <rating> <name>                      enter a player
+  pair                                     pair the next round
+  result <round> <board> <1-0|0-1|1/2-1/2> enter a result
+  standings                                show the standings
+  crosstable [--csv]                       show the crosstable
+
+The tournament is kept in tournament.txt in the current directory, where
+the game records the results of the games it finishes.";
+
+fn run(args: &[String]) -> Result<String, String> {
+    let words: Vec<&str> = args.iter().map(String::as_str).collect();
+    if let ["new", name, kind, rest @ ..] = words.as_slice() {
+        let kind: Kind = kind.parse().map_err(|error| format!("{}", error))?;
+        let rounds = match (kind, rest) {
+            (Kind::RoundRobin, []) => 0,
+            (_, [rounds]) => rounds.parse().map_err(|_| USAGE.to_string())?,
+            _ => return Err(USAGE.to_string()),
+        };
+        Tournament::new(name, kind, rounds)
+            .save(TOURNAMENT_PATH)
+            .map_err(|error| error.to_string())?;
+        return Ok(format!("Started {}", name));
+    }
+    let mut tournament = Tournament::load(TOURNAMENT_PATH).map_err(|error| error.to_string())?;
+    let message = match words.as_slice() {
+        ["add", rating, name @ ..] if !name.is_empty() => {
+            let rating = rating.parse().map_err(|_| USAGE.to_string())?;
+            let name = name.join(" ");
+            tournament
+                .add_player(&name, rating)
+                .map_err(|error| error.to_string())?;
+            format!("{} joins with {}", name, rating)
+        }
+        ["pair"] => {
+            let round = tournament
+                .pair_next_round()
+                .map_err(|error| error.to_string())?
+                .clone();
+            let mut lines = vec![];
+            for (board, game) in round.games.iter().enumerate() {
+                lines.push(format!(
+                    "{:>3}. {} - {}",
+                    board + 1,
+                    tournament.players[game.white].name,
+                    tournament.players[game.black].name
+                ));
+            }
+            if let Some(bye) = round.bye {
+                lines.push(format!("     {} has a bye", tournament.players[bye].name));
+            }
+            format!("Round {}\n{}", tournament.played.len(), lines.join("\n"))
+        }
+        ["result", round, board, result] => {
+            let round = round.parse().map_err(|_| USAGE.to_string())?;
+            let board = board.parse().map_err(|_| USAGE.to_string())?;
+            let result = parse_result(result).ok_or_else(|| USAGE.to_string())?;
+            tournament
+                .set_result(round, board, result)
+                .map_err(|error| error.to_string())?;
+            format!("Round {} board {}: {}", round, board, words[3])
+        }
+        ["standings"] => {
+            let standings = tournament.standings();
+            let lines: Vec<String> = standings
+                .iter()
+                .enumerate()
+                .map(|(rank, standing)| {
+                    format!(
+                        "{:>3}. {:<24} {:>4}",
+                        rank + 1,
+                        tournament.players[standing.player].name,
+                        format_points(standing.points)
+                    )
+                })
+                .collect();
+            lines.join("\n")
+        }
+        ["crosstable"] => tournament.crosstable_text(),
+        ["crosstable", "--csv"] => tournament.crosstable_csv(),
+        _ => return Err(USAGE.to_string()),
+    };
+    tournament
+        .save(TOURNAMENT_PATH)
+        .map_err(|error| error.to_string())?;
+    Ok(message)
+}
+
+/// Runs a club tournament from the command line, one command at a time.
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match run(&args) {
+        Ok(message) => println!("{}", message.trim_end()),
+        Err(message) => {
+            eprintln!("{}", message);
+            std::process::exit(1);
+        }
+    }
+}
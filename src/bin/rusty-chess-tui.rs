@@ -0,0 +1,14 @@
+use rusty_chess::terminal::run;
+use std::io::{stdin, stdout, IsTerminal};
+
+/// Plays in the terminal, with colours unless `--no-color` is given, the
+/// `NO_COLOR` variable is set or the output is not a terminal.
+fn main() {
+    let plain = std::env::args().any(|arg| arg == "--no-color")
+        || std::env::var_os("NO_COLOR").is_some()
+        || !stdout().is_terminal();
+    if let Err(error) = run(stdin().lock(), stdout().lock(), !plain) {
+        eprintln!("{}", error);
+        std::process::exit(1);
+    }
+}
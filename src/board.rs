@@ -7,11 +7,22 @@ use crate::pieces::factory::PieceFactory;
 use crate::pieces::piece_type::PieceType;
 use crate::pieces::Piece;
 use std::collections::HashMap;
+use thiserror::Error;
 
 #[derive(Clone)]
 pub struct CheckerBoard {
     moves: Vec<BoardMove>,
     pieces: HashMap<BoardPosition, Box<dyn Piece>>,
+    passes: usize,
+    /// Home squares of kings and rooks that may no longer castle although no
+    /// move from them was played on this board, as set up from a FEN.
+    castling_lost: Vec<BoardPosition>,
+}
+
+#[derive(Error, Debug)]
+pub enum FenError {
+    #[error("Invalid FEN: {0}")]
+    Invalid(String),
 }
 
 impl CheckerBoard {
@@ -19,6 +30,8 @@ impl CheckerBoard {
         Self {
             pieces: HashMap::with_capacity(32),
             moves: vec![],
+            passes: 0,
+            castling_lost: vec![],
         }
     }
 
@@ -26,6 +39,8 @@ impl CheckerBoard {
         let mut board = Self {
             pieces: HashMap::with_capacity(32),
             moves: vec![],
+            passes: 0,
+            castling_lost: vec![],
         };
 
         for x in 0..board.width() {
@@ -113,6 +128,8 @@ impl CheckerBoard {
         Self {
             pieces: pieces_map,
             moves: vec![],
+            passes: 0,
+            castling_lost: vec![],
         }
     }
     pub fn is_empty(&self) -> bool {
@@ -129,6 +146,10 @@ impl CheckerBoard {
         self.pieces.get(position)
     }
 
+    pub fn get_pieces(&self) -> impl Iterator<Item = (&BoardPosition, &Box<dyn Piece>)> {
+        self.pieces.iter()
+    }
+
     fn force_move_piece(&mut self, from: &BoardPosition, to: &BoardPosition) {
         let piece = self.pieces.remove(from);
         if let Some(from_piece) = piece {
@@ -140,6 +161,7 @@ impl CheckerBoard {
         let mut board_side_effects = BoardSideEffects {
             takes: vec![],
             updates: vec![],
+            moves: vec![],
         };
         if !self.is_valid_move(from, to) {
             return board_side_effects;
@@ -147,6 +169,17 @@ impl CheckerBoard {
         if let Some(p) = self.piece_at(from) {
             board_side_effects.takes = p.takes(self, from, to);
             board_side_effects.updates = p.side_effects(self, from, to);
+            if p.piece_type() == &PieceType::King && from.x().abs_diff(to.x()) == 2 {
+                let rook_x = if to.x() > from.x() {
+                    self.width() - 1
+                } else {
+                    0
+                };
+                board_side_effects.moves.push((
+                    BoardPosition::new(rook_x, from.y()),
+                    BoardPosition::new((from.x() + to.x()) / 2, from.y()),
+                ));
+            }
         }
         if let Some(p) = self.pieces.remove(from) {
             for takes in board_side_effects.takes.iter() {
@@ -162,6 +195,9 @@ impl CheckerBoard {
                 self.pieces
                     .insert(side_effect.pos().clone(), side_effect.piece().clone());
             }
+            for (moved_from, moved_to) in board_side_effects.moves.iter() {
+                self.force_move_piece(moved_from, moved_to);
+            }
         }
         board_side_effects
     }
@@ -181,26 +217,63 @@ impl CheckerBoard {
                     })
                     .collect();
                 if piece.piece_type() == &PieceType::King && !self.is_checked(piece.color()) {
-                    if let Some(piece) = self.piece_at(&BoardPosition::new(0, 0)) {
-                        if piece.piece_type() == &PieceType::Rook {
-                            moves.push(BoardPosition::new(2, 0));
-                        }
-                    }
-                    if let Some(piece) = self.piece_at(&BoardPosition::new(self.width() - 1, 0)) {
-                        if piece.piece_type() == &PieceType::Rook {
-                            moves.push(BoardPosition::new(6, 0));
-                        }
-                    }
+                    moves.extend(self.get_castle_moves(from, piece.color()));
                 }
                 moves
             }
         };
     }
 
+    fn get_castle_moves(&self, from: &BoardPosition, color: &PieceColor) -> Vec<BoardPosition> {
+        let row = match color {
+            PieceColor::White => 0,
+            PieceColor::Black => self.length() - 1,
+        };
+        let home = BoardPosition::new(4, row);
+        if from != &home || self.has_moved_from(&home) {
+            return vec![];
+        }
+        let mut moves = Vec::with_capacity(2);
+        for (rook_x, king_x, between) in [(0, 2, 1..4), (self.width() - 1, 6, 5..7)] {
+            let rook_pos = BoardPosition::new(rook_x, row);
+            let has_rook = match self.piece_at(&rook_pos) {
+                Some(rook) => rook.piece_type() == &PieceType::Rook && rook.color() == color,
+                None => false,
+            };
+            if !has_rook || self.has_moved_from(&rook_pos) {
+                continue;
+            }
+            if between
+                .into_iter()
+                .any(|x| self.piece_at(&BoardPosition::new(x, row)).is_some())
+            {
+                continue;
+            }
+            let is_safe = [(4 + king_x) / 2, king_x].iter().all(|x| {
+                let mut prediction_board = self.clone();
+                prediction_board.force_move_piece(from, &BoardPosition::new(*x, row));
+                !prediction_board.is_checked(color)
+            });
+            if is_safe {
+                moves.push(BoardPosition::new(king_x, row));
+            }
+        }
+        moves
+    }
+
+    fn has_moved_from(&self, pos: &BoardPosition) -> bool {
+        self.castling_lost.contains(pos)
+            || self.moves.iter().any(|board_move| board_move.from() == pos)
+    }
+
     pub fn get_last_move(&self) -> Option<&BoardMove> {
         self.moves.last()
     }
 
+    pub fn get_moves(&self) -> &Vec<BoardMove> {
+        &self.moves
+    }
+
     pub fn is_last_row_for_white(&self, board_position: &BoardPosition) -> bool {
         board_position.y() + 1 == self.length()
     }
@@ -254,6 +327,14 @@ impl CheckerBoard {
             Some(pos) => opponent_moves.contains(pos),
         };
     }
+
+    pub fn get_king_position(&self, color: &PieceColor) -> Option<&BoardPosition> {
+        self.pieces
+            .iter()
+            .find(|(_, piece)| Self::is_king(piece, color))
+            .map(|(pos, _)| pos)
+    }
+
     pub fn is_mated(&self, color: &PieceColor) -> bool {
         if !self.is_checked(color) {
             return false;
@@ -262,16 +343,33 @@ impl CheckerBoard {
         possible_moves.is_empty()
     }
 
+    /// Stalemate: the side to move is not in check but has no legal move.
     pub fn is_draw(&self) -> bool {
-        let colors = [PieceColor::Black, PieceColor::White];
-        colors
-            .iter()
-            .any(|color| self.get_moves_for_color(color).is_empty())
+        let color = self.active_turn();
+        !self.is_checked(color) && self.get_moves_for_color(color).is_empty()
     }
 
     pub fn active_turn(&self) -> &PieceColor {
         let turns = [&PieceColor::White, &PieceColor::Black];
-        turns[self.moves.len() % turns.len()]
+        turns[(self.moves.len() + self.passes) % turns.len()]
+    }
+
+    /// Hands the move to the other side without moving a piece.
+    pub fn pass_turn(&mut self) {
+        self.passes += 1;
+    }
+
+    pub fn get_legal_moves(&self) -> Vec<BoardMove> {
+        let color = self.active_turn();
+        self.pieces
+            .iter()
+            .filter(|(_, piece)| piece.color() == color)
+            .flat_map(|(from, piece)| {
+                self.get_possible_moves(from)
+                    .into_iter()
+                    .map(|to| BoardMove::new(piece.piece_type().clone(), from.clone(), to))
+            })
+            .collect()
     }
 
     pub fn is_valid_move(&self, from: &BoardPosition, to: &BoardPosition) -> bool {
@@ -284,6 +382,145 @@ impl CheckerBoard {
         return moves.contains(to);
     }
 
+    /// The board does not track captures, so the halfmove clock is always
+    /// written as zero.
+    pub fn to_fen(&self) -> String {
+        let mut ranks = Vec::with_capacity(self.length() as usize);
+        for y in (0..self.length()).rev() {
+            let mut rank = String::new();
+            let mut empty = 0;
+            for x in 0..self.width() {
+                match self.piece_at(&BoardPosition::new(x, y)) {
+                    None => empty += 1,
+                    Some(piece) => {
+                        if empty > 0 {
+                            rank.push_str(&empty.to_string());
+                            empty = 0;
+                        }
+                        rank.push(match piece.color() {
+                            PieceColor::White => piece.piece_type().symbol(),
+                            PieceColor::Black => piece.piece_type().symbol().to_ascii_lowercase(),
+                        });
+                    }
+                }
+            }
+            if empty > 0 {
+                rank.push_str(&empty.to_string());
+            }
+            ranks.push(rank);
+        }
+        let turn = match self.active_turn() {
+            PieceColor::White => "w",
+            PieceColor::Black => "b",
+        };
+        let en_passant = match self.get_last_move() {
+            Some(last_move)
+                if last_move.piece_type() == &PieceType::Pawn
+                    && last_move.from().y().abs_diff(last_move.to().y()) == 2 =>
+            {
+                BoardPosition::new(
+                    last_move.to().x(),
+                    (last_move.from().y() + last_move.to().y()) / 2,
+                )
+                .to_string()
+            }
+            _ => "-".to_string(),
+        };
+        format!(
+            "{} {} {} {} 0 {}",
+            ranks.join("/"),
+            turn,
+            self.castling_fen(),
+            en_passant,
+            (self.moves.len() + self.passes) / 2 + 1
+        )
+    }
+
+    /// The castling rights as FEN writes them, such as `KQkq`, or `-`.
+    fn castling_fen(&self) -> String {
+        let last = self.length() - 1;
+        let rights = [
+            ('K', PieceColor::White, self.width() - 1, 0),
+            ('Q', PieceColor::White, 0, 0),
+            ('k', PieceColor::Black, self.width() - 1, last),
+            ('q', PieceColor::Black, 0, last),
+        ];
+        let castling: String = rights
+            .into_iter()
+            .filter(|(_, color, rook_x, y)| {
+                let is = |pos: &BoardPosition, piece_type: PieceType| {
+                    self.piece_at(pos).is_some_and(|piece| {
+                        piece.piece_type() == &piece_type && piece.color() == color
+                    }) && !self.has_moved_from(pos)
+                };
+                is(&BoardPosition::new(4, *y), PieceType::King)
+                    && is(&BoardPosition::new(*rook_x, *y), PieceType::Rook)
+            })
+            .map(|(right, ..)| right)
+            .collect();
+        match castling.is_empty() {
+            true => "-".to_string(),
+            false => castling,
+        }
+    }
+
+    /// Sets up the position described by `fen`. The en passant square and the
+    /// move counters are not kept, as the board derives those from its moves.
+    pub fn from_fen(fen: &str) -> Result<Self, FenError> {
+        let invalid = || FenError::Invalid(fen.to_string());
+        let mut fields = fen.split_whitespace();
+        let placement = fields.next().ok_or_else(invalid)?;
+        let turn = fields.next().unwrap_or("w");
+        let castling = fields.next().unwrap_or("-");
+        let mut board = Self::new();
+        let ranks: Vec<&str> = placement.split('/').collect();
+        if ranks.len() != board.length() as usize {
+            return Err(invalid());
+        }
+        for (row, rank) in ranks.iter().enumerate() {
+            let y = board.length() - 1 - row as u8;
+            let mut x = 0;
+            for symbol in rank.chars() {
+                if let Some(empty) = symbol.to_digit(10) {
+                    x += empty as u8;
+                    continue;
+                }
+                let piece_type =
+                    PieceType::from_symbol(symbol.to_ascii_uppercase()).ok_or_else(invalid)?;
+                let color = if symbol.is_ascii_uppercase() {
+                    PieceColor::White
+                } else {
+                    PieceColor::Black
+                };
+                if x >= board.width() {
+                    return Err(invalid());
+                }
+                board.spawn(&BoardPosition::new(x, y), piece_type, color);
+                x += 1;
+            }
+            if x != board.width() {
+                return Err(invalid());
+            }
+        }
+        match turn {
+            "w" => {}
+            "b" => board.pass_turn(),
+            _ => return Err(invalid()),
+        }
+        let last = board.length() - 1;
+        for (right, rook_x, y) in [('K', 7, 0), ('Q', 0, 0), ('k', 7, last), ('q', 0, last)] {
+            if !castling.contains(right) {
+                board.castling_lost.push(BoardPosition::new(rook_x, y));
+            }
+        }
+        for (kingside, queenside, y) in [('K', 'Q', 0), ('k', 'q', last)] {
+            if !castling.contains(kingside) && !castling.contains(queenside) {
+                board.castling_lost.push(BoardPosition::new(4, y));
+            }
+        }
+        Ok(board)
+    }
+
     fn get_moves_for_color(&self, color: &PieceColor) -> Vec<BoardPosition> {
         let possible_moves = self
             .pieces
@@ -310,6 +547,19 @@ mod chess_board_tests {
     use crate::pieces::piece_type::PieceType;
     use std::str::FromStr;
 
+    #[test]
+    fn it_finds_the_king() {
+        let board = CheckerBoard::default();
+        assert_eq!(
+            board.get_king_position(&PieceColor::Black),
+            Some(&board_pos!("e8"))
+        );
+        assert_eq!(
+            CheckerBoard::new().get_king_position(&PieceColor::White),
+            None
+        );
+    }
+
     #[test]
     fn new_board_is_empty() {
         let board = CheckerBoard::new();
@@ -383,6 +633,17 @@ mod chess_board_tests {
         assert_eq!(last_move.to(), &board_pos!["b3"]);
     }
 
+    #[test]
+    fn it_keeps_all_moves_in_order() {
+        let mut board = CheckerBoard::default();
+        board.move_piece(&board_pos!["e2"], &board_pos!["e4"]);
+        board.move_piece(&board_pos!["e7"], &board_pos!["e5"]);
+        let moves = board.get_moves();
+        assert_eq!(moves.len(), 2);
+        assert_eq!(moves[0].from(), &board_pos!["e2"]);
+        assert_eq!(moves[1].from(), &board_pos!["e7"]);
+    }
+
     #[test]
     fn non_eight_row_is_not_last_row_for_white() {
         let board = CheckerBoard::new();
@@ -704,6 +965,32 @@ mod chess_board_tests {
         assert_eq!(board.active_turn(), &PieceColor::White)
     }
 
+    #[test]
+    fn passing_turn_lets_black_move_first() {
+        let mut board = CheckerBoard::default();
+        board.pass_turn();
+        assert_eq!(board.active_turn(), &PieceColor::Black);
+        board.move_piece(&board_pos!("e7"), &board_pos!("e5"));
+        assert!(board.piece_at(&board_pos!("e5")).is_some());
+        assert_eq!(board.active_turn(), &PieceColor::White);
+    }
+
+    #[test]
+    fn default_board_has_twenty_legal_moves() {
+        let board = CheckerBoard::default();
+        assert_eq!(board.get_legal_moves().len(), 20);
+    }
+
+    #[test]
+    fn legal_moves_are_for_active_turn() {
+        let mut board = CheckerBoard::default();
+        board.move_piece(&board_pos!("e2"), &board_pos!("e4"));
+        assert!(board
+            .get_legal_moves()
+            .iter()
+            .all(|board_move| board_move.from().y() >= 6));
+    }
+
     #[test]
     fn first_cant_move_black_pieces() {
         let mut board = CheckerBoard::default();
@@ -738,6 +1025,39 @@ mod chess_board_tests {
         let side_effects = board.move_piece(&board_pos!("e7"), &board_pos!("e6"));
         assert!(side_effects.takes.is_empty());
     }
+    #[test]
+    fn castling_king_side_moves_the_rook() {
+        let pieces = vec![
+            BoardPiece::build(PieceType::King, PieceColor::White, "e1"),
+            BoardPiece::build(PieceType::Rook, PieceColor::White, "h1"),
+        ];
+        let mut board = CheckerBoard::with_pieces(pieces);
+        let side_effects = board.move_piece(&board_pos!("e1"), &board_pos!("g1"));
+        assert_eq!(
+            side_effects.moves,
+            vec![(board_pos!("h1"), board_pos!("f1"))]
+        );
+        assert!(board.piece_at(&board_pos!("h1")).is_none());
+        assert_eq!(
+            board.piece_at(&board_pos!("f1")).unwrap().piece_type(),
+            &PieceType::Rook
+        );
+    }
+
+    #[test]
+    fn castling_queen_side_moves_the_rook() {
+        let pieces = vec![
+            BoardPiece::build(PieceType::King, PieceColor::Black, "e8"),
+            BoardPiece::build(PieceType::Rook, PieceColor::Black, "a8"),
+        ];
+        let mut board = CheckerBoard::with_pieces(pieces);
+        board.pass_turn();
+        board.move_piece(&board_pos!("e8"), &board_pos!("c8"));
+        assert!(board.piece_at(&board_pos!("a8")).is_none());
+        assert!(board.piece_at(&board_pos!("d8")).is_some());
+        assert!(board.piece_at(&board_pos!("c8")).is_some());
+    }
+
     #[test]
     fn side_effect_takes_removes_piece_from_board() {
         let d4 = BoardPiece::build(PieceType::Pawn, PieceColor::Black, "d4");
@@ -761,6 +1081,75 @@ mod chess_board_tests {
         assert_eq!(piece.piece_type(), &PieceType::Queen);
     }
 
+    #[test]
+    fn default_board_fen() {
+        let board = CheckerBoard::default();
+        assert_eq!(
+            board.to_fen(),
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"
+        );
+    }
+
+    #[test]
+    fn fen_has_en_passant_square_after_double_pawn_move() {
+        let mut board = CheckerBoard::default();
+        board.move_piece(&board_pos!("e2"), &board_pos!("e4"));
+        assert_eq!(
+            board.to_fen(),
+            "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1"
+        );
+    }
+
+    #[test]
+    fn fen_counts_full_moves() {
+        let mut board = CheckerBoard::default();
+        board.move_piece(&board_pos!("g1"), &board_pos!("f3"));
+        board.move_piece(&board_pos!("g8"), &board_pos!("f6"));
+        assert_eq!(
+            board.to_fen(),
+            "rnbqkb1r/pppppppp/5n2/8/8/5N2/PPPPPPPP/RNBQKB1R w KQkq - 0 2"
+        );
+    }
+
+    #[test]
+    fn fen_round_trips_the_piece_placement() {
+        let mut board = CheckerBoard::default();
+        board.move_piece(&board_pos!("g1"), &board_pos!("f3"));
+        let loaded = CheckerBoard::from_fen(&board.to_fen()).unwrap();
+        assert_eq!(loaded.to_fen(), board.to_fen());
+        assert_eq!(loaded.active_turn(), &PieceColor::Black);
+    }
+
+    #[test]
+    fn fen_without_castling_rights_forbids_castling() {
+        let board = CheckerBoard::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w Kq - 0 1").unwrap();
+        let moves = board.get_possible_moves(&board_pos!("e1"));
+        assert!(moves.contains(&board_pos!("g1")));
+        assert!(!moves.contains(&board_pos!("c1")));
+    }
+
+    #[test]
+    fn fen_round_trips_partial_castling_rights() {
+        let fen = "r3k2r/8/8/8/8/8/8/R3K2R w Kq - 0 1";
+        assert_eq!(CheckerBoard::from_fen(fen).unwrap().to_fen(), fen);
+    }
+
+    #[test]
+    fn moving_a_rook_loses_its_castling_right() {
+        let mut board = CheckerBoard::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+        board.move_piece(&board_pos!("h1"), &board_pos!("h2"));
+        assert!(board.to_fen().contains(" b Qkq - "));
+    }
+
+    #[test]
+    fn malformed_fen_is_rejected() {
+        assert!(CheckerBoard::from_fen("").is_err());
+        assert!(CheckerBoard::from_fen("8/8/8 w - - 0 1").is_err());
+        assert!(CheckerBoard::from_fen("rnbqkbnr/pppppppp/9/8/8/8/PPPPPPPP/RNBQKBNR w").is_err());
+        assert!(CheckerBoard::from_fen("xnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w").is_err());
+        assert!(CheckerBoard::from_fen("8/8/8/8/8/8/8/8 x - - 0 1").is_err());
+    }
+
     fn assert_all_pos_have_pieces(
         board: CheckerBoard,
         rook_positions: impl Iterator<Item = BoardPosition>,
@@ -0,0 +1,317 @@
+use crate::board_position::BoardPosition;
+use crate::board_preview::BoardPreview;
+use crate::board_ui_factory::BoardUiFactory;
+use crate::game_history::GameHistory;
+use crate::pgn::Pgn;
+use crate::piece_drag::Dragging;
+use crate::{BoardPieceComponent, BoardPosComponent};
+use bevy::prelude::{
+    ButtonInput, Color, Entity, EventReader, Gizmos, KeyCode, Query, Res, ResMut, Resource, With,
+};
+use bevy::utils::HashMap;
+use bevy_mod_picking::prelude::{Down, Pointer, PointerButton, Up};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AnnotationColor {
+    Green,
+    Red,
+    Blue,
+    Yellow,
+}
+
+impl AnnotationColor {
+    /// Shift draws red, alt blue and both together yellow.
+    pub fn from_modifiers(shift: bool, alt: bool) -> Self {
+        match (shift, alt) {
+            (false, false) => AnnotationColor::Green,
+            (true, false) => AnnotationColor::Red,
+            (false, true) => AnnotationColor::Blue,
+            (true, true) => AnnotationColor::Yellow,
+        }
+    }
+
+    fn letter(&self) -> char {
+        match self {
+            AnnotationColor::Green => 'G',
+            AnnotationColor::Red => 'R',
+            AnnotationColor::Blue => 'B',
+            AnnotationColor::Yellow => 'Y',
+        }
+    }
+
+    fn color(&self) -> Color {
+        match self {
+            AnnotationColor::Green => Color::srgb(0.1, 0.6, 0.2),
+            AnnotationColor::Red => Color::srgb(0.85, 0.15, 0.1),
+            AnnotationColor::Blue => Color::srgb(0.1, 0.4, 0.9),
+            AnnotationColor::Yellow => Color::srgb(0.95, 0.75, 0.1),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Annotation {
+    Square(BoardPosition, AnnotationColor),
+    Arrow(BoardPosition, BoardPosition, AnnotationColor),
+}
+
+impl Annotation {
+    fn color(&self) -> &AnnotationColor {
+        match self {
+            Annotation::Square(_, color) | Annotation::Arrow(_, _, color) => color,
+        }
+    }
+
+    fn is_on(&self, other: &Annotation) -> bool {
+        match (self, other) {
+            (Annotation::Square(pos, _), Annotation::Square(other, _)) => pos == other,
+            (Annotation::Arrow(from, to, _), Annotation::Arrow(other_from, other_to, _)) => {
+                from == other_from && to == other_to
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Arrows and highlighted squares drawn with the secondary button, kept per
+/// position so that every move starts with a clean board.
+#[derive(Resource, Default)]
+pub struct BoardAnnotations {
+    by_ply: HashMap<usize, Vec<Annotation>>,
+    pending: Option<(BoardPosition, AnnotationColor)>,
+}
+
+impl BoardAnnotations {
+    pub fn at(&self, ply: usize) -> &[Annotation] {
+        self.by_ply.get(&ply).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    /// Adds `annotation`, replacing one of another colour in the same place.
+    /// Drawing the same annotation again removes it.
+    pub fn toggle(&mut self, ply: usize, annotation: Annotation) {
+        let annotations = self.by_ply.entry(ply).or_default();
+        let existing = annotations.iter().position(|old| old.is_on(&annotation));
+        if let Some(index) = existing {
+            if annotations.remove(index) == annotation {
+                return;
+            }
+        }
+        annotations.push(annotation);
+    }
+
+    pub fn clear(&mut self) {
+        self.by_ply.clear();
+        self.pending = None;
+    }
+
+    /// The annotations of `ply` as `[%csl]` and `[%cal]` commands.
+    pub fn comment(&self, ply: usize) -> Option<String> {
+        let mut squares = vec![];
+        let mut arrows = vec![];
+        for annotation in self.at(ply) {
+            let letter = annotation.color().letter();
+            match annotation {
+                Annotation::Square(pos, _) => squares.push(format!("{}{}", letter, pos)),
+                Annotation::Arrow(from, to, _) => arrows.push(format!("{}{}{}", letter, from, to)),
+            }
+        }
+        let mut comment = String::new();
+        if !squares.is_empty() {
+            comment.push_str(&format!("[%csl {}]", squares.join(",")));
+        }
+        if !arrows.is_empty() {
+            comment.push_str(&format!("[%cal {}]", arrows.join(",")));
+        }
+        Some(comment).filter(|comment| !comment.is_empty())
+    }
+
+    /// Writes the annotations of every position into `pgn`, the ones of the
+    /// start position before the first move.
+    pub fn annotate(&self, pgn: &mut Pgn) {
+        for ply in self.by_ply.keys() {
+            let Some(comment) = self.comment(*ply) else {
+                continue;
+            };
+            match ply.checked_sub(1) {
+                Some(index) => pgn.append_comment(index, &comment),
+                None => pgn.set_start_comment(&comment),
+            }
+        }
+    }
+}
+
+/// Right click highlights a square and right drag draws an arrow, on the
+/// position currently shown.
+pub fn draw_annotation_input(
+    mut downs: EventReader<Pointer<Down>>,
+    mut ups: EventReader<Pointer<Up>>,
+    keys: Res<ButtonInput<KeyCode>>,
+    history: Res<GameHistory>,
+    preview: Res<BoardPreview>,
+    mut annotations: ResMut<BoardAnnotations>,
+    pos_query: Query<&BoardPosComponent>,
+    pieces_query: Query<&BoardPieceComponent>,
+    dragging_query: Query<Entity, With<Dragging>>,
+) {
+    let pos_at = |target: Entity| {
+        pos_query
+            .get(target)
+            .map(|square| square.0.clone())
+            .or_else(|_| pieces_query.get(target).map(|piece| piece.0.clone()))
+            .ok()
+    };
+    for down in downs.read() {
+        if down.button != PointerButton::Secondary || !dragging_query.is_empty() {
+            continue;
+        }
+        let color = AnnotationColor::from_modifiers(
+            keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]),
+            keys.any_pressed([KeyCode::AltLeft, KeyCode::AltRight]),
+        );
+        annotations.pending = pos_at(down.target).map(|pos| (pos, color));
+    }
+    for up in ups.read() {
+        if up.button != PointerButton::Secondary {
+            continue;
+        }
+        let Some((from, color)) = annotations.pending.take() else {
+            continue;
+        };
+        let Some(to) = pos_at(up.target) else {
+            continue;
+        };
+        let annotation = if from == to {
+            Annotation::Square(to, color)
+        } else {
+            Annotation::Arrow(from, to, color)
+        };
+        let ply = preview.ply().unwrap_or(history.len());
+        annotations.toggle(ply, annotation);
+    }
+}
+
+pub fn draw_annotations(
+    annotations: Res<BoardAnnotations>,
+    history: Res<GameHistory>,
+    preview: Res<BoardPreview>,
+    board_ui_factory: Res<BoardUiFactory>,
+    mut gizmos: Gizmos,
+) {
+    let ply = preview.ply().unwrap_or(history.len());
+    let radius = board_ui_factory.pos_size().min_element() / 2. - 3.;
+    for annotation in annotations.at(ply) {
+        let color = annotation.color().color();
+        match annotation {
+            Annotation::Square(pos, _) => {
+                let center = board_ui_factory.get_pos_transform(pos).translation;
+                gizmos.circle_2d(center.truncate(), radius, color);
+            }
+            Annotation::Arrow(from, to, _) => {
+                let from = board_ui_factory.get_pos_transform(from).translation;
+                let to = board_ui_factory.get_pos_transform(to).translation;
+                gizmos.arrow_2d(from.truncate(), to.truncate(), color);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod board_annotations_tests {
+    use crate::board_annotations::{Annotation, AnnotationColor, BoardAnnotations};
+    use crate::board_pos;
+    use crate::pgn::Pgn;
+    use std::str::FromStr;
+
+    #[test]
+    fn modifiers_pick_the_colour() {
+        assert_eq!(
+            AnnotationColor::from_modifiers(false, false),
+            AnnotationColor::Green
+        );
+        assert_eq!(
+            AnnotationColor::from_modifiers(true, false),
+            AnnotationColor::Red
+        );
+        assert_eq!(
+            AnnotationColor::from_modifiers(false, true),
+            AnnotationColor::Blue
+        );
+        assert_eq!(
+            AnnotationColor::from_modifiers(true, true),
+            AnnotationColor::Yellow
+        );
+    }
+
+    #[test]
+    fn drawing_the_same_annotation_twice_removes_it() {
+        let mut annotations = BoardAnnotations::default();
+        let square = Annotation::Square(board_pos!("e4"), AnnotationColor::Green);
+        annotations.toggle(0, square.clone());
+        assert_eq!(annotations.at(0), &[square.clone()]);
+        annotations.toggle(0, square);
+        assert!(annotations.at(0).is_empty());
+    }
+
+    #[test]
+    fn another_colour_replaces_the_annotation() {
+        let mut annotations = BoardAnnotations::default();
+        annotations.toggle(
+            0,
+            Annotation::Arrow(board_pos!("e2"), board_pos!("e4"), AnnotationColor::Green),
+        );
+        let red = Annotation::Arrow(board_pos!("e2"), board_pos!("e4"), AnnotationColor::Red);
+        annotations.toggle(0, red.clone());
+        assert_eq!(annotations.at(0), &[red]);
+    }
+
+    #[test]
+    fn each_position_has_its_own_annotations() {
+        let mut annotations = BoardAnnotations::default();
+        annotations.toggle(
+            0,
+            Annotation::Square(board_pos!("d4"), AnnotationColor::Green),
+        );
+        assert!(annotations.at(1).is_empty());
+    }
+
+    #[test]
+    fn it_writes_csl_and_cal_commands() {
+        let mut annotations = BoardAnnotations::default();
+        annotations.toggle(
+            1,
+            Annotation::Square(board_pos!("d5"), AnnotationColor::Red),
+        );
+        annotations.toggle(
+            1,
+            Annotation::Arrow(board_pos!("g1"), board_pos!("f3"), AnnotationColor::Green),
+        );
+        annotations.toggle(
+            1,
+            Annotation::Arrow(board_pos!("d2"), board_pos!("d4"), AnnotationColor::Blue),
+        );
+        assert_eq!(
+            annotations.comment(1),
+            Some("[%csl Rd5][%cal Gg1f3,Bd2d4]".to_string())
+        );
+        assert_eq!(annotations.comment(0), None);
+    }
+
+    #[test]
+    fn it_annotates_the_move_leading_to_the_position() {
+        let mut annotations = BoardAnnotations::default();
+        annotations.toggle(
+            0,
+            Annotation::Square(board_pos!("e4"), AnnotationColor::Green),
+        );
+        annotations.toggle(
+            1,
+            Annotation::Square(board_pos!("e5"), AnnotationColor::Yellow),
+        );
+        let history = Pgn::parse("1. e4 *").unwrap();
+        let mut pgn = Pgn::new(&history);
+        annotations.annotate(&mut pgn);
+        assert!(pgn
+            .to_string()
+            .ends_with("\n{ [%csl Ge4] } 1. e4 { [%csl Ye5] } *\n"));
+    }
+}
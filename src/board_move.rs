@@ -1,7 +1,8 @@
 use crate::board_position::BoardPosition;
 use crate::pieces::piece_type::PieceType;
+use std::fmt::Display;
 
-#[derive(Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct BoardMove(PieceType, BoardPosition, BoardPosition);
 impl BoardMove {
     pub fn new(piece_type: PieceType, from: BoardPosition, to: BoardPosition) -> Self {
@@ -18,4 +19,45 @@ impl BoardMove {
     pub fn piece_type(&self) -> &PieceType {
         &self.0
     }
+
+    pub fn is_promotion(&self) -> bool {
+        self.piece_type() == &PieceType::Pawn && (self.to().y() == 0 || self.to().y() == 7)
+    }
+}
+
+impl Display for BoardMove {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}{}", self.from(), self.to())?;
+        if self.is_promotion() {
+            write!(f, "q")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod board_move_tests {
+    use crate::board_move::BoardMove;
+    use crate::board_pos;
+    use crate::pieces::piece_type::PieceType;
+    use std::str::FromStr;
+
+    #[test]
+    fn can_turn_to_string() {
+        let board_move = BoardMove::new(PieceType::Knight, board_pos!("g1"), board_pos!("f3"));
+        assert_eq!(board_move.to_string(), "g1f3");
+    }
+
+    #[test]
+    fn promotion_adds_queen_suffix() {
+        let board_move = BoardMove::new(PieceType::Pawn, board_pos!("d7"), board_pos!("d8"));
+        assert!(board_move.is_promotion());
+        assert_eq!(board_move.to_string(), "d7d8q");
+    }
+
+    #[test]
+    fn pawn_move_to_middle_is_not_promotion() {
+        let board_move = BoardMove::new(PieceType::Pawn, board_pos!("e2"), board_pos!("e4"));
+        assert!(!board_move.is_promotion());
+    }
 }
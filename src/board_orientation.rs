@@ -0,0 +1,123 @@
+use crate::animation::{CaptureAnimation, MoveAnimation};
+use crate::board_preview::BoardPreview;
+use crate::board_ui_factory::BoardUiFactory;
+use crate::click_to_move::Selection;
+use crate::piece_drag::Dragging;
+use crate::{BoardPieceComponent, BoardPosComponent, WithBoardPosition};
+use bevy::prelude::{
+    ButtonInput, DetectChangesMut, KeyCode, Local, Query, Res, ResMut, Transform, Vec2, Without,
+};
+
+/// F turns the board around.
+pub fn flip_board(keys: Res<ButtonInput<KeyCode>>, mut board_ui_factory: ResMut<BoardUiFactory>) {
+    if keys.just_pressed(KeyCode::KeyF) {
+        board_ui_factory.flip();
+    }
+}
+
+/// Puts squares and pieces in place after the board was turned, by hand or
+/// when a new game started, or its squares changed size. Moves and captures
+/// still being animated end at once, as they were drawn on the old squares.
+pub fn orient_board(
+    board_ui_factory: Res<BoardUiFactory>,
+    mut placed: Local<(bool, Vec2)>,
+    mut selection: ResMut<Selection>,
+    mut preview: ResMut<BoardPreview>,
+    mut square_query: Query<(&BoardPosComponent, &mut Transform), Without<BoardPieceComponent>>,
+    mut piece_query: Query<
+        (
+            &BoardPieceComponent,
+            &mut Transform,
+            Option<&mut MoveAnimation>,
+        ),
+        Without<Dragging>,
+    >,
+    mut capture_query: Query<&mut CaptureAnimation>,
+) {
+    let layout = (board_ui_factory.is_flipped(), board_ui_factory.pos_size());
+    if layout == *placed {
+        return;
+    }
+    *placed = layout;
+    for (square, mut transform) in square_query.iter_mut() {
+        place(&board_ui_factory, square, &mut transform);
+    }
+    for (piece, mut transform, animation) in piece_query.iter_mut() {
+        place(&board_ui_factory, piece, &mut transform);
+        if let Some(mut animation) = animation {
+            animation.retarget(&transform);
+        }
+    }
+    for mut animation in capture_query.iter_mut() {
+        animation.finish();
+    }
+    // Redraw what is derived from square positions.
+    selection.set_changed();
+    preview.set_changed();
+}
+
+fn place(
+    board_ui_factory: &BoardUiFactory,
+    component: &impl WithBoardPosition,
+    transform: &mut Transform,
+) {
+    let target = board_ui_factory.get_pos_transform(component.pos());
+    transform.translation.x = target.translation.x;
+    transform.translation.y = target.translation.y;
+}
+
+#[cfg(test)]
+mod board_orientation_tests {
+    use crate::animation::{animate_captures, animate_moves, CaptureAnimation, MoveAnimation};
+    use crate::board::CheckerBoard;
+    use crate::board_orientation::orient_board;
+    use crate::board_pos;
+    use crate::board_preview::BoardPreview;
+    use crate::board_ui_factory::BoardUiFactory;
+    use crate::click_to_move::Selection;
+    use crate::BoardPieceComponent;
+    use bevy::ecs::system::RunSystemOnce;
+    use bevy::prelude::{Sprite, Time, Transform, Vec3, World};
+    use std::str::FromStr;
+    use std::time::Duration;
+
+    #[test]
+    fn turning_the_board_ends_running_animations() {
+        let mut world = World::new();
+        let mut board_ui_factory = BoardUiFactory::new(64., 64., CheckerBoard::default());
+        board_ui_factory.flip();
+        let target = board_ui_factory
+            .get_pos_transform(&board_pos!("e4"))
+            .translation;
+        world.insert_resource(board_ui_factory);
+        world.init_resource::<Selection>();
+        world.init_resource::<BoardPreview>();
+        let mut time = Time::<()>::default();
+        time.advance_by(Duration::from_secs(1));
+        world.insert_resource(time);
+        let moving = world
+            .spawn((
+                BoardPieceComponent(board_pos!("e4")),
+                Transform::from_xyz(10., 20., 3.),
+                MoveAnimation::new(Vec3::new(-100., -100., 1.)),
+            ))
+            .id();
+        let captured = world
+            .spawn((
+                Sprite::default(),
+                Transform::default(),
+                CaptureAnimation::default(),
+            ))
+            .id();
+        world.run_system_once(orient_board);
+        world.run_system_once(animate_moves);
+        world.run_system_once(animate_captures);
+        let piece = world.entity(moving);
+        assert!(!piece.contains::<MoveAnimation>());
+        assert_eq!(
+            piece.get::<Transform>().unwrap().translation,
+            Vec3::new(target.x, target.y, 1.)
+        );
+        assert!(world.get_entity(captured).is_none());
+    }
+}
@@ -1,5 +1,6 @@
 use bevy::asset::AssetServer;
 use bevy::prelude::{default, Added, Commands, Component, Entity, Query, Res, SpriteBundle};
+use bevy_mod_picking::prelude::Pickable;
 
 #[derive(Component)]
 pub struct BoardPositionMarker;
@@ -10,9 +11,12 @@ pub fn add_board_pos_markers_sprite(
     asset_server: Res<AssetServer>,
 ) {
     for entity in query.iter() {
-        commands.entity(entity).insert(SpriteBundle {
-            texture: asset_server.load("board_position_marker.png"),
-            ..default()
-        });
+        commands.entity(entity).insert((
+            SpriteBundle {
+                texture: asset_server.load("board_position_marker.png"),
+                ..default()
+            },
+            Pickable::IGNORE,
+        ));
     }
 }
@@ -0,0 +1,123 @@
+use crate::board_ui_factory::{BoardUiFactory, PieceSprites};
+use crate::game_history::GameHistory;
+use crate::BoardPieceComponent;
+use bevy::prelude::{
+    default, Commands, Component, DespawnRecursiveExt, Entity, Query, Res, Resource, SpriteBundle,
+    Transform, Visibility, With,
+};
+use bevy_mod_picking::prelude::Pickable;
+
+/// Shows a past position of the game on top of the live board. The live
+/// piece sprites are hidden while a preview is shown.
+#[derive(Resource, Default)]
+pub struct BoardPreview {
+    ply: Option<usize>,
+}
+
+impl BoardPreview {
+    pub fn show(&mut self, ply: usize) {
+        self.ply = Some(ply);
+    }
+
+    /// Shows `ply`, or goes back to the live board when `ply` is the latest
+    /// position of a game with `last` moves.
+    pub fn jump(&mut self, ply: usize, last: usize) {
+        if ply >= last {
+            self.close();
+        } else {
+            self.show(ply);
+        }
+    }
+
+    pub fn step(&mut self, offset: isize, last: usize) {
+        let ply = self.ply.unwrap_or(last) as isize + offset;
+        self.jump(ply.clamp(0, last as isize) as usize, last);
+    }
+
+    pub fn close(&mut self) {
+        self.ply = None;
+    }
+
+    pub fn ply(&self) -> Option<usize> {
+        self.ply
+    }
+}
+
+#[derive(Component)]
+pub struct PreviewPiece;
+
+pub fn update_board_preview(
+    mut commands: Commands,
+    preview: Res<BoardPreview>,
+    history: Res<GameHistory>,
+    board_ui_factory: Res<BoardUiFactory>,
+    piece_sprites: Res<PieceSprites>,
+    preview_query: Query<Entity, With<PreviewPiece>>,
+    mut live_query: Query<&mut Visibility, With<BoardPieceComponent>>,
+) {
+    for entity in preview_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    let position = preview.ply.and_then(|ply| history.position(ply));
+    for mut visibility in live_query.iter_mut() {
+        *visibility = if position.is_some() {
+            Visibility::Hidden
+        } else {
+            Visibility::Inherited
+        };
+    }
+    let Some(position) = position else {
+        return;
+    };
+    for (pos, piece) in position.get_pieces() {
+        let pos_transform = board_ui_factory.get_pos_transform(pos);
+        let index = BoardUiFactory::sprite_index(piece.color(), piece.piece_type());
+        commands.spawn((
+            SpriteBundle {
+                sprite: piece_sprites.sprite(),
+                texture: piece_sprites.texture(index),
+                transform: Transform::from_xyz(
+                    pos_transform.translation.x,
+                    pos_transform.translation.y,
+                    pos_transform.translation.z + 1.,
+                ),
+                ..default()
+            },
+            piece_sprites.atlas(index),
+            Pickable::IGNORE,
+            PreviewPiece,
+        ));
+    }
+}
+
+#[cfg(test)]
+mod board_preview_tests {
+    use crate::board_preview::BoardPreview;
+
+    #[test]
+    fn stepping_back_from_the_live_board_shows_the_previous_position() {
+        let mut preview = BoardPreview::default();
+        preview.step(-1, 4);
+        assert_eq!(preview.ply(), Some(3));
+    }
+
+    #[test]
+    fn stepping_stops_at_the_start_position() {
+        let mut preview = BoardPreview::default();
+        preview.jump(0, 4);
+        preview.step(-1, 4);
+        assert_eq!(preview.ply(), Some(0));
+    }
+
+    #[test]
+    fn reaching_the_last_move_returns_to_the_live_board() {
+        let mut preview = BoardPreview::default();
+        preview.jump(2, 4);
+        preview.step(1, 4);
+        assert_eq!(preview.ply(), Some(3));
+        preview.step(1, 4);
+        assert_eq!(preview.ply(), None);
+        preview.step(1, 4);
+        assert_eq!(preview.ply(), None);
+    }
+}
@@ -4,4 +4,6 @@ use crate::board_position::BoardPosition;
 pub struct BoardSideEffects {
     pub takes: Vec<BoardPosition>,
     pub updates: Vec<BoardPiece>,
+    /// Other pieces moved by the move, like the rook when castling.
+    pub moves: Vec<(BoardPosition, BoardPosition)>,
 }
@@ -1,19 +1,70 @@
+use crate::animation::{CaptureAnimation, MoveAnimation, PromotionAnimation};
 use crate::board::CheckerBoard;
+use crate::board_move::BoardMove;
 use crate::board_piece::BoardPiece;
 use crate::board_position::BoardPosition;
 use crate::board_position_marker::BoardPositionMarker;
+use crate::board_side_effects::BoardSideEffects;
 use crate::pieces::color::PieceColor;
 use crate::pieces::piece_type::PieceType;
 use crate::{BoardPieceComponent, WithBoardPosition};
-use bevy::prelude::{BuildChildren, Commands, Component, Entity, Query, Resource, Transform, With};
-use bevy::sprite::TextureAtlas;
+use bevy::prelude::{
+    default, BuildChildren, Commands, Component, Entity, Handle, Image, Query, Resource, Sprite,
+    TextureAtlas, TextureAtlasLayout, Transform, Vec2, Vec3, With,
+};
 use bevy::utils::HashMap;
+use bevy_mod_picking::prelude::Pickable;
+
+/// The textures of the current theme's pieces, by sprite index.
+#[derive(Resource, Clone)]
+pub struct PieceSprites {
+    textures: Vec<Handle<Image>>,
+    layout: Handle<TextureAtlasLayout>,
+    indices: Vec<usize>,
+    size: Vec2,
+}
+
+impl PieceSprites {
+    pub fn new(
+        textures: Vec<Handle<Image>>,
+        layout: Handle<TextureAtlasLayout>,
+        indices: Vec<usize>,
+        size: Vec2,
+    ) -> Self {
+        Self {
+            textures,
+            layout,
+            indices,
+            size,
+        }
+    }
+
+    pub fn texture(&self, index: usize) -> Handle<Image> {
+        self.textures[index].clone()
+    }
+
+    pub fn atlas(&self, index: usize) -> TextureAtlas {
+        TextureAtlas {
+            layout: self.layout.clone(),
+            index: self.indices[index],
+        }
+    }
+
+    /// A sprite drawn at the theme's piece size.
+    pub fn sprite(&self) -> Sprite {
+        Sprite {
+            custom_size: Some(self.size),
+            ..default()
+        }
+    }
+}
 
 #[derive(Resource)]
 pub struct BoardUiFactory {
     pos_width: f32,
     pos_height: f32,
     pub board: CheckerBoard,
+    flipped: bool,
     pos_entities: HashMap<BoardPosition, Entity>,
     piece_entities: HashMap<BoardPosition, Entity>,
 }
@@ -24,18 +75,61 @@ impl BoardUiFactory {
             pos_width,
             pos_height,
             board,
+            flipped: false,
             pos_entities: HashMap::with_capacity(64),
             piece_entities: HashMap::with_capacity(32),
         }
     }
     pub fn get_pos_transform(&self, pos: &BoardPosition) -> Transform {
+        let (x, y) = if self.flipped {
+            (
+                self.board.width() - 1 - pos.x(),
+                self.board.length() - 1 - pos.y(),
+            )
+        } else {
+            (pos.x(), pos.y())
+        };
         Transform::from_xyz(
-            (self.pos_width * (pos.x() as f32 - 4.)) + (self.pos_width / 2.),
-            (self.pos_height * (pos.y() as f32 - 4.)) + (self.pos_height / 2.),
+            (self.pos_width * (x as f32 - 4.)) + (self.pos_width / 2.),
+            (self.pos_height * (y as f32 - 4.)) + (self.pos_height / 2.),
             1.,
         )
     }
 
+    /// Whether black is shown at the bottom of the board.
+    pub fn is_flipped(&self) -> bool {
+        self.flipped
+    }
+
+    pub fn flip(&mut self) {
+        self.flipped = !self.flipped;
+    }
+
+    pub fn set_flipped(&mut self, flipped: bool) {
+        self.flipped = flipped;
+    }
+
+    /// Size of a single square.
+    pub fn pos_size(&self) -> Vec2 {
+        Vec2::new(self.pos_width, self.pos_height)
+    }
+
+    pub fn set_pos_size(&mut self, size: Vec2) {
+        self.pos_width = size.x;
+        self.pos_height = size.y;
+    }
+
+    /// Keeps a dragged piece's centre within the board.
+    pub fn clamp_to_board(&self, translation: Vec3) -> Vec3 {
+        let half_width = self.pos_width * self.board.width() as f32 / 2.;
+        let half_height = self.pos_height * self.board.length() as f32 / 2.;
+        Vec3::new(
+            translation.x.clamp(-half_width, half_width),
+            translation.y.clamp(-half_height, half_height),
+            translation.z,
+        )
+    }
+
     pub fn get_pos_iter(&self) -> impl Iterator<Item = BoardPosition> {
         let mut board_positions =
             Vec::with_capacity((self.board.width() * self.board.length()) as usize);
@@ -74,30 +168,32 @@ impl BoardUiFactory {
     }
 
     pub fn get_sprite_index(&self, pos: &BoardPosition) -> Option<usize> {
-        return match self.board.piece_at(pos) {
-            None => None,
-            Some(piece) => {
-                return match piece.color() {
-                    PieceColor::White => match piece.piece_type() {
-                        PieceType::Pawn => Some(6),
-                        PieceType::Knight => Some(9),
-                        PieceType::King => Some(10),
-                        PieceType::Rook => Some(7),
-                        PieceType::Bishop => Some(8),
-                        PieceType::Queen => Some(11),
-                    },
-                    PieceColor::Black => match piece.piece_type() {
-                        PieceType::Pawn => Some(0),
-                        PieceType::Knight => Some(3),
-                        PieceType::King => Some(4),
-                        PieceType::Rook => Some(1),
-                        PieceType::Bishop => Some(2),
-                        PieceType::Queen => Some(5),
-                    },
-                }
-            }
-        };
+        self.board
+            .piece_at(pos)
+            .map(|piece| Self::sprite_index(piece.color(), piece.piece_type()))
     }
+
+    pub fn sprite_index(color: &PieceColor, piece_type: &PieceType) -> usize {
+        match color {
+            PieceColor::White => match piece_type {
+                PieceType::Pawn => 6,
+                PieceType::Knight => 9,
+                PieceType::King => 10,
+                PieceType::Rook => 7,
+                PieceType::Bishop => 8,
+                PieceType::Queen => 11,
+            },
+            PieceColor::Black => match piece_type {
+                PieceType::Pawn => 0,
+                PieceType::Knight => 3,
+                PieceType::King => 4,
+                PieceType::Rook => 1,
+                PieceType::Bishop => 2,
+                PieceType::Queen => 5,
+            },
+        }
+    }
+
     pub fn get_piece_entity_at(&self, pos: &BoardPosition) -> Option<&Entity> {
         self.piece_entities.get(pos)
     }
@@ -112,34 +208,60 @@ impl BoardUiFactory {
         piece_entity: Entity,
         mut commands: &mut Commands,
         pieces_query: Query<(Entity, &BoardPieceComponent)>,
-        mut texture_query: Query<&mut TextureAtlas>,
         from: Option<BoardPosition>,
         to: Option<BoardPosition>,
-    ) {
-        if let (Some(from), Some(to)) = (from, to) {
-            if !self.board.is_valid_move(&from, &to) {
-                self.move_piece_entity_transform(piece_entity, &mut commands, &from);
-            } else {
-                let side_effects = self.board.move_piece(&from, &to);
-                self.remove_all_taken_pieces(&mut commands, pieces_query, side_effects.takes);
-                self.move_piece_to(piece_entity, &mut commands, &from, &to);
-                self.update_entities_from_side_effects(&mut texture_query, side_effects.updates);
+    ) -> Option<BoardSideEffects> {
+        let (from, to) = (from?, to?);
+        if !self.board.is_valid_move(&from, &to) {
+            self.move_piece_entity_transform(piece_entity, &mut commands, &from);
+            return None;
+        }
+        let side_effects = self.board.move_piece(&from, &to);
+        self.remove_all_taken_pieces(&mut commands, pieces_query, &side_effects.takes);
+        self.move_piece_to(piece_entity, &mut commands, &from, &to);
+        for (moved_from, moved_to) in side_effects.moves.iter() {
+            if let Some(entity) = self.piece_entities.get(moved_from).copied() {
+                self.move_piece_to(entity, &mut commands, moved_from, moved_to);
             }
         }
+        self.update_entities_from_side_effects(&mut commands, &side_effects.updates);
+        Some(side_effects)
+    }
+
+    /// Plays a move that was not made by hand, such as the computer's.
+    pub fn play_move(
+        &mut self,
+        commands: &mut Commands,
+        pieces_query: Query<(Entity, &BoardPieceComponent)>,
+        board_move: &BoardMove,
+    ) -> Option<BoardSideEffects> {
+        let piece_entity = *self.piece_entities.get(board_move.from())?;
+        self.move_pieces(
+            piece_entity,
+            commands,
+            pieces_query,
+            Some(board_move.from().clone()),
+            Some(board_move.to().clone()),
+        )
+    }
+
+    /// Forgets the piece sprites of the previous game.
+    pub fn clear_piece_entities(&mut self) {
+        self.piece_entities.clear();
     }
 
     // not tested
     fn update_entities_from_side_effects(
         &mut self,
-        texture_query: &mut Query<&mut TextureAtlas>,
-        side_effects: Vec<BoardPiece>,
+        commands: &mut Commands,
+        side_effects: &[BoardPiece],
     ) {
         for piece_update in side_effects {
             if let Some(entity) = self.piece_entities.get(piece_update.pos()) {
-                if let Some(mut texture) = texture_query.get_mut(entity.clone()).ok() {
-                    if let Some(index) = self.get_sprite_index(piece_update.pos()) {
-                        texture.index = index;
-                    }
+                if let Some(index) = self.get_sprite_index(piece_update.pos()) {
+                    commands
+                        .entity(*entity)
+                        .insert(PromotionAnimation::new(index));
                 }
             }
         }
@@ -152,8 +274,8 @@ impl BoardUiFactory {
         commands: &mut Commands,
         to: &BoardPosition,
     ) {
-        let transform = self.get_pos_transform(&to);
-        commands.entity(entity).insert(transform);
+        let target = self.get_pos_transform(to).translation + Vec3::Z;
+        commands.entity(entity).insert(MoveAnimation::new(target));
     }
     //not tested
     pub fn move_piece_to(
@@ -175,13 +297,16 @@ impl BoardUiFactory {
         &mut self,
         commands: &mut Commands,
         pieces_query: Query<(Entity, &BoardPieceComponent)>,
-        takes: Vec<BoardPosition>,
+        takes: &[BoardPosition],
     ) {
         for (entity, board_piece) in pieces_query.iter() {
             for takes in takes.iter() {
                 self.piece_entities.remove(takes);
                 if takes == &board_piece.0 {
-                    commands.entity(entity).despawn();
+                    commands
+                        .entity(entity)
+                        .remove::<BoardPieceComponent>()
+                        .insert((CaptureAnimation::default(), Pickable::IGNORE));
                 }
             }
         }
@@ -215,7 +340,7 @@ mod board_positions_test {
     use crate::board_pos;
     use crate::board_position_marker::BoardPositionMarker;
     use crate::board_ui_factory::BoardUiFactory;
-    use bevy::prelude::{App, Transform};
+    use bevy::prelude::{App, Transform, Vec3};
     use std::str::FromStr;
 
     #[test]
@@ -236,6 +361,36 @@ mod board_positions_test {
         assert_eq!(board_vector_pos, Transform::from_xyz(239.75, -252., 1.))
     }
 
+    #[test]
+    fn flipped_board_puts_a8_bottom_right() {
+        let board = CheckerBoard::new();
+        let mut board_ui_factory = create_board_ui_factory(68.5, 72., board);
+        board_ui_factory.flip();
+        assert!(board_ui_factory.is_flipped());
+        assert_eq!(
+            board_ui_factory.get_pos_transform(&board_pos!("a8")),
+            Transform::from_xyz(239.75, -252., 1.)
+        );
+        assert_eq!(
+            board_ui_factory.get_pos_transform(&board_pos!("h1")),
+            Transform::from_xyz(-239.75, 252., 1.)
+        );
+    }
+
+    #[test]
+    fn it_clamps_dragged_pieces_to_the_board() {
+        let board = CheckerBoard::default();
+        let board_ui_factory = create_board_ui_factory(68.5, 72., board);
+        assert_eq!(
+            board_ui_factory.clamp_to_board(Vec3::new(-900., 100., 3.)),
+            Vec3::new(-274., 100., 3.)
+        );
+        assert_eq!(
+            board_ui_factory.clamp_to_board(Vec3::new(10., -500., 3.)),
+            Vec3::new(10., -288., 3.)
+        );
+    }
+
     #[test]
     fn it_creates_all_board_positions() {
         let board = CheckerBoard::new();
@@ -0,0 +1,223 @@
+use crate::analysis::Analysis;
+use crate::board::CheckerBoard;
+use crate::board_position::BoardPosition;
+use crate::board_position_marker::BoardPositionMarker;
+use crate::board_preview::BoardPreview;
+use crate::board_ui_factory::BoardUiFactory;
+use crate::game_setup::{AppState, GameSetup};
+use crate::sound::SoundEffect;
+use crate::{BoardPieceComponent, BoardPosComponent};
+use bevy::prelude::{
+    default, ButtonInput, Color, Commands, Component, DespawnRecursiveExt, Entity, EventReader,
+    EventWriter, KeyCode, Query, Res, ResMut, Resource, Sprite, SpriteBundle, State, With,
+};
+use bevy_mod_picking::prelude::{Click, Pickable, Pointer, PointerButton};
+
+#[derive(Resource, Default)]
+pub struct Selection {
+    pos: Option<BoardPosition>,
+}
+
+impl Selection {
+    pub fn selected(&self) -> Option<&BoardPosition> {
+        self.pos.as_ref()
+    }
+
+    pub fn clear(&mut self) {
+        self.pos = None;
+    }
+
+    /// Handles a click on `pos` and returns the move to try when it completes one.
+    /// Clicking the selected piece again deselects it, clicking another piece
+    /// that may move selects that one instead.
+    pub fn click(
+        &mut self,
+        board: &CheckerBoard,
+        pos: &BoardPosition,
+        any_color: bool,
+    ) -> Option<(BoardPosition, BoardPosition)> {
+        if let Some(from) = self.pos.take() {
+            if &from == pos {
+                return None;
+            }
+            if board.get_possible_moves(&from).contains(pos) {
+                return Some((from, pos.clone()));
+            }
+        }
+        let selectable = board
+            .piece_at(pos)
+            .map(|piece| any_color || piece.color() == board.active_turn())
+            .unwrap_or(false);
+        if selectable {
+            self.pos = Some(pos.clone());
+        }
+        None
+    }
+}
+
+#[derive(Component)]
+pub struct SelectedSquare;
+
+pub fn click_to_move(
+    mut commands: Commands,
+    mut clicks: EventReader<Pointer<Click>>,
+    mut selection: ResMut<Selection>,
+    mut board_ui_factory: ResMut<BoardUiFactory>,
+    analysis: Res<Analysis>,
+    preview: Res<BoardPreview>,
+    state: Res<State<AppState>>,
+    setup: Res<GameSetup>,
+    pos_query: Query<&BoardPosComponent>,
+    pieces_query: Query<(Entity, &BoardPieceComponent)>,
+    mut sounds: EventWriter<SoundEffect>,
+) {
+    let Some(click) = clicks
+        .read()
+        .filter(|click| click.button == PointerButton::Primary)
+        .last()
+    else {
+        return;
+    };
+    if preview.ply().is_some() || !setup.may_move(state.get(), &board_ui_factory.board) {
+        return;
+    }
+    let pos = pos_query
+        .get(click.target)
+        .map(|component| component.0.clone())
+        .or_else(|_| {
+            pieces_query
+                .get(click.target)
+                .map(|(_, piece)| piece.0.clone())
+        });
+    let Ok(pos) = pos else {
+        selection.clear();
+        return;
+    };
+    let attempt = selection.click(&board_ui_factory.board, &pos, analysis.is_enabled());
+    if let Some((from, to)) = attempt {
+        let Some(&piece_entity) = board_ui_factory.get_piece_entity_at(&from) else {
+            return;
+        };
+        analysis.prepare_move(&mut board_ui_factory.board, &from, &to);
+        let side_effects = board_ui_factory.move_pieces(
+            piece_entity,
+            &mut commands,
+            pieces_query,
+            Some(from),
+            Some(to),
+        );
+        if let Some(side_effects) = side_effects {
+            sounds.send(SoundEffect::from_move(
+                &side_effects,
+                &board_ui_factory.board,
+            ));
+        }
+    }
+}
+
+pub fn clear_selection(keys: Res<ButtonInput<KeyCode>>, mut selection: ResMut<Selection>) {
+    if keys.just_pressed(KeyCode::Escape) && selection.selected().is_some() {
+        selection.clear();
+    }
+}
+
+pub fn clear_selection_after_move(mut selection: ResMut<Selection>) {
+    selection.clear();
+}
+
+/// Tints the selected square and marks where the selected piece can go.
+pub fn update_selection_highlight(
+    mut commands: Commands,
+    selection: Res<Selection>,
+    board_ui_factory: Res<BoardUiFactory>,
+    highlight_query: Query<Entity, With<SelectedSquare>>,
+    marker_query: Query<Entity, With<BoardPositionMarker>>,
+) {
+    for entity in highlight_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    BoardUiFactory::remove_all_markers(&mut commands, &marker_query);
+    let Some(pos) = selection.selected() else {
+        return;
+    };
+    let mut transform = board_ui_factory.get_pos_transform(pos);
+    transform.translation.z += 0.5;
+    commands.spawn((
+        SpriteBundle {
+            sprite: Sprite {
+                color: Color::srgba(1., 0.85, 0.2, 0.45),
+                custom_size: Some(board_ui_factory.pos_size()),
+                ..default()
+            },
+            transform,
+            ..default()
+        },
+        Pickable::IGNORE,
+        SelectedSquare,
+    ));
+    board_ui_factory.add_markers_to_possible_board_moves(pos, &mut commands);
+}
+
+#[cfg(test)]
+mod selection_tests {
+    use crate::board::CheckerBoard;
+    use crate::board_pos;
+    use crate::click_to_move::Selection;
+    use std::str::FromStr;
+
+    #[test]
+    fn clicking_a_piece_of_the_side_to_move_selects_it() {
+        let board = CheckerBoard::default();
+        let mut selection = Selection::default();
+        assert_eq!(selection.click(&board, &board_pos!("e2"), false), None);
+        assert_eq!(selection.selected(), Some(&board_pos!("e2")));
+    }
+
+    #[test]
+    fn opponent_pieces_and_empty_squares_are_not_selected() {
+        let board = CheckerBoard::default();
+        let mut selection = Selection::default();
+        selection.click(&board, &board_pos!("e7"), false);
+        assert_eq!(selection.selected(), None);
+        selection.click(&board, &board_pos!("e4"), false);
+        assert_eq!(selection.selected(), None);
+    }
+
+    #[test]
+    fn second_click_on_the_selected_piece_deselects() {
+        let board = CheckerBoard::default();
+        let mut selection = Selection::default();
+        selection.click(&board, &board_pos!("e2"), false);
+        assert_eq!(selection.click(&board, &board_pos!("e2"), false), None);
+        assert_eq!(selection.selected(), None);
+    }
+
+    #[test]
+    fn clicking_a_target_returns_the_move() {
+        let board = CheckerBoard::default();
+        let mut selection = Selection::default();
+        selection.click(&board, &board_pos!("g1"), false);
+        assert_eq!(
+            selection.click(&board, &board_pos!("f3"), false),
+            Some((board_pos!("g1"), board_pos!("f3")))
+        );
+        assert_eq!(selection.selected(), None);
+    }
+
+    #[test]
+    fn clicking_another_own_piece_moves_the_selection() {
+        let board = CheckerBoard::default();
+        let mut selection = Selection::default();
+        selection.click(&board, &board_pos!("g1"), false);
+        selection.click(&board, &board_pos!("b1"), false);
+        assert_eq!(selection.selected(), Some(&board_pos!("b1")));
+    }
+
+    #[test]
+    fn analysis_can_select_either_side() {
+        let board = CheckerBoard::default();
+        let mut selection = Selection::default();
+        selection.click(&board, &board_pos!("e7"), true);
+        assert_eq!(selection.selected(), Some(&board_pos!("e7")));
+    }
+}
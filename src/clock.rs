@@ -0,0 +1,265 @@
+use crate::game_history::GameHistory;
+use crate::game_over::GameOutcome;
+use crate::game_result::GameResult;
+use crate::game_setup::{AppState, GameSetup, Opponent, TimeControl};
+use crate::pieces::color::PieceColor;
+use crate::screen_layout::PanelPlacement;
+use crate::sound::SoundEffect;
+use bevy::prelude::{
+    default, Commands, Component, EventWriter, NextState, PositionType, Query, Res, ResMut,
+    Resource, Style, Text, TextBundle, TextStyle, Time, Val, With,
+};
+use std::time::Duration;
+
+const LOW_TIME: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ClockEvent {
+    LowTime,
+    Flagged,
+}
+
+/// Time left for both sides. Only the side to move loses time and a side
+/// gets its increment once it has moved.
+#[derive(Resource, Debug, Clone, Default)]
+pub struct Clock {
+    /// White and black, in that order; `None` for untimed games.
+    remaining: Option<[Duration; 2]>,
+    increment: Duration,
+    plies: usize,
+    warned: [bool; 2],
+}
+
+impl Clock {
+    pub fn new(time_control: Option<TimeControl>) -> Self {
+        let Some(time_control) = time_control else {
+            return Self::default();
+        };
+        let start = Duration::from_secs(time_control.minutes as u64 * 60);
+        Self {
+            remaining: Some([start; 2]),
+            increment: Duration::from_secs(time_control.increment as u64),
+            plies: 0,
+            warned: [false; 2],
+        }
+    }
+
+    pub fn remaining(&self, color: &PieceColor) -> Option<Duration> {
+        self.remaining
+            .map(|remaining| remaining[Self::index(color)])
+    }
+
+    /// Hands out the increment when a single move was played since the last
+    /// call, which is by the side opposite to `to_move`. Moves loaded all at
+    /// once earn nothing.
+    pub fn moves_played(&mut self, plies: usize, to_move: &PieceColor) {
+        let moved = match to_move {
+            PieceColor::White => PieceColor::Black,
+            PieceColor::Black => PieceColor::White,
+        };
+        if let Some(remaining) = self.remaining.as_mut() {
+            if plies == self.plies + 1 {
+                remaining[Self::index(&moved)] += self.increment;
+            }
+        }
+        self.plies = plies;
+    }
+
+    /// Takes the time left from the game server, which keeps the real clock
+    /// of online games.
+    pub fn sync(&mut self, remaining: [Duration; 2]) {
+        if self.remaining.is_some() {
+            self.remaining = Some(remaining);
+        }
+    }
+
+    /// Puts back the time left in a game picked up again after `plies`
+    /// moves, which earn no increment.
+    pub fn resume(&mut self, remaining: [Duration; 2], plies: usize) {
+        self.sync(remaining);
+        self.plies = plies;
+    }
+
+    /// Runs the clock of `color` and reports when it gets low or runs out.
+    pub fn tick(&mut self, color: &PieceColor, delta: Duration) -> Option<ClockEvent> {
+        let index = Self::index(color);
+        let remaining = self.remaining.as_mut()?;
+        remaining[index] = remaining[index].saturating_sub(delta);
+        if remaining[index].is_zero() {
+            Some(ClockEvent::Flagged)
+        } else if remaining[index] <= LOW_TIME && !self.warned[index] {
+            self.warned[index] = true;
+            Some(ClockEvent::LowTime)
+        } else {
+            None
+        }
+    }
+
+    fn index(color: &PieceColor) -> usize {
+        match color {
+            PieceColor::White => 0,
+            PieceColor::Black => 1,
+        }
+    }
+}
+
+pub fn format_time(time: Duration) -> String {
+    let seconds = time.as_secs();
+    if time < LOW_TIME {
+        format!("{}.{}", seconds, time.subsec_millis() / 100)
+    } else {
+        format!("{}:{:02}", seconds / 60, seconds % 60)
+    }
+}
+
+#[derive(Component)]
+pub struct ClockLabel;
+
+pub fn spawn_clock_label(mut commands: Commands) {
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font_size: 24.,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            left: Val::Px(930.),
+            top: Val::Px(8.),
+            ..default()
+        }),
+        ClockLabel,
+        PanelPlacement::new(930., 8.).narrow(8., 656.),
+    ));
+}
+
+pub fn run_clock(
+    time: Res<Time>,
+    history: Res<GameHistory>,
+    mut clock: ResMut<Clock>,
+    mut outcome: ResMut<GameOutcome>,
+    mut next_state: ResMut<NextState<AppState>>,
+    setup: Res<GameSetup>,
+    mut sounds: EventWriter<SoundEffect>,
+) {
+    let to_move = history.current().active_turn();
+    clock.moves_played(history.len(), to_move);
+    match clock.tick(to_move, time.delta()) {
+        Some(ClockEvent::LowTime) => {
+            sounds.send(SoundEffect::LowTime);
+        }
+        // The server decides when an online game is lost on time.
+        Some(ClockEvent::Flagged) if setup.opponent == Opponent::Online => {}
+        Some(ClockEvent::Flagged) => {
+            let result = match to_move {
+                PieceColor::White => GameResult::BlackWins,
+                PieceColor::Black => GameResult::WhiteWins,
+            };
+            *outcome = GameOutcome::new(result, "on time");
+            next_state.set(AppState::GameOver);
+            sounds.send(SoundEffect::GameEnd);
+        }
+        None => {}
+    }
+}
+
+pub fn update_clock_label(clock: Res<Clock>, mut query: Query<&mut Text, With<ClockLabel>>) {
+    let value = match (
+        clock.remaining(&PieceColor::White),
+        clock.remaining(&PieceColor::Black),
+    ) {
+        (Some(white), Some(black)) => {
+            format!("White {}\nBlack {}", format_time(white), format_time(black))
+        }
+        _ => String::new(),
+    };
+    for mut text in query.iter_mut() {
+        if text.sections[0].value != value {
+            text.sections[0].value = value.clone();
+        }
+    }
+}
+
+#[cfg(test)]
+mod clock_tests {
+    use crate::clock::{format_time, Clock, ClockEvent};
+    use crate::game_setup::TimeControl;
+    use crate::pieces::color::PieceColor;
+    use std::time::Duration;
+
+    #[test]
+    fn untimed_games_have_no_clock() {
+        let mut clock = Clock::new(None);
+        assert_eq!(clock.remaining(&PieceColor::White), None);
+        assert_eq!(
+            clock.tick(&PieceColor::White, Duration::from_secs(1000)),
+            None
+        );
+    }
+
+    #[test]
+    fn only_the_side_to_move_loses_time() {
+        let mut clock = Clock::new(Some(TimeControl::new(1, 0)));
+        clock.tick(&PieceColor::White, Duration::from_secs(5));
+        assert_eq!(
+            clock.remaining(&PieceColor::White),
+            Some(Duration::from_secs(55))
+        );
+        assert_eq!(
+            clock.remaining(&PieceColor::Black),
+            Some(Duration::from_secs(60))
+        );
+    }
+
+    #[test]
+    fn the_side_that_moved_gets_the_increment() {
+        let mut clock = Clock::new(Some(TimeControl::new(3, 2)));
+        clock.moves_played(1, &PieceColor::Black);
+        clock.moves_played(1, &PieceColor::Black);
+        clock.moves_played(5, &PieceColor::Black);
+        assert_eq!(
+            clock.remaining(&PieceColor::White),
+            Some(Duration::from_secs(182))
+        );
+        assert_eq!(
+            clock.remaining(&PieceColor::Black),
+            Some(Duration::from_secs(180))
+        );
+    }
+
+    #[test]
+    fn it_warns_once_on_low_time_and_flags_at_zero() {
+        let mut clock = Clock::new(Some(TimeControl::new(1, 0)));
+        let white = PieceColor::White;
+        assert_eq!(
+            clock.tick(&white, Duration::from_secs(51)),
+            Some(ClockEvent::LowTime)
+        );
+        assert_eq!(clock.tick(&white, Duration::from_secs(1)), None);
+        assert_eq!(
+            clock.tick(&white, Duration::from_secs(9)),
+            Some(ClockEvent::Flagged)
+        );
+    }
+
+    #[test]
+    fn it_shows_tenths_when_time_is_low() {
+        assert_eq!(format_time(Duration::from_secs(125)), "2:05");
+        assert_eq!(format_time(Duration::from_millis(9_450)), "9.4");
+    }
+
+    #[test]
+    fn online_games_take_the_time_from_the_server() {
+        let mut clock = Clock::new(Some(TimeControl::new(1, 0)));
+        clock.sync([Duration::from_secs(30), Duration::from_secs(45)]);
+        assert_eq!(
+            clock.remaining(&PieceColor::Black),
+            Some(Duration::from_secs(45))
+        );
+        let mut untimed = Clock::new(None);
+        untimed.sync([Duration::from_secs(30); 2]);
+        assert_eq!(untimed.remaining(&PieceColor::White), None);
+    }
+}
@@ -0,0 +1,88 @@
+use crate::board_move::BoardMove;
+use crate::board_ui_factory::BoardUiFactory;
+use crate::engine::Engine;
+use crate::game_setup::{GameSetup, Opponent};
+use crate::sound::SoundEffect;
+use crate::tablebase::Tablebase;
+use crate::BoardPieceComponent;
+use bevy::prelude::{Commands, Entity, EventWriter, Query, Res, ResMut, Resource};
+use bevy::tasks::{block_on, poll_once, AsyncComputeTaskPool, Task};
+
+/// The search for the computer's next move, started at `ply`.
+#[derive(Resource, Default)]
+pub struct ComputerPlayer {
+    task: Option<Task<Option<BoardMove>>>,
+    ply: usize,
+}
+
+/// Searches in the background whenever it is the computer's turn and plays
+/// the move it finds, unless the game moved on in the meantime.
+pub fn play_computer_move(
+    mut commands: Commands,
+    setup: Res<GameSetup>,
+    mut computer: ResMut<ComputerPlayer>,
+    mut board_ui_factory: ResMut<BoardUiFactory>,
+    pieces_query: Query<(Entity, &BoardPieceComponent)>,
+    mut sounds: EventWriter<SoundEffect>,
+    tablebase: Res<Tablebase>,
+) {
+    let board = &board_ui_factory.board;
+    if setup.opponent != Opponent::Computer || setup.is_human(board.active_turn()) {
+        if computer.task.is_some() {
+            computer.task = None;
+        }
+        return;
+    }
+    let ply = board.get_moves().len();
+    let Some(task) = computer.task.as_mut() else {
+        // Endgames in the tables are played perfectly, without a search.
+        if let Some(best_move) = tablebase.best_move(board) {
+            play(
+                &mut commands,
+                &mut board_ui_factory,
+                pieces_query,
+                &mut sounds,
+                &best_move,
+            );
+            return;
+        }
+        let board = board.clone();
+        let engine = Engine::new(setup.level);
+        computer.ply = ply;
+        computer.task =
+            Some(AsyncComputeTaskPool::get().spawn(async move { engine.best_move(&board) }));
+        return;
+    };
+    let Some(best_move) = block_on(poll_once(task)) else {
+        return;
+    };
+    computer.task = None;
+    if computer.ply != ply {
+        return;
+    }
+    if let Some(best_move) = best_move {
+        play(
+            &mut commands,
+            &mut board_ui_factory,
+            pieces_query,
+            &mut sounds,
+            &best_move,
+        );
+    }
+}
+
+fn play(
+    commands: &mut Commands,
+    board_ui_factory: &mut BoardUiFactory,
+    pieces_query: Query<(Entity, &BoardPieceComponent)>,
+    sounds: &mut EventWriter<SoundEffect>,
+    best_move: &BoardMove,
+) {
+    let side_effects = board_ui_factory.play_move(commands, pieces_query, best_move);
+    if let Some(side_effects) = side_effects {
+        sounds.send(SoundEffect::from_move(
+            &side_effects,
+            &board_ui_factory.board,
+        ));
+    }
+}
@@ -0,0 +1,70 @@
+use crate::board_position::BoardPosition;
+use crate::board_ui_factory::BoardUiFactory;
+use bevy::prelude::{
+    default, Color, Commands, Component, Query, Res, Text, Text2dBundle, TextStyle, Transform,
+};
+
+const FONT_SIZE: f32 = 14.;
+const INSET: f32 = 8.;
+
+/// A file letter along the bottom edge or a rank number along the left edge.
+#[derive(Component)]
+pub enum CoordinateLabel {
+    File(u8),
+    Rank(u8),
+}
+
+pub fn spawn_coordinate_labels(mut commands: Commands, board_ui_factory: Res<BoardUiFactory>) {
+    let files = (0..board_ui_factory.board.width()).map(CoordinateLabel::File);
+    let ranks = (0..board_ui_factory.board.length()).map(CoordinateLabel::Rank);
+    for label in files.chain(ranks) {
+        let value = match label {
+            CoordinateLabel::File(x) => ((b'a' + x) as char).to_string(),
+            CoordinateLabel::Rank(y) => (y + 1).to_string(),
+        };
+        commands.spawn((
+            Text2dBundle {
+                text: Text::from_section(
+                    value,
+                    TextStyle {
+                        font_size: FONT_SIZE,
+                        color: Color::srgba(0.1, 0.1, 0.1, 0.8),
+                        ..default()
+                    },
+                ),
+                ..default()
+            },
+            label,
+        ));
+    }
+}
+
+/// Files sit in the bottom right corner of the bottom row, ranks in the top
+/// left corner of the left column, whichever way the board is turned.
+pub fn update_coordinate_labels(
+    board_ui_factory: Res<BoardUiFactory>,
+    mut query: Query<(&CoordinateLabel, &mut Transform)>,
+) {
+    let flipped = board_ui_factory.is_flipped();
+    let last_file = board_ui_factory.board.width() - 1;
+    let last_rank = board_ui_factory.board.length() - 1;
+    let half = board_ui_factory.pos_size() / 2.;
+    for (label, mut transform) in query.iter_mut() {
+        let (pos, offset_x, offset_y) = match label {
+            CoordinateLabel::File(x) => {
+                let bottom = if flipped { last_rank } else { 0 };
+                (
+                    BoardPosition::new(*x, bottom),
+                    half.x - INSET,
+                    INSET - half.y,
+                )
+            }
+            CoordinateLabel::Rank(y) => {
+                let left = if flipped { last_file } else { 0 };
+                (BoardPosition::new(left, *y), INSET - half.x, half.y - INSET)
+            }
+        };
+        let square = board_ui_factory.get_pos_transform(&pos).translation;
+        *transform = Transform::from_xyz(square.x + offset_x, square.y + offset_y, 1.5);
+    }
+}
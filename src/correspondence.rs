@@ -0,0 +1,584 @@
+use crate::game_history::GameHistory;
+use crate::game_result::GameResult;
+use crate::game_setup::{AppState, GameSetup, Opponent, StartGame};
+use crate::menu::spawn_button;
+use crate::pgn::{Pgn, PgnError};
+use crate::pieces::color::PieceColor;
+use bevy::log::warn;
+use bevy::prelude::{
+    default, AlignItems, BackgroundColor, BuildChildren, Changed, Color, Commands, Component,
+    DespawnRecursiveExt, Entity, EventWriter, FlexDirection, Interaction, JustifyContent,
+    NextState, NodeBundle, PositionType, Query, Res, ResMut, Resource, StateScoped, Style,
+    TextBundle, TextStyle, Val, With,
+};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use thiserror::Error;
+
+/// Where correspondence games are kept, one PGN file each.
+pub const CORRESPONDENCE_DIR: &str = "correspondence";
+const DAY: u64 = 24 * 60 * 60;
+
+#[derive(Error, Debug)]
+pub enum CorrespondenceError {
+    #[error(transparent)]
+    Pgn(#[from] PgnError),
+    #[error("Missing or invalid tag: {0}")]
+    Tag(String),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+/// Seconds since the Unix epoch, which is how the time of a move is kept.
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.as_secs())
+}
+
+/// A game played one move at a time over days. Besides the moves, its PGN
+/// file keeps the days allowed for a move and when the last one was made.
+#[derive(Clone)]
+pub struct CorrespondenceGame {
+    pub path: PathBuf,
+    pub white: String,
+    pub black: String,
+    pub days_per_move: u32,
+    /// When the last move was made, or the game started, in seconds since
+    /// the Unix epoch.
+    pub last_move_at: u64,
+    pub history: GameHistory,
+    pub result: GameResult,
+    /// How the game ended when it did not end on the board.
+    pub termination: Option<String>,
+}
+
+impl CorrespondenceGame {
+    pub fn new(path: PathBuf, white: &str, black: &str, days_per_move: u32, now: u64) -> Self {
+        Self {
+            path,
+            white: white.to_string(),
+            black: black.to_string(),
+            days_per_move,
+            last_move_at: now,
+            history: GameHistory::default(),
+            result: GameResult::Ongoing,
+            termination: None,
+        }
+    }
+
+    pub fn parse(path: PathBuf, text: &str) -> Result<Self, CorrespondenceError> {
+        let history = Pgn::parse(text)?;
+        let headers = Pgn::parse_headers(text);
+        let header = |name: &str| {
+            headers
+                .iter()
+                .find(|(tag, _)| tag == name)
+                .map(|(_, value)| value.clone())
+                .ok_or_else(|| CorrespondenceError::Tag(name.to_string()))
+        };
+        let invalid = |name: &str| CorrespondenceError::Tag(name.to_string());
+        let result = match header("Result")?.as_str() {
+            "1-0" => GameResult::WhiteWins,
+            "0-1" => GameResult::BlackWins,
+            "1/2-1/2" => GameResult::Draw,
+            _ => GameResult::from_board(history.current()),
+        };
+        Ok(Self {
+            path,
+            white: header("White")?,
+            black: header("Black")?,
+            days_per_move: header("DaysPerMove")?
+                .parse()
+                .map_err(|_| invalid("DaysPerMove"))?,
+            last_move_at: header("LastMove")?
+                .parse()
+                .map_err(|_| invalid("LastMove"))?,
+            history,
+            result,
+            termination: header("Termination").ok(),
+        })
+    }
+
+    pub fn to_pgn(&self) -> Pgn {
+        let mut pgn = Pgn::new(&self.history);
+        pgn.set_header("Event", "Correspondence game");
+        pgn.set_header("White", &self.white);
+        pgn.set_header("Black", &self.black);
+        pgn.set_result(self.result.clone());
+        pgn.set_header("DaysPerMove", &self.days_per_move.to_string());
+        pgn.set_header("LastMove", &self.last_move_at.to_string());
+        if let Some(termination) = &self.termination {
+            pgn.set_header("Termination", termination);
+        }
+        pgn
+    }
+
+    pub fn save(&self) -> Result<(), CorrespondenceError> {
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(&self.path, self.to_pgn().to_string())?;
+        Ok(())
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.result != GameResult::Ongoing
+    }
+
+    pub fn to_move(&self) -> &str {
+        match self.history.current().active_turn() {
+            PieceColor::White => &self.white,
+            PieceColor::Black => &self.black,
+        }
+    }
+
+    /// When the player to move runs out of time.
+    pub fn deadline(&self) -> u64 {
+        self.last_move_at + self.days_per_move as u64 * DAY
+    }
+
+    /// Takes the game on after the next move was made.
+    pub fn play(&mut self, history: GameHistory, now: u64) {
+        self.result = GameResult::from_board(history.current());
+        self.history = history;
+        self.last_move_at = now;
+    }
+
+    /// Ends the game when the player to move let the deadline pass, and
+    /// tells whether it did.
+    pub fn check_time(&mut self, now: u64) -> bool {
+        if self.is_over() || now < self.deadline() {
+            return false;
+        }
+        self.result = match self.history.current().active_turn() {
+            PieceColor::White => GameResult::BlackWins,
+            PieceColor::Black => GameResult::WhiteWins,
+        };
+        self.termination = Some("time forfeit".to_string());
+        true
+    }
+
+    /// A line for the list of games, such as
+    /// `anna against ben, move 12, ben to move, 2 days 3 hours left`.
+    pub fn describe(&self, now: u64) -> String {
+        format!(
+            "{} against {}, move {}, {} to move, {}",
+            self.white,
+            self.black,
+            self.history.len() / 2 + 1,
+            self.to_move(),
+            time_left(self.deadline().saturating_sub(now))
+        )
+    }
+}
+
+fn time_left(seconds: u64) -> String {
+    let days = seconds / DAY;
+    let hours = seconds % DAY / (60 * 60);
+    let plural = |count: u64, unit: &str| match count {
+        1 => format!("1 {}", unit),
+        _ => format!("{} {}s", count, unit),
+    };
+    match (days, hours) {
+        (0, 0) => "under an hour left".to_string(),
+        (0, hours) => format!("{} left", plural(hours, "hour")),
+        (days, 0) => format!("{} left", plural(days, "day")),
+        (days, hours) => format!("{} {} left", plural(days, "day"), plural(hours, "hour")),
+    }
+}
+
+/// The first free file name for a new game in `dir`.
+fn new_game_path(dir: &Path) -> PathBuf {
+    (1..)
+        .map(|number| dir.join(format!("game-{}.pgn", number)))
+        .find(|path| !path.exists())
+        .expect("Some number is free")
+}
+
+/// The correspondence games on disk that are still being played, with the
+/// one whose move is being made.
+#[derive(Resource, Default)]
+pub struct Correspondence {
+    pub games: Vec<CorrespondenceGame>,
+    open: Option<usize>,
+    pub message: String,
+    /// The names typed on the title screen, put aside while a game shows
+    /// the names of its own players.
+    menu_names: Option<(String, String)>,
+}
+
+impl Correspondence {
+    /// Reads every game in `dir`, ending and saving those lost on time. Only
+    /// games still being played are kept, the most urgent first.
+    pub fn load(&mut self, dir: &Path, now: u64) {
+        self.games.clear();
+        self.open = None;
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            return;
+        };
+        for path in entries.flatten().map(|entry| entry.path()) {
+            if path.extension().is_none_or(|extension| extension != "pgn") {
+                continue;
+            }
+            let text = match std::fs::read_to_string(&path) {
+                Ok(text) => text,
+                Err(error) => {
+                    warn!("Could not read {}: {}", path.display(), error);
+                    continue;
+                }
+            };
+            let mut game = match CorrespondenceGame::parse(path.clone(), &text) {
+                Ok(game) => game,
+                Err(error) => {
+                    warn!("Could not read {}: {}", path.display(), error);
+                    continue;
+                }
+            };
+            if game.check_time(now) {
+                let winner = match game.result {
+                    GameResult::WhiteWins => &game.white,
+                    _ => &game.black,
+                };
+                self.message = format!("{} lost on time against {}", game.to_move(), winner);
+                if let Err(error) = game.save() {
+                    warn!("Could not save {}: {}", path.display(), error);
+                }
+            }
+            if !game.is_over() {
+                self.games.push(game);
+            }
+        }
+        self.games.sort_by_key(CorrespondenceGame::deadline);
+    }
+}
+
+/// Opens the list of games at startup when there are moves to make.
+pub fn open_correspondence_on_startup(
+    mut correspondence: ResMut<Correspondence>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    correspondence.load(Path::new(CORRESPONDENCE_DIR), now());
+    if !correspondence.games.is_empty() {
+        next_state.set(AppState::Correspondence);
+    }
+}
+
+/// Reads the games again, as the other player may have moved since.
+pub fn load_correspondence(mut correspondence: ResMut<Correspondence>) {
+    correspondence.load(Path::new(CORRESPONDENCE_DIR), now());
+}
+
+/// Puts the names typed on the title screen back once a game is left, so
+/// they are neither lost nor given to the next new game.
+pub fn restore_menu_names(
+    mut setup: ResMut<GameSetup>,
+    mut correspondence: ResMut<Correspondence>,
+) {
+    if correspondence.menu_names.is_none() {
+        return;
+    }
+    if let Some((white, black)) = correspondence.menu_names.take() {
+        setup.white_name = white;
+        setup.black_name = black;
+    }
+}
+
+/// Saves a correspondence game after each move and goes back to the list,
+/// as only one move is made at a time. Games that end on the board go on to
+/// the game over screen.
+pub fn save_correspondence_move(
+    setup: Res<GameSetup>,
+    history: Res<GameHistory>,
+    mut correspondence: ResMut<Correspondence>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    if setup.opponent != Opponent::Correspondence {
+        return;
+    }
+    let Some(index) = correspondence.open else {
+        return;
+    };
+    let game = &mut correspondence.games[index];
+    let moves = game.history.moves();
+    if history.len() != moves.len() + 1 || !history.moves().starts_with(moves) {
+        return;
+    }
+    game.play(history.clone(), now());
+    let message = match game.save() {
+        Ok(()) if game.is_over() => "Game over".to_string(),
+        Ok(()) => format!("Move saved, {} to move", game.to_move()),
+        Err(error) => format!("Could not save {}: {}", game.path.display(), error),
+    };
+    correspondence.message = message;
+    correspondence.open = None;
+    next_state.set(AppState::Correspondence);
+}
+
+#[derive(Component, Clone, Copy, PartialEq)]
+pub enum CorrespondenceButton {
+    Open(usize),
+    NewGame,
+    DaysPerMove,
+    Back,
+}
+
+/// Holds the buttons for new games, the message and a button for every game.
+#[derive(Component)]
+pub struct CorrespondenceList;
+
+fn days_per_move_label(days: u32) -> String {
+    match days {
+        1 => "1 day per move".to_string(),
+        days => format!("{} days per move", days),
+    }
+}
+
+pub fn spawn_correspondence_screen(mut commands: Commands) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    width: Val::Percent(100.),
+                    height: Val::Percent(100.),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    row_gap: Val::Px(8.),
+                    ..default()
+                },
+                background_color: BackgroundColor(Color::srgb(0.1, 0.1, 0.12)),
+                ..default()
+            },
+            StateScoped(AppState::Correspondence),
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                "Correspondence",
+                TextStyle {
+                    font_size: 48.,
+                    ..default()
+                },
+            ));
+            parent.spawn((
+                NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Column,
+                        align_items: AlignItems::Center,
+                        row_gap: Val::Px(8.),
+                        ..default()
+                    },
+                    ..default()
+                },
+                CorrespondenceList,
+            ));
+        });
+}
+
+/// Lists the games still being played, the most urgent first.
+pub fn update_correspondence_screen(
+    mut commands: Commands,
+    correspondence: Res<Correspondence>,
+    setup: Res<GameSetup>,
+    list_query: Query<Entity, With<CorrespondenceList>>,
+) {
+    let now = now();
+    for list in list_query.iter() {
+        commands.entity(list).despawn_descendants();
+        commands.entity(list).with_children(|parent| {
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Row,
+                        column_gap: Val::Px(8.),
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|parent| {
+                    spawn_button(parent, "New game", CorrespondenceButton::NewGame);
+                    spawn_button(
+                        parent,
+                        &days_per_move_label(setup.days_per_move),
+                        CorrespondenceButton::DaysPerMove,
+                    );
+                    spawn_button(parent, "Back", CorrespondenceButton::Back);
+                });
+            parent.spawn(TextBundle::from_section(
+                correspondence.message.clone(),
+                TextStyle {
+                    font_size: 18.,
+                    ..default()
+                },
+            ));
+            for (index, game) in correspondence.games.iter().enumerate() {
+                spawn_button(
+                    parent,
+                    &game.describe(now),
+                    CorrespondenceButton::Open(index),
+                );
+            }
+        });
+    }
+}
+
+/// New games are between the players named on the title screen, with white
+/// to make the first move right away.
+pub fn correspondence_buttons(
+    query: Query<(&Interaction, &CorrespondenceButton), Changed<Interaction>>,
+    mut setup: ResMut<GameSetup>,
+    mut correspondence: ResMut<Correspondence>,
+    mut start_game: EventWriter<StartGame>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    for (interaction, button) in query.iter() {
+        if interaction != &Interaction::Pressed {
+            continue;
+        }
+        let index = match button {
+            CorrespondenceButton::Open(index) => *index,
+            CorrespondenceButton::NewGame => {
+                let name = |color: PieceColor, side: &str| {
+                    setup
+                        .player_name(&color)
+                        .unwrap_or_else(|| side.to_string())
+                };
+                let game = CorrespondenceGame::new(
+                    new_game_path(Path::new(CORRESPONDENCE_DIR)),
+                    &name(PieceColor::White, "White"),
+                    &name(PieceColor::Black, "Black"),
+                    setup.days_per_move,
+                    now(),
+                );
+                if let Err(error) = game.save() {
+                    correspondence.message =
+                        format!("Could not save {}: {}", game.path.display(), error);
+                    continue;
+                }
+                correspondence.games.push(game);
+                correspondence.games.len() - 1
+            }
+            CorrespondenceButton::DaysPerMove => {
+                setup.next_days_per_move();
+                continue;
+            }
+            CorrespondenceButton::Back => {
+                next_state.set(AppState::Menu);
+                continue;
+            }
+        };
+        if index >= correspondence.games.len() {
+            continue;
+        }
+        if correspondence.menu_names.is_none() {
+            correspondence.menu_names = Some((setup.white_name.clone(), setup.black_name.clone()));
+        }
+        let game = &correspondence.games[index];
+        setup.opponent = Opponent::Correspondence;
+        setup.white_name = game.white.clone();
+        setup.black_name = game.black.clone();
+        start_game.send(StartGame(game.history.clone()));
+        correspondence.open = Some(index);
+        correspondence.message.clear();
+    }
+}
+
+#[cfg(test)]
+mod correspondence_tests {
+    use crate::board::CheckerBoard;
+    use crate::board_pos;
+    use crate::correspondence::{
+        restore_menu_names, time_left, Correspondence, CorrespondenceGame, DAY,
+    };
+    use crate::game_history::GameHistory;
+    use crate::game_result::GameResult;
+    use crate::game_setup::GameSetup;
+    use bevy::ecs::system::RunSystemOnce;
+    use bevy::prelude::World;
+    use std::path::PathBuf;
+    use std::str::FromStr;
+
+    fn opened(now: u64) -> CorrespondenceGame {
+        let mut board = CheckerBoard::default();
+        board.move_piece(&board_pos!("e2"), &board_pos!("e4"));
+        let history = GameHistory::replay(&CheckerBoard::default(), board.get_moves());
+        let mut game = CorrespondenceGame::new(PathBuf::from("game-1.pgn"), "anna", "ben", 3, 0);
+        game.play(history, now);
+        game
+    }
+
+    #[test]
+    fn games_round_trip_through_pgn() {
+        let game = opened(1_000);
+        let text = game.to_pgn().to_string();
+        assert!(text.contains("[DaysPerMove \"3\"]"));
+        let read = CorrespondenceGame::parse(PathBuf::from("game-1.pgn"), &text).unwrap();
+        assert_eq!(read.white, "anna");
+        assert_eq!(read.black, "ben");
+        assert_eq!(read.last_move_at, 1_000);
+        assert_eq!(read.history.sans(), &vec!["e4"]);
+        assert_eq!(read.to_move(), "ben");
+        assert!(!read.is_over());
+        assert!(CorrespondenceGame::parse(PathBuf::new(), "1. e4 *").is_err());
+    }
+
+    #[test]
+    fn missing_the_deadline_loses_the_game() {
+        let mut game = opened(1_000);
+        assert!(!game.check_time(1_000 + 3 * DAY - 1));
+        assert!(game.check_time(1_000 + 3 * DAY));
+        assert_eq!(game.result, GameResult::WhiteWins);
+        let text = game.to_pgn().to_string();
+        assert!(text.contains("[Termination \"time forfeit\"]"));
+        let read = CorrespondenceGame::parse(PathBuf::new(), &text).unwrap();
+        assert!(read.is_over());
+    }
+
+    #[test]
+    fn time_left_is_told_in_days_and_hours() {
+        assert_eq!(time_left(2 * DAY + 3 * 3600 + 5), "2 days 3 hours left");
+        assert_eq!(time_left(DAY), "1 day left");
+        assert_eq!(time_left(3600), "1 hour left");
+        assert_eq!(time_left(59), "under an hour left");
+    }
+
+    #[test]
+    fn only_running_games_are_listed() {
+        let dir = std::env::temp_dir().join(format!("correspondence-{}", std::process::id()));
+        let mut running = opened(1_000);
+        running.path = dir.join("game-1.pgn");
+        let mut late = CorrespondenceGame::new(dir.join("game-2.pgn"), "carl", "dora", 1, 0);
+        running.save().unwrap();
+        late.last_move_at = 500;
+        late.save().unwrap();
+        std::fs::write(dir.join("notes.txt"), "not a game").unwrap();
+        let mut correspondence = Correspondence::default();
+        correspondence.load(&dir, 500 + DAY);
+        assert_eq!(correspondence.games.len(), 1);
+        assert_eq!(correspondence.games[0].white, "anna");
+        assert_eq!(correspondence.message, "carl lost on time against dora");
+        let late = std::fs::read_to_string(dir.join("game-2.pgn")).unwrap();
+        assert!(late.contains("[Result \"0-1\"]"));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn the_title_screen_names_come_back_after_a_game() {
+        let mut world = World::new();
+        world.insert_resource(GameSetup {
+            white_name: "carl".to_string(),
+            black_name: "dora".to_string(),
+            ..GameSetup::default()
+        });
+        world.insert_resource(Correspondence {
+            menu_names: Some(("anna".to_string(), String::new())),
+            ..Correspondence::default()
+        });
+        world.run_system_once(restore_menu_names);
+        let setup = world.resource::<GameSetup>();
+        assert_eq!(setup.white_name, "anna");
+        assert_eq!(setup.black_name, "");
+        assert!(world.resource::<Correspondence>().menu_names.is_none());
+    }
+}
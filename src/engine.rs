@@ -0,0 +1,312 @@
+use crate::board::CheckerBoard;
+use crate::board_move::BoardMove;
+use crate::board_position::BoardPosition;
+use crate::pieces::color::PieceColor;
+use crate::pieces::piece_type::PieceType;
+use std::fmt::Display;
+use std::time::{Duration, Instant};
+
+const MATE: i32 = 100_000;
+const MATE_THRESHOLD: i32 = MATE - 1_000;
+
+/// Evaluation from white's point of view.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Score {
+    Centipawns(i32),
+    /// Moves until mate, negative when black is mating.
+    Mate(i32),
+}
+
+impl Score {
+    fn from_search(score: i32, color: &PieceColor) -> Self {
+        let score = match color {
+            PieceColor::White => score,
+            PieceColor::Black => -score,
+        };
+        if score.abs() < MATE_THRESHOLD {
+            return Score::Centipawns(score);
+        }
+        let moves = (MATE - score.abs() + 1) / 2;
+        Score::Mate(moves * score.signum())
+    }
+}
+
+impl Display for Score {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Score::Centipawns(centipawns) => write!(f, "{:+.2}", *centipawns as f32 / 100.),
+            Score::Mate(moves) => write!(f, "#{}", moves),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PrincipalVariation {
+    pub score: Score,
+    pub moves: Vec<BoardMove>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Engine {
+    depth: u8,
+}
+
+impl Engine {
+    pub fn new(depth: u8) -> Self {
+        Self {
+            depth: depth.max(1),
+        }
+    }
+
+    /// Searches every root move and returns the best `lines`, strongest first.
+    pub fn analyse(&self, board: &CheckerBoard, lines: usize) -> Vec<PrincipalVariation> {
+        let color = board.active_turn().clone();
+        let mut variations: Vec<(i32, Vec<BoardMove>)> =
+            Self::ordered(board, board.get_legal_moves())
+                .into_iter()
+                .map(|board_move| {
+                    let mut next = board.clone();
+                    next.move_piece(board_move.from(), board_move.to());
+                    let (score, mut line) = self.negamax(&next, self.depth - 1, 1, -MATE, MATE);
+                    line.insert(0, board_move);
+                    (-score, line)
+                })
+                .collect();
+        variations.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+        variations
+            .into_iter()
+            .take(lines)
+            .map(|(score, moves)| PrincipalVariation {
+                score: Score::from_search(score, &color),
+                moves,
+            })
+            .collect()
+    }
+
+    pub fn best_move(&self, board: &CheckerBoard) -> Option<BoardMove> {
+        self.analyse(board, 1)
+            .into_iter()
+            .next()
+            .and_then(|variation| variation.moves.into_iter().next())
+    }
+
+    /// Deepens the search one ply at a time, up to the engine's depth, while
+    /// another ply still looks like it fits in `time`.
+    pub fn think(&self, board: &CheckerBoard, time: Duration) -> Option<PrincipalVariation> {
+        let started = Instant::now();
+        let mut best = None;
+        let mut last = Duration::ZERO;
+        for depth in 1..=self.depth {
+            let searched = Instant::now();
+            best = Engine::new(depth)
+                .analyse(board, 1)
+                .into_iter()
+                .next()
+                .or(best);
+            let took = searched.elapsed();
+            // Each ply takes several times as long as the one before, and
+            // the last two show how many.
+            let growth = match last.is_zero() {
+                true => 8.,
+                false => (took.as_secs_f64() / last.as_secs_f64()).max(4.),
+            };
+            if started.elapsed() + took.mul_f64(growth) > time {
+                break;
+            }
+            last = took;
+        }
+        best
+    }
+
+    /// Static evaluation in centipawns from white's point of view.
+    pub fn evaluate(board: &CheckerBoard) -> i32 {
+        board
+            .get_pieces()
+            .map(|(pos, piece)| {
+                let value = Self::piece_value(piece.piece_type())
+                    + Self::position_bonus(piece.piece_type(), piece.color(), pos);
+                match piece.color() {
+                    PieceColor::White => value,
+                    PieceColor::Black => -value,
+                }
+            })
+            .sum()
+    }
+
+    pub fn piece_value(piece_type: &PieceType) -> i32 {
+        match piece_type {
+            PieceType::Pawn => 100,
+            PieceType::Knight => 320,
+            PieceType::Bishop => 330,
+            PieceType::Rook => 500,
+            PieceType::Queen => 900,
+            PieceType::King => 0,
+        }
+    }
+
+    fn position_bonus(piece_type: &PieceType, color: &PieceColor, pos: &BoardPosition) -> i32 {
+        let centre = 14 - (2 * pos.x() as i32 - 7).abs() - (2 * pos.y() as i32 - 7).abs();
+        match piece_type {
+            PieceType::Pawn => {
+                let advance = match color {
+                    PieceColor::White => pos.y() as i32 - 1,
+                    PieceColor::Black => 6 - pos.y() as i32,
+                };
+                let file = 3 - (2 * pos.x() as i32 - 7).abs() / 2;
+                advance * file * 5
+            }
+            PieceType::Knight | PieceType::Bishop => centre * 2,
+            PieceType::Queen => centre,
+            PieceType::Rook | PieceType::King => 0,
+        }
+    }
+
+    fn negamax(
+        &self,
+        board: &CheckerBoard,
+        depth: u8,
+        ply: i32,
+        mut alpha: i32,
+        beta: i32,
+    ) -> (i32, Vec<BoardMove>) {
+        let color = board.active_turn().clone();
+        if depth == 0 {
+            let score = Self::evaluate(board);
+            return match color {
+                PieceColor::White => (score, vec![]),
+                PieceColor::Black => (-score, vec![]),
+            };
+        }
+        let moves = board.get_legal_moves();
+        if moves.is_empty() {
+            if board.is_checked(&color) {
+                return (-MATE + ply, vec![]);
+            }
+            return (0, vec![]);
+        }
+        let mut best: (i32, Vec<BoardMove>) = (-MATE, vec![]);
+        for board_move in Self::ordered(board, moves) {
+            let mut next = board.clone();
+            next.move_piece(board_move.from(), board_move.to());
+            let (score, mut line) = self.negamax(&next, depth - 1, ply + 1, -beta, -alpha);
+            let score = -score;
+            if score > best.0 || best.1.is_empty() {
+                line.insert(0, board_move);
+                best = (score, line);
+            }
+            alpha = alpha.max(score);
+            if alpha >= beta {
+                break;
+            }
+        }
+        best
+    }
+
+    /// Most valuable captures first so alpha-beta cuts early.
+    fn ordered(board: &CheckerBoard, mut moves: Vec<BoardMove>) -> Vec<BoardMove> {
+        moves.sort_by_key(|board_move| {
+            -board
+                .piece_at(board_move.to())
+                .map(|piece| Self::piece_value(piece.piece_type()))
+                .unwrap_or(0)
+        });
+        moves
+    }
+}
+
+#[cfg(test)]
+mod engine_tests {
+    use crate::board::CheckerBoard;
+    use crate::board_piece::BoardPiece;
+    use crate::board_pos;
+    use crate::engine::{Engine, Score};
+    use crate::pieces::color::PieceColor;
+    use crate::pieces::piece_type::PieceType;
+    use std::str::FromStr;
+    use std::time::Duration;
+
+    #[test]
+    fn default_board_is_balanced() {
+        let board = CheckerBoard::default();
+        assert_eq!(Engine::evaluate(&board), 0);
+    }
+
+    #[test]
+    fn extra_material_is_better() {
+        let board = CheckerBoard::with_pieces(vec![
+            BoardPiece::build(PieceType::King, PieceColor::White, "e1"),
+            BoardPiece::build(PieceType::Rook, PieceColor::White, "d4"),
+            BoardPiece::build(PieceType::King, PieceColor::Black, "e8"),
+        ]);
+        assert!(Engine::evaluate(&board) > 400);
+    }
+
+    #[test]
+    fn it_takes_a_free_queen() {
+        let board = CheckerBoard::with_pieces(vec![
+            BoardPiece::build(PieceType::King, PieceColor::White, "h2"),
+            BoardPiece::build(PieceType::Rook, PieceColor::White, "d1"),
+            BoardPiece::build(PieceType::Queen, PieceColor::Black, "d6"),
+            BoardPiece::build(PieceType::King, PieceColor::Black, "h8"),
+        ]);
+        let lines = Engine::new(1).analyse(&board, 1);
+        assert_eq!(lines[0].moves[0].from(), &board_pos!("d1"));
+        assert_eq!(lines[0].moves[0].to(), &board_pos!("d6"));
+    }
+
+    #[test]
+    fn it_finds_mate_in_one() {
+        let board = CheckerBoard::with_pieces(vec![
+            BoardPiece::build(PieceType::Pawn, PieceColor::White, "a7"),
+            BoardPiece::build(PieceType::Pawn, PieceColor::White, "b6"),
+            BoardPiece::build(PieceType::King, PieceColor::White, "a6"),
+            BoardPiece::build(PieceType::King, PieceColor::Black, "a8"),
+        ]);
+        let lines = Engine::new(2).analyse(&board, 1);
+        assert_eq!(lines[0].score, Score::Mate(1));
+        assert_eq!(lines[0].moves[0].to(), &board_pos!("b7"));
+    }
+
+    #[test]
+    fn best_move_is_the_first_move_of_the_best_line() {
+        let board = CheckerBoard::with_pieces(vec![
+            BoardPiece::build(PieceType::King, PieceColor::White, "h2"),
+            BoardPiece::build(PieceType::Rook, PieceColor::White, "d1"),
+            BoardPiece::build(PieceType::Queen, PieceColor::Black, "d6"),
+            BoardPiece::build(PieceType::King, PieceColor::Black, "h8"),
+        ]);
+        let best_move = Engine::new(1).best_move(&board).unwrap();
+        assert_eq!(best_move.to(), &board_pos!("d6"));
+        assert!(Engine::new(1).best_move(&CheckerBoard::new()).is_none());
+    }
+
+    #[test]
+    fn thinking_stops_early_when_time_is_short() {
+        let board = CheckerBoard::with_pieces(vec![
+            BoardPiece::build(PieceType::King, PieceColor::White, "h2"),
+            BoardPiece::build(PieceType::Rook, PieceColor::White, "d1"),
+            BoardPiece::build(PieceType::Queen, PieceColor::Black, "d6"),
+            BoardPiece::build(PieceType::King, PieceColor::Black, "h8"),
+        ]);
+        let line = Engine::new(20).think(&board, Duration::ZERO).unwrap();
+        assert_eq!(line.moves.len(), 1);
+        assert_eq!(line.moves[0].to(), &board_pos!("d6"));
+        assert!(Engine::new(3)
+            .think(&CheckerBoard::new(), Duration::ZERO)
+            .is_none());
+    }
+
+    #[test]
+    fn it_returns_requested_number_of_lines() {
+        let board = CheckerBoard::default();
+        let lines = Engine::new(1).analyse(&board, 3);
+        assert_eq!(lines.len(), 3);
+    }
+
+    #[test]
+    fn score_is_shown_in_pawns() {
+        assert_eq!(Score::Centipawns(35).to_string(), "+0.35");
+        assert_eq!(Score::Centipawns(-120).to_string(), "-1.20");
+        assert_eq!(Score::Mate(-2).to_string(), "#-2");
+    }
+}
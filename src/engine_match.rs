@@ -0,0 +1,1124 @@
+use crate::board::CheckerBoard;
+use crate::board_move::BoardMove;
+use crate::board_position::BoardPosition;
+use crate::engine::{Engine, Score};
+use crate::game_result::GameResult;
+use crate::notation::San;
+use crate::pieces::color::PieceColor;
+use crate::pieces::piece_type::PieceType;
+use crate::protocol::parse_uci;
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use thiserror::Error;
+
+/// The score reported for a mate, in centipawns, less the moves to it.
+const MATE_SCORE: i32 = 100_000;
+
+/// How long an external engine has to start up and answer `isready`.
+const HANDSHAKE: Duration = Duration::from_secs(10);
+
+#[derive(Error, Debug)]
+pub enum MatchError {
+    #[error("Invalid option: {0}")]
+    Invalid(String),
+    #[error("Engine failed: {0}")]
+    Engine(String),
+    #[error("Io error: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+/// One of the two sides of a match: the built-in engine searching at most
+/// `depth` plies, or an external engine speaking UCI.
+#[derive(Debug, Clone, PartialEq)]
+pub enum EngineSpec {
+    BuiltIn { depth: u8 },
+    Uci { command: String },
+}
+
+impl EngineSpec {
+    pub fn start(&self) -> Result<Box<dyn Player>, MatchError> {
+        Ok(match self {
+            EngineSpec::BuiltIn { depth } => Box::new(BuiltInPlayer {
+                engine: Engine::new(*depth),
+            }),
+            EngineSpec::Uci { command } => Box::new(UciPlayer::start(command)?),
+        })
+    }
+}
+
+/// Written as `builtin:<depth>` or `uci:<command line>`.
+impl FromStr for EngineSpec {
+    type Err = MatchError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let invalid = || MatchError::Invalid(format!("engine {}", text));
+        match text.split_once(':') {
+            Some(("builtin", depth)) => Ok(EngineSpec::BuiltIn {
+                depth: depth.parse().map_err(|_| invalid())?,
+            }),
+            Some(("uci", command)) if !command.trim().is_empty() => Ok(EngineSpec::Uci {
+                command: command.trim().to_string(),
+            }),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+impl Display for EngineSpec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EngineSpec::BuiltIn { depth } => write!(f, "builtin:{}", depth),
+            EngineSpec::Uci { command } => write!(f, "uci:{}", command),
+        }
+    }
+}
+
+/// Minutes are too coarse for engine games, so both parts are in seconds,
+/// as in `10+0.1`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TimeControl {
+    pub base: Duration,
+    pub increment: Duration,
+}
+
+impl FromStr for TimeControl {
+    type Err = MatchError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let invalid = || MatchError::Invalid(format!("time control {}", text));
+        let (base, increment) = text.split_once('+').unwrap_or((text, "0"));
+        let seconds = |word: &str| {
+            word.parse::<f64>()
+                .ok()
+                .filter(|seconds| seconds.is_finite() && *seconds >= 0.)
+                .map(Duration::from_secs_f64)
+                .ok_or_else(invalid)
+        };
+        Ok(Self {
+            base: seconds(base)?,
+            increment: seconds(increment)?,
+        })
+    }
+}
+
+impl Display for TimeControl {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}+{}",
+            self.base.as_secs_f64(),
+            self.increment.as_secs_f64()
+        )
+    }
+}
+
+/// When a game is called before it ends on the board. Scores are in
+/// centipawns from the point of view of the side that reports them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Adjudication {
+    /// Both engines agree that one side is this far ahead...
+    pub resign_score: i32,
+    /// ...for this many moves in a row each.
+    pub resign_moves: usize,
+    /// Both engines see the game as level within this...
+    pub draw_score: i32,
+    /// ...for this many moves in a row each...
+    pub draw_moves: usize,
+    /// ...once this many plies have been played.
+    pub draw_after: usize,
+    /// A game this long is a draw, however it stands.
+    pub max_plies: usize,
+}
+
+impl Default for Adjudication {
+    fn default() -> Self {
+        Self {
+            resign_score: 1_000,
+            resign_moves: 3,
+            draw_score: 10,
+            draw_moves: 8,
+            draw_after: 80,
+            max_plies: 400,
+        }
+    }
+}
+
+/// Where a game starts: the standard position or a FEN, followed by the
+/// opening moves in UCI notation.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Opening {
+    pub fen: Option<String>,
+    pub moves: Vec<String>,
+}
+
+impl Opening {
+    fn board(&self) -> Result<CheckerBoard, MatchError> {
+        let mut board = match &self.fen {
+            Some(fen) => CheckerBoard::from_fen(fen)
+                .map_err(|_| MatchError::Invalid(format!("opening {}", fen)))?,
+            None => CheckerBoard::default(),
+        };
+        for uci in self.moves.iter() {
+            play_uci(&mut board, uci)
+                .ok_or_else(|| MatchError::Invalid(format!("opening move {}", uci)))?;
+        }
+        Ok(board)
+    }
+
+    /// Reads an opening suite, one opening a line: either a FEN or EPD, or
+    /// a line of moves in SAN or UCI notation with or without move numbers.
+    /// Lines starting with `#` are comments.
+    pub fn parse_suite(text: &str) -> Result<Vec<Opening>, MatchError> {
+        let mut openings = vec![];
+        for line in text.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if line
+                .split_whitespace()
+                .next()
+                .is_some_and(|word| word.contains('/'))
+            {
+                let fen = line.split_whitespace().take(4).collect::<Vec<&str>>();
+                let opening = Opening {
+                    fen: Some(fen.join(" ")),
+                    moves: vec![],
+                };
+                opening.board()?;
+                openings.push(opening);
+                continue;
+            }
+            let mut board = CheckerBoard::default();
+            let mut moves = vec![];
+            for word in line.split_whitespace() {
+                if ["1-0", "0-1", "1/2-1/2", "*"].contains(&word) {
+                    continue;
+                }
+                let word = word.trim_start_matches(|c: char| c.is_ascii_digit() || c == '.');
+                if word.is_empty() {
+                    continue;
+                }
+                let board_move = San::parse(&board, word)
+                    .or_else(|| {
+                        let (from, to) = parse_uci(word)?;
+                        legal_move(&board, &from, &to)
+                    })
+                    .ok_or_else(|| MatchError::Invalid(format!("opening {}", line)))?;
+                board.move_piece(board_move.from(), board_move.to());
+                moves.push(board_move.to_string());
+            }
+            openings.push(Opening { fen: None, moves });
+        }
+        Ok(openings)
+    }
+}
+
+fn legal_move(board: &CheckerBoard, from: &BoardPosition, to: &BoardPosition) -> Option<BoardMove> {
+    board
+        .get_legal_moves()
+        .into_iter()
+        .find(|board_move| board_move.from() == from && board_move.to() == to)
+}
+
+/// Whether the move promotes to anything but a queen. Engines that do so
+/// lose the game, so the reason is told apart from other illegal moves.
+fn is_underpromotion(uci: &str) -> bool {
+    uci.len() == 5 && uci.ends_with(['r', 'b', 'n'])
+}
+
+/// Plays a move given in UCI notation, if it is legal. Only promotions to a
+/// queen are, as the board knows no other.
+fn play_uci(board: &mut CheckerBoard, uci: &str) -> Option<BoardMove> {
+    let (from, to) = parse_uci(uci)?;
+    let board_move = legal_move(board, &from, &to)?;
+    if board_move.is_promotion() != (uci.len() == 5) {
+        return None;
+    }
+    board.move_piece(&from, &to);
+    Some(board_move)
+}
+
+/// The position an engine is asked about and the time left on the clocks.
+pub struct Position<'a> {
+    pub opening: &'a Opening,
+    /// The moves played since the opening, in UCI notation.
+    pub moves: &'a [String],
+    pub board: &'a CheckerBoard,
+    /// White's time and then Black's.
+    pub clock: [Duration; 2],
+    pub increment: Duration,
+}
+
+impl Position<'_> {
+    /// How long the side to move should think: a share of what is left
+    /// plus most of the increment, never more than half the clock.
+    pub fn budget(&self) -> Duration {
+        let left = self.clock[index(self.board.active_turn())];
+        (left / 30 + self.increment * 4 / 5).min(left / 2)
+    }
+}
+
+/// A move chosen by an engine, with its score in centipawns for the side
+/// that played it, when the engine gave one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reply {
+    pub uci: String,
+    pub score: Option<i32>,
+}
+
+pub trait Player {
+    fn new_game(&mut self) -> Result<(), MatchError>;
+
+    fn go(&mut self, position: &Position) -> Result<Reply, MatchError>;
+}
+
+pub struct BuiltInPlayer {
+    engine: Engine,
+}
+
+impl Player for BuiltInPlayer {
+    fn new_game(&mut self) -> Result<(), MatchError> {
+        Ok(())
+    }
+
+    fn go(&mut self, position: &Position) -> Result<Reply, MatchError> {
+        let line = self
+            .engine
+            .think(position.board, position.budget())
+            .ok_or_else(|| MatchError::Engine("no move found".to_string()))?;
+        let score = match line.score {
+            Score::Centipawns(centipawns) => centipawns,
+            Score::Mate(moves) => (MATE_SCORE - moves.abs()) * moves.signum(),
+        };
+        let score = match position.board.active_turn() {
+            PieceColor::White => score,
+            PieceColor::Black => -score,
+        };
+        Ok(Reply {
+            uci: line.moves[0].to_string(),
+            score: Some(score),
+        })
+    }
+}
+
+/// An engine in another process, spoken to over its standard input and
+/// output. The output is read on a thread of its own so that an engine that
+/// stops answering loses on time rather than hanging the match.
+pub struct UciPlayer {
+    child: Child,
+    input: ChildStdin,
+    output: Receiver<String>,
+}
+
+impl UciPlayer {
+    pub fn start(command: &str) -> Result<Self, MatchError> {
+        let mut words = command.split_whitespace();
+        let program = words
+            .next()
+            .ok_or_else(|| MatchError::Invalid("empty engine command".to_string()))?;
+        let mut child = Command::new(program)
+            .args(words)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+        let input = child.stdin.take().expect("stdin is piped");
+        let stdout = child.stdout.take().expect("stdout is piped");
+        let (sender, output) = channel();
+        std::thread::spawn(move || {
+            for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+        let mut player = Self {
+            child,
+            input,
+            output,
+        };
+        player.send("uci")?;
+        player.wait_for("uciok", HANDSHAKE)?;
+        player.send("isready")?;
+        player.wait_for("readyok", HANDSHAKE)?;
+        Ok(player)
+    }
+
+    fn send(&mut self, line: &str) -> Result<(), MatchError> {
+        writeln!(self.input, "{}", line)?;
+        Ok(self.input.flush()?)
+    }
+
+    /// Reads lines until one starts with `word`, and returns it.
+    fn wait_for(&mut self, word: &str, time: Duration) -> Result<String, MatchError> {
+        let deadline = Instant::now() + time;
+        loop {
+            let left = deadline.saturating_duration_since(Instant::now());
+            match self.output.recv_timeout(left) {
+                Ok(line) if line.split_whitespace().next() == Some(word) => return Ok(line),
+                Ok(_) => {}
+                Err(RecvTimeoutError::Timeout) => {
+                    return Err(MatchError::Engine(format!("no {} in time", word)))
+                }
+                Err(RecvTimeoutError::Disconnected) => {
+                    return Err(MatchError::Engine("the engine quit".to_string()))
+                }
+            }
+        }
+    }
+}
+
+/// The score in an `info` line, in centipawns for the side to move.
+pub fn parse_info_score(line: &str) -> Option<i32> {
+    let words: Vec<&str> = line.split_whitespace().collect();
+    let at = words.iter().position(|word| *word == "score")?;
+    let value: i32 = words.get(at + 2)?.parse().ok()?;
+    match *words.get(at + 1)? {
+        "cp" => Some(value),
+        "mate" => Some((MATE_SCORE - value.abs()) * value.signum()),
+        _ => None,
+    }
+}
+
+impl Player for UciPlayer {
+    fn new_game(&mut self) -> Result<(), MatchError> {
+        self.send("ucinewgame")?;
+        self.send("isready")?;
+        self.wait_for("readyok", HANDSHAKE).map(|_| ())
+    }
+
+    fn go(&mut self, position: &Position) -> Result<Reply, MatchError> {
+        let start = match &position.opening.fen {
+            Some(fen) => format!("fen {}", fen),
+            None => "startpos".to_string(),
+        };
+        let moves = [position.opening.moves.as_slice(), position.moves].concat();
+        if moves.is_empty() {
+            self.send(&format!("position {}", start))?;
+        } else {
+            self.send(&format!("position {} moves {}", start, moves.join(" ")))?;
+        }
+        let [white, black] = position.clock.map(|time| time.as_millis());
+        let increment = position.increment.as_millis();
+        self.send(&format!(
+            "go wtime {} btime {} winc {} binc {}",
+            white, black, increment, increment
+        ))?;
+        let left = position.clock[index(position.board.active_turn())];
+        let deadline = Instant::now() + left + Duration::from_secs(1);
+        let mut score = None;
+        loop {
+            let left = deadline.saturating_duration_since(Instant::now());
+            let line = match self.output.recv_timeout(left) {
+                Ok(line) => line,
+                Err(RecvTimeoutError::Timeout) => {
+                    return Err(MatchError::Engine("no bestmove in time".to_string()))
+                }
+                Err(RecvTimeoutError::Disconnected) => {
+                    return Err(MatchError::Engine("the engine quit".to_string()))
+                }
+            };
+            let mut words = line.split_whitespace();
+            match words.next() {
+                Some("info") => score = parse_info_score(&line).or(score),
+                Some("bestmove") => {
+                    let uci = words.next().unwrap_or_default().to_string();
+                    return Ok(Reply { uci, score });
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+impl Drop for UciPlayer {
+    fn drop(&mut self) {
+        let _ = self.send("quit");
+        std::thread::sleep(Duration::from_millis(50));
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+fn index(color: &PieceColor) -> usize {
+    match color {
+        PieceColor::White => 0,
+        PieceColor::Black => 1,
+    }
+}
+
+/// The result of a game `color` forfeits.
+fn loss_for(color: &PieceColor) -> GameResult {
+    match color {
+        PieceColor::White => GameResult::BlackWins,
+        PieceColor::Black => GameResult::WhiteWins,
+    }
+}
+
+/// How a game of the match went.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GameRecord {
+    pub result: GameResult,
+    pub reason: String,
+    /// The moves played after the opening, in UCI notation.
+    pub moves: Vec<String>,
+}
+
+/// The pieces, the side to move and the squares a pawn could take en
+/// passant on, which is what a repetition compares.
+fn position_key(board: &CheckerBoard) -> String {
+    let fen = board.to_fen();
+    fen.split_whitespace()
+        .take(4)
+        .collect::<Vec<&str>>()
+        .join(" ")
+}
+
+/// Plays a game from `opening` between two players, White first. A player
+/// that fails, runs out of time or makes an illegal move loses. The board
+/// does not count repetitions or quiet moves, so the game does both here.
+pub fn play_game(
+    mut players: [&mut dyn Player; 2],
+    opening: &Opening,
+    time_control: &TimeControl,
+    adjudication: &Adjudication,
+) -> Result<GameRecord, MatchError> {
+    for player in players.iter_mut() {
+        player.new_game()?;
+    }
+    let mut board = opening.board()?;
+    let mut moves: Vec<String> = vec![];
+    let mut clock = [time_control.base; 2];
+    let mut repetitions: HashMap<String, usize> = HashMap::from([(position_key(&board), 1)]);
+    let mut quiet_plies = 0;
+    // The side both engines see as lost and for how many plies in a row.
+    let mut losing: Option<(usize, usize)> = None;
+    let mut drawing = 0;
+    let end = |result: GameResult, reason: &str, moves: Vec<String>| {
+        Ok(GameRecord {
+            result,
+            reason: reason.to_string(),
+            moves,
+        })
+    };
+    loop {
+        let result = GameResult::from_board(&board);
+        if result != GameResult::Ongoing {
+            let reason = match result {
+                GameResult::Draw => "stalemate",
+                _ => "checkmate",
+            };
+            return end(result, reason, moves);
+        }
+        if moves.len() >= adjudication.max_plies {
+            return end(GameResult::Draw, "move limit", moves);
+        }
+        let color = board.active_turn().clone();
+        let side = index(&color);
+        let started = Instant::now();
+        let reply = players[side].go(&Position {
+            opening,
+            moves: &moves,
+            board: &board,
+            clock,
+            increment: time_control.increment,
+        });
+        let elapsed = started.elapsed();
+        let reply = match reply {
+            Ok(reply) => reply,
+            Err(MatchError::Engine(reason)) => return end(loss_for(&color), &reason, moves),
+            Err(error) => return Err(error),
+        };
+        if elapsed > clock[side] {
+            return end(loss_for(&color), "time forfeit", moves);
+        }
+        clock[side] = clock[side] - elapsed + time_control.increment;
+        let capture = parse_uci(&reply.uci).is_some_and(|(_, to)| board.piece_at(&to).is_some());
+        let Some(board_move) = play_uci(&mut board, &reply.uci) else {
+            let reason = match is_underpromotion(&reply.uci) {
+                true => "underpromotion, which the board cannot play",
+                false => "illegal move",
+            };
+            return end(loss_for(&color), reason, moves);
+        };
+        moves.push(reply.uci);
+        quiet_plies = match !capture && board_move.piece_type() != &PieceType::Pawn {
+            true => quiet_plies + 1,
+            false => 0,
+        };
+        let seen = repetitions.entry(position_key(&board)).or_default();
+        *seen += 1;
+        if *seen >= 3 {
+            return end(GameResult::Draw, "repetition", moves);
+        }
+        if quiet_plies >= 100 {
+            return end(GameResult::Draw, "fifty moves", moves);
+        }
+        let Some(score) = reply.score else {
+            losing = None;
+            drawing = 0;
+            continue;
+        };
+        let loser = if score <= -adjudication.resign_score {
+            Some(side)
+        } else if score >= adjudication.resign_score {
+            Some(1 - side)
+        } else {
+            None
+        };
+        losing = match (loser, losing) {
+            (Some(loser), Some((side, plies))) if loser == side => Some((side, plies + 1)),
+            (Some(loser), _) => Some((loser, 1)),
+            (None, _) => None,
+        };
+        if let Some((loser, plies)) = losing {
+            if plies >= 2 * adjudication.resign_moves {
+                let result = match loser {
+                    0 => GameResult::BlackWins,
+                    _ => GameResult::WhiteWins,
+                };
+                return end(result, "adjudication", moves);
+            }
+        }
+        drawing = match score.abs() <= adjudication.draw_score {
+            true => drawing + 1,
+            false => 0,
+        };
+        if moves.len() >= adjudication.draw_after && drawing >= 2 * adjudication.draw_moves {
+            return end(GameResult::Draw, "adjudication", moves);
+        }
+    }
+}
+
+/// Wins, draws and losses of the first engine of a match.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Tally {
+    pub wins: u32,
+    pub draws: u32,
+    pub losses: u32,
+}
+
+/// The expected score of a player rated `elo` above the opponent.
+fn expected_score(elo: f64) -> f64 {
+    1. / (1. + 10f64.powf(-elo / 400.))
+}
+
+fn elo_of_score(score: f64) -> f64 {
+    -400. * (1. / score - 1.).log10()
+}
+
+/// The error function, to within 1.5e-7 (Abramowitz and Stegun 7.1.26).
+fn erf(x: f64) -> f64 {
+    let t = 1. / (1. + 0.3275911 * x.abs());
+    let polynomial = t
+        * (0.254829592
+            + t * (-0.284496736 + t * (1.421413741 + t * (-1.453152027 + t * 1.061405429))));
+    let value = 1. - polynomial * (-x * x).exp();
+    value.copysign(x)
+}
+
+impl Tally {
+    pub fn games(&self) -> u32 {
+        self.wins + self.draws + self.losses
+    }
+
+    pub fn add(&mut self, result: f64) {
+        match result {
+            result if result > 0.5 => self.wins += 1,
+            result if result < 0.5 => self.losses += 1,
+            _ => self.draws += 1,
+        }
+    }
+
+    /// The mean score per game and its variance.
+    fn score(&self) -> (f64, f64) {
+        let games = self.games() as f64;
+        let score = (self.wins as f64 + self.draws as f64 / 2.) / games;
+        let variance = (self.wins as f64 * (1. - score).powi(2)
+            + self.draws as f64 * (0.5 - score).powi(2)
+            + self.losses as f64 * score.powi(2))
+            / games;
+        (score, variance)
+    }
+
+    /// The Elo difference the score stands for and the half width of its
+    /// 95% confidence interval. Neither is finite before both engines have
+    /// scored.
+    pub fn elo(&self) -> (f64, f64) {
+        if self.games() == 0 {
+            return (0., f64::INFINITY);
+        }
+        let (score, variance) = self.score();
+        if score <= 0. || score >= 1. {
+            return (elo_of_score(score), f64::INFINITY);
+        }
+        let error = 1.959964 * (variance / self.games() as f64).sqrt();
+        let bound = |score: f64| elo_of_score(score.clamp(0., 1.));
+        (
+            elo_of_score(score),
+            (bound(score + error) - bound(score - error)) / 2.,
+        )
+    }
+
+    /// The likelihood that the first engine is the stronger one.
+    pub fn los(&self) -> f64 {
+        let decisive = (self.wins + self.losses) as f64;
+        if decisive == 0. {
+            return 0.5;
+        }
+        0.5 * (1. + erf((self.wins as f64 - self.losses as f64) / (2. * decisive).sqrt()))
+    }
+}
+
+impl Display for Tally {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (elo, error) = self.elo();
+        write!(
+            f,
+            "+{} ={} -{}  Elo {:+.1} +/- {:.1}  LOS {:.1}%",
+            self.wins,
+            self.draws,
+            self.losses,
+            elo,
+            error,
+            self.los() * 100.
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Hypothesis {
+    /// The first engine is no stronger than `elo0`.
+    Null,
+    /// The first engine is at least `elo1` stronger.
+    Alternative,
+}
+
+/// A sequential probability ratio test between two Elo differences, which
+/// stops a match as soon as the games played tell them apart.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sprt {
+    pub elo0: f64,
+    pub elo1: f64,
+    /// The chance of accepting the alternative when the null holds.
+    pub alpha: f64,
+    /// The chance of accepting the null when the alternative holds.
+    pub beta: f64,
+}
+
+impl Sprt {
+    /// Below the first the null is accepted, above the second the
+    /// alternative.
+    pub fn bounds(&self) -> (f64, f64) {
+        (
+            (self.beta / (1. - self.alpha)).ln(),
+            ((1. - self.beta) / self.alpha).ln(),
+        )
+    }
+
+    /// The log likelihood ratio of the tally, taking the scores as normally
+    /// distributed.
+    pub fn llr(&self, tally: &Tally) -> f64 {
+        if tally.games() == 0 {
+            return 0.;
+        }
+        let (score, variance) = tally.score();
+        if variance == 0. {
+            return 0.;
+        }
+        let (score0, score1) = (expected_score(self.elo0), expected_score(self.elo1));
+        tally.games() as f64 * (score1 - score0) * (2. * score - score0 - score1) / (2. * variance)
+    }
+
+    pub fn decide(&self, tally: &Tally) -> Option<Hypothesis> {
+        let llr = self.llr(tally);
+        let (lower, upper) = self.bounds();
+        if llr <= lower {
+            Some(Hypothesis::Null)
+        } else if llr >= upper {
+            Some(Hypothesis::Alternative)
+        } else {
+            None
+        }
+    }
+}
+
+/// Everything a match needs. Each opening is played twice, with the engines
+/// swapping colours.
+#[derive(Debug, Clone)]
+pub struct MatchConfig {
+    pub engines: [EngineSpec; 2],
+    pub games: usize,
+    pub openings: Vec<Opening>,
+    pub time_control: TimeControl,
+    pub adjudication: Adjudication,
+    pub concurrency: usize,
+    pub sprt: Option<Sprt>,
+}
+
+/// A finished game of a match.
+#[derive(Debug, Clone)]
+pub struct MatchGame {
+    /// Counted from zero, in the order the games were started.
+    pub number: usize,
+    /// Whether the first engine had White.
+    pub first_is_white: bool,
+    pub opening: Opening,
+    pub record: GameRecord,
+}
+
+impl MatchGame {
+    /// What the first engine scored.
+    pub fn score(&self) -> f64 {
+        let white = match self.record.result {
+            GameResult::WhiteWins => 1.,
+            GameResult::BlackWins => 0.,
+            _ => 0.5,
+        };
+        match self.first_is_white {
+            true => white,
+            false => 1. - white,
+        }
+    }
+}
+
+/// Plays the games of a match on `concurrency` threads, each with its own
+/// pair of engines, and calls `report` with every game as it finishes.
+/// Stops early when the SPRT, if any, comes to a decision.
+pub fn run_match(
+    config: &MatchConfig,
+    mut report: impl FnMut(&MatchGame, &Tally),
+) -> Result<(Tally, Option<Hypothesis>), MatchError> {
+    let openings = match config.openings.is_empty() {
+        true => vec![Opening::default()],
+        false => config.openings.clone(),
+    };
+    let next = Arc::new(AtomicUsize::new(0));
+    let stop = Arc::new(AtomicBool::new(false));
+    let (sender, receiver) = channel();
+    let mut workers = vec![];
+    for _ in 0..config.concurrency.clamp(1, config.games.max(1)) {
+        let (config, openings) = (config.clone(), openings.clone());
+        let (next, stop, sender) = (next.clone(), stop.clone(), sender.clone());
+        workers.push(std::thread::spawn(move || -> Result<(), MatchError> {
+            let mut first = config.engines[0].start()?;
+            let mut second = config.engines[1].start()?;
+            loop {
+                let number = next.fetch_add(1, Ordering::SeqCst);
+                if number >= config.games || stop.load(Ordering::SeqCst) {
+                    return Ok(());
+                }
+                let opening = &openings[number / 2 % openings.len()];
+                let first_is_white = number % 2 == 0;
+                let players: [&mut dyn Player; 2] = match first_is_white {
+                    true => [first.as_mut(), second.as_mut()],
+                    false => [second.as_mut(), first.as_mut()],
+                };
+                let record =
+                    play_game(players, opening, &config.time_control, &config.adjudication)?;
+                let game = MatchGame {
+                    number,
+                    first_is_white,
+                    opening: opening.clone(),
+                    record,
+                };
+                if sender.send(game).is_err() {
+                    return Ok(());
+                }
+            }
+        }));
+    }
+    drop(sender);
+    let mut tally = Tally::default();
+    let mut decision = None;
+    for game in receiver {
+        if decision.is_some() {
+            continue;
+        }
+        tally.add(game.score());
+        report(&game, &tally);
+        decision = config.sprt.and_then(|sprt| sprt.decide(&tally));
+        if decision.is_some() {
+            stop.store(true, Ordering::SeqCst);
+        }
+    }
+    for worker in workers {
+        worker
+            .join()
+            .map_err(|_| MatchError::Engine("a game thread panicked".to_string()))??;
+    }
+    Ok((tally, decision))
+}
+
+#[cfg(test)]
+mod engine_match_tests {
+    use crate::engine_match::{
+        parse_info_score, play_game, run_match, Adjudication, EngineSpec, Hypothesis, MatchConfig,
+        MatchError, Opening, Player, Position, Reply, Sprt, Tally, TimeControl,
+    };
+    use crate::game_result::GameResult;
+    use std::str::FromStr;
+    use std::time::Duration;
+
+    /// Plays the given moves with the given score, then fails.
+    struct Scripted {
+        moves: Vec<(&'static str, Option<i32>)>,
+    }
+
+    impl Player for Scripted {
+        fn new_game(&mut self) -> Result<(), MatchError> {
+            Ok(())
+        }
+
+        fn go(&mut self, _: &Position) -> Result<Reply, MatchError> {
+            if self.moves.is_empty() {
+                return Err(MatchError::Engine("out of moves".to_string()));
+            }
+            let (uci, score) = self.moves.remove(0);
+            Ok(Reply {
+                uci: uci.to_string(),
+                score,
+            })
+        }
+    }
+
+    fn scripted(moves: &[(&'static str, Option<i32>)]) -> Scripted {
+        Scripted {
+            moves: moves.to_vec(),
+        }
+    }
+
+    fn tally(wins: u32, draws: u32, losses: u32) -> Tally {
+        Tally {
+            wins,
+            draws,
+            losses,
+        }
+    }
+
+    #[test]
+    fn options_are_read_from_the_command_line() {
+        assert_eq!(
+            EngineSpec::from_str("builtin:3").unwrap(),
+            EngineSpec::BuiltIn { depth: 3 }
+        );
+        assert_eq!(
+            EngineSpec::from_str("uci:stockfish -q")
+                .unwrap()
+                .to_string(),
+            "uci:stockfish -q"
+        );
+        assert!(EngineSpec::from_str("stockfish").is_err());
+        let time_control = TimeControl::from_str("10+0.1").unwrap();
+        assert_eq!(time_control.base, Duration::from_secs(10));
+        assert_eq!(time_control.increment, Duration::from_millis(100));
+        assert_eq!(time_control.to_string(), "10+0.1");
+        assert!(TimeControl::from_str("-1").is_err());
+    }
+
+    #[test]
+    fn opening_suites_take_fens_and_moves() {
+        let suite = "# Openings\n1. e4 c5 2. Nf3 *\nd2d4 g8f6\n\
+            rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b - - 0 1 c0 \"King pawn\";\n";
+        let openings = Opening::parse_suite(suite).unwrap();
+        assert_eq!(openings.len(), 3);
+        assert_eq!(openings[0].moves, vec!["e2e4", "c7c5", "g1f3"]);
+        assert_eq!(openings[1].moves, vec!["d2d4", "g8f6"]);
+        assert_eq!(
+            openings[2].fen.as_deref(),
+            Some("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b - -")
+        );
+        assert!(Opening::parse_suite("e4 e4").is_err());
+    }
+
+    #[test]
+    fn info_lines_give_the_score_for_the_side_to_move() {
+        assert_eq!(
+            parse_info_score("info depth 12 score cp -35 nodes 1000 pv e2e4"),
+            Some(-35)
+        );
+        assert_eq!(parse_info_score("info depth 9 score mate 3"), Some(99_997));
+        assert_eq!(parse_info_score("info string hello"), None);
+    }
+
+    #[test]
+    fn games_end_on_the_board_or_against_the_rules() {
+        let time_control = TimeControl::from_str("5").unwrap();
+        let adjudication = Adjudication::default();
+        let mut white = scripted(&[("f2f3", None), ("g2g4", None)]);
+        let mut black = scripted(&[("e7e5", None), ("d8h4", None)]);
+        let opening = Opening::default();
+        let record = play_game(
+            [&mut white, &mut black],
+            &opening,
+            &time_control,
+            &adjudication,
+        )
+        .unwrap();
+        assert_eq!(record.result, GameResult::BlackWins);
+        assert_eq!(record.reason, "checkmate");
+        assert_eq!(record.moves.len(), 4);
+        let mut white = scripted(&[("e2e5", None)]);
+        let mut black = scripted(&[]);
+        let record = play_game(
+            [&mut white, &mut black],
+            &opening,
+            &time_control,
+            &adjudication,
+        )
+        .unwrap();
+        assert_eq!(record.result, GameResult::BlackWins);
+        assert_eq!(record.reason, "illegal move");
+        let mut white = scripted(&[
+            ("g1f3", None),
+            ("f3g1", None),
+            ("g1f3", None),
+            ("f3g1", None),
+        ]);
+        let mut black = scripted(&[
+            ("g8f6", None),
+            ("f6g8", None),
+            ("g8f6", None),
+            ("f6g8", None),
+        ]);
+        let record = play_game(
+            [&mut white, &mut black],
+            &opening,
+            &time_control,
+            &adjudication,
+        )
+        .unwrap();
+        assert_eq!(record.result, GameResult::Draw);
+        assert_eq!(record.reason, "repetition");
+    }
+
+    #[test]
+    fn stalemate_needs_the_side_to_move_to_be_stuck() {
+        // After a6 White has no move, but the game goes on as Black has.
+        let opening = Opening {
+            fen: Some("2k5/p7/8/P7/8/7p/4n2P/7K w - - 0 1".to_string()),
+            moves: vec![],
+        };
+        let mut white = scripted(&[("a5a6", None)]);
+        let mut black = scripted(&[("c8b8", None)]);
+        let record = play_game(
+            [&mut white, &mut black],
+            &opening,
+            &TimeControl::from_str("5").unwrap(),
+            &Adjudication::default(),
+        )
+        .unwrap();
+        assert_eq!(record.result, GameResult::Draw);
+        assert_eq!(record.reason, "stalemate");
+        assert_eq!(record.moves, vec!["a5a6", "c8b8"]);
+    }
+
+    #[test]
+    fn underpromotions_forfeit_the_game() {
+        let opening = Opening {
+            fen: Some("8/P6k/8/8/8/8/8/K7 w - - 0 1".to_string()),
+            moves: vec![],
+        };
+        let mut white = scripted(&[("a7a8n", None)]);
+        let mut black = scripted(&[]);
+        let record = play_game(
+            [&mut white, &mut black],
+            &opening,
+            &TimeControl::from_str("5").unwrap(),
+            &Adjudication::default(),
+        )
+        .unwrap();
+        assert_eq!(record.result, GameResult::BlackWins);
+        assert_eq!(record.reason, "underpromotion, which the board cannot play");
+    }
+
+    #[test]
+    fn games_are_adjudicated_when_both_engines_agree() {
+        let adjudication = Adjudication {
+            resign_moves: 2,
+            ..Adjudication::default()
+        };
+        let mut white = scripted(&[("e2e4", Some(-1500)), ("d2d4", Some(-1500))]);
+        let mut black = scripted(&[("e7e5", Some(1500)), ("d7d5", Some(1500))]);
+        let record = play_game(
+            [&mut white, &mut black],
+            &Opening::default(),
+            &TimeControl::from_str("5").unwrap(),
+            &adjudication,
+        )
+        .unwrap();
+        assert_eq!(record.result, GameResult::BlackWins);
+        assert_eq!(record.reason, "adjudication");
+    }
+
+    #[test]
+    fn tallies_give_elo_with_error_bars() {
+        let (elo, error) = tally(6, 0, 4).elo();
+        assert!((elo - 70.4).abs() < 0.1);
+        assert!(error > 100.);
+        assert_eq!(tally(3, 0, 0).elo(), (f64::INFINITY, f64::INFINITY));
+        let (elo, error) = tally(300, 400, 300).elo();
+        assert_eq!(elo, 0.);
+        assert!(error > 10. && error < 20.);
+        assert!((tally(5, 5, 5).los() - 0.5).abs() < 1e-6);
+        assert!(tally(20, 0, 5).los() > 0.99);
+        assert_eq!(
+            tally(2, 1, 0).to_string().split("  ").next(),
+            Some("+2 =1 -0")
+        );
+    }
+
+    #[test]
+    fn sprt_stops_once_the_games_tell_the_hypotheses_apart() {
+        let sprt = Sprt {
+            elo0: 0.,
+            elo1: 10.,
+            alpha: 0.05,
+            beta: 0.05,
+        };
+        let (lower, upper) = sprt.bounds();
+        assert!((upper - 2.944).abs() < 0.001);
+        assert_eq!(lower, -upper);
+        assert_eq!(sprt.decide(&tally(10, 10, 10)), None);
+        assert_eq!(
+            sprt.decide(&tally(800, 400, 600)),
+            Some(Hypothesis::Alternative)
+        );
+        assert_eq!(sprt.decide(&tally(600, 400, 800)), Some(Hypothesis::Null));
+    }
+
+    #[test]
+    fn matches_play_both_colours_of_each_opening() {
+        let config = MatchConfig {
+            engines: [
+                EngineSpec::BuiltIn { depth: 1 },
+                EngineSpec::BuiltIn { depth: 1 },
+            ],
+            games: 4,
+            openings: Opening::parse_suite("e4 e5\nd4 d5").unwrap(),
+            time_control: TimeControl::from_str("10").unwrap(),
+            adjudication: Adjudication {
+                max_plies: 6,
+                ..Adjudication::default()
+            },
+            concurrency: 2,
+            sprt: None,
+        };
+        let mut games = vec![];
+        let (tally, decision) = run_match(&config, |game, _| games.push(game.clone())).unwrap();
+        assert_eq!(tally.games(), 4);
+        assert_eq!(decision, None);
+        games.sort_by_key(|game| game.number);
+        assert!(games[0].first_is_white && !games[1].first_is_white);
+        assert_eq!(games[1].opening, games[0].opening);
+        assert_ne!(games[2].opening, games[0].opening);
+        assert_eq!(games[0].opening.moves, vec!["e2e4", "e7e5"]);
+    }
+}
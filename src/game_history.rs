@@ -0,0 +1,162 @@
+use crate::board::CheckerBoard;
+use crate::board_move::BoardMove;
+use crate::board_ui_factory::BoardUiFactory;
+use crate::notation::San;
+use bevy::prelude::{Res, ResMut, Resource};
+
+/// Every position of a game, from the start position to the current one,
+/// together with the moves between them.
+#[derive(Resource, Clone)]
+pub struct GameHistory {
+    positions: Vec<CheckerBoard>,
+    moves: Vec<BoardMove>,
+    sans: Vec<String>,
+}
+
+impl GameHistory {
+    pub fn new(start: CheckerBoard) -> Self {
+        Self {
+            positions: vec![start],
+            moves: vec![],
+            sans: vec![],
+        }
+    }
+
+    pub fn replay(start: &CheckerBoard, moves: &[BoardMove]) -> Self {
+        let mut history = Self::new(start.clone());
+        for board_move in moves {
+            history.push(board_move.clone());
+        }
+        history
+    }
+
+    /// Plays `board_move` on the current position. Moves made out of turn,
+    /// as analysis mode allows, hand the turn over first.
+    pub fn push(&mut self, board_move: BoardMove) {
+        let current = self.current();
+        let mut next = current.clone();
+        if let Some(piece) = current.piece_at(board_move.from()) {
+            if piece.color() != current.active_turn() {
+                next.pass_turn();
+            }
+        }
+        let san = San::from_move(&next, &board_move);
+        next.move_piece(board_move.from(), board_move.to());
+        self.positions.push(next);
+        self.moves.push(board_move);
+        self.sans.push(san);
+    }
+
+    pub fn current(&self) -> &CheckerBoard {
+        self.positions
+            .last()
+            .expect("Game history always has a start position")
+    }
+
+    pub fn position(&self, ply: usize) -> Option<&CheckerBoard> {
+        self.positions.get(ply)
+    }
+
+    pub fn positions(&self) -> &Vec<CheckerBoard> {
+        &self.positions
+    }
+
+    pub fn moves(&self) -> &Vec<BoardMove> {
+        &self.moves
+    }
+
+    pub fn sans(&self) -> &Vec<String> {
+        &self.sans
+    }
+
+    pub fn len(&self) -> usize {
+        self.moves.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.moves.is_empty()
+    }
+}
+
+impl Default for GameHistory {
+    fn default() -> Self {
+        Self::new(CheckerBoard::default())
+    }
+}
+
+/// Keeps the history in step with the moves played on the live board.
+pub fn update_game_history(
+    board_ui_factory: Res<BoardUiFactory>,
+    mut history: ResMut<GameHistory>,
+) {
+    let moves = board_ui_factory.board.get_moves();
+    if moves == history.moves() {
+        return;
+    }
+    if moves.starts_with(history.moves()) {
+        for board_move in moves[history.len()..].iter() {
+            history.push(board_move.clone());
+        }
+    } else {
+        let start = history.positions()[0].clone();
+        *history = GameHistory::replay(&start, moves);
+    }
+}
+
+#[cfg(test)]
+mod game_history_tests {
+    use crate::board::CheckerBoard;
+    use crate::board_pos;
+    use crate::game_history::GameHistory;
+    use crate::pieces::color::PieceColor;
+    use std::str::FromStr;
+
+    #[test]
+    fn new_history_has_only_the_start_position() {
+        let history = GameHistory::default();
+        assert!(history.is_empty());
+        assert_eq!(history.positions().len(), 1);
+    }
+
+    #[test]
+    fn replay_keeps_every_position() {
+        let mut board = CheckerBoard::default();
+        board.move_piece(&board_pos!("e2"), &board_pos!("e4"));
+        board.move_piece(&board_pos!("e7"), &board_pos!("e5"));
+        board.move_piece(&board_pos!("g1"), &board_pos!("f3"));
+        let history = GameHistory::replay(&CheckerBoard::default(), board.get_moves());
+        assert_eq!(history.len(), 3);
+        assert_eq!(history.positions().len(), 4);
+        assert!(history
+            .position(1)
+            .unwrap()
+            .piece_at(&board_pos!("e4"))
+            .is_some());
+        assert!(history
+            .position(1)
+            .unwrap()
+            .piece_at(&board_pos!("e5"))
+            .is_none());
+        assert_eq!(history.current().to_fen(), board.to_fen());
+    }
+
+    #[test]
+    fn it_records_san() {
+        let mut board = CheckerBoard::default();
+        board.move_piece(&board_pos!("e2"), &board_pos!("e4"));
+        board.move_piece(&board_pos!("e7"), &board_pos!("e5"));
+        board.move_piece(&board_pos!("g1"), &board_pos!("f3"));
+        let history = GameHistory::replay(&CheckerBoard::default(), board.get_moves());
+        assert_eq!(history.sans(), &vec!["e4", "e5", "Nf3"]);
+    }
+
+    #[test]
+    fn replay_follows_out_of_turn_moves() {
+        let mut board = CheckerBoard::default();
+        board.pass_turn();
+        board.move_piece(&board_pos!("e7"), &board_pos!("e5"));
+        let history = GameHistory::replay(&CheckerBoard::default(), board.get_moves());
+        assert_eq!(history.sans(), &vec!["e5"]);
+        assert_eq!(history.current().active_turn(), &PieceColor::White);
+    }
+}
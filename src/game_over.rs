@@ -0,0 +1,221 @@
+use crate::announcements::Announcements;
+use crate::game_history::GameHistory;
+use crate::game_result::GameResult;
+use crate::game_setup::{AppState, GameSetup, Opponent, StartGame};
+use crate::menu::spawn_button;
+use crate::pieces::color::PieceColor;
+use crate::puzzle::Puzzles;
+use crate::screen_layout::PanelPlacement;
+use bevy::prelude::{
+    default, AlignItems, BackgroundColor, BuildChildren, Changed, Color, Commands, Component,
+    EventWriter, FlexDirection, Interaction, JustifyContent, NextState, NodeBundle, PositionType,
+    Query, Res, ResMut, Resource, StateScoped, Style, TextBundle, TextStyle, UiRect, Val,
+};
+use rusty_chess::tournament::{Tournament, TOURNAMENT_PATH};
+
+/// How the last game ended.
+#[derive(Resource, Debug, Clone)]
+pub struct GameOutcome {
+    pub result: GameResult,
+    pub reason: String,
+}
+
+impl GameOutcome {
+    pub fn new(result: GameResult, reason: &str) -> Self {
+        Self {
+            result,
+            reason: reason.to_string(),
+        }
+    }
+
+    /// The outcome of a game that ended on the board, if it did.
+    pub fn from_history(history: &GameHistory) -> Option<Self> {
+        let board = history.current();
+        let result = GameResult::from_board(board);
+        let reason = match result {
+            GameResult::Ongoing => return None,
+            GameResult::Draw => "by stalemate",
+            GameResult::WhiteWins | GameResult::BlackWins => "by checkmate",
+        };
+        Some(Self::new(result, reason))
+    }
+}
+
+impl Default for GameOutcome {
+    fn default() -> Self {
+        Self::new(GameResult::Ongoing, "")
+    }
+}
+
+#[derive(Component, Clone, Copy)]
+pub enum GameOverButton {
+    PlayAgain,
+    NextPuzzle,
+    Analyse,
+    Menu,
+}
+
+/// Ends the game once the position on the board is mate or stalemate.
+/// Puzzles are ended by `follow_puzzle`.
+pub fn detect_game_over(
+    history: Res<GameHistory>,
+    setup: Res<GameSetup>,
+    mut outcome: ResMut<GameOutcome>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    if setup.opponent == Opponent::Puzzle {
+        return;
+    }
+    if let Some(game_outcome) = GameOutcome::from_history(&history) {
+        *outcome = game_outcome;
+        next_state.set(AppState::GameOver);
+    }
+}
+
+/// Enters the result in the club tournament, when one is running and the
+/// two players are paired in its current round.
+pub fn record_tournament_result(
+    outcome: Res<GameOutcome>,
+    setup: Res<GameSetup>,
+    mut announcements: ResMut<Announcements>,
+) {
+    if setup.opponent == Opponent::Puzzle || setup.watching.is_some() {
+        return;
+    }
+    let Ok(mut tournament) = Tournament::load(TOURNAMENT_PATH) else {
+        return;
+    };
+    let (Some(white), Some(black)) = (
+        setup.player_name(&PieceColor::White),
+        setup.player_name(&PieceColor::Black),
+    ) else {
+        return;
+    };
+    let Ok(board) = tournament.record(&white, &black, outcome.result.clone()) else {
+        return;
+    };
+    match tournament.save(TOURNAMENT_PATH) {
+        Ok(()) => announcements.push(format!(
+            "{} recorded in {}, round {} board {}",
+            outcome.result,
+            tournament.name,
+            tournament.played.len(),
+            board
+        )),
+        Err(error) => announcements.push(format!("Could not save the tournament: {}", error)),
+    }
+}
+
+pub fn spawn_game_over_screen(
+    mut commands: Commands,
+    outcome: Res<GameOutcome>,
+    setup: Res<GameSetup>,
+) {
+    let winner = match outcome.result {
+        GameResult::WhiteWins => "White wins",
+        GameResult::BlackWins => "Black wins",
+        GameResult::Draw => "Draw",
+        GameResult::Ongoing => "Game over",
+    };
+    let mut buttons = vec![("Play again", GameOverButton::PlayAgain)];
+    if setup.watching.is_some() {
+        buttons = vec![("Lobby", GameOverButton::PlayAgain)];
+    }
+    if setup.opponent == Opponent::Correspondence {
+        buttons = vec![("Games", GameOverButton::PlayAgain)];
+    }
+    if setup.opponent == Opponent::Puzzle {
+        buttons = vec![
+            ("Try again", GameOverButton::PlayAgain),
+            ("Next puzzle", GameOverButton::NextPuzzle),
+        ];
+    }
+    buttons.push(("Analyse", GameOverButton::Analyse));
+    buttons.push(("Menu", GameOverButton::Menu));
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    left: Val::Px(930.),
+                    top: Val::Px(330.),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Stretch,
+                    justify_content: JustifyContent::Center,
+                    padding: UiRect::all(Val::Px(12.)),
+                    row_gap: Val::Px(8.),
+                    ..default()
+                },
+                background_color: BackgroundColor(Color::srgba(0., 0., 0., 0.8)),
+                ..default()
+            },
+            StateScoped(AppState::GameOver),
+            PanelPlacement::new(930., 330.).narrow(330., 656.),
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                format!("{} {}\n{}", outcome.result, winner, outcome.reason),
+                TextStyle {
+                    font_size: 24.,
+                    ..default()
+                },
+            ));
+            for (label, button) in buttons {
+                spawn_button(parent, label, button);
+            }
+        });
+}
+
+pub fn game_over_buttons(
+    query: Query<(&Interaction, &GameOverButton), Changed<Interaction>>,
+    setup: Res<GameSetup>,
+    mut puzzles: ResMut<Puzzles>,
+    mut start_game: EventWriter<StartGame>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    for (interaction, button) in query.iter() {
+        if interaction != &Interaction::Pressed {
+            continue;
+        }
+        match button {
+            GameOverButton::PlayAgain if setup.opponent == Opponent::Puzzle => {
+                start_game.send_batch(puzzles.restart().map(StartGame));
+            }
+            GameOverButton::PlayAgain if setup.opponent == Opponent::Online => {
+                next_state.set(AppState::Lobby);
+            }
+            GameOverButton::PlayAgain if setup.opponent == Opponent::Correspondence => {
+                next_state.set(AppState::Correspondence);
+            }
+            GameOverButton::PlayAgain => {
+                start_game.send(StartGame(GameHistory::new(setup.variant.start_board())));
+            }
+            GameOverButton::NextPuzzle => {
+                start_game.send_batch(puzzles.next().map(StartGame));
+            }
+            GameOverButton::Analyse => next_state.set(AppState::Analysis),
+            GameOverButton::Menu => next_state.set(AppState::Menu),
+        }
+    }
+}
+
+#[cfg(test)]
+mod game_over_tests {
+    use crate::board::CheckerBoard;
+    use crate::game_history::GameHistory;
+    use crate::game_over::GameOutcome;
+    use crate::game_result::GameResult;
+
+    #[test]
+    fn ongoing_games_have_no_outcome() {
+        assert!(GameOutcome::from_history(&GameHistory::default()).is_none());
+    }
+
+    #[test]
+    fn mate_ends_the_game() {
+        let board = CheckerBoard::from_fen("R5k1/5ppp/8/8/8/8/8/6K1 b - - 0 1").unwrap();
+        let outcome = GameOutcome::from_history(&GameHistory::new(board)).unwrap();
+        assert_eq!(outcome.result, GameResult::WhiteWins);
+        assert_eq!(outcome.reason, "by checkmate");
+    }
+}
@@ -0,0 +1,88 @@
+use crate::board::CheckerBoard;
+use crate::pieces::color::PieceColor;
+use std::fmt::Display;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum GameResult {
+    WhiteWins,
+    BlackWins,
+    Draw,
+    Ongoing,
+}
+
+impl GameResult {
+    pub fn from_board(board: &CheckerBoard) -> Self {
+        if board.is_mated(&PieceColor::White) {
+            GameResult::BlackWins
+        } else if board.is_mated(&PieceColor::Black) {
+            GameResult::WhiteWins
+        } else if board.is_draw() {
+            GameResult::Draw
+        } else {
+            GameResult::Ongoing
+        }
+    }
+}
+
+impl Display for GameResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let result = match self {
+            GameResult::WhiteWins => "1-0",
+            GameResult::BlackWins => "0-1",
+            GameResult::Draw => "1/2-1/2",
+            GameResult::Ongoing => "*",
+        };
+        write!(f, "{}", result)
+    }
+}
+
+#[cfg(test)]
+mod game_result_tests {
+    use crate::board::CheckerBoard;
+    use crate::board_piece::BoardPiece;
+    use crate::board_pos;
+    use crate::game_result::GameResult;
+    use crate::pieces::color::PieceColor;
+    use crate::pieces::piece_type::PieceType;
+    use std::str::FromStr;
+
+    #[test]
+    fn new_game_is_ongoing() {
+        let board = CheckerBoard::default();
+        assert_eq!(GameResult::from_board(&board), GameResult::Ongoing);
+        assert_eq!(GameResult::Ongoing.to_string(), "*");
+    }
+
+    #[test]
+    fn mate_is_a_win() {
+        let pieces = vec![
+            BoardPiece::build(PieceType::Pawn, PieceColor::White, "a7"),
+            BoardPiece::build(PieceType::Pawn, PieceColor::White, "b6"),
+            BoardPiece::build(PieceType::King, PieceColor::White, "a6"),
+            BoardPiece::build(PieceType::King, PieceColor::Black, "a8"),
+        ];
+        let mut board = CheckerBoard::with_pieces(pieces);
+        board.move_piece(&board_pos!("b6"), &board_pos!("b7"));
+        let result = GameResult::from_board(&board);
+        assert_eq!(result, GameResult::WhiteWins);
+        assert_eq!(result.to_string(), "1-0");
+    }
+
+    #[test]
+    fn stalemate_is_a_draw() {
+        let pieces = vec![
+            BoardPiece::build(PieceType::King, PieceColor::White, "h1"),
+            BoardPiece::build(PieceType::Pawn, PieceColor::Black, "h2"),
+            BoardPiece::build(PieceType::King, PieceColor::Black, "g3"),
+        ];
+        let board = CheckerBoard::with_pieces(pieces);
+        assert_eq!(GameResult::from_board(&board).to_string(), "1/2-1/2");
+    }
+
+    #[test]
+    fn a_blocked_side_not_to_move_is_not_stalemate() {
+        // White is stuck, but Black is to move and has plenty of moves.
+        let board = CheckerBoard::from_fen("k5r1/8/8/8/8/7p/7P/7K b - - 0 1").unwrap();
+        assert_eq!(GameResult::from_board(&board), GameResult::Ongoing);
+    }
+}
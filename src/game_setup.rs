@@ -0,0 +1,403 @@
+use crate::board::CheckerBoard;
+use crate::board_position::BoardPosition;
+use crate::game_history::GameHistory;
+use crate::pieces::color::PieceColor;
+use crate::pieces::piece_type::PieceType;
+use crate::protocol::GUEST_NAME;
+use bevy::prelude::{Event, Resource, States};
+use std::fmt::Display;
+
+pub const MAX_LEVEL: u8 = 4;
+
+#[derive(States, Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum AppState {
+    #[default]
+    Menu,
+    Playing,
+    GameOver,
+    Analysis,
+    /// The board is hidden while the device goes to the other player.
+    Handover,
+    /// Looking for an opponent on the game server.
+    Lobby,
+    /// Picking a correspondence game to make the next move in.
+    Correspondence,
+    /// The ladder of each rating category.
+    Ratings,
+}
+
+/// Starts a game from the given history, which is usually a bare start position.
+#[derive(Event, Clone)]
+pub struct StartGame(pub GameHistory);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Opponent {
+    Human,
+    Computer,
+    /// The moves of the other side come from a puzzle's solution.
+    Puzzle,
+    /// Someone playing from another device through the game server.
+    Online,
+    /// Someone who makes their move in a later session, with days to think.
+    Correspondence,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TimeControl {
+    pub minutes: u32,
+    pub increment: u32,
+}
+
+/// The choices of days per move in correspondence games.
+pub const DAYS_PER_MOVE: [u32; 6] = [1, 2, 3, 5, 7, 14];
+
+impl TimeControl {
+    const PRESETS: [Option<TimeControl>; 6] = [
+        None,
+        Some(TimeControl::new(1, 0)),
+        Some(TimeControl::new(3, 2)),
+        Some(TimeControl::new(5, 0)),
+        Some(TimeControl::new(10, 0)),
+        Some(TimeControl::new(15, 10)),
+    ];
+
+    pub const fn new(minutes: u32, increment: u32) -> Self {
+        Self { minutes, increment }
+    }
+}
+
+impl Display for TimeControl {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}+{}", self.minutes, self.increment)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Variant {
+    Standard,
+    /// Only kings and pawns, to practise pawn endgames.
+    KingsAndPawns,
+    NoQueens,
+}
+
+impl Variant {
+    const ALL: [Variant; 3] = [Variant::Standard, Variant::KingsAndPawns, Variant::NoQueens];
+
+    pub fn start_board(&self) -> CheckerBoard {
+        let mut board = CheckerBoard::default();
+        let removed: &[PieceType] = match self {
+            Variant::Standard => &[],
+            Variant::KingsAndPawns => &[
+                PieceType::Knight,
+                PieceType::Rook,
+                PieceType::Bishop,
+                PieceType::Queen,
+            ],
+            Variant::NoQueens => &[PieceType::Queen],
+        };
+        let squares: Vec<BoardPosition> = board
+            .get_pieces()
+            .filter(|(_, piece)| removed.contains(piece.piece_type()))
+            .map(|(pos, _)| pos.clone())
+            .collect();
+        for pos in squares.iter() {
+            board.despawn(pos);
+        }
+        board
+    }
+}
+
+impl Display for Variant {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Variant::Standard => "Standard",
+            Variant::KingsAndPawns => "Kings and pawns",
+            Variant::NoQueens => "No queens",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// The choices made on the title screen.
+#[derive(Resource, Debug, Clone)]
+pub struct GameSetup {
+    pub opponent: Opponent,
+    /// The side the player takes against the computer or in a puzzle.
+    pub player_color: PieceColor,
+    pub level: u8,
+    pub time_control: Option<TimeControl>,
+    pub variant: Variant,
+    /// The names of the people playing white and black, if given.
+    pub white_name: String,
+    pub black_name: String,
+    /// Turns the board to the side to move after every move between two people.
+    pub auto_flip: bool,
+    /// Hides the board between the moves of two people sharing a device.
+    pub pass_device: bool,
+    /// The name of the opponent in online games.
+    pub online_opponent: String,
+    /// The time each player has for a move in new correspondence games.
+    pub days_per_move: u32,
+    /// The white and black players of an online game we only watch, which
+    /// nobody moves at this screen.
+    pub watching: Option<[String; 2]>,
+}
+
+impl GameSetup {
+    pub fn next_opponent(&mut self) {
+        self.opponent = match self.opponent {
+            Opponent::Human => Opponent::Computer,
+            Opponent::Computer => Opponent::Online,
+            Opponent::Online => Opponent::Correspondence,
+            Opponent::Correspondence | Opponent::Puzzle => Opponent::Human,
+        };
+    }
+
+    pub fn next_color(&mut self) {
+        self.player_color = match self.player_color {
+            PieceColor::White => PieceColor::Black,
+            PieceColor::Black => PieceColor::White,
+        };
+    }
+
+    pub fn next_level(&mut self) {
+        self.level = self.level % MAX_LEVEL + 1;
+    }
+
+    pub fn next_time_control(&mut self) {
+        let presets = TimeControl::PRESETS;
+        let index = presets
+            .iter()
+            .position(|preset| preset == &self.time_control)
+            .unwrap_or(0);
+        self.time_control = presets[(index + 1) % presets.len()];
+    }
+
+    pub fn next_days_per_move(&mut self) {
+        let index = DAYS_PER_MOVE
+            .iter()
+            .position(|days| days == &self.days_per_move)
+            .unwrap_or(0);
+        self.days_per_move = DAYS_PER_MOVE[(index + 1) % DAYS_PER_MOVE.len()];
+    }
+
+    pub fn next_variant(&mut self) {
+        let index = Variant::ALL
+            .iter()
+            .position(|variant| variant == &self.variant)
+            .unwrap_or(0);
+        self.variant = Variant::ALL[(index + 1) % Variant::ALL.len()];
+    }
+
+    /// Whether the pieces of `color` are moved by someone at this screen.
+    pub fn is_human(&self, color: &PieceColor) -> bool {
+        let both = matches!(self.opponent, Opponent::Human | Opponent::Correspondence);
+        self.watching.is_none() && (both || color == &self.player_color)
+    }
+
+    /// Whether the board takes moves for the side to move of `board`.
+    pub fn may_move(&self, state: &AppState, board: &CheckerBoard) -> bool {
+        match state {
+            AppState::Playing => self.is_human(board.active_turn()),
+            AppState::Analysis => true,
+            AppState::Menu
+            | AppState::GameOver
+            | AppState::Handover
+            | AppState::Lobby
+            | AppState::Correspondence
+            | AppState::Ratings => false,
+        }
+    }
+
+    /// Black is shown at the bottom when a single player takes black, or
+    /// has the move in a correspondence game.
+    pub fn is_flipped(&self) -> bool {
+        self.opponent != Opponent::Human && self.player_color == PieceColor::Black
+    }
+
+    /// Whether two people take turns at this screen.
+    pub fn is_hot_seat(&self) -> bool {
+        self.opponent == Opponent::Human
+    }
+
+    /// Who plays `color`, for the PGN headers and the screen.
+    pub fn player_name(&self, color: &PieceColor) -> Option<String> {
+        if let Some([white, black]) = &self.watching {
+            return match color {
+                PieceColor::White => Some(white.clone()),
+                PieceColor::Black => Some(black.clone()),
+            };
+        }
+        if !self.is_human(color) {
+            return match self.opponent {
+                Opponent::Online => Some(self.online_opponent.clone()),
+                _ => Some(format!("Rusty Chess level {}", self.level)),
+            };
+        }
+        let name = match color {
+            PieceColor::White => &self.white_name,
+            PieceColor::Black => &self.black_name,
+        };
+        Some(name.trim().to_string()).filter(|name| !name.is_empty())
+    }
+
+    /// The name of the player on the game server, which must be a single
+    /// word. Without one the server gives out a guest name.
+    pub fn online_name(&self) -> String {
+        let name: String = self
+            .player_name(&self.player_color)
+            .unwrap_or_default()
+            .split_whitespace()
+            .collect::<Vec<&str>>()
+            .join("_")
+            .chars()
+            .filter(|c| c.is_alphanumeric() || *c == '_')
+            .take(20)
+            .collect();
+        if name.is_empty() {
+            GUEST_NAME.to_string()
+        } else {
+            name
+        }
+    }
+
+    /// The time control as minutes and increment, with zeros for none.
+    pub fn minutes_and_increment(&self) -> (u32, u32) {
+        self.time_control.map_or((0, 0), |time_control| {
+            (time_control.minutes, time_control.increment)
+        })
+    }
+}
+
+impl Default for GameSetup {
+    fn default() -> Self {
+        Self {
+            opponent: Opponent::Human,
+            player_color: PieceColor::White,
+            level: 2,
+            time_control: None,
+            variant: Variant::Standard,
+            white_name: String::new(),
+            black_name: String::new(),
+            auto_flip: false,
+            pass_device: false,
+            online_opponent: String::new(),
+            days_per_move: 3,
+            watching: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod game_setup_tests {
+    use crate::board::CheckerBoard;
+    use crate::game_setup::{AppState, GameSetup, Opponent, TimeControl, Variant};
+    use crate::pieces::color::PieceColor;
+
+    #[test]
+    fn options_cycle_through_their_values() {
+        let mut setup = GameSetup::default();
+        setup.next_opponent();
+        assert_eq!(setup.opponent, Opponent::Computer);
+        setup.next_opponent();
+        assert_eq!(setup.opponent, Opponent::Online);
+        setup.next_opponent();
+        assert_eq!(setup.opponent, Opponent::Correspondence);
+        setup.next_opponent();
+        assert_eq!(setup.opponent, Opponent::Human);
+        for level in [3, 4, 1] {
+            setup.next_level();
+            assert_eq!(setup.level, level);
+        }
+        setup.next_time_control();
+        assert_eq!(setup.time_control, Some(TimeControl::new(1, 0)));
+        for _ in 0..5 {
+            setup.next_time_control();
+        }
+        assert_eq!(setup.time_control, None);
+        setup.next_variant();
+        assert_eq!(setup.variant, Variant::KingsAndPawns);
+        setup.next_days_per_move();
+        assert_eq!(setup.days_per_move, 5);
+    }
+
+    #[test]
+    fn only_the_players_side_is_moved_by_hand_against_the_computer() {
+        let setup = GameSetup {
+            opponent: Opponent::Computer,
+            player_color: PieceColor::Black,
+            ..Default::default()
+        };
+        let board = CheckerBoard::default();
+        assert!(!setup.may_move(&AppState::Playing, &board));
+        assert!(setup.may_move(&AppState::Analysis, &board));
+        assert!(!setup.may_move(&AppState::GameOver, &board));
+        assert!(setup.is_flipped());
+    }
+
+    #[test]
+    fn players_are_named_in_hot_seat_games() {
+        let mut setup = GameSetup {
+            white_name: "Alice ".to_string(),
+            ..Default::default()
+        };
+        assert!(setup.is_hot_seat());
+        assert_eq!(
+            setup.player_name(&PieceColor::White),
+            Some("Alice".to_string())
+        );
+        assert_eq!(setup.player_name(&PieceColor::Black), None);
+        setup.opponent = Opponent::Computer;
+        assert_eq!(
+            setup.player_name(&PieceColor::Black),
+            Some("Rusty Chess level 2".to_string())
+        );
+        assert!(!setup.may_move(&AppState::Handover, &CheckerBoard::default()));
+    }
+
+    #[test]
+    fn online_names_are_single_words() {
+        let mut setup = GameSetup {
+            opponent: Opponent::Online,
+            player_color: PieceColor::Black,
+            black_name: " Anna Maria!".to_string(),
+            online_opponent: "ben".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(setup.online_name(), "Anna_Maria");
+        assert_eq!(
+            setup.player_name(&PieceColor::White),
+            Some("ben".to_string())
+        );
+        setup.player_color = PieceColor::White;
+        assert_eq!(setup.online_name(), "Guest");
+    }
+
+    #[test]
+    fn watched_games_are_not_moved_at_this_screen() {
+        let setup = GameSetup {
+            opponent: Opponent::Online,
+            watching: Some(["anna".to_string(), "ben".to_string()]),
+            ..Default::default()
+        };
+        let board = CheckerBoard::default();
+        assert!(!setup.may_move(&AppState::Playing, &board));
+        assert!(setup.may_move(&AppState::Analysis, &board));
+        assert_eq!(
+            setup.player_name(&PieceColor::White),
+            Some("anna".to_string())
+        );
+        assert_eq!(
+            setup.player_name(&PieceColor::Black),
+            Some("ben".to_string())
+        );
+    }
+
+    #[test]
+    fn kings_and_pawns_keeps_only_kings_and_pawns() {
+        let board = Variant::KingsAndPawns.start_board();
+        assert_eq!(board.get_pieces().count(), 18);
+        assert_eq!(Variant::NoQueens.start_board().get_pieces().count(), 30);
+        assert_eq!(Variant::Standard.start_board().get_pieces().count(), 32);
+    }
+}
@@ -0,0 +1,162 @@
+use crate::announcements::Announcements;
+use crate::board_ui_factory::BoardUiFactory;
+use crate::game_history::GameHistory;
+use crate::game_setup::{AppState, GameSetup};
+use crate::menu::spawn_button;
+use crate::pieces::color::PieceColor;
+use bevy::prelude::{
+    default, AlignItems, BackgroundColor, BuildChildren, ButtonInput, Changed, Color, Commands,
+    Component, FlexDirection, Interaction, JustifyContent, KeyCode, NextState, NodeBundle,
+    PositionType, Query, Res, ResMut, Resource, StateScoped, Style, TextBundle, TextStyle, Val,
+    With,
+};
+
+#[derive(Component)]
+pub struct ReadyButton;
+
+/// How many moves the game had when the device was last handed over, or
+/// when it started, so that only a new move hands it over again.
+#[derive(Resource, Default)]
+pub struct Handover {
+    pub seen: usize,
+}
+
+/// Turns the board to the side to move in games between two people.
+pub fn auto_flip(
+    setup: Res<GameSetup>,
+    history: Res<GameHistory>,
+    mut board_ui_factory: ResMut<BoardUiFactory>,
+) {
+    if !setup.is_hot_seat() || !setup.auto_flip {
+        return;
+    }
+    let flipped = history.current().active_turn() == &PieceColor::Black;
+    if board_ui_factory.is_flipped() != flipped {
+        board_ui_factory.set_flipped(flipped);
+    }
+}
+
+/// Hides the board after each move when two people share the device.
+pub fn hand_over(
+    setup: Res<GameSetup>,
+    history: Res<GameHistory>,
+    mut handover: ResMut<Handover>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    let moved = history.len() > handover.seen;
+    handover.seen = history.len();
+    if moved && setup.is_hot_seat() && setup.pass_device {
+        next_state.set(AppState::Handover);
+    }
+}
+
+pub fn spawn_handover_screen(
+    mut commands: Commands,
+    setup: Res<GameSetup>,
+    history: Res<GameHistory>,
+    mut announcements: ResMut<Announcements>,
+) {
+    let color = history.current().active_turn();
+    let side = match color {
+        PieceColor::White => "White",
+        PieceColor::Black => "Black",
+    };
+    let message = match setup.player_name(color) {
+        Some(name) => format!("Pass the device to {}, {} to move", name, side),
+        None => format!("Pass the device to {}", side),
+    };
+    announcements.push(message.clone());
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    width: Val::Percent(100.),
+                    height: Val::Percent(100.),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    row_gap: Val::Px(16.),
+                    ..default()
+                },
+                background_color: BackgroundColor(Color::srgb(0.1, 0.1, 0.12)),
+                ..default()
+            },
+            StateScoped(AppState::Handover),
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                message,
+                TextStyle {
+                    font_size: 36.,
+                    ..default()
+                },
+            ));
+            spawn_button(parent, "Ready", ReadyButton);
+        });
+}
+
+/// Shows the board again once the next player is ready.
+pub fn hand_back(
+    keys: Res<ButtonInput<KeyCode>>,
+    query: Query<&Interaction, (Changed<Interaction>, With<ReadyButton>)>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    let pressed = query
+        .iter()
+        .any(|interaction| interaction == &Interaction::Pressed);
+    if pressed || keys.any_just_pressed([KeyCode::Enter, KeyCode::Space]) {
+        next_state.set(AppState::Playing);
+    }
+}
+
+#[cfg(test)]
+mod hot_seat_tests {
+    use crate::board_move::BoardMove;
+    use crate::board_pos;
+    use crate::game_history::GameHistory;
+    use crate::game_setup::{AppState, GameSetup};
+    use crate::hot_seat::{hand_over, Handover};
+    use crate::pieces::piece_type::PieceType;
+    use bevy::ecs::system::RunSystemOnce;
+    use bevy::prelude::{NextState, World};
+    use std::str::FromStr;
+
+    fn hand_over_after(world: &mut World, history: &GameHistory) -> bool {
+        world.insert_resource(history.clone());
+        world.insert_resource(NextState::<AppState>::Unchanged);
+        world.run_system_once(hand_over);
+        matches!(
+            world.resource::<NextState<AppState>>(),
+            NextState::Pending(AppState::Handover)
+        )
+    }
+
+    #[test]
+    fn only_new_moves_hand_the_device_over() {
+        let mut world = World::new();
+        world.insert_resource(GameSetup {
+            pass_device: true,
+            ..GameSetup::default()
+        });
+        let start = GameHistory::default();
+        let mut history = start.clone();
+        history.push(BoardMove::new(
+            PieceType::Pawn,
+            board_pos!("e2"),
+            board_pos!("e4"),
+        ));
+        // A game resumed from its save waits for the next move.
+        world.insert_resource(Handover { seen: 1 });
+        assert!(!hand_over_after(&mut world, &history));
+        history.push(BoardMove::new(
+            PieceType::Pawn,
+            board_pos!("e7"),
+            board_pos!("e5"),
+        ));
+        assert!(hand_over_after(&mut world, &history));
+        assert!(!hand_over_after(&mut world, &history));
+        // Going back is not a move.
+        assert!(!hand_over_after(&mut world, &start));
+    }
+}
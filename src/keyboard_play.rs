@@ -0,0 +1,306 @@
+use crate::analysis::Analysis;
+use crate::announcements::{describe_square, AnnouncementLog, Announcements};
+use crate::board::CheckerBoard;
+use crate::board_move::BoardMove;
+use crate::board_position::BoardPosition;
+use crate::board_preview::BoardPreview;
+use crate::board_ui_factory::BoardUiFactory;
+use crate::click_to_move::Selection;
+use crate::game_setup::{AppState, GameSetup};
+use crate::notation::San;
+use crate::sound::SoundEffect;
+use crate::BoardPieceComponent;
+use bevy::input::keyboard::{Key, KeyboardInput};
+use bevy::input::ButtonState;
+use bevy::prelude::{
+    ButtonInput, Color, Commands, Entity, EventReader, EventWriter, Gizmos, KeyCode, Query, Res,
+    ResMut, Resource, State, Text, With,
+};
+
+/// A square picked with the arrow keys, shown once the board is played
+/// from the keyboard.
+#[derive(Resource)]
+pub struct KeyboardCursor {
+    pos: BoardPosition,
+    visible: bool,
+}
+
+impl KeyboardCursor {
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    /// Moves by `dx` files and `dy` ranks as seen on screen, so up is
+    /// towards the top of the board whichever way it is turned.
+    pub fn step(&mut self, dx: i8, dy: i8, flipped: bool) {
+        let (dx, dy) = if flipped { (-dx, -dy) } else { (dx, dy) };
+        let x = (self.pos.x() as i8 + dx).clamp(0, 7) as u8;
+        let y = (self.pos.y() as i8 + dy).clamp(0, 7) as u8;
+        self.pos = BoardPosition::new(x, y);
+    }
+}
+
+impl Default for KeyboardCursor {
+    fn default() -> Self {
+        Self {
+            pos: BoardPosition::new(4, 1),
+            visible: false,
+        }
+    }
+}
+
+/// The move being typed, while the move box is open.
+#[derive(Resource, Default)]
+pub struct MoveEntry {
+    text: Option<String>,
+}
+
+impl MoveEntry {
+    pub fn is_open(&self) -> bool {
+        self.text.is_some()
+    }
+}
+
+/// Keeps the single key shortcuts from firing while a move is typed.
+pub fn move_entry_closed(entry: Res<MoveEntry>) -> bool {
+    !entry.is_open()
+}
+
+/// Leaves the arrow keys to the move list unless the cursor is in use.
+pub fn keyboard_cursor_hidden(cursor: Res<KeyboardCursor>) -> bool {
+    !cursor.is_visible()
+}
+
+/// The legal move on `board` written in SAN, such as `Nf3`, or in UCI
+/// notation, such as `g1f3`. A promotion may leave out its piece.
+pub fn parse_move(board: &CheckerBoard, text: &str) -> Option<BoardMove> {
+    let text = text.trim();
+    if let Some(board_move) = San::parse(board, text) {
+        return Some(board_move);
+    }
+    let uci = text.to_lowercase();
+    board.get_legal_moves().into_iter().find(|board_move| {
+        let long = board_move.to_string();
+        long == uci || (board_move.is_promotion() && long[..4] == uci)
+    })
+}
+
+/// Plays `board_move` on the live board, as a drop or click would.
+pub fn play(
+    commands: &mut Commands,
+    board_ui_factory: &mut BoardUiFactory,
+    pieces_query: Query<(Entity, &BoardPieceComponent)>,
+    sounds: &mut EventWriter<SoundEffect>,
+    board_move: &BoardMove,
+) {
+    if let Some(side_effects) = board_ui_factory.play_move(commands, pieces_query, board_move) {
+        sounds.send(SoundEffect::from_move(
+            &side_effects,
+            &board_ui_factory.board,
+        ));
+    }
+}
+
+/// Enter shows the cursor, the arrow keys move it and Enter or Space picks
+/// up the piece under it and puts it down again, like clicks do. Escape
+/// hides the cursor.
+pub fn keyboard_cursor(
+    mut commands: Commands,
+    keys: Res<ButtonInput<KeyCode>>,
+    mut cursor: ResMut<KeyboardCursor>,
+    mut selection: ResMut<Selection>,
+    mut board_ui_factory: ResMut<BoardUiFactory>,
+    mut announcements: ResMut<Announcements>,
+    analysis: Res<Analysis>,
+    preview: Res<BoardPreview>,
+    state: Res<State<AppState>>,
+    setup: Res<GameSetup>,
+    pieces_query: Query<(Entity, &BoardPieceComponent)>,
+    mut sounds: EventWriter<SoundEffect>,
+) {
+    let pick = keys.any_just_pressed([KeyCode::Enter, KeyCode::NumpadEnter, KeyCode::Space]);
+    if !cursor.visible {
+        if pick {
+            cursor.visible = true;
+            announcements.push(describe_square(&board_ui_factory.board, &cursor.pos));
+        }
+        return;
+    }
+    if keys.just_pressed(KeyCode::Escape) {
+        cursor.visible = false;
+        return;
+    }
+    let steps = [
+        (KeyCode::ArrowLeft, -1, 0),
+        (KeyCode::ArrowRight, 1, 0),
+        (KeyCode::ArrowUp, 0, 1),
+        (KeyCode::ArrowDown, 0, -1),
+    ];
+    for (key, dx, dy) in steps {
+        if keys.just_pressed(key) {
+            cursor.step(dx, dy, board_ui_factory.is_flipped());
+            announcements.push(describe_square(&board_ui_factory.board, &cursor.pos));
+        }
+    }
+    if !pick || preview.ply().is_some() || !setup.may_move(state.get(), &board_ui_factory.board) {
+        return;
+    }
+    let pos = cursor.pos.clone();
+    let had_selection = selection.selected().is_some();
+    let attempt = selection.click(&board_ui_factory.board, &pos, analysis.is_enabled());
+    match selection.selected() {
+        Some(selected) => announcements.push(format!(
+            "Picked up {}",
+            describe_square(&board_ui_factory.board, selected)
+        )),
+        None if had_selection && attempt.is_none() => {
+            announcements.push("Put down".to_string());
+        }
+        None => {}
+    }
+    let Some((from, to)) = attempt else {
+        return;
+    };
+    analysis.prepare_move(&mut board_ui_factory.board, &from, &to);
+    let board_move = board_ui_factory
+        .board
+        .get_legal_moves()
+        .into_iter()
+        .find(|board_move| board_move.from() == &from && board_move.to() == &to);
+    if let Some(board_move) = board_move {
+        play(
+            &mut commands,
+            &mut board_ui_factory,
+            pieces_query,
+            &mut sounds,
+            &board_move,
+        );
+    }
+}
+
+/// Tab opens a box to type a move in SAN or UCI notation, Enter plays it
+/// and Escape closes the box.
+pub fn type_move(
+    mut commands: Commands,
+    mut keys: EventReader<KeyboardInput>,
+    mut entry: ResMut<MoveEntry>,
+    mut board_ui_factory: ResMut<BoardUiFactory>,
+    mut announcements: ResMut<Announcements>,
+    preview: Res<BoardPreview>,
+    state: Res<State<AppState>>,
+    setup: Res<GameSetup>,
+    pieces_query: Query<(Entity, &BoardPieceComponent)>,
+    mut sounds: EventWriter<SoundEffect>,
+) {
+    let mut submitted = None;
+    for key in keys.read() {
+        if key.state != ButtonState::Pressed {
+            continue;
+        }
+        let Some(text) = entry.text.as_mut() else {
+            if key.logical_key == Key::Tab {
+                entry.text = Some(String::new());
+                announcements.push("Type a move".to_string());
+            }
+            continue;
+        };
+        match &key.logical_key {
+            Key::Character(typed) => text.push_str(typed),
+            Key::Backspace => {
+                text.pop();
+            }
+            Key::Escape | Key::Tab => entry.text = None,
+            Key::Enter => submitted = entry.text.take(),
+            _ => {}
+        }
+    }
+    let Some(text) = submitted else {
+        return;
+    };
+    let board = &board_ui_factory.board;
+    if preview.ply().is_some() || !setup.may_move(state.get(), board) {
+        announcements.push("It is not your move".to_string());
+        return;
+    }
+    match parse_move(board, &text) {
+        Some(board_move) => play(
+            &mut commands,
+            &mut board_ui_factory,
+            pieces_query,
+            &mut sounds,
+            &board_move,
+        ),
+        None => announcements.push(format!("{} is not a legal move", text.trim())),
+    }
+}
+
+pub fn update_move_entry_label(
+    entry: Res<MoveEntry>,
+    mut query: Query<&mut Text, With<AnnouncementLog>>,
+) {
+    let prompt = match &entry.text {
+        Some(text) => format!("Move: {}_\n", text),
+        None => String::new(),
+    };
+    for mut text in query.iter_mut() {
+        text.sections[0].value = prompt.clone();
+    }
+}
+
+pub fn draw_keyboard_cursor(
+    cursor: Res<KeyboardCursor>,
+    board_ui_factory: Res<BoardUiFactory>,
+    mut gizmos: Gizmos,
+) {
+    if !cursor.visible {
+        return;
+    }
+    let center = board_ui_factory.get_pos_transform(&cursor.pos).translation;
+    gizmos.rect_2d(
+        center.truncate(),
+        0.,
+        board_ui_factory.pos_size() - 4.,
+        Color::srgb(0.2, 0.5, 1.),
+    );
+}
+
+#[cfg(test)]
+mod keyboard_play_tests {
+    use crate::board::CheckerBoard;
+    use crate::board_pos;
+    use crate::keyboard_play::{parse_move, KeyboardCursor};
+    use std::str::FromStr;
+
+    #[test]
+    fn moves_are_read_in_san_or_uci() {
+        let board = CheckerBoard::default();
+        let san = parse_move(&board, "Nf3").unwrap();
+        assert_eq!(san.to(), &board_pos!("f3"));
+        let uci = parse_move(&board, " G1F3 ").unwrap();
+        assert_eq!(uci, san);
+        assert!(parse_move(&board, "e5").is_none());
+        assert!(parse_move(&board, "e2e5").is_none());
+        assert!(parse_move(&board, "").is_none());
+    }
+
+    #[test]
+    fn promotions_may_leave_out_the_piece() {
+        let board = CheckerBoard::from_fen("8/4P3/8/8/8/8/k7/4K3 w - - 0 1").unwrap();
+        assert!(parse_move(&board, "e7e8q").is_some());
+        assert!(parse_move(&board, "e7e8").is_some());
+        assert!(parse_move(&board, "e8=Q").is_some());
+    }
+
+    #[test]
+    fn the_cursor_moves_as_seen_on_screen() {
+        let mut cursor = KeyboardCursor::default();
+        cursor.step(0, 1, false);
+        assert_eq!(&cursor.pos, &board_pos!("e3"));
+        cursor.step(0, 1, true);
+        assert_eq!(&cursor.pos, &board_pos!("e2"));
+        for _ in 0..10 {
+            cursor.step(-1, -1, false);
+        }
+        assert_eq!(&cursor.pos, &board_pos!("a1"));
+    }
+}
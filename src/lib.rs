@@ -0,0 +1,16 @@
+pub mod board;
+pub mod board_move;
+pub mod board_piece;
+pub mod board_position;
+pub mod board_side_effects;
+pub mod engine;
+pub mod engine_match;
+pub mod game_result;
+pub mod notation;
+pub mod pieces;
+pub mod protocol;
+pub mod rating;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod server;
+pub mod terminal;
+pub mod tournament;
@@ -0,0 +1,240 @@
+use crate::game_setup::{AppState, GameSetup};
+use crate::menu::spawn_button;
+use crate::online::Online;
+use crate::pieces::color::PieceColor;
+use crate::protocol::{ClientMessage, LiveGame, Seek};
+use bevy::prelude::{
+    default, AlignItems, BackgroundColor, BuildChildren, Changed, Color, Commands, Component,
+    DespawnRecursiveExt, Entity, FlexDirection, Interaction, JustifyContent, NextState, NodeBundle,
+    PositionType, Query, Res, ResMut, StateScoped, Style, Text, TextBundle, TextStyle, Val, With,
+};
+
+#[derive(Component, Clone, PartialEq)]
+pub enum LobbyButton {
+    Seek,
+    Accept(u32),
+    Cancel(u32),
+    Challenge(String),
+    Watch(u32),
+    Back,
+}
+
+#[derive(Component)]
+pub struct LobbyStatusLabel;
+
+/// Holds a button for every seek, player and game in the lobby.
+#[derive(Component)]
+pub struct LobbyList;
+
+fn time_control_name(minutes: u32, increment: u32) -> String {
+    if minutes == 0 && increment == 0 {
+        "unlimited".to_string()
+    } else {
+        format!("{}+{}", minutes, increment)
+    }
+}
+
+fn side_name(color: &PieceColor) -> &'static str {
+    match color {
+        PieceColor::White => "white",
+        PieceColor::Black => "black",
+    }
+}
+
+fn seek_label(seek: &Seek, challenge: bool, own_name: &str) -> String {
+    let time_control = time_control_name(seek.minutes, seek.increment);
+    match (seek.name == own_name, challenge) {
+        (true, true) => format!("Withdraw challenge, {}", time_control),
+        (true, false) => format!("Withdraw seek, {}", time_control),
+        (false, true) => format!(
+            "Accept challenge from {}, {}, {} plays {}",
+            seek.name,
+            time_control,
+            seek.name,
+            side_name(&seek.color)
+        ),
+        (false, false) => format!(
+            "Play {}, {}, {} plays {}",
+            seek.name,
+            time_control,
+            seek.name,
+            side_name(&seek.color)
+        ),
+    }
+}
+
+fn watch_label(game: &LiveGame) -> String {
+    format!(
+        "Watch {} against {}, {}",
+        game.white,
+        game.black,
+        time_control_name(game.minutes, game.increment)
+    )
+}
+
+pub fn spawn_lobby(mut commands: Commands, setup: Res<GameSetup>) {
+    let (minutes, increment) = setup.minutes_and_increment();
+    let seek = format!(
+        "Seek a game, {} as {}",
+        time_control_name(minutes, increment),
+        side_name(&setup.player_color)
+    );
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    width: Val::Percent(100.),
+                    height: Val::Percent(100.),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    row_gap: Val::Px(8.),
+                    ..default()
+                },
+                background_color: BackgroundColor(Color::srgb(0.1, 0.1, 0.12)),
+                ..default()
+            },
+            StateScoped(AppState::Lobby),
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                "Play online",
+                TextStyle {
+                    font_size: 48.,
+                    ..default()
+                },
+            ));
+            parent.spawn((
+                TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font_size: 18.,
+                        ..default()
+                    },
+                ),
+                LobbyStatusLabel,
+            ));
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Row,
+                        column_gap: Val::Px(8.),
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|parent| {
+                    spawn_button(parent, &seek, LobbyButton::Seek);
+                    spawn_button(parent, "Back", LobbyButton::Back);
+                });
+            parent.spawn((
+                NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Column,
+                        align_items: AlignItems::Center,
+                        row_gap: Val::Px(8.),
+                        ..default()
+                    },
+                    ..default()
+                },
+                LobbyList,
+            ));
+        });
+}
+
+/// Lists the open seeks, the players free to challenge and the games
+/// anyone may watch.
+pub fn update_lobby(
+    mut commands: Commands,
+    online: Res<Online>,
+    list_query: Query<Entity, With<LobbyList>>,
+    mut status_query: Query<&mut Text, With<LobbyStatusLabel>>,
+) {
+    for mut text in status_query.iter_mut() {
+        text.sections[0].value = online.status.clone();
+    }
+    for list in list_query.iter() {
+        commands.entity(list).despawn_descendants();
+        commands.entity(list).with_children(|parent| {
+            for (seek, challenge) in online.seeks.iter() {
+                let label = seek_label(seek, *challenge, online.name());
+                let button = if seek.name == online.name() {
+                    LobbyButton::Cancel(seek.id)
+                } else {
+                    LobbyButton::Accept(seek.id)
+                };
+                spawn_button(parent, &label, button);
+            }
+            for player in online.players.iter() {
+                if player != online.name() {
+                    let label = format!("Challenge {}", player);
+                    spawn_button(parent, &label, LobbyButton::Challenge(player.clone()));
+                }
+            }
+            for game in online.games.iter() {
+                spawn_button(parent, &watch_label(game), LobbyButton::Watch(game.id));
+            }
+        });
+    }
+}
+
+/// Seeks and challenges use the time control and side picked on the title
+/// screen.
+pub fn lobby_buttons(
+    query: Query<(&Interaction, &LobbyButton), Changed<Interaction>>,
+    setup: Res<GameSetup>,
+    mut online: ResMut<Online>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    let (minutes, increment) = setup.minutes_and_increment();
+    let color = setup.player_color.clone();
+    for (interaction, button) in query.iter() {
+        if interaction != &Interaction::Pressed {
+            continue;
+        }
+        match button {
+            LobbyButton::Seek => online.send(ClientMessage::Seek {
+                minutes,
+                increment,
+                color: color.clone(),
+            }),
+            LobbyButton::Accept(id) => online.send(ClientMessage::Accept(*id)),
+            LobbyButton::Cancel(id) => online.send(ClientMessage::Cancel(*id)),
+            LobbyButton::Challenge(opponent) => online.send(ClientMessage::Challenge {
+                opponent: opponent.clone(),
+                minutes,
+                increment,
+                color: color.clone(),
+            }),
+            LobbyButton::Watch(id) => online.send(ClientMessage::Watch(*id)),
+            LobbyButton::Back => next_state.set(AppState::Menu),
+        }
+    }
+}
+
+#[cfg(test)]
+mod lobby_tests {
+    use crate::lobby::seek_label;
+    use crate::pieces::color::PieceColor;
+    use crate::protocol::Seek;
+
+    #[test]
+    fn seeks_read_from_the_players_side() {
+        let seek = Seek {
+            id: 1,
+            name: "ben".to_string(),
+            minutes: 0,
+            increment: 0,
+            color: PieceColor::Black,
+        };
+        assert_eq!(
+            seek_label(&seek, false, "anna"),
+            "Play ben, unlimited, ben plays black"
+        );
+        assert_eq!(
+            seek_label(&seek, true, "ben"),
+            "Withdraw challenge, unlimited"
+        );
+    }
+}
@@ -1,36 +1,146 @@
-mod board;
-mod board_move;
-mod board_piece;
-mod board_position;
+mod analysis;
+mod animation;
+mod announcements;
+mod autosave;
+mod board_annotations;
+mod board_orientation;
 mod board_position_marker;
-mod board_side_effects;
+mod board_preview;
 mod board_ui_factory;
-mod pieces;
+mod click_to_move;
+mod clock;
+mod computer_player;
+mod coordinate_labels;
+mod correspondence;
+mod game_history;
+mod game_over;
+mod game_setup;
+mod hot_seat;
+mod keyboard_play;
+mod lobby;
+mod menu;
+mod move_highlights;
+mod move_history_panel;
+mod online;
+mod opening;
+mod opening_label;
+mod pgn;
+mod piece_drag;
+mod puzzle;
+mod ratings;
+mod review;
+mod screen_layout;
+mod server_connection;
+mod settings;
+mod sound;
+mod storage;
+mod svg_loader;
+mod tablebase;
+mod tablebase_label;
+mod theme;
 
+#[cfg(test)]
+use rusty_chess::board_pos;
+use rusty_chess::{
+    board, board_move, board_piece, board_position, board_side_effects, engine, game_result,
+    notation, pieces, protocol,
+};
+
+use crate::analysis::{
+    draw_best_move_arrow, restart_analysis, run_analysis, spawn_analysis_panel, start_analysis,
+    stop_analysis, toggle_analysis, update_analysis_panel, Analysis,
+};
+use crate::animation::{
+    animate_captures, animate_moves, animate_promotions, CaptureAnimation, MoveAnimation,
+};
+use crate::announcements::{
+    announce_game_over, announce_moves, spawn_announcement_log, update_announcement_log,
+    Announcements,
+};
+use crate::autosave::{
+    autosave_game, autosave_on_exit, clear_autosave, restore_autosave_clock, Autosave,
+};
 use crate::board::CheckerBoard;
+use crate::board_annotations::{draw_annotation_input, draw_annotations, BoardAnnotations};
+use crate::board_orientation::{flip_board, orient_board};
 use crate::board_position::BoardPosition;
 use crate::board_position_marker::{add_board_pos_markers_sprite, BoardPositionMarker};
+use crate::board_preview::{update_board_preview, BoardPreview};
+use crate::click_to_move::{
+    clear_selection, clear_selection_after_move, click_to_move, update_selection_highlight,
+    Selection,
+};
+use crate::clock::{run_clock, spawn_clock_label, update_clock_label, Clock};
+use crate::computer_player::{play_computer_move, ComputerPlayer};
+use crate::coordinate_labels::{spawn_coordinate_labels, update_coordinate_labels};
+use crate::correspondence::{
+    correspondence_buttons, load_correspondence, open_correspondence_on_startup,
+    restore_menu_names, save_correspondence_move, spawn_correspondence_screen,
+    update_correspondence_screen, Correspondence,
+};
+use crate::game_history::{update_game_history, GameHistory};
+use crate::game_over::{
+    detect_game_over, game_over_buttons, record_tournament_result, spawn_game_over_screen,
+    GameOutcome,
+};
+use crate::game_setup::{AppState, GameSetup, Opponent, StartGame};
+use crate::hot_seat::{auto_flip, hand_back, hand_over, spawn_handover_screen, Handover};
+use crate::keyboard_play::{
+    draw_keyboard_cursor, keyboard_cursor, keyboard_cursor_hidden, move_entry_closed, type_move,
+    update_move_entry_label, KeyboardCursor, MoveEntry,
+};
+use crate::lobby::{lobby_buttons, spawn_lobby, update_lobby};
+use crate::menu::{
+    menu_buttons, return_to_menu, spawn_menu, spawn_return_to_menu_button, type_text,
+    update_menu_labels, update_return_to_menu_button, MenuStatus,
+};
+use crate::move_highlights::update_move_highlights;
+use crate::move_history_panel::{
+    navigate_history, select_move, spawn_move_history_panel, update_move_history_panel,
+};
+use crate::online::{
+    connect_online, leave_online, online_buttons, poll_online, spawn_online_panel,
+    sync_online_game, update_online_panel, Online,
+};
+use crate::piece_drag::{cancel_drag, settle_dropped_pieces, Dragging, Settling};
+use crate::puzzle::{follow_puzzle, spawn_puzzle_label, update_puzzle_label, Puzzles};
+use crate::ratings::{
+    ratings_buttons, record_rating_result, spawn_ratings_screen, update_ratings_screen,
+    PlayerRatings,
+};
+use crate::review::{
+    export_pgn, leave_review, poll_review, spawn_review_label, start_review, update_review, Review,
+};
+use crate::screen_layout::{apply_layout, fit_to_window, place_panels, ScreenLayout};
+use crate::settings::Settings;
+use crate::sound::{adjust_volume, play_sounds, SoundEffect};
+use crate::svg_loader::SvgLoader;
+use crate::theme::{apply_theme, BoardSprite, Themes};
 use bevy::asset::AssetMetaCheck;
 use bevy::prelude::*;
-use bevy_mod_picking::prelude::{Drag, DragEnd, DragStart, Drop, Listener, On, Pickable, Pointer};
+use bevy_mod_picking::prelude::{
+    Drag, DragEnd, DragStart, Drop, Listener, On, Pickable, Pointer, PointerButton,
+};
 use bevy_mod_picking::{low_latency_window_plugin, DefaultPickingPlugins, PickableBundle};
-use board_ui_factory::BoardUiFactory;
+use board_ui_factory::{BoardUiFactory, PieceSprites};
+use opening::OpeningBook;
+use opening_label::{spawn_opening_label, update_opening_label};
+use tablebase::Tablebase;
+use tablebase_label::{spawn_tablebase_label, update_tablebase_label};
 
 //TODO:
-// * Game Loop (Restart after game over)
 // * Castle Moves
 // * Pormoting pawn
 // * Season cycles
 // * Seasonal Pieces
-// * AI easy
-// * Sounds
-// * Title Screen
 // * AI - Hard Monte carlo tree search
-// * - Bug Dropping outside board should return piece.
-//   --- (Fix by only allowing board to move inside of board)
 
 fn main() {
     let board = CheckerBoard::default();
+    let settings = Settings::load();
+    let mut themes = Themes::load();
+    themes.select(&settings.theme);
+    let square = themes.current().square();
     let mut app = App::new();
     app.add_plugins((
         DefaultPlugins
@@ -39,7 +149,7 @@ fn main() {
                 meta_check: AssetMetaCheck::Never,
                 ..default()
             })
-            .set(low_latency_window_plugin()),
+            .set(window_plugin()),
         DefaultPickingPlugins,
     ));
     #[cfg(feature = "debug")]
@@ -49,13 +159,261 @@ fn main() {
         app.add_plugins(WorldInspectorPlugin::new());
         app.insert_resource(DebugPickingMode::Normal);
     }
-    app.insert_resource(BoardUiFactory::new(68.5, 72., board))
-        .add_systems(Startup, setup)
-        .add_systems(Update, add_board_pos_markers_sprite);
+    app.init_asset_loader::<SvgLoader>()
+        .init_state::<AppState>()
+        .enable_state_scoped_entities::<AppState>()
+        .insert_resource(BoardUiFactory::new(square.x, square.y, board))
+        .init_resource::<OpeningBook>()
+        .insert_resource(Tablebase::from_env())
+        .init_resource::<Analysis>()
+        .init_resource::<GameHistory>()
+        .init_resource::<BoardPreview>()
+        .init_resource::<Review>()
+        .init_resource::<Selection>()
+        .insert_resource(settings)
+        .insert_resource(themes)
+        .init_resource::<GameSetup>()
+        .init_resource::<GameOutcome>()
+        .init_resource::<Clock>()
+        .init_resource::<ComputerPlayer>()
+        .init_resource::<Puzzles>()
+        .init_resource::<MenuStatus>()
+        .init_resource::<BoardAnnotations>()
+        .init_resource::<ScreenLayout>()
+        .init_resource::<KeyboardCursor>()
+        .init_resource::<MoveEntry>()
+        .init_resource::<Announcements>()
+        .init_resource::<Online>()
+        .init_resource::<Correspondence>()
+        .init_resource::<Handover>()
+        .insert_resource(PlayerRatings::load())
+        .insert_resource(Autosave::load())
+        .add_event::<SoundEffect>()
+        .add_event::<StartGame>()
+        .add_systems(
+            Startup,
+            (
+                setup,
+                spawn_opening_label,
+                spawn_tablebase_label,
+                spawn_analysis_panel,
+                spawn_review_label,
+                spawn_move_history_panel,
+                spawn_coordinate_labels,
+                spawn_clock_label,
+                spawn_puzzle_label,
+                spawn_return_to_menu_button,
+                spawn_announcement_log,
+                spawn_online_panel,
+                open_correspondence_on_startup,
+            ),
+        )
+        .add_systems(
+            OnEnter(AppState::Menu),
+            (
+                spawn_menu,
+                leave_online,
+                restore_menu_names.before(spawn_menu),
+            ),
+        )
+        .add_systems(OnEnter(AppState::Lobby), (connect_online, spawn_lobby))
+        .add_systems(
+            OnEnter(AppState::Correspondence),
+            (
+                load_correspondence,
+                restore_menu_names,
+                spawn_correspondence_screen,
+            ),
+        )
+        .add_systems(OnEnter(AppState::Ratings), spawn_ratings_screen)
+        .add_systems(
+            OnEnter(AppState::GameOver),
+            (
+                spawn_game_over_screen,
+                announce_game_over,
+                record_tournament_result.after(announce_game_over),
+                record_rating_result.after(announce_game_over),
+                clear_autosave,
+            ),
+        )
+        .add_systems(OnEnter(AppState::Handover), spawn_handover_screen)
+        .add_systems(OnEnter(AppState::Analysis), start_analysis)
+        .add_systems(OnExit(AppState::Analysis), stop_analysis)
+        .add_systems(Last, autosave_on_exit)
+        .add_systems(
+            Update,
+            (
+                (
+                    (
+                        menu_buttons,
+                        type_text,
+                        update_menu_labels.run_if(
+                            resource_changed::<GameSetup>
+                                .or_else(resource_changed::<Settings>)
+                                .or_else(resource_changed::<MenuStatus>),
+                        ),
+                    )
+                        .run_if(in_state(AppState::Menu)),
+                    (
+                        lobby_buttons,
+                        update_lobby.run_if(resource_changed::<Online>),
+                    )
+                        .chain()
+                        .run_if(in_state(AppState::Lobby)),
+                    (
+                        correspondence_buttons,
+                        update_correspondence_screen.run_if(
+                            resource_changed::<Correspondence>
+                                .or_else(resource_changed::<GameSetup>),
+                        ),
+                    )
+                        .chain()
+                        .run_if(in_state(AppState::Correspondence)),
+                    (
+                        ratings_buttons,
+                        update_ratings_screen.run_if(
+                            resource_changed::<PlayerRatings>.or_else(state_changed::<AppState>),
+                        ),
+                    )
+                        .chain()
+                        .run_if(in_state(AppState::Ratings)),
+                ),
+                (
+                    return_to_menu,
+                    update_return_to_menu_button.run_if(state_changed::<AppState>),
+                    game_over_buttons,
+                    hand_back.run_if(in_state(AppState::Handover)),
+                    online_buttons,
+                    poll_online,
+                    start_game,
+                    restore_autosave_clock.run_if(resource_changed::<GameHistory>),
+                    sync_online_game,
+                    update_online_panel
+                        .run_if(resource_changed::<Online>.or_else(state_changed::<AppState>)),
+                )
+                    .chain(),
+                (
+                    fit_to_window,
+                    apply_layout.run_if(resource_changed::<ScreenLayout>),
+                    place_panels,
+                )
+                    .chain(),
+                add_board_pos_markers_sprite,
+                update_opening_label.run_if(resource_changed::<BoardUiFactory>),
+                update_tablebase_label.run_if(resource_changed::<BoardUiFactory>),
+                (
+                    toggle_analysis
+                        .run_if(in_state(AppState::Analysis).and_then(move_entry_closed)),
+                    restart_analysis.run_if(resource_changed::<BoardUiFactory>),
+                    run_analysis,
+                    update_analysis_panel,
+                    draw_best_move_arrow,
+                )
+                    .chain(),
+                (
+                    update_game_history.run_if(resource_changed::<BoardUiFactory>),
+                    (
+                        hand_over,
+                        save_correspondence_move,
+                        follow_puzzle,
+                        detect_game_over,
+                        autosave_game,
+                    )
+                        .chain()
+                        .run_if(
+                            in_state(AppState::Playing).and_then(resource_changed::<GameHistory>),
+                        ),
+                    start_review.run_if(move_entry_closed),
+                    poll_review,
+                    leave_review,
+                    select_move,
+                    navigate_history.run_if(move_entry_closed.and_then(keyboard_cursor_hidden)),
+                    update_review.run_if(
+                        resource_changed::<Review>
+                            .or_else(resource_changed::<BoardPreview>)
+                            .or_else(resource_changed::<GameHistory>),
+                    ),
+                    update_move_history_panel.run_if(
+                        resource_changed::<BoardPreview>
+                            .or_else(resource_changed::<GameHistory>)
+                            .or_else(resource_changed::<ScreenLayout>),
+                    ),
+                    update_board_preview.run_if(
+                        resource_changed::<BoardPreview>.or_else(resource_changed::<GameHistory>),
+                    ),
+                    export_pgn.run_if(move_entry_closed),
+                )
+                    .chain()
+                    .run_if(not(in_state(AppState::Menu))),
+                (
+                    run_clock.run_if(in_state(AppState::Playing)),
+                    update_clock_label.run_if(resource_changed::<Clock>),
+                )
+                    .chain(),
+                play_computer_move.run_if(in_state(AppState::Playing)),
+                update_puzzle_label
+                    .run_if(resource_changed::<Puzzles>.or_else(resource_changed::<GameSetup>)),
+                (cancel_drag, settle_dropped_pieces).chain(),
+                (
+                    // The key that dismisses the handover screen must not
+                    // also pick up a piece.
+                    keyboard_cursor
+                        .run_if(move_entry_closed.and_then(not(in_state(AppState::Handover)))),
+                    type_move,
+                    update_move_entry_label.run_if(resource_changed::<MoveEntry>),
+                    draw_keyboard_cursor,
+                    announce_moves.run_if(resource_changed::<GameHistory>),
+                    update_announcement_log.run_if(resource_changed::<Announcements>),
+                )
+                    .chain()
+                    .run_if(not(in_state(AppState::Menu))),
+                (draw_annotation_input, draw_annotations)
+                    .chain()
+                    .run_if(not(in_state(AppState::Menu))),
+                (animate_moves, animate_captures, animate_promotions),
+                (
+                    adjust_volume.run_if(not(in_state(AppState::Menu)).and_then(move_entry_closed)),
+                    play_sounds,
+                ),
+                (
+                    flip_board.run_if(not(in_state(AppState::Menu)).and_then(move_entry_closed)),
+                    auto_flip.run_if(
+                        not(in_state(AppState::Menu)).and_then(resource_changed::<GameHistory>),
+                    ),
+                    apply_theme.run_if(resource_changed::<Themes>),
+                    orient_board.run_if(resource_changed::<BoardUiFactory>),
+                )
+                    .chain(),
+                update_coordinate_labels.run_if(resource_changed::<BoardUiFactory>),
+                update_move_highlights.run_if(
+                    resource_changed::<BoardUiFactory>.or_else(resource_changed::<BoardPreview>),
+                ),
+                (
+                    click_to_move,
+                    clear_selection,
+                    clear_selection_after_move.run_if(resource_changed::<GameHistory>),
+                    update_selection_highlight.run_if(resource_changed::<Selection>),
+                )
+                    .chain(),
+            ),
+        );
 
     app.run();
 }
 
+/// A resizable window that fills the page on the web, as the layout follows
+/// its size.
+fn window_plugin() -> WindowPlugin {
+    let mut plugin = low_latency_window_plugin();
+    if let Some(window) = plugin.primary_window.as_mut() {
+        window.title = "Rusty Chess".to_string();
+        window.resizable = true;
+        window.fit_canvas_to_parent = true;
+        window.prevent_default_event_handling = true;
+    }
+    plugin
+}
+
 #[derive(Component, Clone)]
 struct BoardPosComponent(BoardPosition);
 
@@ -83,20 +441,31 @@ fn setup(
     asset_server: Res<AssetServer>,
     mut board_ui_factory: ResMut<BoardUiFactory>,
     mut texture_atlas_layouts: ResMut<Assets<TextureAtlasLayout>>,
+    themes: Res<Themes>,
 ) {
     commands.spawn(Camera2dBundle {
         transform: Transform::from_xyz(0., 0., 500.0),
         ..default()
     });
-    commands.spawn(SpriteBundle {
-        texture: asset_server.load("board.png"),
-        ..Default::default()
-    });
+    let theme = themes.current();
+    commands.spawn((
+        SpriteBundle {
+            texture: asset_server.load(theme.board().to_string()),
+            ..Default::default()
+        },
+        BoardSprite,
+    ));
+    let piece_sprites = theme.piece_sprites(&asset_server, &mut texture_atlas_layouts);
+    commands.insert_resource(piece_sprites.clone());
     for pos in board_ui_factory.get_pos_iter() {
         let pos_transform = board_ui_factory.get_pos_transform(&pos);
         let id = commands
             .spawn((
                 SpriteBundle {
+                    sprite: Sprite {
+                        custom_size: Some(board_ui_factory.pos_size()),
+                        ..default()
+                    },
                     texture: asset_server.load("board_position_empty.png"),
                     transform: pos_transform.clone(),
                     ..default()
@@ -109,83 +478,217 @@ fn setup(
                      mut board_ui_factory: ResMut<BoardUiFactory>,
                      board_piece_query: Query<(Entity, &BoardPieceComponent)>,
                      board_pos_query: Query<(Entity, &BoardPosComponent)>,
-                     texture_query: Query<&mut TextureAtlas>,
-                     marker_query: Query<Entity, With<BoardPositionMarker>>| {
+                     marker_query: Query<Entity, With<BoardPositionMarker>>,
+                     analysis: Res<Analysis>,
+                     preview: Res<BoardPreview>,
+                     dragging_query: Query<(), With<Dragging>>,
+                     state: Res<State<AppState>>,
+                     setup: Res<GameSetup>,
+                     mut sounds: EventWriter<SoundEffect>| {
+                        if preview.ply().is_some()
+                            || dragging_query.get(event.dropped).is_err()
+                            || !setup.may_move(state.get(), &board_ui_factory.board)
+                        {
+                            return;
+                        }
                         let from = BoardUiFactory::get_pos(event.dropped, &board_piece_query);
                         let to = BoardUiFactory::get_pos(event.target, &board_pos_query);
-                        board_ui_factory.move_pieces(
+                        if let (Some(from), Some(to)) = (&from, &to) {
+                            analysis.prepare_move(&mut board_ui_factory.board, from, to);
+                        }
+                        let side_effects = board_ui_factory.move_pieces(
                             event.dropped,
                             &mut commands,
                             board_piece_query,
-                            texture_query,
                             from,
                             to,
                         );
+                        if let Some(side_effects) = side_effects {
+                            sounds.send(SoundEffect::from_move(
+                                &side_effects,
+                                &board_ui_factory.board,
+                            ));
+                        }
                         BoardUiFactory::remove_all_markers(&mut commands, &marker_query);
                     },
                 ),
             ))
             .id();
         board_ui_factory.add_board_pos_entity(&pos, id);
-        if let Some(index) = board_ui_factory.get_sprite_index(&pos) {
-            let texture = asset_server.load("pieces.png");
-            let layout = TextureAtlasLayout::from_grid(UVec2::splat(54), 6, 2, None, None);
-            let texture_atlas_layout = texture_atlas_layouts.add(layout);
-            let piece_entity = commands.spawn((
-                SpriteBundle {
-                    texture,
-                    transform: Transform::from_xyz(
-                        pos_transform.translation.x,
-                        pos_transform.translation.y,
-                        pos_transform.translation.z + 1.,
-                    ),
-                    ..default()
-                },
-                TextureAtlas {
-                    layout: texture_atlas_layout,
-                    index,
-                },
-                BoardPieceComponent(pos.clone()),
-                PickableBundle::default(),
-                On::<Pointer<DragStart>>::run(
-                    |event: Listener<Pointer<DragStart>>,
-                     mut commands: Commands,
-                     board_ui_factory: Res<BoardUiFactory>,
-                     query: Query<&BoardPieceComponent>| {
-                        commands.entity(event.target).insert(Pickable::IGNORE);
-                        for board_piece in query.get(event.target).into_iter() {
-                            board_ui_factory
-                                .add_markers_to_possible_board_moves(&board_piece.0, &mut commands);
-                        }
-                    },
-                ),
-                On::<Pointer<Drag>>::target_component_mut::<Transform>(|drag, transform| {
-                    transform.translation.x += drag.delta.x;
-                    transform.translation.y -= drag.delta.y;
-                }),
-                On::<Pointer<DragEnd>>::target_insert(Pickable::default()),
-                On::<Pointer<Drop>>::run(
-                    |event: Listener<Pointer<Drop>>,
-                     mut commands: Commands,
-                     mut board_ui_factory: ResMut<BoardUiFactory>,
-                     pieces_query: Query<(Entity, &BoardPieceComponent)>,
-                     texture_query: Query<&mut TextureAtlas>,
-                     marker_query: Query<Entity, With<BoardPositionMarker>>| {
-                        let from = BoardUiFactory::get_pos(event.dropped, &pieces_query);
-                        let to = BoardUiFactory::get_pos(event.target, &pieces_query);
-                        board_ui_factory.move_pieces(
-                            event.dropped,
-                            &mut commands,
-                            pieces_query,
-                            texture_query,
-                            from,
-                            to,
-                        );
-                        BoardUiFactory::remove_all_markers(&mut commands, &marker_query);
+        spawn_piece(&mut commands, &mut board_ui_factory, &piece_sprites, &pos);
+    }
+}
+
+/// Spawns the sprite of the piece on `pos`, if there is one.
+fn spawn_piece(
+    commands: &mut Commands,
+    board_ui_factory: &mut BoardUiFactory,
+    piece_sprites: &PieceSprites,
+    pos: &BoardPosition,
+) {
+    let Some(index) = board_ui_factory.get_sprite_index(pos) else {
+        return;
+    };
+    let pos_transform = board_ui_factory.get_pos_transform(pos);
+    let piece_entity =
+        commands
+            .spawn(
+                (
+                    SpriteBundle {
+                        sprite: piece_sprites.sprite(),
+                        texture: piece_sprites.texture(index),
+                        transform: Transform::from_xyz(
+                            pos_transform.translation.x,
+                            pos_transform.translation.y,
+                            pos_transform.translation.z + 1.,
+                        ),
+                        ..default()
                     },
+                    piece_sprites.atlas(index),
+                    BoardPieceComponent(pos.clone()),
+                    PickableBundle::default(),
+                    On::<Pointer<DragStart>>::run(
+                        |event: Listener<Pointer<DragStart>>,
+                         mut commands: Commands,
+                         board_ui_factory: Res<BoardUiFactory>,
+                         query: Query<&BoardPieceComponent>,
+                         preview: Res<BoardPreview>,
+                         state: Res<State<AppState>>,
+                         setup: Res<GameSetup>| {
+                            if preview.ply().is_some()
+                                || event.button != PointerButton::Primary
+                                || !setup.may_move(state.get(), &board_ui_factory.board)
+                            {
+                                return;
+                            }
+                            commands
+                                .entity(event.target)
+                                .remove::<MoveAnimation>()
+                                .insert((Pickable::IGNORE, Dragging));
+                            for board_piece in query.get(event.target).into_iter() {
+                                board_ui_factory.add_markers_to_possible_board_moves(
+                                    &board_piece.0,
+                                    &mut commands,
+                                );
+                            }
+                        },
+                    ),
+                    On::<Pointer<Drag>>::run(
+                        |event: Listener<Pointer<Drag>>,
+                         board_ui_factory: Res<BoardUiFactory>,
+                         mut query: Query<
+                            (&BoardPieceComponent, &mut Transform),
+                            With<Dragging>,
+                        >| {
+                            if let Ok((board_piece, mut transform)) = query.get_mut(event.target) {
+                                let origin = board_ui_factory.get_pos_transform(&board_piece.0);
+                                transform.translation = board_ui_factory.clamp_to_board(Vec3::new(
+                                    origin.translation.x + event.distance.x,
+                                    origin.translation.y - event.distance.y,
+                                    origin.translation.z + 2.,
+                                ));
+                            }
+                        },
+                    ),
+                    On::<Pointer<DragEnd>>::target_insert((Pickable::default(), Settling)),
+                    On::<Pointer<Drop>>::run(
+                        |event: Listener<Pointer<Drop>>,
+                         mut commands: Commands,
+                         mut board_ui_factory: ResMut<BoardUiFactory>,
+                         pieces_query: Query<(Entity, &BoardPieceComponent)>,
+                         marker_query: Query<Entity, With<BoardPositionMarker>>,
+                         analysis: Res<Analysis>,
+                         preview: Res<BoardPreview>,
+                         dragging_query: Query<(), With<Dragging>>,
+                         state: Res<State<AppState>>,
+                         setup: Res<GameSetup>,
+                         mut sounds: EventWriter<SoundEffect>| {
+                            if preview.ply().is_some()
+                                || dragging_query.get(event.dropped).is_err()
+                                || !setup.may_move(state.get(), &board_ui_factory.board)
+                            {
+                                return;
+                            }
+                            let from = BoardUiFactory::get_pos(event.dropped, &pieces_query);
+                            let to = BoardUiFactory::get_pos(event.target, &pieces_query);
+                            if let (Some(from), Some(to)) = (&from, &to) {
+                                analysis.prepare_move(&mut board_ui_factory.board, from, to);
+                            }
+                            let side_effects = board_ui_factory.move_pieces(
+                                event.dropped,
+                                &mut commands,
+                                pieces_query,
+                                from,
+                                to,
+                            );
+                            if let Some(side_effects) = side_effects {
+                                sounds.send(SoundEffect::from_move(
+                                    &side_effects,
+                                    &board_ui_factory.board,
+                                ));
+                            }
+                            BoardUiFactory::remove_all_markers(&mut commands, &marker_query);
+                        },
+                    ),
                 ),
-            )).id();
-            board_ui_factory.add_piece_entity(&pos, piece_entity);
+            )
+            .id();
+    board_ui_factory.add_piece_entity(pos, piece_entity);
+}
+
+type PieceFilter = Or<(With<BoardPieceComponent>, With<CaptureAnimation>)>;
+
+/// Replaces the pieces on the board with the ones of the new game and resets
+/// everything that belonged to the previous one.
+fn start_game(
+    mut commands: Commands,
+    mut events: EventReader<StartGame>,
+    mut board_ui_factory: ResMut<BoardUiFactory>,
+    piece_sprites: Res<PieceSprites>,
+    mut setup: ResMut<GameSetup>,
+    mut history: ResMut<GameHistory>,
+    mut preview: ResMut<BoardPreview>,
+    mut review: ResMut<Review>,
+    mut selection: ResMut<Selection>,
+    mut clock: ResMut<Clock>,
+    mut computer: ResMut<ComputerPlayer>,
+    mut annotations: ResMut<BoardAnnotations>,
+    mut handover: ResMut<Handover>,
+    mut next_state: ResMut<NextState<AppState>>,
+    piece_query: Query<Entity, PieceFilter>,
+) {
+    let Some(StartGame(start)) = events.read().last().cloned() else {
+        return;
+    };
+    for entity in piece_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    board_ui_factory.clear_piece_entities();
+    board_ui_factory.board = start.current().clone();
+    let time_control = match setup.opponent {
+        Opponent::Puzzle => {
+            setup.player_color = start.positions()[0].active_turn().clone();
+            None
+        }
+        // Correspondence games are turned to the side to move and have no clock.
+        Opponent::Correspondence => {
+            setup.player_color = start.current().active_turn().clone();
+            None
         }
+        _ => setup.time_control,
+    };
+    let flipped = setup.is_flipped();
+    board_ui_factory.set_flipped(flipped);
+    for pos in board_ui_factory.get_pos_iter() {
+        spawn_piece(&mut commands, &mut board_ui_factory, &piece_sprites, &pos);
     }
+    handover.seen = start.len();
+    *history = start;
+    preview.close();
+    *review = Review::default();
+    selection.clear();
+    *clock = Clock::new(time_control);
+    *computer = ComputerPlayer::default();
+    annotations.clear();
+    next_state.set(AppState::Playing);
 }
@@ -0,0 +1,455 @@
+use crate::autosave::Autosave;
+use crate::board::CheckerBoard;
+use crate::game_history::GameHistory;
+use crate::game_setup::{AppState, GameSetup, Opponent, StartGame};
+use crate::pgn::Pgn;
+use crate::pieces::color::PieceColor;
+use crate::puzzle::Puzzles;
+use crate::review::PGN_PATH;
+use crate::settings::Settings;
+use crate::sound::VOLUME_STEP;
+use crate::theme::Themes;
+use bevy::input::keyboard::{Key, KeyboardInput};
+use bevy::input::ButtonState;
+use bevy::prelude::{
+    default, AlignItems, BackgroundColor, BuildChildren, ButtonBundle, Changed, ChildBuilder,
+    Color, Commands, Component, EventReader, EventWriter, FlexDirection, Interaction,
+    JustifyContent, NextState, NodeBundle, PositionType, Query, Res, ResMut, Resource, State,
+    StateScoped, Style, Text, TextBundle, TextStyle, UiRect, Val, Visibility, With, Without,
+};
+
+const FONT_SIZE: f32 = 22.;
+const BUTTON_COLOR: Color = Color::srgb(0.25, 0.25, 0.3);
+
+#[derive(Component, Clone, Copy, PartialEq)]
+pub enum MenuButton {
+    Opponent,
+    Side,
+    Level,
+    TimeControl,
+    Variant,
+    WhiteName,
+    BlackName,
+    AutoFlip,
+    PassDevice,
+    /// Picks up the game left unfinished last time.
+    Continue,
+    NewGame,
+    LoadPgn,
+    LoadFen,
+    Puzzles,
+    Ratings,
+    Theme,
+    VolumeDown,
+    Mute,
+    VolumeUp,
+}
+
+/// The text inside a menu button whose label depends on the setup.
+#[derive(Component)]
+pub struct MenuButtonLabel(MenuButton);
+
+/// Goes back to the title screen from a game.
+#[derive(Component)]
+pub struct ReturnToMenuButton;
+
+#[derive(Component)]
+pub struct MenuStatusLabel;
+
+/// What the text typed on the title screen is for.
+#[derive(Clone, Copy, PartialEq)]
+enum TextField {
+    Fen,
+    WhiteName,
+    BlackName,
+}
+
+/// The text being typed, if any, and the last thing to tell the user.
+#[derive(Resource, Default)]
+pub struct MenuStatus {
+    typing: Option<(TextField, String)>,
+    message: String,
+}
+
+fn button_bundle() -> ButtonBundle {
+    ButtonBundle {
+        style: Style {
+            padding: UiRect::axes(Val::Px(16.), Val::Px(6.)),
+            justify_content: JustifyContent::Center,
+            ..default()
+        },
+        background_color: BackgroundColor(BUTTON_COLOR),
+        ..default()
+    }
+}
+
+fn button_text(label: &str) -> TextBundle {
+    TextBundle::from_section(
+        label,
+        TextStyle {
+            font_size: FONT_SIZE,
+            ..default()
+        },
+    )
+}
+
+pub fn spawn_button(parent: &mut ChildBuilder, label: &str, button: impl Component) {
+    parent
+        .spawn((button_bundle(), button))
+        .with_children(|parent| {
+            parent.spawn(button_text(label));
+        });
+}
+
+fn spawn_menu_button(parent: &mut ChildBuilder, button: MenuButton, label: String) {
+    parent
+        .spawn((button_bundle(), button))
+        .with_children(|parent| {
+            parent.spawn((button_text(&label), MenuButtonLabel(button)));
+        });
+}
+
+pub fn spawn_menu(
+    mut commands: Commands,
+    setup: Res<GameSetup>,
+    settings: Res<Settings>,
+    autosave: Res<Autosave>,
+) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    width: Val::Percent(100.),
+                    height: Val::Percent(100.),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    row_gap: Val::Px(8.),
+                    ..default()
+                },
+                background_color: BackgroundColor(Color::srgb(0.1, 0.1, 0.12)),
+                ..default()
+            },
+            StateScoped(AppState::Menu),
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                "Rusty Chess",
+                TextStyle {
+                    font_size: 64.,
+                    ..default()
+                },
+            ));
+            let rows: [&[MenuButton]; 10] = [
+                &[MenuButton::Opponent, MenuButton::Side],
+                &[MenuButton::Level, MenuButton::TimeControl],
+                &[MenuButton::Variant],
+                &[MenuButton::WhiteName, MenuButton::BlackName],
+                &[MenuButton::AutoFlip, MenuButton::PassDevice],
+                &[MenuButton::Continue, MenuButton::NewGame],
+                &[MenuButton::LoadPgn, MenuButton::LoadFen],
+                &[MenuButton::Puzzles, MenuButton::Ratings],
+                &[MenuButton::Theme],
+                &[
+                    MenuButton::VolumeDown,
+                    MenuButton::Mute,
+                    MenuButton::VolumeUp,
+                ],
+            ];
+            for row in rows {
+                parent
+                    .spawn(NodeBundle {
+                        style: Style {
+                            flex_direction: FlexDirection::Row,
+                            column_gap: Val::Px(8.),
+                            ..default()
+                        },
+                        ..default()
+                    })
+                    .with_children(|parent| {
+                        for button in row {
+                            if button == &MenuButton::Continue && autosave.saved.is_none() {
+                                continue;
+                            }
+                            spawn_menu_button(
+                                parent,
+                                *button,
+                                button_label(button, &setup, &settings),
+                            );
+                        }
+                    });
+            }
+            parent.spawn((
+                TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font_size: 18.,
+                        ..default()
+                    },
+                ),
+                MenuStatusLabel,
+            ));
+        });
+}
+
+pub fn spawn_return_to_menu_button(mut commands: Commands) {
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                right: Val::Px(8.),
+                bottom: Val::Px(8.),
+                ..default()
+            },
+            ..default()
+        })
+        .with_children(|parent| spawn_button(parent, "Menu", ReturnToMenuButton));
+}
+
+pub fn update_return_to_menu_button(
+    state: Res<State<AppState>>,
+    mut query: Query<&mut Visibility, With<ReturnToMenuButton>>,
+) {
+    for mut visibility in query.iter_mut() {
+        *visibility = if state.get() == &AppState::Menu {
+            Visibility::Hidden
+        } else {
+            Visibility::Inherited
+        };
+    }
+}
+
+pub fn return_to_menu(
+    query: Query<&Interaction, (Changed<Interaction>, With<ReturnToMenuButton>)>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    if query
+        .iter()
+        .any(|interaction| interaction == &Interaction::Pressed)
+    {
+        next_state.set(AppState::Menu);
+    }
+}
+
+pub fn update_menu_labels(
+    setup: Res<GameSetup>,
+    settings: Res<Settings>,
+    status: Res<MenuStatus>,
+    mut label_query: Query<(&MenuButtonLabel, &mut Text)>,
+    mut status_query: Query<&mut Text, (With<MenuStatusLabel>, Without<MenuButtonLabel>)>,
+) {
+    for (label, mut text) in label_query.iter_mut() {
+        text.sections[0].value = button_label(&label.0, &setup, &settings);
+    }
+    let status = match &status.typing {
+        Some((field, text)) => {
+            let prompt = match field {
+                TextField::Fen => "FEN",
+                TextField::WhiteName => "White",
+                TextField::BlackName => "Black",
+            };
+            format!("{}: {}_\n{}", prompt, text, status.message)
+        }
+        None => status.message.clone(),
+    };
+    for mut text in status_query.iter_mut() {
+        text.sections[0].value = status.clone();
+    }
+}
+
+fn on_off(value: bool) -> &'static str {
+    if value {
+        "on"
+    } else {
+        "off"
+    }
+}
+
+fn button_label(button: &MenuButton, setup: &GameSetup, settings: &Settings) -> String {
+    match button {
+        MenuButton::Opponent => match setup.opponent {
+            Opponent::Human => "Opponent: Human".to_string(),
+            Opponent::Computer | Opponent::Puzzle => "Opponent: Computer".to_string(),
+            Opponent::Online => "Opponent: Online".to_string(),
+            Opponent::Correspondence => "Opponent: Correspondence".to_string(),
+        },
+        MenuButton::Side => match setup.player_color {
+            PieceColor::White => "Play as: White".to_string(),
+            PieceColor::Black => "Play as: Black".to_string(),
+        },
+        MenuButton::Level => format!("Computer level: {}", setup.level),
+        MenuButton::TimeControl => match setup.time_control {
+            Some(time_control) => format!("Time: {}", time_control),
+            None => "Time: Unlimited".to_string(),
+        },
+        MenuButton::Variant => format!("Variant: {}", setup.variant),
+        MenuButton::WhiteName => {
+            let name = setup.player_name(&PieceColor::White);
+            format!("White: {}", name.as_deref().unwrap_or("?"))
+        }
+        MenuButton::BlackName => {
+            let name = setup.player_name(&PieceColor::Black);
+            format!("Black: {}", name.as_deref().unwrap_or("?"))
+        }
+        MenuButton::AutoFlip => format!("Auto flip: {}", on_off(setup.auto_flip)),
+        MenuButton::PassDevice => format!("Pass device: {}", on_off(setup.pass_device)),
+        MenuButton::Continue => "Continue".to_string(),
+        MenuButton::NewGame => "New game".to_string(),
+        MenuButton::LoadPgn => format!("Load PGN ({})", PGN_PATH),
+        MenuButton::LoadFen => "Load FEN".to_string(),
+        MenuButton::Puzzles => "Puzzles".to_string(),
+        MenuButton::Ratings => "Ratings".to_string(),
+        MenuButton::Theme => format!("Theme: {}", settings.theme),
+        MenuButton::VolumeDown => "Volume -".to_string(),
+        MenuButton::Mute if settings.muted => "Sound: off".to_string(),
+        MenuButton::Mute => format!("Sound: {:.0}%", settings.volume * 100.),
+        MenuButton::VolumeUp => "Volume +".to_string(),
+    }
+}
+
+pub fn menu_buttons(
+    query: Query<(&Interaction, &MenuButton), Changed<Interaction>>,
+    mut setup: ResMut<GameSetup>,
+    mut settings: ResMut<Settings>,
+    mut status: ResMut<MenuStatus>,
+    mut puzzles: ResMut<Puzzles>,
+    mut themes: ResMut<Themes>,
+    mut autosave: ResMut<Autosave>,
+    mut start_game: EventWriter<StartGame>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    for (interaction, button) in query.iter() {
+        if interaction != &Interaction::Pressed {
+            continue;
+        }
+        status.message.clear();
+        match button {
+            MenuButton::Opponent => setup.next_opponent(),
+            MenuButton::Side => setup.next_color(),
+            MenuButton::Level => setup.next_level(),
+            MenuButton::TimeControl => setup.next_time_control(),
+            MenuButton::Variant => setup.next_variant(),
+            MenuButton::WhiteName => {
+                status.typing = Some((TextField::WhiteName, setup.white_name.clone()));
+                status.message = "Type a name, Enter to keep it.".to_string();
+            }
+            MenuButton::BlackName => {
+                status.typing = Some((TextField::BlackName, setup.black_name.clone()));
+                status.message = "Type a name, Enter to keep it.".to_string();
+            }
+            MenuButton::AutoFlip => setup.auto_flip = !setup.auto_flip,
+            MenuButton::PassDevice => setup.pass_device = !setup.pass_device,
+            MenuButton::Continue => autosave.resume(&mut setup, &mut start_game),
+            MenuButton::NewGame if setup.opponent == Opponent::Online => {
+                next_state.set(AppState::Lobby);
+            }
+            MenuButton::NewGame if setup.opponent == Opponent::Correspondence => {
+                next_state.set(AppState::Correspondence);
+            }
+            MenuButton::NewGame => {
+                if setup.opponent == Opponent::Puzzle {
+                    setup.opponent = Opponent::Human;
+                }
+                start_game.send(StartGame(GameHistory::new(setup.variant.start_board())));
+            }
+            MenuButton::LoadPgn => match std::fs::read_to_string(PGN_PATH) {
+                Ok(text) => match Pgn::parse(&text) {
+                    Ok(history) => {
+                        if matches!(
+                            setup.opponent,
+                            Opponent::Puzzle | Opponent::Online | Opponent::Correspondence
+                        ) {
+                            setup.opponent = Opponent::Human;
+                        }
+                        start_game.send(StartGame(history));
+                    }
+                    Err(error) => status.message = error.to_string(),
+                },
+                Err(error) => status.message = format!("Could not read {}: {}", PGN_PATH, error),
+            },
+            MenuButton::LoadFen => {
+                status.typing = Some((TextField::Fen, String::new()));
+                status.message = "Type a FEN, Enter to load, Escape to cancel.".to_string();
+            }
+            MenuButton::Puzzles => {
+                setup.opponent = Opponent::Puzzle;
+                start_game.send_batch(puzzles.next().map(StartGame));
+            }
+            MenuButton::Ratings => next_state.set(AppState::Ratings),
+            MenuButton::Theme => {
+                themes.next();
+                settings.theme = themes.current().name().to_string();
+                settings.save();
+            }
+            MenuButton::VolumeDown | MenuButton::VolumeUp => {
+                let step = if button == &MenuButton::VolumeUp {
+                    VOLUME_STEP
+                } else {
+                    -VOLUME_STEP
+                };
+                let volume = settings.volume + step;
+                settings.set_volume(volume);
+                settings.save();
+            }
+            MenuButton::Mute => {
+                settings.muted = !settings.muted;
+                settings.save();
+            }
+        }
+    }
+}
+
+/// Collects the typed FEN or player name. Enter starts a game from the FEN
+/// or keeps the name.
+pub fn type_text(
+    mut keys: EventReader<KeyboardInput>,
+    mut status: ResMut<MenuStatus>,
+    mut setup: ResMut<GameSetup>,
+    mut start_game: EventWriter<StartGame>,
+) {
+    for key in keys.read() {
+        if key.state != ButtonState::Pressed {
+            continue;
+        }
+        let Some((field, text)) = status.typing.as_mut() else {
+            continue;
+        };
+        let field = *field;
+        match &key.logical_key {
+            Key::Character(typed) => text.push_str(typed),
+            Key::Space => text.push(' '),
+            Key::Backspace => {
+                text.pop();
+            }
+            Key::Escape => {
+                status.typing = None;
+                status.message.clear();
+            }
+            Key::Enter if field == TextField::Fen => match CheckerBoard::from_fen(text) {
+                Ok(board) => {
+                    status.typing = None;
+                    status.message.clear();
+                    if matches!(
+                        setup.opponent,
+                        Opponent::Puzzle | Opponent::Online | Opponent::Correspondence
+                    ) {
+                        setup.opponent = Opponent::Human;
+                    }
+                    start_game.send(StartGame(GameHistory::new(board)));
+                }
+                Err(error) => status.message = error.to_string(),
+            },
+            Key::Enter => {
+                let name = text.trim().to_string();
+                match field {
+                    TextField::WhiteName => setup.white_name = name,
+                    _ => setup.black_name = name,
+                }
+                status.typing = None;
+                status.message.clear();
+            }
+            _ => {}
+        }
+    }
+}
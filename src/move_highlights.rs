@@ -0,0 +1,58 @@
+use crate::board_position::BoardPosition;
+use crate::board_preview::BoardPreview;
+use crate::board_ui_factory::BoardUiFactory;
+use crate::game_history::GameHistory;
+use bevy::prelude::{
+    default, Color, Commands, Component, DespawnRecursiveExt, Entity, Query, Res, Sprite,
+    SpriteBundle, With,
+};
+use bevy_mod_picking::prelude::Pickable;
+
+#[derive(Component)]
+pub struct MoveHighlight;
+
+/// Marks the squares of the last move and tints the king of the side to move
+/// when it is in check, for the live board or the previewed position.
+pub fn update_move_highlights(
+    mut commands: Commands,
+    board_ui_factory: Res<BoardUiFactory>,
+    history: Res<GameHistory>,
+    preview: Res<BoardPreview>,
+    query: Query<Entity, With<MoveHighlight>>,
+) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    let board = preview
+        .ply()
+        .and_then(|ply| history.position(ply))
+        .unwrap_or(&board_ui_factory.board);
+    let mut highlight = |pos: &BoardPosition, color: Color, z: f32| {
+        let mut transform = board_ui_factory.get_pos_transform(pos);
+        transform.translation.z += z;
+        commands.spawn((
+            SpriteBundle {
+                sprite: Sprite {
+                    color,
+                    custom_size: Some(board_ui_factory.pos_size()),
+                    ..default()
+                },
+                transform,
+                ..default()
+            },
+            Pickable::IGNORE,
+            MoveHighlight,
+        ));
+    };
+    if let Some(last_move) = board.get_last_move() {
+        let color = Color::srgba(0.75, 0.85, 0.25, 0.4);
+        highlight(last_move.from(), color, 0.2);
+        highlight(last_move.to(), color, 0.2);
+    }
+    let color = board.active_turn();
+    if board.is_checked(color) {
+        if let Some(king) = board.get_king_position(color) {
+            highlight(king, Color::srgba(0.9, 0.1, 0.1, 0.55), 0.3);
+        }
+    }
+}
@@ -0,0 +1,202 @@
+use crate::board_preview::BoardPreview;
+use crate::game_history::GameHistory;
+use crate::pieces::color::PieceColor;
+use crate::screen_layout::{LayoutMode, PanelPlacement, ScreenLayout};
+use bevy::prelude::{
+    default, BackgroundColor, BuildChildren, ButtonBundle, ButtonInput, Changed, Color, Commands,
+    Component, DespawnRecursiveExt, Entity, FlexDirection, Interaction, KeyCode, NodeBundle,
+    Overflow, PositionType, Query, Res, ResMut, Style, TextBundle, TextStyle, UiRect, Val, With,
+};
+
+const MAX_ROWS: usize = 22;
+/// Below the board the list shares the column with the announcements.
+const NARROW_MAX_ROWS: usize = 6;
+const FONT_SIZE: f32 = 18.;
+
+#[derive(Component)]
+pub struct MoveHistoryPanel;
+
+/// A move in the panel, pointing at the position after it was played.
+#[derive(Component)]
+pub struct MoveButton(usize);
+
+pub fn spawn_move_history_panel(mut commands: Commands) {
+    commands.spawn((
+        NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                left: Val::Px(8.),
+                top: Val::Px(72.),
+                width: Val::Px(300.),
+                max_height: Val::Px(576.),
+                flex_direction: FlexDirection::Column,
+                overflow: Overflow::clip(),
+                ..default()
+            },
+            ..default()
+        },
+        MoveHistoryPanel,
+        PanelPlacement::new(8., 72.).narrow(330., 656.),
+    ));
+}
+
+/// Lists the moves in SAN, one full move per row, scrolled so the selected
+/// move stays visible.
+pub fn update_move_history_panel(
+    mut commands: Commands,
+    history: Res<GameHistory>,
+    preview: Res<BoardPreview>,
+    layout: Res<ScreenLayout>,
+    panel_query: Query<Entity, With<MoveHistoryPanel>>,
+) {
+    let max_rows = match layout.mode() {
+        LayoutMode::Wide => MAX_ROWS,
+        LayoutMode::Narrow => NARROW_MAX_ROWS,
+    };
+    let selected = preview.ply().unwrap_or(history.len());
+    let rows = rows(&history);
+    let selected_row = rows
+        .iter()
+        .position(|plies| plies.contains(&Some(selected)))
+        .unwrap_or_default();
+    let first_row = (selected_row + 1).saturating_sub(max_rows);
+    for panel in panel_query.iter() {
+        commands.entity(panel).despawn_descendants();
+        commands.entity(panel).with_children(|parent| {
+            for (row, plies) in rows.iter().enumerate().skip(first_row).take(max_rows) {
+                parent
+                    .spawn(NodeBundle {
+                        style: Style {
+                            flex_direction: FlexDirection::Row,
+                            ..default()
+                        },
+                        ..default()
+                    })
+                    .with_children(|parent| {
+                        parent.spawn(
+                            TextBundle::from_section(
+                                format!("{}.", row + 1),
+                                TextStyle {
+                                    font_size: FONT_SIZE,
+                                    ..default()
+                                },
+                            )
+                            .with_style(Style {
+                                width: Val::Px(44.),
+                                ..default()
+                            }),
+                        );
+                        for ply in plies {
+                            let Some(ply) = *ply else {
+                                parent.spawn(
+                                    TextBundle::from_section(
+                                        "...",
+                                        TextStyle {
+                                            font_size: FONT_SIZE,
+                                            ..default()
+                                        },
+                                    )
+                                    .with_style(Style {
+                                        width: Val::Px(110.),
+                                        padding: UiRect::horizontal(Val::Px(4.)),
+                                        ..default()
+                                    }),
+                                );
+                                continue;
+                            };
+                            let background = if ply == selected {
+                                Color::srgba(1., 1., 1., 0.25)
+                            } else {
+                                Color::NONE
+                            };
+                            parent
+                                .spawn((
+                                    ButtonBundle {
+                                        style: Style {
+                                            width: Val::Px(110.),
+                                            padding: UiRect::horizontal(Val::Px(4.)),
+                                            ..default()
+                                        },
+                                        background_color: BackgroundColor(background),
+                                        ..default()
+                                    },
+                                    MoveButton(ply),
+                                ))
+                                .with_children(|parent| {
+                                    parent.spawn(TextBundle::from_section(
+                                        history.sans()[ply - 1].clone(),
+                                        TextStyle {
+                                            font_size: FONT_SIZE,
+                                            ..default()
+                                        },
+                                    ));
+                                });
+                        }
+                    });
+            }
+        });
+    }
+}
+
+/// The plies of the moves, one full move per row. A game that starts with
+/// Black to move has no White move in its first row, as in `1... e5`.
+fn rows(history: &GameHistory) -> Vec<Vec<Option<usize>>> {
+    let first_ply = match history.positions()[0].active_turn() {
+        PieceColor::White => 0,
+        PieceColor::Black => 1,
+    };
+    let plies: Vec<Option<usize>> = (0..first_ply)
+        .map(|_| None)
+        .chain((1..=history.len()).map(Some))
+        .collect();
+    plies.chunks(2).map(<[Option<usize>]>::to_vec).collect()
+}
+
+pub fn select_move(
+    history: Res<GameHistory>,
+    mut preview: ResMut<BoardPreview>,
+    query: Query<(&Interaction, &MoveButton), Changed<Interaction>>,
+) {
+    for (interaction, move_button) in query.iter() {
+        if interaction == &Interaction::Pressed {
+            preview.jump(move_button.0, history.len());
+        }
+    }
+}
+
+/// Home, left, right and end go to the first, previous, next and last position.
+pub fn navigate_history(
+    keys: Res<ButtonInput<KeyCode>>,
+    history: Res<GameHistory>,
+    mut preview: ResMut<BoardPreview>,
+) {
+    let last = history.len();
+    if keys.just_pressed(KeyCode::Home) {
+        preview.jump(0, last);
+    } else if keys.just_pressed(KeyCode::ArrowLeft) {
+        preview.step(-1, last);
+    } else if keys.just_pressed(KeyCode::ArrowRight) {
+        preview.step(1, last);
+    } else if keys.just_pressed(KeyCode::End) {
+        preview.jump(last, last);
+    }
+}
+
+#[cfg(test)]
+mod move_history_panel_tests {
+    use crate::move_history_panel::rows;
+    use crate::pgn::Pgn;
+
+    #[test]
+    fn white_moves_open_each_row() {
+        let history = Pgn::parse("1. e4 e5 2. Nf3 *").unwrap();
+        assert_eq!(rows(&history), vec![vec![Some(1), Some(2)], vec![Some(3)]]);
+    }
+
+    #[test]
+    fn a_start_with_black_to_move_leaves_the_first_white_move_empty() {
+        let text = "[SetUp \"1\"]\n[FEN \"4k3/8/8/8/8/8/8/R3K3 b - - 0 1\"]\n\n1... Kd7 2. Ra7+ *";
+        let history = Pgn::parse(text).unwrap();
+        assert_eq!(rows(&history), vec![vec![None, Some(1)], vec![Some(2)]]);
+    }
+}
@@ -0,0 +1,276 @@
+use crate::board::CheckerBoard;
+use crate::board_move::BoardMove;
+use crate::pieces::piece_type::PieceType;
+
+pub struct San;
+
+impl San {
+    /// Standard algebraic notation for `board_move` played on `board`.
+    pub fn from_move(board: &CheckerBoard, board_move: &BoardMove) -> String {
+        let from = board_move.from();
+        let to = board_move.to();
+        let mut san =
+            if board_move.piece_type() == &PieceType::King && from.x().abs_diff(to.x()) == 2 {
+                if to.x() > from.x() {
+                    "O-O".to_string()
+                } else {
+                    "O-O-O".to_string()
+                }
+            } else {
+                let is_capture = board.piece_at(to).is_some()
+                    || (board_move.piece_type() == &PieceType::Pawn && from.x() != to.x());
+                let mut san = String::with_capacity(7);
+                if board_move.piece_type() == &PieceType::Pawn {
+                    if is_capture {
+                        san.push_str(&from.to_string()[0..1]);
+                    }
+                } else {
+                    san.push(board_move.piece_type().symbol());
+                    san.push_str(&Self::disambiguation(board, board_move));
+                }
+                if is_capture {
+                    san.push('x');
+                }
+                san.push_str(&to.to_string());
+                if board_move.is_promotion() {
+                    san.push_str("=Q");
+                }
+                san
+            };
+        let mut after = board.clone();
+        if let Some(piece) = board.piece_at(from) {
+            if piece.color() != after.active_turn() {
+                after.pass_turn();
+            }
+        }
+        after.move_piece(from, to);
+        let opponent = after.active_turn().clone();
+        if after.is_mated(&opponent) {
+            san.push('#');
+        } else if after.is_checked(&opponent) {
+            san.push('+');
+        }
+        san
+    }
+
+    /// Notation for a sequence of moves played one after another from `board`.
+    pub fn from_line(board: &CheckerBoard, moves: &[BoardMove]) -> Vec<String> {
+        let mut board = board.clone();
+        moves
+            .iter()
+            .map(|board_move| {
+                let san = Self::from_move(&board, board_move);
+                board.move_piece(board_move.from(), board_move.to());
+                san
+            })
+            .collect()
+    }
+
+    /// The legal move on `board` written as `san`. Check marks, annotation
+    /// glyphs and the promotion sign are optional and castling may be written
+    /// with zeros.
+    pub fn parse(board: &CheckerBoard, san: &str) -> Option<BoardMove> {
+        let wanted = Self::normalize(san);
+        if wanted.is_empty() {
+            return None;
+        }
+        board
+            .get_legal_moves()
+            .into_iter()
+            .find(|board_move| Self::normalize(&Self::from_move(board, board_move)) == wanted)
+    }
+
+    fn normalize(san: &str) -> String {
+        san.trim()
+            .chars()
+            .filter(|c| !matches!(c, '+' | '#' | '!' | '?' | '='))
+            .map(|c| if c == '0' { 'O' } else { c })
+            .collect()
+    }
+
+    fn disambiguation(board: &CheckerBoard, board_move: &BoardMove) -> String {
+        let from = board_move.from();
+        let color = match board.piece_at(from) {
+            Some(piece) => piece.color().clone(),
+            None => return String::new(),
+        };
+        let rivals: Vec<_> = board
+            .get_pieces()
+            .filter(|(pos, piece)| {
+                *pos != from
+                    && piece.color() == &color
+                    && piece.piece_type() == board_move.piece_type()
+                    && board.get_possible_moves(pos).contains(board_move.to())
+            })
+            .map(|(pos, _)| pos.clone())
+            .collect();
+        let square = from.to_string();
+        if rivals.is_empty() {
+            String::new()
+        } else if rivals.iter().all(|pos| pos.x() != from.x()) {
+            square[0..1].to_string()
+        } else if rivals.iter().all(|pos| pos.y() != from.y()) {
+            square[1..2].to_string()
+        } else {
+            square
+        }
+    }
+}
+
+#[cfg(test)]
+mod san_tests {
+    use crate::board::CheckerBoard;
+    use crate::board_move::BoardMove;
+    use crate::board_piece::BoardPiece;
+    use crate::board_pos;
+    use crate::notation::San;
+    use crate::pieces::color::PieceColor;
+    use crate::pieces::piece_type::PieceType;
+    use std::str::FromStr;
+
+    #[test]
+    fn pawn_push() {
+        let board = CheckerBoard::default();
+        assert_eq!(san(&board, PieceType::Pawn, "e2", "e4"), "e4");
+    }
+
+    #[test]
+    fn knight_move() {
+        let board = CheckerBoard::default();
+        assert_eq!(san(&board, PieceType::Knight, "g1", "f3"), "Nf3");
+    }
+
+    #[test]
+    fn pawn_capture_names_the_file() {
+        let mut board = CheckerBoard::default();
+        board.move_piece(&board_pos!("e2"), &board_pos!("e4"));
+        board.move_piece(&board_pos!("d7"), &board_pos!("d5"));
+        assert_eq!(san(&board, PieceType::Pawn, "e4", "d5"), "exd5");
+    }
+
+    #[test]
+    fn en_passant_is_a_capture() {
+        let pieces = vec![
+            BoardPiece::build(PieceType::Pawn, PieceColor::White, "e5"),
+            BoardPiece::build(PieceType::Pawn, PieceColor::Black, "d7"),
+        ];
+        let mut board = CheckerBoard::with_pieces(pieces);
+        board.pass_turn();
+        board.move_piece(&board_pos!("d7"), &board_pos!("d5"));
+        assert_eq!(san(&board, PieceType::Pawn, "e5", "d6"), "exd6");
+    }
+
+    #[test]
+    fn same_pieces_are_disambiguated_by_file() {
+        let pieces = vec![
+            BoardPiece::build(PieceType::Rook, PieceColor::White, "a1"),
+            BoardPiece::build(PieceType::Rook, PieceColor::White, "h1"),
+        ];
+        let board = CheckerBoard::with_pieces(pieces);
+        assert_eq!(san(&board, PieceType::Rook, "a1", "d1"), "Rad1");
+    }
+
+    #[test]
+    fn same_pieces_on_a_file_are_disambiguated_by_rank() {
+        let pieces = vec![
+            BoardPiece::build(PieceType::Rook, PieceColor::White, "a1"),
+            BoardPiece::build(PieceType::Rook, PieceColor::White, "a5"),
+        ];
+        let board = CheckerBoard::with_pieces(pieces);
+        assert_eq!(san(&board, PieceType::Rook, "a1", "a3"), "R1a3");
+    }
+
+    #[test]
+    fn castling() {
+        let pieces = vec![
+            BoardPiece::build(PieceType::King, PieceColor::White, "e1"),
+            BoardPiece::build(PieceType::Rook, PieceColor::White, "a1"),
+            BoardPiece::build(PieceType::Rook, PieceColor::White, "h1"),
+        ];
+        let board = CheckerBoard::with_pieces(pieces);
+        assert_eq!(san(&board, PieceType::King, "e1", "g1"), "O-O");
+        assert_eq!(san(&board, PieceType::King, "e1", "c1"), "O-O-O");
+    }
+
+    #[test]
+    fn promotion() {
+        let pieces = vec![BoardPiece::build(PieceType::Pawn, PieceColor::White, "d7")];
+        let board = CheckerBoard::with_pieces(pieces);
+        assert_eq!(san(&board, PieceType::Pawn, "d7", "d8"), "d8=Q");
+    }
+
+    #[test]
+    fn mate_suffix() {
+        let pieces = vec![
+            BoardPiece::build(PieceType::Pawn, PieceColor::White, "a7"),
+            BoardPiece::build(PieceType::Pawn, PieceColor::White, "b6"),
+            BoardPiece::build(PieceType::King, PieceColor::White, "a6"),
+            BoardPiece::build(PieceType::King, PieceColor::Black, "a8"),
+        ];
+        let board = CheckerBoard::with_pieces(pieces);
+        assert_eq!(san(&board, PieceType::Pawn, "b6", "b7"), "b7#");
+    }
+
+    #[test]
+    fn check_suffix() {
+        let pieces = vec![
+            BoardPiece::build(PieceType::King, PieceColor::White, "c1"),
+            BoardPiece::build(PieceType::Rook, PieceColor::White, "h1"),
+            BoardPiece::build(PieceType::King, PieceColor::Black, "a8"),
+        ];
+        let board = CheckerBoard::with_pieces(pieces);
+        assert_eq!(san(&board, PieceType::Rook, "h1", "h8"), "Rh8+");
+    }
+
+    #[test]
+    fn line_is_played_move_by_move() {
+        let board = CheckerBoard::default();
+        let moves = vec![
+            BoardMove::new(PieceType::Pawn, board_pos!("e2"), board_pos!("e4")),
+            BoardMove::new(PieceType::Pawn, board_pos!("d7"), board_pos!("d5")),
+            BoardMove::new(PieceType::Pawn, board_pos!("e4"), board_pos!("d5")),
+        ];
+        assert_eq!(San::from_line(&board, &moves), vec!["e4", "d5", "exd5"]);
+    }
+
+    #[test]
+    fn parses_moves_written_in_san() {
+        let board = CheckerBoard::default();
+        assert_eq!(
+            San::parse(&board, "Nf3"),
+            Some(BoardMove::new(
+                PieceType::Knight,
+                board_pos!("g1"),
+                board_pos!("f3")
+            ))
+        );
+        assert_eq!(San::parse(&board, "e5"), None);
+        assert_eq!(San::parse(&board, ""), None);
+    }
+
+    #[test]
+    fn parsing_ignores_suffixes_and_accepts_zero_castling() {
+        let pieces = vec![
+            BoardPiece::build(PieceType::King, PieceColor::White, "e1"),
+            BoardPiece::build(PieceType::Rook, PieceColor::White, "h1"),
+            BoardPiece::build(PieceType::King, PieceColor::Black, "a8"),
+        ];
+        let board = CheckerBoard::with_pieces(pieces);
+        assert_eq!(
+            San::parse(&board, "0-0"),
+            Some(BoardMove::new(
+                PieceType::King,
+                board_pos!("e1"),
+                board_pos!("g1")
+            ))
+        );
+        assert!(San::parse(&board, "Rh8+!?").is_some());
+    }
+
+    fn san(board: &CheckerBoard, piece_type: PieceType, from: &str, to: &str) -> String {
+        San::from_move(
+            board,
+            &BoardMove::new(piece_type, board_pos!(from), board_pos!(to)),
+        )
+    }
+}
@@ -0,0 +1,597 @@
+use crate::board_move::BoardMove;
+use crate::board_ui_factory::BoardUiFactory;
+use crate::clock::Clock;
+use crate::game_history::GameHistory;
+use crate::game_over::GameOutcome;
+use crate::game_result::GameResult;
+use crate::game_setup::{AppState, GameSetup, Opponent, StartGame, TimeControl};
+use crate::keyboard_play::{parse_move, play};
+use crate::menu::spawn_button;
+use crate::pieces::color::PieceColor;
+use crate::protocol::{ClientMessage, LiveGame, Seek, ServerMessage};
+use crate::screen_layout::PanelPlacement;
+use crate::server_connection::{default_address, Connection};
+use crate::sound::SoundEffect;
+use crate::BoardPieceComponent;
+use bevy::log::warn;
+use bevy::prelude::{
+    default, BackgroundColor, BuildChildren, Changed, Color, Commands, Component, DetectChangesMut,
+    Display, Entity, EventWriter, FlexDirection, Interaction, NextState, NodeBundle, PositionType,
+    Query, Res, ResMut, Resource, State, Style, Text, TextBundle, TextStyle, Time, UiRect, Val,
+    Visibility, With,
+};
+use std::str::FromStr;
+use std::time::Duration;
+
+/// Where to find the game server, as `host:port`, or as a `ws://` address in
+/// the browser.
+pub const SERVER_ENV: &str = "RUSTY_CHESS_SERVER";
+const RECONNECT_AFTER: Duration = Duration::from_secs(2);
+
+/// The game being played on the server, as far as the server confirmed it.
+pub struct OnlineGame {
+    id: u32,
+    /// Our side, or the side at the bottom of the board when watching.
+    pub color: PieceColor,
+    pub opponent: String,
+    /// The white and black players of a game we only watch.
+    pub watching: Option<[String; 2]>,
+    /// The moves the server accepted, in UCI notation.
+    moves: Vec<String>,
+    /// Our last move, sent and not confirmed yet.
+    pending: Option<String>,
+    /// The time left as last told by the server, until it is on the clock.
+    clock: Option<[Duration; 2]>,
+    /// Set when the board no longer matches the server, which happens after
+    /// a move is turned down.
+    desynced: bool,
+    pub draw_offered: bool,
+    pub opponent_away: bool,
+}
+
+impl OnlineGame {
+    fn new(id: u32, color: PieceColor, opponent: String) -> Self {
+        Self {
+            id,
+            color,
+            opponent,
+            watching: None,
+            moves: vec![],
+            pending: None,
+            clock: None,
+            desynced: false,
+            draw_offered: false,
+            opponent_away: false,
+        }
+    }
+}
+
+/// What the app has to act on after a message from the server.
+#[derive(Debug, PartialEq)]
+pub enum OnlineEvent {
+    Started(TimeControl),
+    Ended(GameResult, String),
+}
+
+/// The connection to the game server and what it told us about the lobby
+/// and our game.
+#[derive(Resource, Default)]
+pub struct Online {
+    connection: Option<Connection>,
+    address: String,
+    /// The name chosen on the title screen.
+    requested_name: String,
+    /// The name we are signed in with, which the server numbers for guests.
+    name: String,
+    /// Takes our seat back after the connection dropped.
+    token: Option<String>,
+    /// Whether to stay connected, so a dropped connection is made again.
+    active: bool,
+    retry_at: Duration,
+    pub status: String,
+    pub players: Vec<String>,
+    /// Open seeks, with whether each is a challenge.
+    pub seeks: Vec<(Seek, bool)>,
+    /// The games being played that we may watch.
+    pub games: Vec<LiveGame>,
+    pub game: Option<OnlineGame>,
+}
+
+impl Online {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn send(&mut self, message: ClientMessage) {
+        if let Some(connection) = self.connection.as_mut() {
+            connection.send(message);
+        }
+    }
+
+    /// Takes in a message from the server.
+    pub fn handle(&mut self, message: ServerMessage) -> Option<OnlineEvent> {
+        match message {
+            ServerMessage::Welcome { name, token } => {
+                self.status = format!("Signed in as {}", name);
+                self.name = name;
+                self.token = Some(token);
+                // The server forgets what we watched when the connection drops.
+                if let Some(id) = self
+                    .game
+                    .as_ref()
+                    .filter(|game| game.watching.is_some())
+                    .map(|game| game.id)
+                {
+                    self.send(ClientMessage::Watch(id));
+                }
+            }
+            ServerMessage::Players(players) => self.players = players,
+            ServerMessage::Seek(seek) => self.seeks.push((seek, false)),
+            ServerMessage::Challenge(seek) => self.seeks.push((seek, true)),
+            ServerMessage::Unseek(id) => self.seeks.retain(|(seek, _)| seek.id != id),
+            ServerMessage::Game(game) => self.games.push(game),
+            ServerMessage::GameEnded(id) => self.games.retain(|game| game.id != id),
+            ServerMessage::Start {
+                game,
+                color,
+                opponent,
+                minutes,
+                increment,
+            } => {
+                self.status = format!("Playing {}", opponent);
+                self.game = Some(OnlineGame::new(game, color, opponent));
+                return Some(OnlineEvent::Started(TimeControl::new(minutes, increment)));
+            }
+            ServerMessage::Watching(live) => {
+                self.status = format!("Watching {} against {}", live.white, live.black);
+                let mut game = OnlineGame::new(live.id, PieceColor::White, live.black.clone());
+                game.watching = Some([live.white, live.black]);
+                self.game = Some(game);
+                let time_control = TimeControl::new(live.minutes, live.increment);
+                return Some(OnlineEvent::Started(time_control));
+            }
+            ServerMessage::Move { ply, uci, clock } => {
+                let game = self.game.as_mut()?;
+                if ply == game.moves.len() {
+                    if game.pending.as_ref() == Some(&uci) {
+                        game.pending = None;
+                    }
+                    game.moves.push(uci);
+                    game.clock = clock.or(game.clock);
+                    game.draw_offered = false;
+                }
+            }
+            ServerMessage::Clock(clock) => {
+                let game = self.game.as_mut()?;
+                game.clock = Some(clock);
+            }
+            ServerMessage::DrawOffered => self.game.as_mut()?.draw_offered = true,
+            ServerMessage::OpponentLeft => self.game.as_mut()?.opponent_away = true,
+            ServerMessage::OpponentReturned => self.game.as_mut()?.opponent_away = false,
+            ServerMessage::End { result, reason } => {
+                self.game = None;
+                self.status = format!("Game over, {} {}", result, reason);
+                return Some(OnlineEvent::Ended(result, reason));
+            }
+            ServerMessage::Error(error) => {
+                if let Some(game) = self.game.as_mut().filter(|game| game.pending.is_some()) {
+                    game.pending = None;
+                    game.desynced = true;
+                }
+                self.status = error;
+            }
+        }
+        None
+    }
+
+    /// Connects again when the connection was lost, a little while after
+    /// the last attempt.
+    fn reconnect(&mut self, now: Duration) {
+        if self.connection.as_ref().is_some_and(Connection::is_closed) {
+            self.connection = None;
+            self.players.clear();
+            self.seeks.clear();
+            self.games.clear();
+            self.status = "Connection lost, reconnecting".to_string();
+        }
+        if self.connection.is_some() || now < self.retry_at {
+            return;
+        }
+        self.retry_at = now + RECONNECT_AFTER;
+        match Connection::connect(&self.address) {
+            Ok(connection) => {
+                self.connection = Some(connection);
+                self.send(ClientMessage::Hello {
+                    name: self.name.clone(),
+                    token: self.token.clone(),
+                });
+            }
+            Err(error) => {
+                self.status = format!("Could not reach {}: {}", self.address, error);
+            }
+        }
+    }
+}
+
+/// Replays the moves in UCI notation from the start position, as far as
+/// they are legal.
+fn replay(moves: &[String]) -> GameHistory {
+    let mut history = GameHistory::default();
+    for uci in moves {
+        match parse_move(history.current(), uci) {
+            Some(board_move) => history.push(board_move),
+            None => break,
+        }
+    }
+    history
+}
+
+/// Goes online with the name chosen on the title screen.
+pub fn connect_online(mut online: ResMut<Online>, setup: Res<GameSetup>, time: Res<Time>) {
+    let name = setup.online_name();
+    if online.requested_name != name {
+        online.token = None;
+        online.connection = None;
+        online.requested_name = name.clone();
+        online.name = name;
+    }
+    online.address = std::env::var(SERVER_ENV).unwrap_or_else(|_| default_address());
+    online.active = true;
+    if online.connection.is_none() {
+        online.status = format!("Connecting to {}", online.address);
+        online.retry_at = time.elapsed();
+    }
+}
+
+/// Leaves the server for the title screen. A game in progress keeps its
+/// seat on the server for a while, so going back online resumes it.
+pub fn leave_online(mut online: ResMut<Online>, mut setup: ResMut<GameSetup>) {
+    setup.watching = None;
+    if online.active {
+        online.active = false;
+        online.connection = None;
+        online.players.clear();
+        online.seeks.clear();
+        online.games.clear();
+        online.game = None;
+    }
+}
+
+/// Reads the server and starts and ends games as it says.
+pub fn poll_online(
+    time: Res<Time>,
+    mut online: ResMut<Online>,
+    mut setup: ResMut<GameSetup>,
+    mut outcome: ResMut<GameOutcome>,
+    mut start_game: EventWriter<StartGame>,
+    mut sounds: EventWriter<SoundEffect>,
+    state: Res<State<AppState>>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    if !online.active {
+        return;
+    }
+    // Only what the server says counts as a change, not every poll.
+    let lines = match online.bypass_change_detection().connection.as_mut() {
+        Some(connection) => connection.receive(),
+        None => vec![],
+    };
+    let closed = online.connection.as_ref().is_none_or(Connection::is_closed);
+    if closed && (online.connection.is_some() || time.elapsed() >= online.retry_at) {
+        online.reconnect(time.elapsed());
+    }
+    for line in lines {
+        let message = match ServerMessage::from_str(&line) {
+            Ok(message) => message,
+            Err(error) => {
+                warn!("{}", error);
+                continue;
+            }
+        };
+        match online.handle(message) {
+            Some(OnlineEvent::Started(time_control)) => {
+                let game = online.game.as_ref().expect("A started game is kept");
+                setup.opponent = Opponent::Online;
+                setup.player_color = game.color.clone();
+                setup.online_opponent = game.opponent.clone();
+                setup.watching = game.watching.clone();
+                setup.time_control = Some(time_control)
+                    .filter(|time_control| time_control.minutes > 0 || time_control.increment > 0);
+                start_game.send(StartGame(GameHistory::default()));
+            }
+            Some(OnlineEvent::Ended(result, reason)) if state.get() == &AppState::Playing => {
+                *outcome = GameOutcome::new(result, &reason);
+                next_state.set(AppState::GameOver);
+                sounds.send(SoundEffect::GameEnd);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Keeps the board and the server in step: sends our moves, plays the
+/// opponent's and starts over from the server's moves when they disagree.
+pub fn sync_online_game(
+    mut commands: Commands,
+    mut online: ResMut<Online>,
+    history: Res<GameHistory>,
+    mut board_ui_factory: ResMut<BoardUiFactory>,
+    mut clock: ResMut<Clock>,
+    mut start_game: EventWriter<StartGame>,
+    pieces_query: Query<(Entity, &BoardPieceComponent)>,
+    mut sounds: EventWriter<SoundEffect>,
+) {
+    // Nothing shown depends on what is tracked here.
+    let online = online.bypass_change_detection();
+    let Some(game) = online.game.as_mut() else {
+        return;
+    };
+    if let Some(remaining) = game.clock.take() {
+        clock.sync(remaining);
+    }
+    let local: Vec<String> = history.moves().iter().map(BoardMove::to_string).collect();
+    if local == game.moves {
+        return;
+    }
+    let extends = |longer: &[String], shorter: &[String]| {
+        longer.len() == shorter.len() + 1 && longer.starts_with(shorter)
+    };
+    if !game.desynced && extends(&local, &game.moves) {
+        let last = local.last().cloned();
+        let mover = history.positions()[local.len() - 1].active_turn();
+        if game.pending.is_none() && mover == &game.color {
+            game.pending = last.clone();
+            if let (Some(connection), Some(uci)) = (online.connection.as_mut(), last) {
+                connection.send(ClientMessage::Move(uci));
+            }
+        }
+        if game.pending.is_some() {
+            return;
+        }
+    }
+    if !game.desynced && extends(&game.moves, &local) {
+        let uci = game.moves.last().expect("The server is a move ahead");
+        if let Some(board_move) = parse_move(&board_ui_factory.board, uci) {
+            play(
+                &mut commands,
+                &mut board_ui_factory,
+                pieces_query,
+                &mut sounds,
+                &board_move,
+            );
+            return;
+        }
+    }
+    game.desynced = false;
+    game.pending = None;
+    // Starting over resets the clock, so the time left is put back after.
+    if let (Some(white), Some(black)) = (
+        clock.remaining(&PieceColor::White),
+        clock.remaining(&PieceColor::Black),
+    ) {
+        game.clock = Some([white, black]);
+    }
+    start_game.send(StartGame(replay(&game.moves)));
+}
+
+#[derive(Component)]
+pub struct OnlinePanel;
+
+#[derive(Component)]
+pub struct OnlineLabel;
+
+#[derive(Component, Clone, Copy)]
+pub enum OnlineButton {
+    Resign,
+    Draw,
+    /// Stops watching and goes back to the lobby.
+    Lobby,
+}
+
+/// The opponent's name and the resign and draw buttons of online games, or
+/// the players of a game we watch.
+pub fn spawn_online_panel(mut commands: Commands) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    left: Val::Px(930.),
+                    top: Val::Px(470.),
+                    flex_direction: FlexDirection::Column,
+                    padding: UiRect::all(Val::Px(8.)),
+                    row_gap: Val::Px(8.),
+                    ..default()
+                },
+                background_color: BackgroundColor(Color::srgba(0., 0., 0., 0.6)),
+                visibility: Visibility::Hidden,
+                ..default()
+            },
+            OnlinePanel,
+            PanelPlacement::new(930., 470.).narrow(330., 940.),
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font_size: 18.,
+                        ..default()
+                    },
+                ),
+                OnlineLabel,
+            ));
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Row,
+                        column_gap: Val::Px(8.),
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|parent| {
+                    spawn_button(parent, "Resign", OnlineButton::Resign);
+                    spawn_button(parent, "Draw", OnlineButton::Draw);
+                    spawn_button(parent, "Lobby", OnlineButton::Lobby);
+                });
+        });
+}
+
+pub fn update_online_panel(
+    online: Res<Online>,
+    state: Res<State<AppState>>,
+    mut panel_query: Query<&mut Visibility, With<OnlinePanel>>,
+    mut label_query: Query<&mut Text, With<OnlineLabel>>,
+    mut button_query: Query<(&OnlineButton, &mut Style)>,
+) {
+    let game = online
+        .game
+        .as_ref()
+        .filter(|_| state.get() == &AppState::Playing);
+    for mut visibility in panel_query.iter_mut() {
+        *visibility = match game {
+            Some(_) => Visibility::Inherited,
+            None => Visibility::Hidden,
+        };
+    }
+    let Some(game) = game else {
+        return;
+    };
+    for (button, mut style) in button_query.iter_mut() {
+        let shown = match button {
+            OnlineButton::Resign | OnlineButton::Draw => game.watching.is_none(),
+            OnlineButton::Lobby => game.watching.is_some(),
+        };
+        style.display = if shown { Display::Flex } else { Display::None };
+    }
+    let mut value = match &game.watching {
+        Some([white, black]) => format!("Watching {} against {}", white, black),
+        None => format!("Playing {} online", game.opponent),
+    };
+    if game.opponent_away {
+        value.push_str("\nOpponent disconnected");
+    }
+    if game.draw_offered {
+        value.push_str("\nDraw offered, press Draw to accept");
+    }
+    if online.connection.is_none() {
+        value.push('\n');
+        value.push_str(&online.status);
+    }
+    for mut text in label_query.iter_mut() {
+        text.sections[0].value = value.clone();
+    }
+}
+
+pub fn online_buttons(
+    query: Query<(&Interaction, &OnlineButton), Changed<Interaction>>,
+    mut online: ResMut<Online>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    for (interaction, button) in query.iter() {
+        if interaction != &Interaction::Pressed {
+            continue;
+        }
+        match button {
+            OnlineButton::Resign => online.send(ClientMessage::Resign),
+            OnlineButton::Draw => online.send(ClientMessage::Draw),
+            OnlineButton::Lobby => {
+                online.send(ClientMessage::Unwatch);
+                online.game = None;
+                next_state.set(AppState::Lobby);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod online_tests {
+    use crate::game_result::GameResult;
+    use crate::game_setup::TimeControl;
+    use crate::online::{replay, Online, OnlineEvent};
+    use crate::pieces::color::PieceColor;
+    use crate::protocol::{LiveGame, Seek, ServerMessage};
+
+    fn started() -> Online {
+        let mut online = Online::default();
+        let event = online.handle(ServerMessage::Start {
+            game: 1,
+            color: PieceColor::White,
+            opponent: "ben".to_string(),
+            minutes: 3,
+            increment: 2,
+        });
+        assert_eq!(event, Some(OnlineEvent::Started(TimeControl::new(3, 2))));
+        online
+    }
+
+    #[test]
+    fn seeks_come_and_go() {
+        let mut online = Online::default();
+        let seek = Seek {
+            id: 7,
+            name: "ben".to_string(),
+            minutes: 5,
+            increment: 0,
+            color: PieceColor::Black,
+        };
+        online.handle(ServerMessage::Challenge(seek));
+        assert!(online.seeks[0].1);
+        online.handle(ServerMessage::Unseek(7));
+        assert!(online.seeks.is_empty());
+    }
+
+    #[test]
+    fn confirmed_moves_settle_the_pending_one() {
+        let mut online = started();
+        let game = online.game.as_mut().unwrap();
+        game.pending = Some("e2e4".to_string());
+        online.handle(ServerMessage::Move {
+            ply: 0,
+            uci: "e2e4".to_string(),
+            clock: None,
+        });
+        let game = online.game.as_ref().unwrap();
+        assert_eq!(game.pending, None);
+        assert_eq!(game.moves, vec!["e2e4".to_string()]);
+        assert_eq!(replay(&game.moves).len(), 1);
+    }
+
+    #[test]
+    fn watched_games_show_both_players() {
+        let mut online = Online::default();
+        let live = LiveGame {
+            id: 4,
+            white: "anna".to_string(),
+            black: "ben".to_string(),
+            minutes: 0,
+            increment: 0,
+        };
+        online.handle(ServerMessage::Game(live.clone()));
+        assert_eq!(online.games, vec![live.clone()]);
+        let event = online.handle(ServerMessage::Watching(live));
+        assert_eq!(event, Some(OnlineEvent::Started(TimeControl::new(0, 0))));
+        let game = online.game.as_ref().unwrap();
+        assert_eq!(game.watching, Some(["anna".to_string(), "ben".to_string()]));
+        online.handle(ServerMessage::GameEnded(4));
+        assert!(online.games.is_empty());
+    }
+
+    #[test]
+    fn a_turned_down_move_puts_the_board_back() {
+        let mut online = started();
+        online.game.as_mut().unwrap().pending = Some("e2e5".to_string());
+        online.handle(ServerMessage::Error("e2e5 is not a legal move".to_string()));
+        assert!(online.game.as_ref().unwrap().desynced);
+        let event = online.handle(ServerMessage::End {
+            result: GameResult::BlackWins,
+            reason: "by resignation".to_string(),
+        });
+        assert!(matches!(
+            event,
+            Some(OnlineEvent::Ended(GameResult::BlackWins, _))
+        ));
+        assert!(online.game.is_none());
+    }
+}
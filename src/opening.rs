@@ -0,0 +1,145 @@
+use crate::board_move::BoardMove;
+use bevy::prelude::Resource;
+use std::fmt::Display;
+
+const ECO_TABLE: &str = include_str!("eco.tsv");
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Opening {
+    eco: String,
+    name: String,
+    moves: Vec<String>,
+}
+
+impl Opening {
+    pub fn new(eco: &str, name: &str, moves: &str) -> Self {
+        Self {
+            eco: eco.to_string(),
+            name: name.to_string(),
+            moves: moves.split_whitespace().map(|m| m.to_string()).collect(),
+        }
+    }
+
+    pub fn eco(&self) -> &str {
+        &self.eco
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn is_played_in(&self, moves: &[String]) -> bool {
+        self.moves.len() <= moves.len() && self.moves.iter().zip(moves).all(|(a, b)| a == b)
+    }
+}
+
+impl Display for Opening {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", self.eco, self.name)
+    }
+}
+
+#[derive(Resource)]
+pub struct OpeningBook {
+    openings: Vec<Opening>,
+}
+
+impl OpeningBook {
+    pub fn new(openings: Vec<Opening>) -> Self {
+        Self { openings }
+    }
+
+    /// Returns the most specific opening whose moves start the game,
+    /// so "C50 Italian Game" stays shown once the line leaves the book.
+    pub fn classify(&self, moves: &[BoardMove]) -> Option<&Opening> {
+        let moves: Vec<String> = moves.iter().map(|m| m.to_string()).collect();
+        self.openings
+            .iter()
+            .filter(|opening| opening.is_played_in(&moves))
+            .fold(None, |best: Option<&Opening>, opening| match best {
+                Some(best) if best.moves.len() >= opening.moves.len() => Some(best),
+                _ => Some(opening),
+            })
+    }
+}
+
+impl Default for OpeningBook {
+    fn default() -> Self {
+        let openings = ECO_TABLE
+            .lines()
+            .filter_map(|line| {
+                let mut columns = line.split('\t');
+                match (columns.next(), columns.next(), columns.next()) {
+                    (Some(eco), Some(name), Some(moves)) => Some(Opening::new(eco, name, moves)),
+                    _ => None,
+                }
+            })
+            .collect();
+        Self::new(openings)
+    }
+}
+
+#[cfg(test)]
+mod opening_tests {
+    use crate::board::CheckerBoard;
+    use crate::board_pos;
+    use crate::opening::{Opening, OpeningBook};
+    use std::str::FromStr;
+
+    #[test]
+    fn bundled_table_is_loaded() {
+        let book = OpeningBook::default();
+        assert!(book.openings.len() > 50);
+    }
+
+    #[test]
+    fn no_opening_before_first_move() {
+        let book = OpeningBook::default();
+        let board = CheckerBoard::default();
+        assert_eq!(book.classify(board.get_moves()), None);
+    }
+
+    #[test]
+    fn it_recognizes_kings_pawn_game() {
+        let book = OpeningBook::default();
+        let mut board = CheckerBoard::default();
+        board.move_piece(&board_pos!("e2"), &board_pos!("e4"));
+        let opening = book.classify(board.get_moves()).unwrap();
+        assert_eq!(opening.eco(), "B00");
+        assert_eq!(opening.name(), "King's Pawn Game");
+    }
+
+    #[test]
+    fn it_recognizes_italian_game() {
+        let book = OpeningBook::default();
+        let board = play(&["e2e4", "e7e5", "g1f3", "b8c6", "f1c4"]);
+        let opening = book.classify(board.get_moves()).unwrap();
+        assert_eq!(opening.to_string(), "C50 Italian Game");
+    }
+
+    #[test]
+    fn it_keeps_last_known_opening_after_leaving_the_book() {
+        let book = OpeningBook::default();
+        let board = play(&["e2e4", "c7c5", "g1f3", "d7d6", "h2h3"]);
+        let opening = book.classify(board.get_moves()).unwrap();
+        assert_eq!(opening.to_string(), "B50 Sicilian Defense");
+    }
+
+    #[test]
+    fn it_prefers_the_longest_matching_line() {
+        let book = OpeningBook::new(vec![
+            Opening::new("C20", "King's Pawn Game", "e2e4 e7e5"),
+            Opening::new("C40", "King's Knight Opening", "e2e4 e7e5 g1f3"),
+        ]);
+        let board = play(&["e2e4", "e7e5", "g1f3"]);
+        assert_eq!(book.classify(board.get_moves()).unwrap().eco(), "C40");
+    }
+
+    fn play(moves: &[&str]) -> CheckerBoard {
+        let mut board = CheckerBoard::default();
+        for m in moves {
+            board.move_piece(&board_pos!(&m[0..2]), &board_pos!(&m[2..4]));
+        }
+        board
+    }
+}
@@ -0,0 +1,44 @@
+use crate::board_ui_factory::BoardUiFactory;
+use crate::opening::OpeningBook;
+use crate::screen_layout::PanelPlacement;
+use bevy::prelude::{
+    default, Commands, Component, PositionType, Query, Res, Style, Text, TextBundle, TextStyle,
+    Val, With,
+};
+
+#[derive(Component)]
+pub struct OpeningLabel;
+
+pub fn spawn_opening_label(mut commands: Commands) {
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font_size: 20.,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(8.),
+            left: Val::Px(8.),
+            ..default()
+        }),
+        OpeningLabel,
+        PanelPlacement::new(8., 8.).narrow(8., 8.),
+    ));
+}
+
+pub fn update_opening_label(
+    board_ui_factory: Res<BoardUiFactory>,
+    opening_book: Res<OpeningBook>,
+    mut query: Query<&mut Text, With<OpeningLabel>>,
+) {
+    let label = opening_book
+        .classify(board_ui_factory.board.get_moves())
+        .map(|opening| format!("{} {}", opening.eco(), opening.name()))
+        .unwrap_or_default();
+    for mut text in query.iter_mut() {
+        text.sections[0].value = label.clone();
+    }
+}
@@ -0,0 +1,350 @@
+use crate::board::{CheckerBoard, FenError};
+use crate::game_history::GameHistory;
+use crate::game_result::GameResult;
+use crate::notation::San;
+use crate::pieces::color::PieceColor;
+use std::fmt::Display;
+use thiserror::Error;
+
+const LINE_WIDTH: usize = 80;
+
+#[derive(Debug, Clone, PartialEq)]
+struct PgnMove {
+    san: String,
+    nag: Option<u8>,
+    comment: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Pgn {
+    headers: Vec<(String, String)>,
+    moves: Vec<PgnMove>,
+    result: GameResult,
+    /// 1 when the game starts with black to move.
+    first_ply: usize,
+    /// A comment on the start position, written before the first move.
+    start_comment: Option<String>,
+}
+
+#[derive(Error, Debug)]
+pub enum PgnError {
+    #[error(transparent)]
+    Fen(#[from] FenError),
+    #[error("Illegal move: {0}")]
+    IllegalMove(String),
+}
+
+impl Pgn {
+    pub fn new(history: &GameHistory) -> Self {
+        let result = GameResult::from_board(history.current());
+        let headers = [
+            ("Event", "?"),
+            ("Site", "?"),
+            ("Date", "????.??.??"),
+            ("Round", "?"),
+            ("White", "?"),
+            ("Black", "?"),
+        ]
+        .into_iter()
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .chain([("Result".to_string(), result.to_string())])
+        .collect();
+        let start = &history.positions()[0];
+        let moves = history
+            .sans()
+            .iter()
+            .map(|san| PgnMove {
+                san: san.clone(),
+                nag: None,
+                comment: None,
+            })
+            .collect();
+        let mut pgn = Self {
+            headers,
+            moves,
+            result,
+            first_ply: match start.active_turn() {
+                PieceColor::White => 0,
+                PieceColor::Black => 1,
+            },
+            start_comment: None,
+        };
+        let fen = start.to_fen();
+        if fen != CheckerBoard::default().to_fen() {
+            pgn.set_header("SetUp", "1");
+            pgn.set_header("FEN", &fen);
+        }
+        pgn
+    }
+
+    /// Reads the first game in `text`, starting from its FEN tag if it has
+    /// one. Comments, variations and annotations are skipped.
+    pub fn parse(text: &str) -> Result<GameHistory, PgnError> {
+        let mut start = CheckerBoard::default();
+        let mut movetext = String::with_capacity(text.len());
+        for line in text.lines() {
+            let line = line.trim();
+            if let Some(tag) = line.strip_prefix('[') {
+                if let Some(fen) = tag
+                    .strip_prefix("FEN")
+                    .and_then(|value| value.trim().strip_prefix('"'))
+                    .and_then(|value| value.split('"').next())
+                {
+                    start = CheckerBoard::from_fen(fen)?;
+                }
+                continue;
+            }
+            if line.is_empty() && !movetext.trim().is_empty() {
+                break;
+            }
+            movetext.push_str(line.split(';').next().unwrap_or_default());
+            movetext.push(' ');
+        }
+        let mut history = GameHistory::new(start);
+        let mut comment = false;
+        let mut variations = 0;
+        for token in movetext
+            .replace('{', " { ")
+            .replace('}', " } ")
+            .replace('(', " ( ")
+            .replace(')', " ) ")
+            .split_whitespace()
+        {
+            match token {
+                "{" => comment = true,
+                "}" => comment = false,
+                _ if comment => {}
+                "(" => variations += 1,
+                ")" => variations -= 1,
+                _ if variations > 0 => {}
+                "1-0" | "0-1" | "1/2-1/2" | "*" => break,
+                _ if token.starts_with('$') => {}
+                _ => {
+                    let san = token.trim_start_matches(|c: char| c.is_ascii_digit() || c == '.');
+                    if san.is_empty() {
+                        continue;
+                    }
+                    let board_move = San::parse(history.current(), san)
+                        .ok_or_else(|| PgnError::IllegalMove(token.to_string()))?;
+                    history.push(board_move);
+                }
+            }
+        }
+        Ok(history)
+    }
+
+    /// The tags of the first game in `text`, in the order they are written.
+    pub fn parse_headers(text: &str) -> Vec<(String, String)> {
+        let mut headers = vec![];
+        for line in text.lines().map(str::trim) {
+            if line.is_empty() && !headers.is_empty() {
+                break;
+            }
+            let Some(tag) = line.strip_prefix('[').and_then(|tag| tag.strip_suffix(']')) else {
+                continue;
+            };
+            let Some((name, value)) = tag.split_once(' ') else {
+                continue;
+            };
+            let value = value.trim();
+            let value = value.strip_prefix('"').unwrap_or(value);
+            let value = value.strip_suffix('"').unwrap_or(value);
+            headers.push((name.to_string(), value.replace("\\\"", "\"")));
+        }
+        headers
+    }
+
+    /// Replaces the tag `name`, appending it after the existing ones if missing.
+    pub fn set_header(&mut self, name: &str, value: &str) {
+        match self.headers.iter_mut().find(|(tag, _)| tag == name) {
+            Some((_, old)) => *old = value.to_string(),
+            None => self.headers.push((name.to_string(), value.to_string())),
+        }
+    }
+
+    /// Sets the result of a game that ended off the board, such as on time.
+    pub fn set_result(&mut self, result: GameResult) {
+        self.set_header("Result", &result.to_string());
+        self.result = result;
+    }
+
+    pub fn annotate(&mut self, ply: usize, nag: Option<u8>, comment: Option<String>) {
+        if let Some(pgn_move) = self.moves.get_mut(ply) {
+            pgn_move.nag = nag;
+            pgn_move.comment = comment;
+        }
+    }
+
+    /// Adds `comment` after the one already on the move at `ply`, if any.
+    pub fn append_comment(&mut self, ply: usize, comment: &str) {
+        if let Some(pgn_move) = self.moves.get_mut(ply) {
+            pgn_move.comment = Some(match pgn_move.comment.take() {
+                Some(old) => format!("{} {}", old, comment),
+                None => comment.to_string(),
+            });
+        }
+    }
+
+    pub fn set_start_comment(&mut self, comment: &str) {
+        self.start_comment = Some(comment.to_string());
+    }
+
+    fn movetext(&self) -> Vec<String> {
+        let mut tokens = vec![];
+        if let Some(comment) = &self.start_comment {
+            tokens.push(format!("{{ {} }}", comment));
+        }
+        let mut after_comment = self.start_comment.is_some();
+        for (index, pgn_move) in self.moves.iter().enumerate() {
+            let ply = index + self.first_ply;
+            if ply.is_multiple_of(2) {
+                tokens.push(format!("{}.", ply / 2 + 1));
+            } else if after_comment || index == 0 {
+                tokens.push(format!("{}...", ply / 2 + 1));
+            }
+            tokens.push(pgn_move.san.clone());
+            if let Some(nag) = pgn_move.nag {
+                tokens.push(format!("${}", nag));
+            }
+            after_comment = pgn_move.comment.is_some();
+            if let Some(comment) = &pgn_move.comment {
+                tokens.push(format!("{{ {} }}", comment));
+            }
+        }
+        tokens.push(self.result.to_string());
+        tokens
+    }
+}
+
+impl Display for Pgn {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (name, value) in self.headers.iter() {
+            writeln!(f, "[{} \"{}\"]", name, value.replace('"', "\\\""))?;
+        }
+        writeln!(f)?;
+        let mut line = String::with_capacity(LINE_WIDTH);
+        for token in self.movetext() {
+            if !line.is_empty() && line.len() + token.len() + 1 > LINE_WIDTH {
+                writeln!(f, "{}", line)?;
+                line.clear();
+            }
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.push_str(&token);
+        }
+        writeln!(f, "{}", line)
+    }
+}
+
+#[cfg(test)]
+mod pgn_tests {
+    use crate::board::CheckerBoard;
+    use crate::board_pos;
+    use crate::game_history::GameHistory;
+    use crate::game_result::GameResult;
+    use crate::pgn::Pgn;
+    use std::str::FromStr;
+
+    #[test]
+    fn it_writes_the_seven_tag_roster() {
+        let pgn = Pgn::new(&GameHistory::default());
+        assert_eq!(
+            pgn.to_string(),
+            "[Event \"?\"]\n[Site \"?\"]\n[Date \"????.??.??\"]\n[Round \"?\"]\n\
+             [White \"?\"]\n[Black \"?\"]\n[Result \"*\"]\n\n*\n"
+        );
+    }
+
+    #[test]
+    fn it_numbers_moves() {
+        let pgn = Pgn::new(&italian());
+        assert!(pgn.to_string().ends_with("\n1. e4 e5 2. Nf3 *\n"));
+    }
+
+    #[test]
+    fn it_writes_nags_and_comments() {
+        let mut pgn = Pgn::new(&italian());
+        pgn.annotate(0, None, Some("[%eval 0.3]".to_string()));
+        pgn.annotate(1, Some(2), None);
+        assert!(pgn
+            .to_string()
+            .ends_with("\n1. e4 { [%eval 0.3] } 1... e5 $2 2. Nf3 *\n"));
+    }
+
+    #[test]
+    fn it_replaces_headers() {
+        let mut pgn = Pgn::new(&GameHistory::default());
+        pgn.set_header("White", "Carlsen");
+        pgn.set_header("ECO", "C50");
+        let text = pgn.to_string();
+        assert!(text.contains("[White \"Carlsen\"]\n"));
+        assert!(text.contains("[Result \"*\"]\n[ECO \"C50\"]\n"));
+    }
+
+    #[test]
+    fn it_wraps_long_movetext() {
+        let mut board = CheckerBoard::default();
+        for _ in 0..10 {
+            board.move_piece(&board_pos!("g1"), &board_pos!("f3"));
+            board.move_piece(&board_pos!("g8"), &board_pos!("f6"));
+            board.move_piece(&board_pos!("f3"), &board_pos!("g1"));
+            board.move_piece(&board_pos!("f6"), &board_pos!("g8"));
+        }
+        let history = GameHistory::replay(&CheckerBoard::default(), board.get_moves());
+        let text = Pgn::new(&history).to_string();
+        let movetext = text.split("\n\n").nth(1).unwrap();
+        assert!(movetext.lines().count() > 1);
+        assert!(movetext.lines().all(|line| line.len() <= 80));
+    }
+
+    #[test]
+    fn it_reads_its_own_output() {
+        let mut pgn = Pgn::new(&italian());
+        pgn.annotate(0, Some(1), Some("[%eval 0.3]".to_string()));
+        let history = Pgn::parse(&pgn.to_string()).unwrap();
+        assert_eq!(history.sans(), &vec!["e4", "e5", "Nf3"]);
+    }
+
+    #[test]
+    fn it_skips_variations_and_comments() {
+        let text = "[Event \"?\"]\n\n1. e4 (1. d4 d5) e5 {good} 2. Nf3 ; book\n2... Nc6 1-0\n";
+        let history = Pgn::parse(text).unwrap();
+        assert_eq!(history.sans(), &vec!["e4", "e5", "Nf3", "Nc6"]);
+    }
+
+    #[test]
+    fn it_starts_from_the_fen_tag() {
+        let text = "[SetUp \"1\"]\n[FEN \"4k3/8/8/8/8/8/8/R3K3 b - - 0 1\"]\n\n1... Kd7 2. Ra7+ *";
+        let history = Pgn::parse(text).unwrap();
+        assert_eq!(history.sans(), &vec!["Kd7", "Ra7+"]);
+        let written = Pgn::new(&history).to_string();
+        assert!(written.contains("[FEN \"4k3/8/8/8/8/8/8/R3K3 b - - 0 1\"]"));
+        assert!(written.ends_with("\n1... Kd7 2. Ra7+ *\n"));
+    }
+
+    #[test]
+    fn it_reads_back_headers_and_results() {
+        let mut pgn = Pgn::new(&italian());
+        pgn.set_header("White", "Anna \"The Rook\"");
+        pgn.set_result(GameResult::BlackWins);
+        let text = pgn.to_string();
+        assert!(text.ends_with(" 0-1\n"));
+        let headers = Pgn::parse_headers(&text);
+        assert!(headers.contains(&("White".to_string(), "Anna \"The Rook\"".to_string())));
+        assert!(headers.contains(&("Result".to_string(), "0-1".to_string())));
+    }
+
+    #[test]
+    fn it_rejects_illegal_moves() {
+        assert!(Pgn::parse("1. e5 *").is_err());
+    }
+
+    fn italian() -> GameHistory {
+        let mut board = CheckerBoard::default();
+        board.move_piece(&board_pos!("e2"), &board_pos!("e4"));
+        board.move_piece(&board_pos!("e7"), &board_pos!("e5"));
+        board.move_piece(&board_pos!("g1"), &board_pos!("f3"));
+        GameHistory::replay(&CheckerBoard::default(), board.get_moves())
+    }
+}
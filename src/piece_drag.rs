@@ -0,0 +1,60 @@
+use crate::animation::MoveAnimation;
+use crate::board_position_marker::BoardPositionMarker;
+use crate::board_ui_factory::BoardUiFactory;
+use crate::click_to_move::Selection;
+use crate::BoardPieceComponent;
+use bevy::prelude::{
+    ButtonInput, Commands, Component, Entity, KeyCode, MouseButton, Query, Res, ResMut, Vec3, With,
+};
+
+/// A piece picked up with the primary button. Drops are only accepted for
+/// pieces that are still being dragged.
+#[derive(Component, Clone)]
+pub struct Dragging;
+
+/// A piece that was let go of and has to be put on its square, which is the
+/// new square after a legal move and the origin square otherwise.
+#[derive(Component, Clone)]
+pub struct Settling;
+
+/// Right click or escape puts the dragged piece back.
+pub fn cancel_drag(
+    mut commands: Commands,
+    keys: Res<ButtonInput<KeyCode>>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    query: Query<Entity, With<Dragging>>,
+) {
+    if !keys.just_pressed(KeyCode::Escape) && !mouse.just_pressed(MouseButton::Right) {
+        return;
+    }
+    for entity in query.iter() {
+        commands
+            .entity(entity)
+            .remove::<Dragging>()
+            .insert(Settling);
+    }
+}
+
+pub fn settle_dropped_pieces(
+    mut commands: Commands,
+    board_ui_factory: Res<BoardUiFactory>,
+    mut selection: ResMut<Selection>,
+    query: Query<(Entity, &BoardPieceComponent), With<Settling>>,
+    marker_query: Query<Entity, With<BoardPositionMarker>>,
+) {
+    if query.is_empty() {
+        return;
+    }
+    for (entity, board_piece) in query.iter() {
+        let target = board_ui_factory
+            .get_pos_transform(&board_piece.0)
+            .translation
+            + Vec3::Z;
+        commands
+            .entity(entity)
+            .remove::<(Dragging, Settling)>()
+            .insert(MoveAnimation::new(target));
+    }
+    BoardUiFactory::remove_all_markers(&mut commands, &marker_query);
+    selection.clear();
+}
@@ -7,3 +7,28 @@ pub enum PieceType {
     Bishop,
     Queen,
 }
+
+impl PieceType {
+    pub fn symbol(&self) -> char {
+        match self {
+            PieceType::Pawn => 'P',
+            PieceType::Knight => 'N',
+            PieceType::King => 'K',
+            PieceType::Rook => 'R',
+            PieceType::Bishop => 'B',
+            PieceType::Queen => 'Q',
+        }
+    }
+
+    pub fn from_symbol(symbol: char) -> Option<Self> {
+        match symbol {
+            'P' => Some(PieceType::Pawn),
+            'N' => Some(PieceType::Knight),
+            'K' => Some(PieceType::King),
+            'R' => Some(PieceType::Rook),
+            'B' => Some(PieceType::Bishop),
+            'Q' => Some(PieceType::Queen),
+            _ => None,
+        }
+    }
+}
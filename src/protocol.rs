@@ -0,0 +1,627 @@
+use crate::board_position::BoardPosition;
+use crate::game_result::GameResult;
+use crate::pieces::color::PieceColor;
+use std::fmt::Display;
+use std::io::{ErrorKind, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::str::FromStr;
+use std::time::Duration;
+use thiserror::Error;
+
+/// The port the server listens on unless told otherwise.
+pub const DEFAULT_PORT: u16 = 7878;
+/// The port the server takes WebSocket connections from browsers on.
+pub const DEFAULT_WEBSOCKET_PORT: u16 = 7879;
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(3);
+
+#[derive(Error, Debug, PartialEq)]
+pub enum ProtocolError {
+    #[error("Invalid message: {0}")]
+    Invalid(String),
+}
+
+/// A game offered in the lobby. Challenges are seeks shown to a single
+/// player only.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Seek {
+    pub id: u32,
+    pub name: String,
+    pub minutes: u32,
+    pub increment: u32,
+    /// The side taken by the player who seeks.
+    pub color: PieceColor,
+}
+
+/// A game being played on the server, which anyone in the lobby may watch.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LiveGame {
+    pub id: u32,
+    pub white: String,
+    pub black: String,
+    pub minutes: u32,
+    pub increment: u32,
+}
+
+/// Lines sent by the players. Every message is a single line of words
+/// separated by spaces, so names may not contain spaces.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ClientMessage {
+    /// Joins the lobby, or takes back a seat after a dropped connection
+    /// when the token handed out on the first visit is given.
+    Hello {
+        name: String,
+        token: Option<String>,
+    },
+    Seek {
+        minutes: u32,
+        increment: u32,
+        color: PieceColor,
+    },
+    Challenge {
+        opponent: String,
+        minutes: u32,
+        increment: u32,
+        color: PieceColor,
+    },
+    Accept(u32),
+    Cancel(u32),
+    /// A move in UCI notation, such as `e2e4`.
+    Move(String),
+    Resign,
+    /// Offers a draw, or takes the draw the opponent offered.
+    Draw,
+    /// Follows a game without taking part, from its first move on.
+    Watch(u32),
+    Unwatch,
+}
+
+/// Lines sent by the server.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ServerMessage {
+    Welcome {
+        name: String,
+        token: String,
+    },
+    /// The players in the lobby who are free to play.
+    Players(Vec<String>),
+    Seek(Seek),
+    Challenge(Seek),
+    /// A seek or challenge that was taken or withdrawn.
+    Unseek(u32),
+    /// A game that started, or that was going on when we signed in.
+    Game(LiveGame),
+    /// A game that ended, so it can no longer be watched.
+    GameEnded(u32),
+    /// The game we watch from now on. Its moves so far and the clock follow,
+    /// and then every move as it is played.
+    Watching(LiveGame),
+    Start {
+        game: u32,
+        color: PieceColor,
+        opponent: String,
+        minutes: u32,
+        increment: u32,
+    },
+    /// The move played at `ply`, counted from zero, and the time both
+    /// sides have left after it in timed games.
+    Move {
+        ply: usize,
+        uci: String,
+        clock: Option<[Duration; 2]>,
+    },
+    Clock([Duration; 2]),
+    DrawOffered,
+    OpponentLeft,
+    OpponentReturned,
+    End {
+        result: GameResult,
+        reason: String,
+    },
+    Error(String),
+}
+
+fn color_word(color: &PieceColor) -> &'static str {
+    match color {
+        PieceColor::White => "white",
+        PieceColor::Black => "black",
+    }
+}
+
+fn parse_color(word: &str) -> Option<PieceColor> {
+    match word {
+        "white" => Some(PieceColor::White),
+        "black" => Some(PieceColor::Black),
+        _ => None,
+    }
+}
+
+fn parse_result(word: &str) -> Option<GameResult> {
+    match word {
+        "1-0" => Some(GameResult::WhiteWins),
+        "0-1" => Some(GameResult::BlackWins),
+        "1/2-1/2" => Some(GameResult::Draw),
+        _ => None,
+    }
+}
+
+fn millis(time: &Duration) -> u128 {
+    time.as_millis()
+}
+
+fn parse_millis(word: &str) -> Option<Duration> {
+    word.parse().ok().map(Duration::from_millis)
+}
+
+/// The squares of a move in UCI notation. The promotion piece, if any, is
+/// left out as pawns always promote to queens.
+pub fn parse_uci(text: &str) -> Option<(BoardPosition, BoardPosition)> {
+    let square = |file: u8, rank: u8| {
+        let x = file.checked_sub(b'a').filter(|x| *x < 8)?;
+        let y = rank.checked_sub(b'1').filter(|y| *y < 8)?;
+        Some(BoardPosition::new(x, y))
+    };
+    match text.as_bytes() {
+        [a, b, c, d] | [a, b, c, d, b'q'] => Some((square(*a, *b)?, square(*c, *d)?)),
+        _ => None,
+    }
+}
+
+/// A name is a single word, so that it fits in a line of the protocol.
+/// The name of players who did not choose one. The server numbers them so
+/// that any number of guests can sign in.
+pub const GUEST_NAME: &str = "Guest";
+
+pub fn is_valid_name(name: &str) -> bool {
+    !name.is_empty() && name.len() <= 20 && name.chars().all(|c| c.is_alphanumeric() || c == '_')
+}
+
+/// Splits a line into its words and checks how many there are.
+struct Words<'a> {
+    line: &'a str,
+    words: Vec<&'a str>,
+}
+
+impl<'a> Words<'a> {
+    fn new(line: &'a str) -> Self {
+        Self {
+            line,
+            words: line.split_whitespace().collect(),
+        }
+    }
+
+    fn invalid(&self) -> ProtocolError {
+        ProtocolError::Invalid(self.line.to_string())
+    }
+
+    fn get(&self, index: usize) -> Result<&'a str, ProtocolError> {
+        self.words.get(index).copied().ok_or_else(|| self.invalid())
+    }
+
+    fn number<T: FromStr>(&self, index: usize) -> Result<T, ProtocolError> {
+        self.get(index)?.parse().map_err(|_| self.invalid())
+    }
+
+    fn color(&self, index: usize) -> Result<PieceColor, ProtocolError> {
+        parse_color(self.get(index)?).ok_or_else(|| self.invalid())
+    }
+
+    /// Everything from the word at `index` on, for free text.
+    fn rest(&self, index: usize) -> String {
+        self.words[index.min(self.words.len())..].join(" ")
+    }
+}
+
+impl FromStr for ClientMessage {
+    type Err = ProtocolError;
+
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        let words = Words::new(line);
+        let message = match words.get(0)? {
+            "HELLO" => ClientMessage::Hello {
+                name: words.get(1)?.to_string(),
+                token: words.get(2).ok().map(str::to_string),
+            },
+            "SEEK" => ClientMessage::Seek {
+                minutes: words.number(1)?,
+                increment: words.number(2)?,
+                color: words.color(3)?,
+            },
+            "CHALLENGE" => ClientMessage::Challenge {
+                opponent: words.get(1)?.to_string(),
+                minutes: words.number(2)?,
+                increment: words.number(3)?,
+                color: words.color(4)?,
+            },
+            "ACCEPT" => ClientMessage::Accept(words.number(1)?),
+            "CANCEL" => ClientMessage::Cancel(words.number(1)?),
+            "MOVE" => ClientMessage::Move(words.get(1)?.to_string()),
+            "RESIGN" => ClientMessage::Resign,
+            "DRAW" => ClientMessage::Draw,
+            "WATCH" => ClientMessage::Watch(words.number(1)?),
+            "UNWATCH" => ClientMessage::Unwatch,
+            _ => return Err(words.invalid()),
+        };
+        Ok(message)
+    }
+}
+
+impl Display for ClientMessage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ClientMessage::Hello { name, token: None } => write!(f, "HELLO {}", name),
+            ClientMessage::Hello {
+                name,
+                token: Some(token),
+            } => write!(f, "HELLO {} {}", name, token),
+            ClientMessage::Seek {
+                minutes,
+                increment,
+                color,
+            } => write!(f, "SEEK {} {} {}", minutes, increment, color_word(color)),
+            ClientMessage::Challenge {
+                opponent,
+                minutes,
+                increment,
+                color,
+            } => write!(
+                f,
+                "CHALLENGE {} {} {} {}",
+                opponent,
+                minutes,
+                increment,
+                color_word(color)
+            ),
+            ClientMessage::Accept(id) => write!(f, "ACCEPT {}", id),
+            ClientMessage::Cancel(id) => write!(f, "CANCEL {}", id),
+            ClientMessage::Move(uci) => write!(f, "MOVE {}", uci),
+            ClientMessage::Resign => write!(f, "RESIGN"),
+            ClientMessage::Draw => write!(f, "DRAW"),
+            ClientMessage::Watch(id) => write!(f, "WATCH {}", id),
+            ClientMessage::Unwatch => write!(f, "UNWATCH"),
+        }
+    }
+}
+
+impl Seek {
+    fn parse(words: &Words) -> Result<Self, ProtocolError> {
+        Ok(Self {
+            id: words.number(1)?,
+            name: words.get(2)?.to_string(),
+            minutes: words.number(3)?,
+            increment: words.number(4)?,
+            color: words.color(5)?,
+        })
+    }
+}
+
+impl Display for Seek {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {} {} {} {}",
+            self.id,
+            self.name,
+            self.minutes,
+            self.increment,
+            color_word(&self.color)
+        )
+    }
+}
+
+impl LiveGame {
+    fn parse(words: &Words) -> Result<Self, ProtocolError> {
+        Ok(Self {
+            id: words.number(1)?,
+            white: words.get(2)?.to_string(),
+            black: words.get(3)?.to_string(),
+            minutes: words.number(4)?,
+            increment: words.number(5)?,
+        })
+    }
+}
+
+impl Display for LiveGame {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {} {} {} {}",
+            self.id, self.white, self.black, self.minutes, self.increment
+        )
+    }
+}
+
+impl FromStr for ServerMessage {
+    type Err = ProtocolError;
+
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        let words = Words::new(line);
+        let message = match words.get(0)? {
+            "WELCOME" => ServerMessage::Welcome {
+                name: words.get(1)?.to_string(),
+                token: words.get(2)?.to_string(),
+            },
+            "PLAYERS" => ServerMessage::Players(
+                words.words[1..]
+                    .iter()
+                    .map(|name| name.to_string())
+                    .collect(),
+            ),
+            "SEEK" => ServerMessage::Seek(Seek::parse(&words)?),
+            "CHALLENGE" => ServerMessage::Challenge(Seek::parse(&words)?),
+            "UNSEEK" => ServerMessage::Unseek(words.number(1)?),
+            "GAME" => ServerMessage::Game(LiveGame::parse(&words)?),
+            "GAME_ENDED" => ServerMessage::GameEnded(words.number(1)?),
+            "WATCHING" => ServerMessage::Watching(LiveGame::parse(&words)?),
+            "START" => ServerMessage::Start {
+                game: words.number(1)?,
+                color: words.color(2)?,
+                opponent: words.get(3)?.to_string(),
+                minutes: words.number(4)?,
+                increment: words.number(5)?,
+            },
+            "MOVE" => {
+                let clock = match (words.get(3)?, words.get(4)?) {
+                    ("-", "-") => None,
+                    (white, black) => Some([
+                        parse_millis(white).ok_or_else(|| words.invalid())?,
+                        parse_millis(black).ok_or_else(|| words.invalid())?,
+                    ]),
+                };
+                ServerMessage::Move {
+                    ply: words.number(1)?,
+                    uci: words.get(2)?.to_string(),
+                    clock,
+                }
+            }
+            "CLOCK" => ServerMessage::Clock([
+                parse_millis(words.get(1)?).ok_or_else(|| words.invalid())?,
+                parse_millis(words.get(2)?).ok_or_else(|| words.invalid())?,
+            ]),
+            "DRAW_OFFERED" => ServerMessage::DrawOffered,
+            "LEFT" => ServerMessage::OpponentLeft,
+            "RETURNED" => ServerMessage::OpponentReturned,
+            "END" => ServerMessage::End {
+                result: parse_result(words.get(1)?).ok_or_else(|| words.invalid())?,
+                reason: words.rest(2),
+            },
+            "ERROR" => ServerMessage::Error(words.rest(1)),
+            _ => return Err(words.invalid()),
+        };
+        Ok(message)
+    }
+}
+
+impl Display for ServerMessage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ServerMessage::Welcome { name, token } => write!(f, "WELCOME {} {}", name, token),
+            ServerMessage::Players(names) if names.is_empty() => write!(f, "PLAYERS"),
+            ServerMessage::Players(names) => write!(f, "PLAYERS {}", names.join(" ")),
+            ServerMessage::Seek(seek) => write!(f, "SEEK {}", seek),
+            ServerMessage::Challenge(seek) => write!(f, "CHALLENGE {}", seek),
+            ServerMessage::Unseek(id) => write!(f, "UNSEEK {}", id),
+            ServerMessage::Game(game) => write!(f, "GAME {}", game),
+            ServerMessage::GameEnded(id) => write!(f, "GAME_ENDED {}", id),
+            ServerMessage::Watching(game) => write!(f, "WATCHING {}", game),
+            ServerMessage::Start {
+                game,
+                color,
+                opponent,
+                minutes,
+                increment,
+            } => write!(
+                f,
+                "START {} {} {} {} {}",
+                game,
+                color_word(color),
+                opponent,
+                minutes,
+                increment
+            ),
+            ServerMessage::Move {
+                ply,
+                uci,
+                clock: None,
+            } => write!(f, "MOVE {} {} - -", ply, uci),
+            ServerMessage::Move {
+                ply,
+                uci,
+                clock: Some([white, black]),
+            } => write!(
+                f,
+                "MOVE {} {} {} {}",
+                ply,
+                uci,
+                millis(white),
+                millis(black)
+            ),
+            ServerMessage::Clock([white, black]) => {
+                write!(f, "CLOCK {} {}", millis(white), millis(black))
+            }
+            ServerMessage::DrawOffered => write!(f, "DRAW_OFFERED"),
+            ServerMessage::OpponentLeft => write!(f, "LEFT"),
+            ServerMessage::OpponentReturned => write!(f, "RETURNED"),
+            ServerMessage::End { result, reason } => write!(f, "END {} {}", result, reason),
+            ServerMessage::Error(error) => write!(f, "ERROR {}", error),
+        }
+    }
+}
+
+/// A connection that never blocks, for polling once a frame. Lines are
+/// read as they arrive and written as fast as the socket takes them.
+pub struct Connection {
+    stream: TcpStream,
+    incoming: Vec<u8>,
+    outgoing: Vec<u8>,
+    closed: bool,
+}
+
+impl Connection {
+    pub fn connect(address: &str) -> std::io::Result<Self> {
+        let address = address.to_socket_addrs()?.next().ok_or_else(|| {
+            std::io::Error::new(ErrorKind::NotFound, format!("No address for {}", address))
+        })?;
+        let stream = TcpStream::connect_timeout(&address, CONNECT_TIMEOUT)?;
+        Self::new(stream)
+    }
+
+    pub fn new(stream: TcpStream) -> std::io::Result<Self> {
+        stream.set_nonblocking(true)?;
+        stream.set_nodelay(true)?;
+        Ok(Self {
+            stream,
+            incoming: vec![],
+            outgoing: vec![],
+            closed: false,
+        })
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.closed
+    }
+
+    pub fn send(&mut self, message: impl Display) {
+        self.outgoing
+            .extend_from_slice(format!("{}\n", message).as_bytes());
+        self.flush();
+    }
+
+    fn flush(&mut self) {
+        while !self.outgoing.is_empty() && !self.closed {
+            match self.stream.write(&self.outgoing) {
+                Ok(0) => self.closed = true,
+                Ok(written) => {
+                    self.outgoing.drain(..written);
+                }
+                Err(error) if error.kind() == ErrorKind::WouldBlock => break,
+                Err(error) if error.kind() == ErrorKind::Interrupted => {}
+                Err(_) => self.closed = true,
+            }
+        }
+    }
+
+    /// The lines that arrived since the last call, without their line ends.
+    pub fn receive(&mut self) -> Vec<String> {
+        self.flush();
+        let mut buffer = [0; 4096];
+        while !self.closed {
+            match self.stream.read(&mut buffer) {
+                Ok(0) => self.closed = true,
+                Ok(read) => self.incoming.extend_from_slice(&buffer[..read]),
+                Err(error) if error.kind() == ErrorKind::WouldBlock => break,
+                Err(error) if error.kind() == ErrorKind::Interrupted => {}
+                Err(_) => self.closed = true,
+            }
+        }
+        let mut lines = vec![];
+        while let Some(end) = self.incoming.iter().position(|byte| *byte == b'\n') {
+            let line: Vec<u8> = self.incoming.drain(..=end).collect();
+            let line = String::from_utf8_lossy(&line).trim().to_string();
+            if !line.is_empty() {
+                lines.push(line);
+            }
+        }
+        lines
+    }
+}
+
+#[cfg(test)]
+mod protocol_tests {
+    use crate::board_pos;
+    use crate::game_result::GameResult;
+    use crate::pieces::color::PieceColor;
+    use crate::protocol::{parse_uci, ClientMessage, LiveGame, Seek, ServerMessage};
+    use std::str::FromStr;
+    use std::time::Duration;
+
+    #[test]
+    fn client_messages_round_trip() {
+        let messages = [
+            ClientMessage::Hello {
+                name: "anna".to_string(),
+                token: None,
+            },
+            ClientMessage::Hello {
+                name: "anna".to_string(),
+                token: Some("1f2e".to_string()),
+            },
+            ClientMessage::Challenge {
+                opponent: "ben".to_string(),
+                minutes: 5,
+                increment: 3,
+                color: PieceColor::Black,
+            },
+            ClientMessage::Move("e7e8q".to_string()),
+            ClientMessage::Draw,
+            ClientMessage::Watch(12),
+            ClientMessage::Unwatch,
+        ];
+        for message in messages {
+            assert_eq!(ClientMessage::from_str(&message.to_string()), Ok(message));
+        }
+    }
+
+    #[test]
+    fn server_messages_round_trip() {
+        let messages = [
+            ServerMessage::Players(vec![]),
+            ServerMessage::Players(vec!["anna".to_string(), "ben".to_string()]),
+            ServerMessage::Seek(Seek {
+                id: 4,
+                name: "anna".to_string(),
+                minutes: 3,
+                increment: 2,
+                color: PieceColor::White,
+            }),
+            ServerMessage::Move {
+                ply: 0,
+                uci: "e2e4".to_string(),
+                clock: Some([Duration::from_millis(179_500), Duration::from_secs(180)]),
+            },
+            ServerMessage::Move {
+                ply: 1,
+                uci: "e7e5".to_string(),
+                clock: None,
+            },
+            ServerMessage::Watching(LiveGame {
+                id: 2,
+                white: "anna".to_string(),
+                black: "ben".to_string(),
+                minutes: 0,
+                increment: 0,
+            }),
+            ServerMessage::GameEnded(2),
+            ServerMessage::End {
+                result: GameResult::Draw,
+                reason: "by agreement".to_string(),
+            },
+            ServerMessage::Error("That is not your move".to_string()),
+        ];
+        for message in messages {
+            assert_eq!(ServerMessage::from_str(&message.to_string()), Ok(message));
+        }
+    }
+
+    #[test]
+    fn bad_lines_are_rejected() {
+        assert!(ClientMessage::from_str("").is_err());
+        assert!(ClientMessage::from_str("SEEK 5").is_err());
+        assert!(ClientMessage::from_str("SEEK 5 0 red").is_err());
+        assert!(ServerMessage::from_str("MOVE 1 e2e4 10").is_err());
+    }
+
+    #[test]
+    fn uci_moves_are_read_without_panicking() {
+        assert_eq!(
+            parse_uci("g1f3"),
+            Some((board_pos!("g1"), board_pos!("f3")))
+        );
+        assert_eq!(
+            parse_uci("e7e8q"),
+            Some((board_pos!("e7"), board_pos!("e8")))
+        );
+        assert_eq!(parse_uci("i1f3"), None);
+        assert_eq!(parse_uci("e9e8"), None);
+        assert_eq!(parse_uci("e2"), None);
+    }
+}
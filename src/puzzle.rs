@@ -0,0 +1,317 @@
+use crate::board::CheckerBoard;
+use crate::board_move::BoardMove;
+use crate::board_ui_factory::BoardUiFactory;
+use crate::game_history::GameHistory;
+use crate::game_over::GameOutcome;
+use crate::game_result::GameResult;
+use crate::game_setup::{AppState, GameSetup, Opponent, StartGame};
+use crate::notation::San;
+use crate::pieces::color::PieceColor;
+use crate::screen_layout::PanelPlacement;
+use crate::sound::SoundEffect;
+use crate::BoardPieceComponent;
+use bevy::prelude::{
+    default, Commands, Component, Entity, EventWriter, NextState, PositionType, Query, Res, ResMut,
+    Resource, Style, Text, TextBundle, TextStyle, Val, With,
+};
+
+const PUZZLE_TABLE: &str = include_str!("puzzles.tsv");
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Puzzle {
+    name: String,
+    fen: String,
+    solution: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PuzzleProgress {
+    Solving,
+    /// The solver's move was right and this is the answer to it.
+    Reply(BoardMove),
+    Solved,
+    Failed,
+}
+
+impl Puzzle {
+    pub fn new(name: &str, fen: &str, solution: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            fen: fen.to_string(),
+            solution: solution.split_whitespace().map(|m| m.to_string()).collect(),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn start(&self) -> CheckerBoard {
+        CheckerBoard::from_fen(&self.fen).unwrap_or_else(|_| CheckerBoard::default())
+    }
+
+    /// The side the solver plays.
+    pub fn color(&self) -> PieceColor {
+        self.start().active_turn().clone()
+    }
+
+    pub fn mate_in(&self) -> usize {
+        self.solution.len().div_ceil(2)
+    }
+
+    /// Follows the moves played so far. A different move from the solver
+    /// still solves the puzzle when it mates.
+    pub fn check(&self, history: &GameHistory) -> PuzzleProgress {
+        for (ply, board_move) in history.moves().iter().enumerate().step_by(2) {
+            let before = &history.positions()[ply];
+            let after = &history.positions()[ply + 1];
+            if after.is_mated(after.active_turn()) {
+                return PuzzleProgress::Solved;
+            }
+            let expected = self
+                .solution
+                .get(ply)
+                .and_then(|san| San::parse(before, san));
+            if expected.as_ref() != Some(board_move) {
+                return PuzzleProgress::Failed;
+            }
+        }
+        let played = history.len();
+        if played >= self.solution.len() {
+            return PuzzleProgress::Solved;
+        }
+        if played % 2 == 1 {
+            if let Some(reply) = San::parse(history.current(), &self.solution[played]) {
+                return PuzzleProgress::Reply(reply);
+            }
+        }
+        PuzzleProgress::Solving
+    }
+}
+
+#[derive(Resource)]
+pub struct Puzzles {
+    puzzles: Vec<Puzzle>,
+    current: Option<usize>,
+    message: String,
+}
+
+impl Puzzles {
+    pub fn parse(table: &str) -> Self {
+        let puzzles = table
+            .lines()
+            .filter_map(|line| {
+                let mut columns = line.split('\t');
+                Some(Puzzle::new(
+                    columns.next()?,
+                    columns.next()?,
+                    columns.next()?,
+                ))
+            })
+            .collect();
+        Self {
+            puzzles,
+            current: None,
+            message: String::new(),
+        }
+    }
+
+    pub fn current(&self) -> Option<&Puzzle> {
+        self.current.and_then(|index| self.puzzles.get(index))
+    }
+
+    /// Sets up puzzle `index`, wrapping around at the end of the list.
+    pub fn start(&mut self, index: usize) -> Option<GameHistory> {
+        if self.puzzles.is_empty() {
+            return None;
+        }
+        let index = index % self.puzzles.len();
+        self.current = Some(index);
+        self.message.clear();
+        Some(GameHistory::new(self.puzzles[index].start()))
+    }
+
+    pub fn restart(&mut self) -> Option<GameHistory> {
+        self.start(self.current.unwrap_or(0))
+    }
+
+    pub fn next(&mut self) -> Option<GameHistory> {
+        self.start(self.current.map(|index| index + 1).unwrap_or(0))
+    }
+}
+
+impl Default for Puzzles {
+    fn default() -> Self {
+        Self::parse(PUZZLE_TABLE)
+    }
+}
+
+#[derive(Component)]
+pub struct PuzzleLabel;
+
+pub fn spawn_puzzle_label(mut commands: Commands) {
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font_size: 18.,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            left: Val::Px(930.),
+            top: Val::Px(600.),
+            ..default()
+        }),
+        PuzzleLabel,
+        PanelPlacement::new(930., 600.).narrow(8., 940.),
+    ));
+}
+
+/// Answers correct moves with the next move of the solution and starts over
+/// after a wrong one.
+pub fn follow_puzzle(
+    mut commands: Commands,
+    setup: Res<GameSetup>,
+    history: Res<GameHistory>,
+    mut puzzles: ResMut<Puzzles>,
+    mut board_ui_factory: ResMut<BoardUiFactory>,
+    pieces_query: Query<(Entity, &BoardPieceComponent)>,
+    mut outcome: ResMut<GameOutcome>,
+    mut next_state: ResMut<NextState<AppState>>,
+    mut start_game: EventWriter<StartGame>,
+    mut sounds: EventWriter<SoundEffect>,
+) {
+    if setup.opponent != Opponent::Puzzle {
+        return;
+    }
+    let Some(puzzle) = puzzles.current() else {
+        return;
+    };
+    match puzzle.check(&history) {
+        PuzzleProgress::Solving => {}
+        PuzzleProgress::Reply(reply) => {
+            let side_effects = board_ui_factory.play_move(&mut commands, pieces_query, &reply);
+            if let Some(side_effects) = side_effects {
+                sounds.send(SoundEffect::from_move(
+                    &side_effects,
+                    &board_ui_factory.board,
+                ));
+            }
+        }
+        PuzzleProgress::Solved => {
+            let result = match puzzle.color() {
+                PieceColor::White => GameResult::WhiteWins,
+                PieceColor::Black => GameResult::BlackWins,
+            };
+            *outcome = GameOutcome::new(result, "puzzle solved");
+            next_state.set(AppState::GameOver);
+        }
+        PuzzleProgress::Failed => {
+            if let Some(start) = puzzles.restart() {
+                start_game.send(StartGame(start));
+            }
+            puzzles.message = "Not the right move, try again.".to_string();
+        }
+    }
+}
+
+pub fn update_puzzle_label(
+    setup: Res<GameSetup>,
+    puzzles: Res<Puzzles>,
+    mut query: Query<&mut Text, With<PuzzleLabel>>,
+) {
+    let value = match puzzles.current() {
+        Some(puzzle) if setup.opponent == Opponent::Puzzle => {
+            let color = match puzzle.color() {
+                PieceColor::White => "White",
+                PieceColor::Black => "Black",
+            };
+            format!(
+                "Puzzle: {}\n{} to move and mate in {}\n{}",
+                puzzle.name(),
+                color,
+                puzzle.mate_in(),
+                puzzles.message
+            )
+        }
+        _ => String::new(),
+    };
+    for mut text in query.iter_mut() {
+        text.sections[0].value = value.clone();
+    }
+}
+
+#[cfg(test)]
+mod puzzle_tests {
+    use crate::board_move::BoardMove;
+    use crate::board_pos;
+    use crate::game_history::GameHistory;
+    use crate::notation::San;
+    use crate::pieces::color::PieceColor;
+    use crate::pieces::piece_type::PieceType;
+    use crate::puzzle::{Puzzle, PuzzleProgress, Puzzles};
+    use std::str::FromStr;
+
+    #[test]
+    fn bundled_solutions_are_legal_and_end_in_mate() {
+        let puzzles = Puzzles::default();
+        assert!(!puzzles.puzzles.is_empty());
+        for puzzle in puzzles.puzzles.iter() {
+            let mut history = GameHistory::new(puzzle.start());
+            for san in puzzle.solution.iter() {
+                let board_move = San::parse(history.current(), san)
+                    .unwrap_or_else(|| panic!("{}: {} is not legal", puzzle.name(), san));
+                history.push(board_move);
+            }
+            let end = history.current();
+            assert!(end.is_mated(end.active_turn()), "{}", puzzle.name());
+        }
+    }
+
+    #[test]
+    fn correct_moves_are_answered_until_solved() {
+        let puzzle = roller();
+        let mut history = GameHistory::new(puzzle.start());
+        assert_eq!(puzzle.check(&history), PuzzleProgress::Solving);
+        history.push(rook("a2", "a7"));
+        let reply = BoardMove::new(PieceType::King, board_pos!("h8"), board_pos!("g8"));
+        assert_eq!(puzzle.check(&history), PuzzleProgress::Reply(reply.clone()));
+        history.push(reply);
+        history.push(rook("b1", "b8"));
+        assert_eq!(puzzle.check(&history), PuzzleProgress::Solved);
+    }
+
+    #[test]
+    fn other_moves_fail_unless_they_mate() {
+        let puzzle = roller();
+        let mut history = GameHistory::new(puzzle.start());
+        history.push(rook("a2", "a6"));
+        assert_eq!(puzzle.check(&history), PuzzleProgress::Failed);
+        let back_rank = Puzzle::new("", "6k1/5ppp/8/8/8/8/R7/R5K1 w - - 0 1", "Ra8#");
+        let mut history = GameHistory::new(back_rank.start());
+        history.push(rook("a2", "a8"));
+        assert_eq!(back_rank.check(&history), PuzzleProgress::Solved);
+    }
+
+    #[test]
+    fn puzzles_wrap_around() {
+        let mut puzzles = Puzzles::parse("One\t8/8/8/8/8/8/8/K6k b - - 0 1\tKg2\n");
+        assert!(puzzles.next().is_some());
+        assert!(puzzles.next().is_some());
+        assert_eq!(puzzles.current().map(|puzzle| puzzle.name()), Some("One"));
+        assert_eq!(
+            puzzles.current().map(|puzzle| puzzle.color()),
+            Some(PieceColor::Black)
+        );
+    }
+
+    fn roller() -> Puzzle {
+        Puzzle::new("", "7k/8/8/8/8/8/R7/1R4K1 w - - 0 1", "Ra7 Kg8 Rb8#")
+    }
+
+    fn rook(from: &str, to: &str) -> BoardMove {
+        BoardMove::new(PieceType::Rook, board_pos!(from), board_pos!(to))
+    }
+}
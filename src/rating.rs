@@ -0,0 +1,542 @@
+use crate::game_result::GameResult;
+use std::f64::consts::PI;
+use std::fmt::Display;
+use std::str::FromStr;
+use thiserror::Error;
+
+/// Converts Glicko ratings to the Glicko-2 scale and back.
+const GLICKO2_SCALE: f64 = 173.7178;
+/// How much the volatility may change from game to game.
+const TAU: f64 = 0.5;
+/// Ratings with a deviation above this are still provisional.
+const PROVISIONAL_DEVIATION: f64 = 110.;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum RatingError {
+    #[error("Invalid ratings: {0}")]
+    Invalid(String),
+    #[error("{0}")]
+    NotAllowed(String),
+}
+
+/// Ratings are kept apart by how long games take, as a player's bullet
+/// strength says little about their classical one.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Category {
+    Bullet,
+    Blitz,
+    Rapid,
+    Classical,
+    Correspondence,
+}
+
+impl Category {
+    pub const ALL: [Category; 5] = [
+        Category::Bullet,
+        Category::Blitz,
+        Category::Rapid,
+        Category::Classical,
+        Category::Correspondence,
+    ];
+
+    /// The category of a game over the board, by its time for forty moves.
+    /// Games without a clock count as classical.
+    pub fn from_clock(minutes_and_increment: Option<(u32, u32)>) -> Self {
+        let Some((minutes, increment)) = minutes_and_increment else {
+            return Category::Classical;
+        };
+        match minutes * 60 + increment * 40 {
+            ..180 => Category::Bullet,
+            180..480 => Category::Blitz,
+            480..1500 => Category::Rapid,
+            _ => Category::Classical,
+        }
+    }
+
+    pub fn next(&self) -> Self {
+        let index = Self::ALL.iter().position(|category| category == self);
+        Self::ALL[index.map_or(0, |index| (index + 1) % Self::ALL.len())]
+    }
+}
+
+impl FromStr for Category {
+    type Err = RatingError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|category| category.to_string().eq_ignore_ascii_case(text))
+            .ok_or_else(|| RatingError::Invalid(format!("Unknown category {}", text)))
+    }
+}
+
+impl Display for Category {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Category::Bullet => "Bullet",
+            Category::Blitz => "Blitz",
+            Category::Rapid => "Rapid",
+            Category::Classical => "Classical",
+            Category::Correspondence => "Correspondence",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// The score expected against an opponent, by the Elo formula.
+pub fn expected_score(rating: f64, opponent: f64) -> f64 {
+    1. / (1. + 10f64.powf((opponent - rating) / 400.))
+}
+
+/// A Glicko-2 rating: the rating, how far off it may be, and how erratic
+/// the player's results are.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Glicko {
+    pub rating: f64,
+    pub deviation: f64,
+    pub volatility: f64,
+}
+
+impl Default for Glicko {
+    fn default() -> Self {
+        Self {
+            rating: 1500.,
+            deviation: 350.,
+            volatility: 0.06,
+        }
+    }
+}
+
+impl Glicko {
+    pub fn is_provisional(&self) -> bool {
+        self.deviation > PROVISIONAL_DEVIATION
+    }
+
+    /// The rating after a rating period with the given games, each against
+    /// an opponent rated as they were before the period, and what was scored
+    /// in it. This follows Glickman's description of Glicko-2 step by step.
+    pub fn update(&self, games: &[(Glicko, f64)]) -> Glicko {
+        let mu = (self.rating - 1500.) / GLICKO2_SCALE;
+        let phi = self.deviation / GLICKO2_SCALE;
+        if games.is_empty() {
+            let phi = (phi.powi(2) + self.volatility.powi(2)).sqrt();
+            return Glicko {
+                deviation: phi * GLICKO2_SCALE,
+                ..*self
+            };
+        }
+        let g = |phi: f64| 1. / (1. + 3. * phi.powi(2) / PI.powi(2)).sqrt();
+        let opponents: Vec<(f64, f64, f64)> = games
+            .iter()
+            .map(|(opponent, score)| {
+                let mu_j = (opponent.rating - 1500.) / GLICKO2_SCALE;
+                let g_j = g(opponent.deviation / GLICKO2_SCALE);
+                let expected = 1. / (1. + (-g_j * (mu - mu_j)).exp());
+                (g_j, expected, *score)
+            })
+            .collect();
+        let variance = 1.
+            / opponents
+                .iter()
+                .map(|(g_j, expected, _)| g_j.powi(2) * expected * (1. - expected))
+                .sum::<f64>();
+        let improvement: f64 = opponents
+            .iter()
+            .map(|(g_j, expected, score)| g_j * (score - expected))
+            .sum();
+        let delta = variance * improvement;
+        let volatility = self.new_volatility(phi, variance, delta);
+        let phi_star = (phi.powi(2) + volatility.powi(2)).sqrt();
+        let phi = 1. / (1. / phi_star.powi(2) + 1. / variance).sqrt();
+        let mu = mu + phi.powi(2) * improvement;
+        Glicko {
+            rating: mu * GLICKO2_SCALE + 1500.,
+            deviation: phi * GLICKO2_SCALE,
+            volatility,
+        }
+    }
+
+    /// Finds the new volatility by the Illinois algorithm.
+    fn new_volatility(&self, phi: f64, variance: f64, delta: f64) -> f64 {
+        let a = self.volatility.powi(2).ln();
+        let f = |x: f64| {
+            let ex = x.exp();
+            ex * (delta.powi(2) - phi.powi(2) - variance - ex)
+                / (2. * (phi.powi(2) + variance + ex).powi(2))
+                - (x - a) / TAU.powi(2)
+        };
+        let mut low = a;
+        let mut high = if delta.powi(2) > phi.powi(2) + variance {
+            (delta.powi(2) - phi.powi(2) - variance).ln()
+        } else {
+            let mut k = 1.;
+            while f(a - k * TAU) < 0. {
+                k += 1.;
+            }
+            a - k * TAU
+        };
+        let (mut f_low, mut f_high) = (f(low), f(high));
+        while (high - low).abs() > 1e-6 {
+            let next = low + (low - high) * f_low / (f_high - f_low);
+            let f_next = f(next);
+            if f_next * f_high <= 0. {
+                low = high;
+                f_low = f_high;
+            } else {
+                f_low /= 2.;
+            }
+            high = next;
+            f_high = f_next;
+        }
+        (low / 2.).exp()
+    }
+}
+
+/// The ratings after a game, for the history chart.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RatingPoint {
+    pub elo: f64,
+    pub glicko: f64,
+}
+
+/// A player's ratings and results in one category.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pool {
+    pub category: Category,
+    pub elo: f64,
+    pub glicko: Glicko,
+    pub wins: u32,
+    pub draws: u32,
+    pub losses: u32,
+    pub history: Vec<RatingPoint>,
+}
+
+impl Pool {
+    fn new(category: Category) -> Self {
+        Self {
+            category,
+            elo: 1500.,
+            glicko: Glicko::default(),
+            wins: 0,
+            draws: 0,
+            losses: 0,
+            history: vec![],
+        }
+    }
+
+    pub fn games(&self) -> u32 {
+        self.wins + self.draws + self.losses
+    }
+
+    /// Elo ratings move fast while a player is new, and slowly at the top,
+    /// as in FIDE's rules.
+    fn k_factor(&self) -> f64 {
+        if self.games() < 30 {
+            40.
+        } else if self.elo < 2400. {
+            20.
+        } else {
+            10.
+        }
+    }
+
+    fn add(&mut self, score: f64, elo: f64, glicko: Glicko) {
+        match score {
+            score if score > 0.5 => self.wins += 1,
+            score if score < 0.5 => self.losses += 1,
+            _ => self.draws += 1,
+        }
+        self.elo = elo;
+        self.glicko = glicko;
+        self.history.push(RatingPoint {
+            elo,
+            glicko: glicko.rating,
+        });
+    }
+}
+
+/// A local player, or a level of the computer, which plays on the same
+/// scale so that its strength can be read off the ladder.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Profile {
+    pub name: String,
+    pub pools: Vec<Pool>,
+}
+
+impl Profile {
+    pub fn pool(&self, category: Category) -> Option<&Pool> {
+        self.pools.iter().find(|pool| pool.category == category)
+    }
+}
+
+/// Every player's ratings, kept as `key=value` lines: a `player` line, then
+/// a `pool` line for each category they played in, followed by its ratings,
+/// score and history.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Ratings {
+    pub profiles: Vec<Profile>,
+}
+
+impl Ratings {
+    pub fn find(&self, name: &str) -> Option<&Profile> {
+        self.profiles
+            .iter()
+            .find(|profile| profile.name.eq_ignore_ascii_case(name.trim()))
+    }
+
+    fn pool(&self, name: &str, category: Category) -> Pool {
+        self.find(name)
+            .and_then(|profile| profile.pool(category))
+            .cloned()
+            .unwrap_or_else(|| Pool::new(category))
+    }
+
+    fn store(&mut self, name: &str, pool: Pool) {
+        let index = match self
+            .profiles
+            .iter()
+            .position(|profile| profile.name.eq_ignore_ascii_case(name.trim()))
+        {
+            Some(index) => index,
+            None => {
+                self.profiles.push(Profile {
+                    name: name.trim().to_string(),
+                    pools: vec![],
+                });
+                self.profiles.len() - 1
+            }
+        };
+        let pools = &mut self.profiles[index].pools;
+        match pools.iter_mut().find(|kept| kept.category == pool.category) {
+            Some(kept) => *kept = pool,
+            None => pools.push(pool),
+        }
+    }
+
+    /// Rates a finished game, making profiles for new players, and tells
+    /// how much White's and Black's Glicko ratings moved.
+    pub fn record(
+        &mut self,
+        white: &str,
+        black: &str,
+        category: Category,
+        result: &GameResult,
+    ) -> Result<[f64; 2], RatingError> {
+        let score = match result {
+            GameResult::WhiteWins => 1.,
+            GameResult::BlackWins => 0.,
+            GameResult::Draw => 0.5,
+            GameResult::Ongoing => {
+                return Err(RatingError::NotAllowed(
+                    "Only finished games are rated".to_string(),
+                ))
+            }
+        };
+        if white.trim().is_empty() || white.trim().eq_ignore_ascii_case(black.trim()) {
+            return Err(RatingError::NotAllowed(
+                "Games need two named players".to_string(),
+            ));
+        }
+        let mut pools = [self.pool(white, category), self.pool(black, category)];
+        let before = pools.clone();
+        for (side, score) in [(0, score), (1, 1. - score)] {
+            let (pool, opponent) = (&before[side], &before[1 - side]);
+            let elo = pool.elo + pool.k_factor() * (score - expected_score(pool.elo, opponent.elo));
+            let glicko = pool.glicko.update(&[(opponent.glicko, score)]);
+            pools[side].add(score, elo, glicko);
+        }
+        let changes = [0, 1].map(|side| pools[side].glicko.rating - before[side].glicko.rating);
+        let [white_pool, black_pool] = pools;
+        self.store(white, white_pool);
+        self.store(black, black_pool);
+        Ok(changes)
+    }
+
+    /// The players who have played in `category`, the highest Glicko rating
+    /// first.
+    pub fn ladder(&self, category: Category) -> Vec<(&Profile, &Pool)> {
+        let mut ladder: Vec<(&Profile, &Pool)> = self
+            .profiles
+            .iter()
+            .filter_map(|profile| Some((profile, profile.pool(category)?)))
+            .filter(|(_, pool)| pool.games() > 0)
+            .collect();
+        ladder.sort_by(|(_, a), (_, b)| b.glicko.rating.total_cmp(&a.glicko.rating));
+        ladder
+    }
+}
+
+impl FromStr for Ratings {
+    type Err = RatingError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let invalid = |line: &str| RatingError::Invalid(line.to_string());
+        let mut ratings = Ratings::default();
+        for line in text.lines().map(str::trim).filter(|line| !line.is_empty()) {
+            let (key, value) = line.split_once('=').ok_or_else(|| invalid(line))?;
+            if key == "player" {
+                ratings.profiles.push(Profile {
+                    name: value.to_string(),
+                    pools: vec![],
+                });
+                continue;
+            }
+            let profile = ratings.profiles.last_mut().ok_or_else(|| invalid(line))?;
+            if key == "pool" {
+                profile.pools.push(Pool::new(value.parse()?));
+                continue;
+            }
+            let pool = profile.pools.last_mut().ok_or_else(|| invalid(line))?;
+            let numbers = value
+                .split_whitespace()
+                .map(|word| word.parse::<f64>().map_err(|_| invalid(line)))
+                .collect::<Result<Vec<f64>, RatingError>>()?;
+            match (key, numbers.as_slice()) {
+                ("elo", [elo]) => pool.elo = *elo,
+                ("glicko", [rating, deviation, volatility]) => {
+                    pool.glicko = Glicko {
+                        rating: *rating,
+                        deviation: *deviation,
+                        volatility: *volatility,
+                    }
+                }
+                ("score", [wins, draws, losses]) => {
+                    pool.wins = *wins as u32;
+                    pool.draws = *draws as u32;
+                    pool.losses = *losses as u32;
+                }
+                ("history", [elo, glicko]) => pool.history.push(RatingPoint {
+                    elo: *elo,
+                    glicko: *glicko,
+                }),
+                _ => return Err(invalid(line)),
+            }
+        }
+        Ok(ratings)
+    }
+}
+
+impl Display for Ratings {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for profile in self.profiles.iter() {
+            writeln!(f, "player={}", profile.name)?;
+            for pool in profile.pools.iter() {
+                writeln!(f, "pool={}", pool.category.to_string().to_lowercase())?;
+                writeln!(f, "elo={}", pool.elo)?;
+                let glicko = &pool.glicko;
+                writeln!(
+                    f,
+                    "glicko={} {} {}",
+                    glicko.rating, glicko.deviation, glicko.volatility
+                )?;
+                writeln!(f, "score={} {} {}", pool.wins, pool.draws, pool.losses)?;
+                for point in pool.history.iter() {
+                    writeln!(f, "history={} {}", point.elo, point.glicko)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod rating_tests {
+    use crate::game_result::GameResult;
+    use crate::rating::{expected_score, Category, Glicko, Ratings};
+    use std::str::FromStr;
+
+    fn glicko(rating: f64, deviation: f64) -> Glicko {
+        Glicko {
+            rating,
+            deviation,
+            volatility: 0.06,
+        }
+    }
+
+    #[test]
+    fn glicko2_matches_glickmans_example() {
+        let player = glicko(1500., 200.);
+        let updated = player.update(&[
+            (glicko(1400., 30.), 1.),
+            (glicko(1550., 100.), 0.),
+            (glicko(1700., 300.), 0.),
+        ]);
+        assert!((updated.rating - 1464.06).abs() < 0.01);
+        assert!((updated.deviation - 151.52).abs() < 0.01);
+        assert!((updated.volatility - 0.05999).abs() < 0.00001);
+        assert!(player.update(&[]).deviation > 200.);
+    }
+
+    #[test]
+    fn elo_moves_by_the_surprise_of_the_result() {
+        assert_eq!(expected_score(1500., 1500.), 0.5);
+        assert!((expected_score(1700., 1500.) - 0.76).abs() < 0.01);
+        let mut ratings = Ratings::default();
+        let changes = ratings
+            .record("Anna", "Ben", Category::Blitz, &GameResult::WhiteWins)
+            .unwrap();
+        assert!(changes[0] > 0. && changes[0] == -changes[1]);
+        let anna = ratings.find("anna").unwrap().pool(Category::Blitz).unwrap();
+        assert_eq!(anna.elo, 1520.);
+        assert_eq!((anna.wins, anna.games()), (1, 1));
+        assert_eq!(anna.history.len(), 1);
+        assert!(ratings.find("Ben").unwrap().pool(Category::Rapid).is_none());
+    }
+
+    #[test]
+    fn games_are_rated_in_the_pool_of_their_time_control() {
+        assert_eq!(Category::from_clock(Some((1, 0))), Category::Bullet);
+        assert_eq!(Category::from_clock(Some((3, 2))), Category::Blitz);
+        assert_eq!(Category::from_clock(Some((10, 0))), Category::Rapid);
+        assert_eq!(Category::from_clock(Some((15, 10))), Category::Rapid);
+        assert_eq!(Category::from_clock(None), Category::Classical);
+        assert_eq!(Category::Correspondence.next(), Category::Bullet);
+    }
+
+    #[test]
+    fn ladders_rank_players_by_glicko() {
+        let mut ratings = Ratings::default();
+        for _ in 0..3 {
+            ratings
+                .record("Anna", "Ben", Category::Rapid, &GameResult::BlackWins)
+                .unwrap();
+        }
+        ratings
+            .record("Cleo", "Anna", Category::Rapid, &GameResult::Draw)
+            .unwrap();
+        let ladder: Vec<&str> = ratings
+            .ladder(Category::Rapid)
+            .iter()
+            .map(|(profile, _)| profile.name.as_str())
+            .collect();
+        assert_eq!(ladder, vec!["Ben", "Cleo", "Anna"]);
+        assert!(ratings.ladder(Category::Blitz).is_empty());
+        assert!(ratings
+            .record("Anna", "anna", Category::Rapid, &GameResult::Draw)
+            .is_err());
+        assert!(ratings
+            .record("Anna", "Ben", Category::Rapid, &GameResult::Ongoing)
+            .is_err());
+    }
+
+    #[test]
+    fn ratings_round_trip_through_text() {
+        let mut ratings = Ratings::default();
+        ratings
+            .record("Anna Smith", "Ben", Category::Bullet, &GameResult::Draw)
+            .unwrap();
+        ratings
+            .record(
+                "Ben",
+                "Anna Smith",
+                Category::Correspondence,
+                &GameResult::WhiteWins,
+            )
+            .unwrap();
+        let text = ratings.to_string();
+        assert!(text.starts_with("player=Anna Smith\npool=bullet\nelo=1500\n"));
+        assert_eq!(Ratings::from_str(&text), Ok(ratings));
+        assert!(Ratings::from_str("elo=1500").is_err());
+        assert!(Ratings::from_str("player=Anna\npool=lightning").is_err());
+    }
+}
@@ -0,0 +1,344 @@
+use crate::announcements::Announcements;
+use crate::game_over::GameOutcome;
+use crate::game_setup::{AppState, GameSetup, Opponent, Variant};
+use crate::menu::spawn_button;
+use crate::pieces::color::PieceColor;
+use crate::storage;
+use bevy::log::warn;
+use bevy::prelude::{
+    default, AlignItems, BackgroundColor, BuildChildren, Changed, ChildBuilder, Color, Commands,
+    Component, DespawnRecursiveExt, Entity, FlexDirection, Interaction, JustifyContent, NextState,
+    NodeBundle, PositionType, Query, Res, ResMut, Resource, StateScoped, Style, TextBundle,
+    TextStyle, Val, With,
+};
+use rusty_chess::rating::{Category, Pool, Ratings};
+
+/// The name of the ratings, as a file in the data directory or as a key in
+/// the browser's local storage.
+pub const RATINGS_NAME: &str = "ratings.txt";
+
+const CHART_WIDTH: f32 = 480.;
+const CHART_HEIGHT: f32 = 160.;
+const GLICKO_COLOR: Color = Color::srgb(0.95, 0.75, 0.2);
+const ELO_COLOR: Color = Color::srgb(0.4, 0.6, 0.95);
+
+/// The ratings of everyone who played at this device, and what the ladder
+/// screen shows of them.
+#[derive(Resource)]
+pub struct PlayerRatings {
+    pub ratings: Ratings,
+    pub category: Category,
+    /// The player whose rating history is charted.
+    pub selected: Option<String>,
+}
+
+impl PlayerRatings {
+    pub fn load() -> Self {
+        let ratings = storage::read(RATINGS_NAME)
+            .map(|text| {
+                text.parse().unwrap_or_else(|error| {
+                    warn!("Could not read the ratings: {}", error);
+                    Ratings::default()
+                })
+            })
+            .unwrap_or_default();
+        Self {
+            ratings,
+            category: Category::Blitz,
+            selected: None,
+        }
+    }
+
+    fn save(&self) {
+        if let Err(error) = storage::write(RATINGS_NAME, &self.ratings.to_string()) {
+            warn!("Could not save the ratings: {}", error);
+        }
+    }
+}
+
+/// The category a game with this setup is rated in.
+fn category(setup: &GameSetup) -> Category {
+    match setup.opponent {
+        Opponent::Correspondence => Category::Correspondence,
+        _ => Category::from_clock(
+            setup
+                .time_control
+                .map(|time_control| (time_control.minutes, time_control.increment)),
+        ),
+    }
+}
+
+/// Rates standard games between two named players. The computer plays
+/// under the name of its level, so each level gets a rating on the same
+/// scale as the people it plays.
+pub fn record_rating_result(
+    outcome: Res<GameOutcome>,
+    setup: Res<GameSetup>,
+    mut player_ratings: ResMut<PlayerRatings>,
+    mut announcements: ResMut<Announcements>,
+) {
+    if setup.opponent == Opponent::Puzzle
+        || setup.watching.is_some()
+        || setup.variant != Variant::Standard
+    {
+        return;
+    }
+    let (Some(white), Some(black)) = (
+        setup.player_name(&PieceColor::White),
+        setup.player_name(&PieceColor::Black),
+    ) else {
+        return;
+    };
+    let category = category(&setup);
+    let Ok(changes) = player_ratings
+        .ratings
+        .record(&white, &black, category, &outcome.result)
+    else {
+        return;
+    };
+    player_ratings.save();
+    let rating = |name: &str, change: f64| {
+        let pool = player_ratings
+            .ratings
+            .find(name)
+            .and_then(|profile| profile.pool(category));
+        let rating = pool.map_or(0., |pool| pool.glicko.rating);
+        format!("{} {:.0} ({:+.0})", name, rating, change)
+    };
+    announcements.push(format!(
+        "{} ratings: {}, {}",
+        category,
+        rating(&white, changes[0]),
+        rating(&black, changes[1])
+    ));
+}
+
+#[derive(Component, Clone, PartialEq)]
+pub enum RatingsButton {
+    Category,
+    Back,
+    /// Charts the history of the player of this name.
+    Select(String),
+}
+
+/// Holds the buttons, the ladder and the chart.
+#[derive(Component)]
+pub struct RatingsList;
+
+pub fn spawn_ratings_screen(mut commands: Commands) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    width: Val::Percent(100.),
+                    height: Val::Percent(100.),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    row_gap: Val::Px(8.),
+                    ..default()
+                },
+                background_color: BackgroundColor(Color::srgb(0.1, 0.1, 0.12)),
+                ..default()
+            },
+            StateScoped(AppState::Ratings),
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                "Ratings",
+                TextStyle {
+                    font_size: 48.,
+                    ..default()
+                },
+            ));
+            parent.spawn((
+                NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Column,
+                        align_items: AlignItems::Center,
+                        row_gap: Val::Px(8.),
+                        ..default()
+                    },
+                    ..default()
+                },
+                RatingsList,
+            ));
+        });
+}
+
+/// A ladder line: the Glicko rating with its deviation, marked `?` while
+/// provisional, then the Elo rating and the score.
+fn ladder_label(rank: usize, name: &str, pool: &Pool) -> String {
+    let glicko = &pool.glicko;
+    format!(
+        "{}. {}  {:.0}{} ±{:.0}  Elo {:.0}  +{} ={} -{}",
+        rank,
+        name,
+        glicko.rating,
+        if glicko.is_provisional() { "?" } else { "" },
+        glicko.deviation,
+        pool.elo,
+        pool.wins,
+        pool.draws,
+        pool.losses
+    )
+}
+
+/// Plots the ratings after each game of `pool` as dots, Glicko in gold and
+/// Elo in blue, between the lowest and highest rating reached.
+fn spawn_chart(parent: &mut ChildBuilder, name: &str, pool: &Pool) {
+    let ratings = pool
+        .history
+        .iter()
+        .flat_map(|point| [point.elo, point.glicko]);
+    let low = ratings.clone().fold(f64::INFINITY, f64::min);
+    let high = ratings.fold(f64::NEG_INFINITY, f64::max);
+    let range = (high - low).max(1.);
+    parent.spawn(TextBundle::from_section(
+        format!(
+            "{}, {} games: Glicko (gold) and Elo (blue) from {:.0} to {:.0}",
+            name,
+            pool.history.len(),
+            low,
+            high
+        ),
+        TextStyle {
+            font_size: 16.,
+            ..default()
+        },
+    ));
+    parent
+        .spawn(NodeBundle {
+            style: Style {
+                width: Val::Px(CHART_WIDTH),
+                height: Val::Px(CHART_HEIGHT),
+                ..default()
+            },
+            background_color: BackgroundColor(Color::srgb(0.18, 0.18, 0.22)),
+            ..default()
+        })
+        .with_children(|chart| {
+            let steps = pool.history.len().saturating_sub(1).max(1) as f32;
+            for (game, point) in pool.history.iter().enumerate() {
+                let left = game as f32 / steps * (CHART_WIDTH - 6.);
+                for (rating, color) in [(point.elo, ELO_COLOR), (point.glicko, GLICKO_COLOR)] {
+                    let bottom = ((rating - low) / range) as f32 * (CHART_HEIGHT - 6.);
+                    chart.spawn(NodeBundle {
+                        style: Style {
+                            position_type: PositionType::Absolute,
+                            left: Val::Px(left),
+                            bottom: Val::Px(bottom),
+                            width: Val::Px(6.),
+                            height: Val::Px(6.),
+                            ..default()
+                        },
+                        background_color: BackgroundColor(color),
+                        ..default()
+                    });
+                }
+            }
+        });
+}
+
+/// Shows the ladder of the chosen category, and the rating history of the
+/// selected player, or else of the leader.
+pub fn update_ratings_screen(
+    mut commands: Commands,
+    player_ratings: Res<PlayerRatings>,
+    list_query: Query<Entity, With<RatingsList>>,
+) {
+    let category = player_ratings.category;
+    let ladder = player_ratings.ratings.ladder(category);
+    for list in list_query.iter() {
+        commands.entity(list).despawn_descendants();
+        commands.entity(list).with_children(|parent| {
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Row,
+                        column_gap: Val::Px(8.),
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|parent| {
+                    spawn_button(parent, &category.to_string(), RatingsButton::Category);
+                    spawn_button(parent, "Back", RatingsButton::Back);
+                });
+            if ladder.is_empty() {
+                parent.spawn(TextBundle::from_section(
+                    format!("No rated {} games yet", category.to_string().to_lowercase()),
+                    TextStyle {
+                        font_size: 18.,
+                        ..default()
+                    },
+                ));
+                return;
+            }
+            for (rank, (profile, pool)) in ladder.iter().enumerate() {
+                spawn_button(
+                    parent,
+                    &ladder_label(rank + 1, &profile.name, pool),
+                    RatingsButton::Select(profile.name.clone()),
+                );
+            }
+            let charted = ladder
+                .iter()
+                .find(|(profile, _)| Some(&profile.name) == player_ratings.selected.as_ref())
+                .unwrap_or(&ladder[0]);
+            spawn_chart(parent, &charted.0.name, charted.1);
+        });
+    }
+}
+
+pub fn ratings_buttons(
+    query: Query<(&Interaction, &RatingsButton), Changed<Interaction>>,
+    mut player_ratings: ResMut<PlayerRatings>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    for (interaction, button) in query.iter() {
+        if interaction != &Interaction::Pressed {
+            continue;
+        }
+        match button {
+            RatingsButton::Category => {
+                player_ratings.category = player_ratings.category.next();
+            }
+            RatingsButton::Back => next_state.set(AppState::Menu),
+            RatingsButton::Select(name) => player_ratings.selected = Some(name.clone()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod ratings_tests {
+    use crate::game_setup::{GameSetup, Opponent, TimeControl};
+    use crate::ratings::{category, ladder_label};
+    use rusty_chess::game_result::GameResult;
+    use rusty_chess::rating::{Category, Ratings};
+
+    #[test]
+    fn games_are_rated_by_their_clock() {
+        let mut setup = GameSetup {
+            time_control: Some(TimeControl::new(3, 2)),
+            ..GameSetup::default()
+        };
+        assert_eq!(category(&setup), Category::Blitz);
+        setup.opponent = Opponent::Correspondence;
+        assert_eq!(category(&setup), Category::Correspondence);
+    }
+
+    #[test]
+    fn new_ratings_are_marked_provisional() {
+        let mut ratings = Ratings::default();
+        ratings
+            .record("Anna", "Ben", Category::Blitz, &GameResult::Draw)
+            .unwrap();
+        let pool = ratings.find("Anna").unwrap().pool(Category::Blitz).unwrap();
+        assert_eq!(
+            ladder_label(1, "Anna", pool),
+            "1. Anna  1500? ±290  Elo 1500  +0 =1 -0"
+        );
+    }
+}
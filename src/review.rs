@@ -0,0 +1,517 @@
+use crate::board::CheckerBoard;
+use crate::board_annotations::BoardAnnotations;
+use crate::board_move::BoardMove;
+use crate::board_preview::BoardPreview;
+use crate::board_ui_factory::BoardUiFactory;
+use crate::engine::{Engine, Score};
+use crate::game_history::GameHistory;
+use crate::game_result::GameResult;
+use crate::game_setup::GameSetup;
+use crate::notation::San;
+use crate::opening::OpeningBook;
+use crate::pgn::Pgn;
+use crate::pieces::color::PieceColor;
+use crate::screen_layout::PanelPlacement;
+use bevy::log::{info, warn};
+use bevy::prelude::{
+    default, ButtonInput, Color, Commands, Component, DespawnRecursiveExt, Entity, KeyCode,
+    PositionType, Query, Res, ResMut, Resource, Style, Text, Text2dBundle, TextBundle, TextStyle,
+    Transform, Val, With,
+};
+use bevy::tasks::{block_on, poll_once, AsyncComputeTaskPool, Task};
+use std::fmt::Display;
+
+pub const PGN_PATH: &str = "game.pgn";
+const REVIEW_DEPTH: u8 = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MoveClass {
+    Best,
+    Good,
+    Inaccuracy,
+    Mistake,
+    Blunder,
+}
+
+impl MoveClass {
+    /// Classifies a move by how much it lowered the mover's winning chances,
+    /// in percentage points.
+    pub fn from_win_drop(win_drop: f32) -> Self {
+        if win_drop >= 30. {
+            MoveClass::Blunder
+        } else if win_drop >= 20. {
+            MoveClass::Mistake
+        } else if win_drop >= 10. {
+            MoveClass::Inaccuracy
+        } else {
+            MoveClass::Good
+        }
+    }
+
+    pub fn glyph(&self) -> Option<&'static str> {
+        match self {
+            MoveClass::Best | MoveClass::Good => None,
+            MoveClass::Inaccuracy => Some("?!"),
+            MoveClass::Mistake => Some("?"),
+            MoveClass::Blunder => Some("??"),
+        }
+    }
+
+    /// Numeric annotation glyph used in PGN.
+    pub fn nag(&self) -> Option<u8> {
+        match self {
+            MoveClass::Best | MoveClass::Good => None,
+            MoveClass::Inaccuracy => Some(6),
+            MoveClass::Mistake => Some(2),
+            MoveClass::Blunder => Some(4),
+        }
+    }
+
+    fn color(&self) -> Color {
+        match self {
+            MoveClass::Best | MoveClass::Good => Color::WHITE,
+            MoveClass::Inaccuracy => Color::srgb(0.95, 0.8, 0.2),
+            MoveClass::Mistake => Color::srgb(1., 0.55, 0.1),
+            MoveClass::Blunder => Color::srgb(0.9, 0.15, 0.15),
+        }
+    }
+}
+
+impl Display for MoveClass {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            MoveClass::Best => "Best",
+            MoveClass::Good => "Good",
+            MoveClass::Inaccuracy => "Inaccuracy",
+            MoveClass::Mistake => "Mistake",
+            MoveClass::Blunder => "Blunder",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ReviewedMove {
+    pub board_move: BoardMove,
+    pub san: String,
+    pub color: PieceColor,
+    pub class: MoveClass,
+    pub accuracy: f32,
+    /// Engine score after the move, `None` once the game is over.
+    pub score: Option<Score>,
+    /// The engine's choice in the position before the move.
+    pub best: Option<String>,
+}
+
+impl ReviewedMove {
+    fn comment(&self) -> Option<String> {
+        let mut parts = vec![];
+        if self.class.glyph().is_some() {
+            if let Some(best) = &self.best {
+                parts.push(format!("{}. {} was best.", self.class, best));
+            }
+        }
+        match self.score {
+            Some(Score::Centipawns(centipawns)) => {
+                parts.push(format!("[%eval {:.2}]", centipawns as f32 / 100.))
+            }
+            Some(Score::Mate(moves)) => parts.push(format!("[%eval #{}]", moves)),
+            None => {}
+        }
+        if parts.is_empty() {
+            None
+        } else {
+            Some(parts.join(" "))
+        }
+    }
+}
+
+struct Evaluation {
+    score: Option<Score>,
+    white_win: f32,
+    best: Option<BoardMove>,
+}
+
+impl Evaluation {
+    fn new(engine: &Engine, board: &CheckerBoard) -> Self {
+        match engine.analyse(board, 1).into_iter().next() {
+            Some(line) => Self {
+                white_win: win_percent(&line.score),
+                best: line.moves.first().cloned(),
+                score: Some(line.score),
+            },
+            None => Self {
+                white_win: match GameResult::from_board(board) {
+                    GameResult::WhiteWins => 100.,
+                    GameResult::BlackWins => 0.,
+                    GameResult::Draw | GameResult::Ongoing => 50.,
+                },
+                best: None,
+                score: None,
+            },
+        }
+    }
+
+    fn win_for(&self, color: &PieceColor) -> f32 {
+        match color {
+            PieceColor::White => self.white_win,
+            PieceColor::Black => 100. - self.white_win,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct GameReview {
+    moves: Vec<ReviewedMove>,
+}
+
+impl GameReview {
+    /// Evaluates every position of the game once and classifies each move by
+    /// the evaluation before and after it.
+    pub fn new(history: &GameHistory, engine: &Engine) -> Self {
+        let evaluations: Vec<Evaluation> = history
+            .positions()
+            .iter()
+            .map(|position| Evaluation::new(engine, position))
+            .collect();
+        let moves = history
+            .moves()
+            .iter()
+            .enumerate()
+            .map(|(ply, board_move)| {
+                let position = &history.positions()[ply];
+                let color = position
+                    .piece_at(board_move.from())
+                    .map(|piece| piece.color().clone())
+                    .unwrap_or_else(|| position.active_turn().clone());
+                let before = &evaluations[ply];
+                let after = &evaluations[ply + 1];
+                let win_drop = (before.win_for(&color) - after.win_for(&color)).max(0.);
+                let class = if before.best.as_ref() == Some(board_move) {
+                    MoveClass::Best
+                } else {
+                    MoveClass::from_win_drop(win_drop)
+                };
+                ReviewedMove {
+                    board_move: board_move.clone(),
+                    san: history.sans()[ply].clone(),
+                    color,
+                    class,
+                    accuracy: move_accuracy(win_drop),
+                    score: after.score,
+                    best: before
+                        .best
+                        .as_ref()
+                        .map(|best| San::from_move(position, best)),
+                }
+            })
+            .collect();
+        Self { moves }
+    }
+
+    pub fn moves(&self) -> &Vec<ReviewedMove> {
+        &self.moves
+    }
+
+    /// Mean accuracy of `color`'s moves, from 0 to 100.
+    pub fn accuracy(&self, color: &PieceColor) -> Option<f32> {
+        let accuracies: Vec<f32> = self
+            .moves
+            .iter()
+            .filter(|reviewed| &reviewed.color == color)
+            .map(|reviewed| reviewed.accuracy)
+            .collect();
+        if accuracies.is_empty() {
+            return None;
+        }
+        Some(accuracies.iter().sum::<f32>() / accuracies.len() as f32)
+    }
+
+    pub fn annotate(&self, pgn: &mut Pgn) {
+        for (ply, reviewed) in self.moves.iter().enumerate() {
+            pgn.annotate(ply, reviewed.class.nag(), reviewed.comment());
+        }
+    }
+}
+
+/// Winning chances in percent for white.
+pub fn win_percent(score: &Score) -> f32 {
+    match score {
+        Score::Centipawns(centipawns) => {
+            let centipawns = (*centipawns).clamp(-1000, 1000) as f32;
+            50. + 50. * (2. / (1. + (-0.00368208 * centipawns).exp()) - 1.)
+        }
+        Score::Mate(moves) if *moves > 0 => 100.,
+        Score::Mate(_) => 0.,
+    }
+}
+
+fn move_accuracy(win_drop: f32) -> f32 {
+    (103.1668 * (-0.04354 * win_drop).exp() - 3.1669).clamp(0., 100.)
+}
+
+#[derive(Resource, Default)]
+pub struct Review {
+    review: Option<GameReview>,
+    task: Option<Task<GameReview>>,
+}
+
+#[derive(Component)]
+pub struct ReviewLabel;
+
+#[derive(Component)]
+pub struct ReviewGlyph;
+
+pub fn spawn_review_label(mut commands: Commands) {
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font_size: 18.,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            left: Val::Px(930.),
+            top: Val::Px(200.),
+            ..default()
+        }),
+        ReviewLabel,
+        PanelPlacement::new(930., 200.).narrow(8., 830.),
+    ));
+}
+
+pub fn start_review(
+    keys: Res<ButtonInput<KeyCode>>,
+    history: Res<GameHistory>,
+    mut review: ResMut<Review>,
+) {
+    if !keys.just_pressed(KeyCode::KeyR) || review.task.is_some() || history.is_empty() {
+        return;
+    }
+    let history = history.clone();
+    let engine = Engine::new(REVIEW_DEPTH);
+    review.task =
+        Some(AsyncComputeTaskPool::get().spawn(async move { GameReview::new(&history, &engine) }));
+}
+
+pub fn poll_review(mut review: ResMut<Review>, mut preview: ResMut<BoardPreview>) {
+    // Looked at before `as_mut`, which would mark the review changed every
+    // frame and have `update_review` redraw it.
+    if review.task.is_none() {
+        return;
+    }
+    let Some(task) = review.task.as_mut() else {
+        return;
+    };
+    if let Some(game_review) = block_on(poll_once(task)) {
+        review.review = Some(game_review);
+        review.task = None;
+        preview.show(0);
+    }
+}
+
+pub fn leave_review(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut review: ResMut<Review>,
+    mut preview: ResMut<BoardPreview>,
+) {
+    if review.review.is_some() && keys.just_pressed(KeyCode::Escape) {
+        review.review = None;
+        preview.close();
+    }
+}
+
+pub fn update_review(
+    mut commands: Commands,
+    review: Res<Review>,
+    preview: Res<BoardPreview>,
+    history: Res<GameHistory>,
+    board_ui_factory: Res<BoardUiFactory>,
+    mut label_query: Query<&mut Text, With<ReviewLabel>>,
+    glyph_query: Query<Entity, With<ReviewGlyph>>,
+) {
+    for entity in glyph_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    let label = match (&review.review, &review.task) {
+        (_, Some(_)) => "Reviewing game...".to_string(),
+        (None, None) => String::new(),
+        (Some(game_review), None) => {
+            let accuracy = |color: &PieceColor| {
+                game_review
+                    .accuracy(color)
+                    .map(|accuracy| format!("{:.1}%", accuracy))
+                    .unwrap_or_else(|| "-".to_string())
+            };
+            let mut label = format!(
+                "Accuracy\nWhite {}\nBlack {}",
+                accuracy(&PieceColor::White),
+                accuracy(&PieceColor::Black)
+            );
+            let reviewed = preview
+                .ply()
+                .unwrap_or(history.len())
+                .checked_sub(1)
+                .and_then(|index| game_review.moves().get(index).map(|m| (index, m)));
+            if let Some((index, reviewed)) = reviewed {
+                let separator = if index % 2 == 0 { "." } else { "..." };
+                label.push_str(&format!(
+                    "\n\n{}{} {}{} {}",
+                    index / 2 + 1,
+                    separator,
+                    reviewed.san,
+                    reviewed.class.glyph().unwrap_or_default(),
+                    reviewed.class
+                ));
+                if let (Some(best), Some(_)) = (&reviewed.best, reviewed.class.glyph()) {
+                    label.push_str(&format!("\nBest was {}", best));
+                }
+                if let Some(glyph) = reviewed.class.glyph() {
+                    let pos_transform =
+                        board_ui_factory.get_pos_transform(reviewed.board_move.to());
+                    commands.spawn((
+                        Text2dBundle {
+                            text: Text::from_section(
+                                glyph,
+                                TextStyle {
+                                    font_size: 28.,
+                                    color: reviewed.class.color(),
+                                    ..default()
+                                },
+                            ),
+                            transform: Transform::from_xyz(
+                                pos_transform.translation.x + 22.,
+                                pos_transform.translation.y + 22.,
+                                10.,
+                            ),
+                            ..default()
+                        },
+                        ReviewGlyph,
+                    ));
+                }
+            }
+            label
+        }
+    };
+    for mut text in label_query.iter_mut() {
+        text.sections[0].value = label.clone();
+    }
+}
+
+/// Writes the game to `PGN_PATH`, annotated when a review is available.
+pub fn export_pgn(
+    keys: Res<ButtonInput<KeyCode>>,
+    history: Res<GameHistory>,
+    review: Res<Review>,
+    opening_book: Res<OpeningBook>,
+    annotations: Res<BoardAnnotations>,
+    setup: Res<GameSetup>,
+) {
+    if !keys.just_pressed(KeyCode::KeyP) {
+        return;
+    }
+    let mut pgn = Pgn::new(&history);
+    for (header, color) in [("White", PieceColor::White), ("Black", PieceColor::Black)] {
+        if let Some(name) = setup.player_name(&color) {
+            pgn.set_header(header, &name);
+        }
+    }
+    if let Some(opening) = opening_book.classify(history.moves()) {
+        pgn.set_header("ECO", opening.eco());
+        pgn.set_header("Opening", opening.name());
+    }
+    if let Some(game_review) = &review.review {
+        game_review.annotate(&mut pgn);
+    }
+    annotations.annotate(&mut pgn);
+    match std::fs::write(PGN_PATH, pgn.to_string()) {
+        Ok(()) => info!("Saved game to {}", PGN_PATH),
+        Err(error) => warn!("Could not save game to {}: {}", PGN_PATH, error),
+    }
+}
+
+#[cfg(test)]
+mod review_tests {
+    use crate::board::CheckerBoard;
+    use crate::board_pos;
+    use crate::board_preview::BoardPreview;
+    use crate::engine::{Engine, Score};
+    use crate::game_history::GameHistory;
+    use crate::pgn::Pgn;
+    use crate::pieces::color::PieceColor;
+    use crate::review::{poll_review, win_percent, GameReview, MoveClass, Review};
+    use bevy::ecs::system::RunSystemOnce;
+    use bevy::prelude::{DetectChanges, Res, World};
+    use std::str::FromStr;
+
+    #[test]
+    fn polling_without_a_review_running_changes_nothing() {
+        let mut world = World::new();
+        world.init_resource::<Review>();
+        world.init_resource::<BoardPreview>();
+        let changed = world.register_system(|review: Res<Review>| review.is_changed());
+        assert!(world.run_system(changed).unwrap());
+        world.run_system_once(poll_review);
+        assert!(!world.run_system(changed).unwrap());
+    }
+
+    #[test]
+    fn win_drop_thresholds() {
+        assert_eq!(MoveClass::from_win_drop(2.), MoveClass::Good);
+        assert_eq!(MoveClass::from_win_drop(12.), MoveClass::Inaccuracy);
+        assert_eq!(MoveClass::from_win_drop(25.), MoveClass::Mistake);
+        assert_eq!(MoveClass::from_win_drop(45.), MoveClass::Blunder);
+    }
+
+    #[test]
+    fn glyphs_and_nags() {
+        assert_eq!(MoveClass::Good.glyph(), None);
+        assert_eq!(MoveClass::Inaccuracy.glyph(), Some("?!"));
+        assert_eq!(MoveClass::Mistake.nag(), Some(2));
+        assert_eq!(MoveClass::Blunder.nag(), Some(4));
+    }
+
+    #[test]
+    fn win_percent_is_even_at_zero() {
+        assert_eq!(win_percent(&Score::Centipawns(0)), 50.);
+        assert!(win_percent(&Score::Centipawns(300)) > 70.);
+        assert_eq!(win_percent(&Score::Mate(-1)), 0.);
+    }
+
+    #[test]
+    fn hanging_the_queen_is_a_blunder() {
+        let review = GameReview::new(&queen_sacrifice(), &Engine::new(2));
+        let queen_takes = &review.moves()[4];
+        assert_eq!(queen_takes.san, "Qxf7+");
+        assert_eq!(queen_takes.class, MoveClass::Blunder);
+        assert_eq!(review.moves()[5].class, MoveClass::Best);
+        assert!(
+            review.accuracy(&PieceColor::White).unwrap()
+                < review.accuracy(&PieceColor::Black).unwrap()
+        );
+    }
+
+    #[test]
+    fn review_annotates_pgn() {
+        let history = queen_sacrifice();
+        let review = GameReview::new(&history, &Engine::new(2));
+        let mut pgn = Pgn::new(&history);
+        review.annotate(&mut pgn);
+        let text = pgn.to_string();
+        assert!(text.contains("Qxf7+ $4 { Blunder."));
+        assert!(text.contains("[%eval"));
+    }
+
+    fn queen_sacrifice() -> GameHistory {
+        let mut board = CheckerBoard::default();
+        board.move_piece(&board_pos!("e2"), &board_pos!("e4"));
+        board.move_piece(&board_pos!("e7"), &board_pos!("e5"));
+        board.move_piece(&board_pos!("d1"), &board_pos!("h5"));
+        board.move_piece(&board_pos!("b8"), &board_pos!("c6"));
+        board.move_piece(&board_pos!("h5"), &board_pos!("f7"));
+        board.move_piece(&board_pos!("e8"), &board_pos!("f7"));
+        GameHistory::replay(&CheckerBoard::default(), board.get_moves())
+    }
+}
@@ -0,0 +1,195 @@
+use bevy::prelude::{
+    Camera2d, Component, DetectChanges, DetectChangesMut, Display, OrthographicProjection, Query,
+    Ref, Res, ResMut, Resource, Style, Transform, UiScale, Val, Vec2, Window, With,
+};
+use bevy::window::PrimaryWindow;
+
+/// The screen the panels were laid out for, with the board to the right of
+/// the move list and the clocks and analysis on its right.
+const WIDE_CANVAS: Vec2 = Vec2::new(1280., 720.);
+const WIDE_BOARD_CENTER: Vec2 = Vec2::new(640., 360.);
+/// A portrait screen with the board on top and the panels below it.
+const NARROW_CANVAS: Vec2 = Vec2::new(600., 1000.);
+const NARROW_BOARD_CENTER: Vec2 = Vec2::new(300., 352.);
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum LayoutMode {
+    #[default]
+    Wide,
+    Narrow,
+}
+
+/// How the fixed size canvas of board and panels is fitted into the window.
+/// Everything is scaled by the same factor, board and UI alike.
+#[derive(Resource, Debug, Clone, PartialEq)]
+pub struct ScreenLayout {
+    mode: LayoutMode,
+    scale: f32,
+    window: Vec2,
+}
+
+impl ScreenLayout {
+    /// Picks whichever canvas shows the board larger in a window of `window`
+    /// logical pixels.
+    pub fn fit(window: Vec2) -> Self {
+        let scale_to = |canvas: Vec2| (window / canvas).min_element().max(f32::EPSILON);
+        let (wide, narrow) = (scale_to(WIDE_CANVAS), scale_to(NARROW_CANVAS));
+        let (mode, scale) = if narrow > wide {
+            (LayoutMode::Narrow, narrow)
+        } else {
+            (LayoutMode::Wide, wide)
+        };
+        Self {
+            mode,
+            scale,
+            window,
+        }
+    }
+
+    pub fn mode(&self) -> LayoutMode {
+        self.mode
+    }
+
+    pub fn scale(&self) -> f32 {
+        self.scale
+    }
+
+    fn board_center(&self) -> Vec2 {
+        match self.mode {
+            LayoutMode::Wide => WIDE_BOARD_CENTER,
+            LayoutMode::Narrow => NARROW_BOARD_CENTER,
+        }
+    }
+
+    /// Where the camera looks so that the board sits at its place on the
+    /// canvas, with the canvas in the top left corner of the window.
+    pub fn camera_translation(&self) -> Vec2 {
+        let visible = self.window / self.scale;
+        let center = self.board_center();
+        Vec2::new(visible.x / 2. - center.x, center.y - visible.y / 2.)
+    }
+}
+
+impl Default for ScreenLayout {
+    fn default() -> Self {
+        Self::fit(WIDE_CANVAS)
+    }
+}
+
+/// Where a panel goes on each canvas. Panels without a place on the narrow
+/// canvas are hidden there.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct PanelPlacement {
+    wide: Vec2,
+    narrow: Option<Vec2>,
+}
+
+impl PanelPlacement {
+    pub fn new(left: f32, top: f32) -> Self {
+        Self {
+            wide: Vec2::new(left, top),
+            narrow: None,
+        }
+    }
+
+    pub fn narrow(mut self, left: f32, top: f32) -> Self {
+        self.narrow = Some(Vec2::new(left, top));
+        self
+    }
+
+    fn place(&self, mode: LayoutMode) -> Option<Vec2> {
+        match mode {
+            LayoutMode::Wide => Some(self.wide),
+            LayoutMode::Narrow => self.narrow,
+        }
+    }
+}
+
+/// Follows the size of the window, which changes on resize and when it
+/// moves to a screen with another scale factor.
+pub fn fit_to_window(
+    windows: Query<&Window, With<PrimaryWindow>>,
+    mut layout: ResMut<ScreenLayout>,
+) {
+    let Ok(window) = windows.get_single() else {
+        return;
+    };
+    layout.set_if_neq(ScreenLayout::fit(Vec2::new(
+        window.width(),
+        window.height(),
+    )));
+}
+
+pub fn apply_layout(
+    layout: Res<ScreenLayout>,
+    mut ui_scale: ResMut<UiScale>,
+    mut camera_query: Query<(&mut OrthographicProjection, &mut Transform), With<Camera2d>>,
+) {
+    ui_scale.0 = layout.scale();
+    for (mut projection, mut transform) in camera_query.iter_mut() {
+        projection.scale = 1. / layout.scale();
+        let translation = layout.camera_translation();
+        transform.translation.x = translation.x;
+        transform.translation.y = translation.y;
+    }
+}
+
+/// Moves the panels to their place on the current canvas, and new panels,
+/// such as the game over screen, as they appear.
+pub fn place_panels(
+    layout: Res<ScreenLayout>,
+    mut panel_query: Query<(Ref<PanelPlacement>, &mut Style)>,
+) {
+    for (placement, mut style) in panel_query.iter_mut() {
+        if !layout.is_changed() && !placement.is_added() {
+            continue;
+        }
+        match placement.place(layout.mode()) {
+            Some(place) => {
+                style.display = Display::Flex;
+                style.left = Val::Px(place.x);
+                style.top = Val::Px(place.y);
+            }
+            None => style.display = Display::None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod screen_layout_tests {
+    use crate::screen_layout::{LayoutMode, PanelPlacement, ScreenLayout};
+    use bevy::prelude::Vec2;
+
+    #[test]
+    fn the_designed_window_is_not_scaled() {
+        let layout = ScreenLayout::fit(Vec2::new(1280., 720.));
+        assert_eq!(layout.mode(), LayoutMode::Wide);
+        assert_eq!(layout.scale(), 1.);
+        assert_eq!(layout.camera_translation(), Vec2::ZERO);
+    }
+
+    #[test]
+    fn large_monitors_scale_everything_up() {
+        let layout = ScreenLayout::fit(Vec2::new(2560., 1440.));
+        assert_eq!(layout.scale(), 2.);
+        assert_eq!(layout.camera_translation(), Vec2::ZERO);
+    }
+
+    #[test]
+    fn extra_width_is_left_empty_on_the_right() {
+        let layout = ScreenLayout::fit(Vec2::new(1920., 720.));
+        assert_eq!(layout.scale(), 1.);
+        // The board stays where the panels expect it.
+        assert_eq!(layout.camera_translation(), Vec2::new(320., 0.));
+    }
+
+    #[test]
+    fn portrait_screens_stack_the_panels_below_the_board() {
+        let layout = ScreenLayout::fit(Vec2::new(390., 844.));
+        assert_eq!(layout.mode(), LayoutMode::Narrow);
+        assert_eq!(layout.scale(), 0.65);
+        let placement = PanelPlacement::new(930., 8.).narrow(8., 656.);
+        assert_eq!(placement.place(layout.mode()), Some(Vec2::new(8., 656.)));
+        assert_eq!(PanelPlacement::new(8., 72.).place(layout.mode()), None);
+    }
+}
@@ -0,0 +1,1125 @@
+use crate::board::CheckerBoard;
+use crate::game_result::GameResult;
+use crate::pieces::color::PieceColor;
+use crate::protocol::{
+    is_valid_name, parse_uci, ClientMessage, LiveGame, Seek, ServerMessage, GUEST_NAME,
+};
+use bevy::log::warn;
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{Shutdown, TcpListener, TcpStream};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+use tungstenite::protocol::{Role, WebSocketConfig};
+use tungstenite::{Message, WebSocket};
+
+/// How long a player who dropped out of a game may stay away before the
+/// game is lost.
+pub const ABANDON_AFTER: Duration = Duration::from_secs(60);
+/// How often clocks are checked when nothing is said.
+const TICK: Duration = Duration::from_millis(100);
+const WRITE_TIMEOUT: Duration = Duration::from_secs(1);
+/// Longer lines and messages are not chess moves, so the client is dropped.
+const MAX_MESSAGE: usize = 1 << 16;
+
+pub type ClientId = u32;
+
+fn index(color: &PieceColor) -> usize {
+    match color {
+        PieceColor::White => 0,
+        PieceColor::Black => 1,
+    }
+}
+
+fn opposite(color: &PieceColor) -> PieceColor {
+    match color {
+        PieceColor::White => PieceColor::Black,
+        PieceColor::Black => PieceColor::White,
+    }
+}
+
+fn win_for(color: &PieceColor) -> GameResult {
+    match color {
+        PieceColor::White => GameResult::WhiteWins,
+        PieceColor::Black => GameResult::BlackWins,
+    }
+}
+
+struct Player {
+    token: String,
+    client: Option<ClientId>,
+    game: Option<u32>,
+    left_at: Option<Instant>,
+}
+
+/// A game in progress. The server keeps its own board and only takes moves
+/// that are legal on it.
+struct Game {
+    /// White and black, in that order.
+    players: [String; 2],
+    board: CheckerBoard,
+    moves: Vec<String>,
+    minutes: u32,
+    increment: Duration,
+    clock: Option<[Duration; 2]>,
+    turn_started: Instant,
+    draw_offer: Option<PieceColor>,
+    /// The connections following the game without playing.
+    spectators: Vec<ClientId>,
+}
+
+impl Game {
+    fn color_of(&self, name: &str) -> PieceColor {
+        if self.players[0] == name {
+            PieceColor::White
+        } else {
+            PieceColor::Black
+        }
+    }
+
+    fn player(&self, color: &PieceColor) -> &String {
+        &self.players[index(color)]
+    }
+
+    /// The time both sides have left, counting the time the side to move
+    /// has been thinking.
+    fn clock_at(&self, now: Instant) -> Option<[Duration; 2]> {
+        let mut clock = self.clock?;
+        let running = index(self.board.active_turn());
+        clock[running] = clock[running].saturating_sub(now - self.turn_started);
+        Some(clock)
+    }
+
+    fn live(&self, id: u32) -> LiveGame {
+        LiveGame {
+            id,
+            white: self.players[0].clone(),
+            black: self.players[1].clone(),
+            minutes: self.minutes,
+            increment: self.increment.as_secs() as u32,
+        }
+    }
+
+    /// The moves so far and the time left, for someone who joins late.
+    fn catch_up(&self, now: Instant) -> Vec<ServerMessage> {
+        let mut messages: Vec<ServerMessage> = self
+            .moves
+            .iter()
+            .enumerate()
+            .map(|(ply, uci)| ServerMessage::Move {
+                ply,
+                uci: uci.clone(),
+                clock: None,
+            })
+            .collect();
+        messages.extend(self.clock_at(now).map(ServerMessage::Clock));
+        messages
+    }
+}
+
+/// Everyone signed in and every game being played. Messages to send are
+/// collected and handed to the connections by `take_messages`, so the lobby
+/// can be driven without sockets.
+#[derive(Default)]
+pub struct Lobby {
+    players: HashMap<String, Player>,
+    clients: HashMap<ClientId, String>,
+    /// Open seeks, with the player a challenge is meant for.
+    seeks: Vec<(Seek, Option<String>)>,
+    games: HashMap<u32, Game>,
+    next_id: u32,
+    outbox: Vec<(ClientId, ServerMessage)>,
+}
+
+impl Lobby {
+    pub fn take_messages(&mut self) -> Vec<(ClientId, ServerMessage)> {
+        std::mem::take(&mut self.outbox)
+    }
+
+    fn next_id(&mut self) -> u32 {
+        self.next_id += 1;
+        self.next_id
+    }
+
+    /// A secret for taking a seat back, from the system's random source.
+    fn new_token() -> Option<String> {
+        let mut bytes = [0; 16];
+        getrandom::getrandom(&mut bytes).ok()?;
+        Some(bytes.iter().map(|byte| format!("{:02x}", byte)).collect())
+    }
+
+    fn send(&mut self, name: &str, message: ServerMessage) {
+        if let Some(client) = self.players.get(name).and_then(|player| player.client) {
+            self.outbox.push((client, message));
+        }
+    }
+
+    fn send_to_all(&mut self, message: ServerMessage) {
+        for client in self.clients.keys() {
+            self.outbox.push((*client, message.clone()));
+        }
+    }
+
+    fn error(&mut self, client: ClientId, error: &str) {
+        self.outbox
+            .push((client, ServerMessage::Error(error.to_string())));
+    }
+
+    /// Tells everyone who is free to play.
+    fn send_players(&mut self) {
+        let mut names: Vec<String> = self
+            .players
+            .iter()
+            .filter(|(_, player)| player.client.is_some() && player.game.is_none())
+            .map(|(name, _)| name.clone())
+            .collect();
+        names.sort();
+        self.send_to_all(ServerMessage::Players(names));
+    }
+
+    pub fn receive(&mut self, client: ClientId, line: &str, now: Instant) {
+        let message = match ClientMessage::from_str(line) {
+            Ok(message) => message,
+            Err(error) => return self.error(client, &error.to_string()),
+        };
+        if let ClientMessage::Hello { name, token } = message {
+            return self.hello(client, name, token, now);
+        }
+        let Some(name) = self.clients.get(&client).cloned() else {
+            return self.error(client, "Say HELLO first");
+        };
+        match message {
+            ClientMessage::Hello { .. } => {}
+            ClientMessage::Seek {
+                minutes,
+                increment,
+                color,
+            } => self.seek(&name, minutes, increment, color, None),
+            ClientMessage::Challenge {
+                opponent,
+                minutes,
+                increment,
+                color,
+            } => self.seek(&name, minutes, increment, color, Some(opponent)),
+            ClientMessage::Accept(id) => self.accept(&name, id, now),
+            ClientMessage::Cancel(id) => self.cancel(&name, id),
+            ClientMessage::Move(uci) => self.play(&name, &uci, now),
+            ClientMessage::Resign => self.resign(&name),
+            ClientMessage::Draw => self.draw(&name),
+            ClientMessage::Watch(id) => self.watch(client, &name, id, now),
+            ClientMessage::Unwatch => self.unwatch(client),
+        }
+    }
+
+    /// Signs a player in, or back in to the seat they left when the token
+    /// matches.
+    fn hello(&mut self, client: ClientId, name: String, token: Option<String>, now: Instant) {
+        if self.clients.contains_key(&client) {
+            return self.error(client, "Already signed in");
+        }
+        if !is_valid_name(&name) {
+            return self.error(client, "Names are single words of letters and digits");
+        }
+        let name = match name == GUEST_NAME {
+            true => (1..)
+                .map(|number| format!("{}{}", GUEST_NAME, number))
+                .find(|guest| !self.players.contains_key(guest))
+                .expect("There is always a free guest number"),
+            false => name,
+        };
+        match self.players.get_mut(&name) {
+            Some(player) if token.as_ref() == Some(&player.token) => {
+                if let Some(old) = player.client.replace(client) {
+                    self.clients.remove(&old);
+                }
+                player.left_at = None;
+            }
+            Some(_) => return self.error(client, &format!("The name {} is taken", name)),
+            None => {
+                let Some(token) = Self::new_token() else {
+                    return self.error(client, "The server could not make a token");
+                };
+                self.players.insert(
+                    name.clone(),
+                    Player {
+                        token,
+                        client: Some(client),
+                        game: None,
+                        left_at: None,
+                    },
+                );
+            }
+        }
+        self.clients.insert(client, name.clone());
+        let token = self.players[&name].token.clone();
+        self.send(
+            &name,
+            ServerMessage::Welcome {
+                name: name.clone(),
+                token,
+            },
+        );
+        let seeks: Vec<ServerMessage> = self
+            .seeks
+            .iter()
+            .filter_map(|(seek, target)| match target {
+                None => Some(ServerMessage::Seek(seek.clone())),
+                Some(target) if target == &name || seek.name == name => {
+                    Some(ServerMessage::Challenge(seek.clone()))
+                }
+                Some(_) => None,
+            })
+            .collect();
+        let mut games: Vec<LiveGame> = self.games.iter().map(|(id, game)| game.live(*id)).collect();
+        games.sort_by_key(|game| game.id);
+        for seek in seeks
+            .into_iter()
+            .chain(games.into_iter().map(ServerMessage::Game))
+        {
+            self.send(&name, seek);
+        }
+        if let Some(id) = self.players[&name].game {
+            self.resume(&name, id, now);
+        }
+        self.send_players();
+    }
+
+    /// Sends the whole game again to a player who came back.
+    fn resume(&mut self, name: &str, id: u32, now: Instant) {
+        let Some(game) = self.games.get(&id) else {
+            return;
+        };
+        let color = game.color_of(name);
+        let mut messages = vec![ServerMessage::Start {
+            game: id,
+            color: color.clone(),
+            opponent: game.player(&opposite(&color)).clone(),
+            minutes: game.minutes,
+            increment: game.increment.as_secs() as u32,
+        }];
+        messages.extend(game.catch_up(now));
+        if game.draw_offer == Some(opposite(&color)) {
+            messages.push(ServerMessage::DrawOffered);
+        }
+        let opponent = game.player(&opposite(&color)).clone();
+        for message in messages {
+            self.send(name, message);
+        }
+        self.send(&opponent, ServerMessage::OpponentReturned);
+    }
+
+    fn seek(
+        &mut self,
+        name: &str,
+        minutes: u32,
+        increment: u32,
+        color: PieceColor,
+        target: Option<String>,
+    ) {
+        let client = self.players[name].client.unwrap_or_default();
+        if self.players[name].game.is_some() {
+            return self.error(client, "Finish your game first");
+        }
+        if let Some(target) = &target {
+            let available = self
+                .players
+                .get(target)
+                .is_some_and(|player| player.client.is_some());
+            if target == name || !available {
+                return self.error(client, &format!("{} is not in the lobby", target));
+            }
+        }
+        let seek = Seek {
+            id: self.next_id(),
+            name: name.to_string(),
+            minutes,
+            increment,
+            color,
+        };
+        match &target {
+            None => self.send_to_all(ServerMessage::Seek(seek.clone())),
+            Some(target) => {
+                self.send(name, ServerMessage::Challenge(seek.clone()));
+                self.send(target, ServerMessage::Challenge(seek.clone()));
+            }
+        }
+        self.seeks.push((seek, target));
+    }
+
+    fn remove_seeks(&mut self, keep: impl Fn(&Seek, &Option<String>) -> bool) {
+        let (kept, removed): (Vec<_>, Vec<_>) = std::mem::take(&mut self.seeks)
+            .into_iter()
+            .partition(|(seek, target)| keep(seek, target));
+        self.seeks = kept;
+        for (seek, _) in removed {
+            self.send_to_all(ServerMessage::Unseek(seek.id));
+        }
+    }
+
+    fn cancel(&mut self, name: &str, id: u32) {
+        self.remove_seeks(|seek, _| seek.id != id || seek.name != name);
+    }
+
+    fn accept(&mut self, name: &str, id: u32, now: Instant) {
+        let client = self.players[name].client.unwrap_or_default();
+        let seek = self.seeks.iter().find(|(seek, target)| {
+            seek.id == id && seek.name != name && target.as_ref().is_none_or(|t| t == name)
+        });
+        let Some((seek, _)) = seek.cloned() else {
+            return self.error(client, "That game is no longer offered");
+        };
+        let busy = [name, seek.name.as_str()]
+            .iter()
+            .any(|player| self.players.get(*player).is_none_or(|p| p.game.is_some()));
+        if busy {
+            return self.error(client, "Finish your game first");
+        }
+        let players = match seek.color {
+            PieceColor::White => [seek.name.clone(), name.to_string()],
+            PieceColor::Black => [name.to_string(), seek.name.clone()],
+        };
+        self.remove_seeks(|seek, target| {
+            !players
+                .iter()
+                .any(|player| &seek.name == player || target.as_ref() == Some(player))
+        });
+        for player in players.iter() {
+            if let Some(client) = self.players.get(player).and_then(|player| player.client) {
+                self.unwatch(client);
+            }
+        }
+        let start = Duration::from_secs(seek.minutes as u64 * 60);
+        let timed = seek.minutes > 0 || seek.increment > 0;
+        let game_id = self.next_id();
+        let game = Game {
+            players: players.clone(),
+            board: CheckerBoard::default(),
+            moves: vec![],
+            minutes: seek.minutes,
+            increment: Duration::from_secs(seek.increment as u64),
+            clock: timed.then_some([start; 2]),
+            turn_started: now,
+            draw_offer: None,
+            spectators: vec![],
+        };
+        self.send_to_all(ServerMessage::Game(game.live(game_id)));
+        self.games.insert(game_id, game);
+        for (player, color) in players.iter().zip([PieceColor::White, PieceColor::Black]) {
+            if let Some(player) = self.players.get_mut(player) {
+                player.game = Some(game_id);
+            }
+            let opponent = players[index(&opposite(&color))].clone();
+            self.send(
+                player,
+                ServerMessage::Start {
+                    game: game_id,
+                    color,
+                    opponent,
+                    minutes: seek.minutes,
+                    increment: seek.increment,
+                },
+            );
+        }
+        self.send_players();
+    }
+
+    fn game_of(&self, name: &str) -> Option<u32> {
+        self.players.get(name).and_then(|player| player.game)
+    }
+
+    /// Checks the move against the server's board before anyone sees it.
+    fn play(&mut self, name: &str, uci: &str, now: Instant) {
+        let client = self.players[name].client.unwrap_or_default();
+        let Some(id) = self.game_of(name) else {
+            return self.error(client, "You are not playing");
+        };
+        let game = self
+            .games
+            .get_mut(&id)
+            .expect("Players only point at running games");
+        let color = game.color_of(name);
+        if game.board.active_turn() != &color {
+            return self.error(client, "It is not your move");
+        }
+        let Some((from, to)) = parse_uci(uci) else {
+            return self.error(client, &format!("{} is not a move", uci));
+        };
+        if !game.board.is_valid_move(&from, &to) {
+            return self.error(client, &format!("{} is not a legal move", uci));
+        }
+        if let Some(clock) = game.clock_at(now) {
+            if clock[index(&color)].is_zero() {
+                return self.end_game(id, win_for(&opposite(&color)), "on time");
+            }
+            let mut clock = clock;
+            clock[index(&color)] += game.increment;
+            game.clock = Some(clock);
+        }
+        game.board.move_piece(&from, &to);
+        game.turn_started = now;
+        if game.draw_offer == Some(opposite(&color)) {
+            game.draw_offer = None;
+        }
+        let uci = game
+            .board
+            .get_last_move()
+            .map(|board_move| board_move.to_string())
+            .unwrap_or_else(|| uci.to_string());
+        game.moves.push(uci.clone());
+        let message = ServerMessage::Move {
+            ply: game.moves.len() - 1,
+            uci,
+            clock: game.clock,
+        };
+        let players = game.players.clone();
+        let result = GameResult::from_board(&game.board);
+        for spectator in game.spectators.iter() {
+            self.outbox.push((*spectator, message.clone()));
+        }
+        for player in players.iter() {
+            self.send(player, message.clone());
+        }
+        match result {
+            GameResult::Ongoing => {}
+            GameResult::Draw => self.end_game(id, result, "by stalemate"),
+            _ => self.end_game(id, result, "by checkmate"),
+        }
+    }
+
+    fn resign(&mut self, name: &str) {
+        let client = self.players[name].client.unwrap_or_default();
+        let Some(id) = self.game_of(name) else {
+            return self.error(client, "You are not playing");
+        };
+        let color = self.games[&id].color_of(name);
+        self.end_game(id, win_for(&opposite(&color)), "by resignation");
+    }
+
+    fn draw(&mut self, name: &str) {
+        let client = self.players[name].client.unwrap_or_default();
+        let Some(id) = self.game_of(name) else {
+            return self.error(client, "You are not playing");
+        };
+        let game = self
+            .games
+            .get_mut(&id)
+            .expect("Players only point at running games");
+        let color = game.color_of(name);
+        if game.draw_offer == Some(opposite(&color)) {
+            return self.end_game(id, GameResult::Draw, "by agreement");
+        }
+        game.draw_offer = Some(color.clone());
+        let opponent = game.player(&opposite(&color)).clone();
+        self.send(&opponent, ServerMessage::DrawOffered);
+    }
+
+    /// Follows a game from its first move on, leaving any game watched
+    /// before.
+    fn watch(&mut self, client: ClientId, name: &str, id: u32, now: Instant) {
+        if self.game_of(name).is_some() {
+            return self.error(client, "Finish your game first");
+        }
+        if !self.games.contains_key(&id) {
+            return self.error(client, "That game is over");
+        }
+        self.unwatch(client);
+        let game = self
+            .games
+            .get_mut(&id)
+            .expect("The game was just looked up");
+        game.spectators.push(client);
+        let mut messages = vec![ServerMessage::Watching(game.live(id))];
+        messages.extend(game.catch_up(now));
+        for message in messages {
+            self.outbox.push((client, message));
+        }
+    }
+
+    fn unwatch(&mut self, client: ClientId) {
+        for game in self.games.values_mut() {
+            game.spectators.retain(|spectator| *spectator != client);
+        }
+    }
+
+    fn end_game(&mut self, id: u32, result: GameResult, reason: &str) {
+        let Some(game) = self.games.remove(&id) else {
+            return;
+        };
+        let end = ServerMessage::End {
+            result,
+            reason: reason.to_string(),
+        };
+        for spectator in game.spectators.iter() {
+            self.outbox.push((*spectator, end.clone()));
+        }
+        self.send_to_all(ServerMessage::GameEnded(id));
+        for name in game.players.iter() {
+            self.send(name, end.clone());
+            let gone = match self.players.get_mut(name) {
+                Some(player) => {
+                    player.game = None;
+                    player.client.is_none()
+                }
+                None => false,
+            };
+            if gone {
+                self.players.remove(name);
+            }
+        }
+        self.send_players();
+    }
+
+    /// Keeps the seat of a player in a game for a while, so they can come
+    /// back, and lets everyone else go.
+    pub fn disconnect(&mut self, client: ClientId, now: Instant) {
+        let Some(name) = self.clients.remove(&client) else {
+            return;
+        };
+        self.unwatch(client);
+        self.remove_seeks(|seek, target| seek.name != name && target.as_ref() != Some(&name));
+        let game = match self.players.get_mut(&name) {
+            Some(player) if player.client == Some(client) => {
+                player.client = None;
+                player.left_at = Some(now);
+                player.game
+            }
+            _ => return,
+        };
+        match game.and_then(|id| self.games.get(&id)) {
+            Some(game) => {
+                let opponent = game.player(&opposite(&game.color_of(&name))).clone();
+                self.send(&opponent, ServerMessage::OpponentLeft);
+            }
+            None => {
+                self.players.remove(&name);
+            }
+        }
+        self.send_players();
+    }
+
+    /// Ends games lost on time or left for too long.
+    pub fn tick(&mut self, now: Instant) {
+        let mut ended = vec![];
+        for (id, game) in self.games.iter() {
+            let to_move = game.board.active_turn();
+            if let Some(clock) = game.clock_at(now) {
+                if clock[index(to_move)].is_zero() {
+                    ended.push((*id, win_for(&opposite(to_move)), "on time"));
+                    continue;
+                }
+            }
+            for color in [PieceColor::White, PieceColor::Black] {
+                let left_at = self
+                    .players
+                    .get(game.player(&color))
+                    .and_then(|player| player.left_at);
+                if left_at.is_some_and(|left_at| now - left_at >= ABANDON_AFTER) {
+                    ended.push((*id, win_for(&opposite(&color)), "by abandonment"));
+                    break;
+                }
+            }
+        }
+        for (id, result, reason) in ended {
+            self.end_game(id, result, reason);
+        }
+    }
+}
+
+/// How a connection frames its lines: plain TCP, or WebSocket for browsers.
+#[derive(Clone, Copy)]
+enum Transport {
+    Lines,
+    WebSocket,
+}
+
+/// The sending half of a connection.
+enum Writer {
+    Lines(TcpStream),
+    WebSocket(Box<WebSocket<TcpStream>>),
+}
+
+impl Writer {
+    fn send(&mut self, message: &ServerMessage) -> bool {
+        match self {
+            Writer::Lines(stream) => writeln!(stream, "{}", message).is_ok(),
+            Writer::WebSocket(socket) => socket.send(Message::text(message.to_string())).is_ok(),
+        }
+    }
+
+    /// Closes the connection, which the reader notices and reports the
+    /// client gone.
+    fn shut_down(&self) {
+        let stream = match self {
+            Writer::Lines(stream) => stream,
+            Writer::WebSocket(socket) => socket.get_ref(),
+        };
+        let _ = stream.shutdown(Shutdown::Both);
+    }
+}
+
+enum Event {
+    Connected(ClientId, Writer),
+    Line(ClientId, String),
+    Closed(ClientId),
+}
+
+fn websocket_config() -> WebSocketConfig {
+    WebSocketConfig {
+        max_message_size: Some(MAX_MESSAGE),
+        max_frame_size: Some(MAX_MESSAGE),
+        ..WebSocketConfig::default()
+    }
+}
+
+/// Reads lines until the client goes away, or sends a line longer than
+/// `MAX_MESSAGE`.
+fn read_lines(client: ClientId, stream: TcpStream, events: Sender<Event>) {
+    let mut reader = BufReader::new(stream);
+    loop {
+        let mut line = String::new();
+        match (&mut reader).take(MAX_MESSAGE as u64).read_line(&mut line) {
+            Ok(0) | Err(_) => break,
+            Ok(length) if length == MAX_MESSAGE && !line.ends_with('\n') => break,
+            Ok(_) => {}
+        }
+        let line = line.trim_end_matches(['\r', '\n']).to_string();
+        if events.send(Event::Line(client, line)).is_err() {
+            return;
+        }
+    }
+    let _ = events.send(Event::Closed(client));
+}
+
+fn read_messages(client: ClientId, mut socket: WebSocket<TcpStream>, events: Sender<Event>) {
+    loop {
+        let text = match socket.read() {
+            Ok(Message::Text(text)) => text,
+            Ok(Message::Close(_)) | Err(_) => break,
+            Ok(_) => continue,
+        };
+        for line in text.lines() {
+            if events.send(Event::Line(client, line.to_string())).is_err() {
+                return;
+            }
+        }
+    }
+    let _ = events.send(Event::Closed(client));
+}
+
+/// Agrees on the transport, then reads the client until it goes away.
+fn open(
+    client: ClientId,
+    stream: TcpStream,
+    reader: TcpStream,
+    transport: Transport,
+    events: Sender<Event>,
+) {
+    match transport {
+        Transport::Lines => {
+            if events
+                .send(Event::Connected(client, Writer::Lines(stream)))
+                .is_ok()
+            {
+                read_lines(client, reader, events);
+            }
+        }
+        Transport::WebSocket => {
+            // Both halves share the connection but keep their own state, so
+            // the lobby can send while this thread waits for the client.
+            let socket = match tungstenite::accept_with_config(reader, Some(websocket_config())) {
+                Ok(socket) => socket,
+                Err(error) => {
+                    warn!("Could not open a WebSocket: {}", error);
+                    return;
+                }
+            };
+            let writer = WebSocket::from_raw_socket(stream, Role::Server, Some(websocket_config()));
+            if events
+                .send(Event::Connected(
+                    client,
+                    Writer::WebSocket(Box::new(writer)),
+                ))
+                .is_ok()
+            {
+                read_messages(client, socket, events);
+            }
+        }
+    }
+}
+
+fn accept(
+    listener: TcpListener,
+    transport: Transport,
+    next_client: Arc<AtomicU32>,
+    events: Sender<Event>,
+) {
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(error) => {
+                warn!("Could not accept a connection: {}", error);
+                continue;
+            }
+        };
+        let client = next_client.fetch_add(1, Ordering::Relaxed);
+        let _ = stream.set_nodelay(true);
+        let _ = stream.set_write_timeout(Some(WRITE_TIMEOUT));
+        let Ok(reader) = stream.try_clone() else {
+            continue;
+        };
+        let events = events.clone();
+        thread::spawn(move || open(client, stream, reader, transport, events));
+    }
+}
+
+/// Runs the server on `listener`, and for browsers on `websocket`, until
+/// the process ends. Each connection is read on its own thread, while the
+/// lobby lives on this one.
+pub fn serve(listener: TcpListener, websocket: Option<TcpListener>) {
+    let (sender, events) = mpsc::channel();
+    let next_client = Arc::new(AtomicU32::new(0));
+    let listeners = [
+        (Some(listener), Transport::Lines),
+        (websocket, Transport::WebSocket),
+    ];
+    for (listener, transport) in listeners {
+        let Some(listener) = listener else {
+            continue;
+        };
+        let sender = sender.clone();
+        let next_client = next_client.clone();
+        thread::spawn(move || accept(listener, transport, next_client, sender));
+    }
+    drop(sender);
+    let mut lobby = Lobby::default();
+    let mut writers: HashMap<ClientId, Writer> = HashMap::new();
+    loop {
+        let now = Instant::now();
+        match events.recv_timeout(TICK) {
+            Ok(Event::Connected(client, writer)) => {
+                writers.insert(client, writer);
+            }
+            Ok(Event::Line(client, line)) => lobby.receive(client, &line, now),
+            Ok(Event::Closed(client)) => {
+                writers.remove(&client);
+                lobby.disconnect(client, now);
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => return,
+        }
+        lobby.tick(now);
+        for (client, message) in lobby.take_messages() {
+            let Some(writer) = writers.get_mut(&client) else {
+                continue;
+            };
+            if !writer.send(&message) {
+                writer.shut_down();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod server_tests {
+    use crate::board::CheckerBoard;
+    use crate::game_result::GameResult;
+    use crate::pieces::color::PieceColor;
+    use crate::protocol::{Connection, LiveGame, ServerMessage};
+    use crate::server::{serve, ClientId, Lobby, ABANDON_AFTER, MAX_MESSAGE};
+    use std::io::{ErrorKind, Read, Write};
+    use std::net::{TcpListener, TcpStream};
+    use std::str::FromStr;
+    use std::time::{Duration, Instant};
+    use tungstenite::Message;
+
+    fn said(lobby: &mut Lobby, client: ClientId) -> Vec<ServerMessage> {
+        lobby
+            .take_messages()
+            .into_iter()
+            .filter(|(to, _)| *to == client)
+            .map(|(_, message)| message)
+            .collect()
+    }
+
+    fn token(messages: &[ServerMessage]) -> String {
+        messages
+            .iter()
+            .find_map(|message| match message {
+                ServerMessage::Welcome { token, .. } => Some(token.clone()),
+                _ => None,
+            })
+            .unwrap()
+    }
+
+    /// Signs in anna and ben and starts a game with anna as white.
+    fn start(lobby: &mut Lobby, minutes: u32, now: Instant) -> String {
+        lobby.receive(1, "HELLO anna", now);
+        let anna = token(&said(lobby, 1));
+        lobby.receive(2, "HELLO ben", now);
+        lobby.receive(1, &format!("SEEK {} 0 white", minutes), now);
+        lobby.take_messages();
+        lobby.receive(2, "ACCEPT 1", now);
+        let messages = said(lobby, 2);
+        assert!(messages.contains(&ServerMessage::Start {
+            game: 2,
+            color: PieceColor::Black,
+            opponent: "anna".to_string(),
+            minutes,
+            increment: 0,
+        }));
+        anna
+    }
+
+    #[test]
+    fn only_legal_moves_in_turn_are_relayed() {
+        let now = Instant::now();
+        let mut lobby = Lobby::default();
+        start(&mut lobby, 1, now);
+        lobby.receive(2, "MOVE e7e5", now);
+        assert!(matches!(said(&mut lobby, 2)[..], [ServerMessage::Error(_)]));
+        lobby.receive(1, "MOVE e2e5", now);
+        assert!(matches!(said(&mut lobby, 1)[..], [ServerMessage::Error(_)]));
+        lobby.receive(1, "MOVE e2e4", now + Duration::from_secs(2));
+        assert_eq!(
+            said(&mut lobby, 2),
+            vec![ServerMessage::Move {
+                ply: 0,
+                uci: "e2e4".to_string(),
+                clock: Some([Duration::from_secs(58), Duration::from_secs(60)]),
+            }]
+        );
+    }
+
+    #[test]
+    fn names_are_kept_for_their_owner() {
+        let now = Instant::now();
+        let mut lobby = Lobby::default();
+        let anna = start(&mut lobby, 1, now);
+        lobby.receive(3, "HELLO anna", now);
+        assert!(matches!(said(&mut lobby, 3)[..], [ServerMessage::Error(_)]));
+        lobby.disconnect(1, now);
+        assert!(said(&mut lobby, 2).contains(&ServerMessage::OpponentLeft));
+        lobby.receive(3, &format!("HELLO anna {}", anna), now);
+        let messages = said(&mut lobby, 3);
+        assert!(matches!(messages[1], ServerMessage::Game(_)));
+        assert!(matches!(messages[2], ServerMessage::Start { .. }));
+        lobby.receive(3, "MOVE e2e4", now);
+        assert!(matches!(
+            said(&mut lobby, 2)[..],
+            [ServerMessage::Move { .. }]
+        ));
+    }
+
+    #[test]
+    fn guests_are_numbered() {
+        let now = Instant::now();
+        let mut lobby = Lobby::default();
+        lobby.receive(1, "HELLO Guest", now);
+        let messages = said(&mut lobby, 1);
+        assert!(matches!(&messages[0], ServerMessage::Welcome { name, .. } if name == "Guest1"));
+        let guest = token(&messages);
+        lobby.receive(2, "HELLO Guest", now);
+        assert!(
+            matches!(&said(&mut lobby, 2)[0], ServerMessage::Welcome { name, .. } if name == "Guest2")
+        );
+        lobby.disconnect(1, now);
+        lobby.take_messages();
+        lobby.receive(3, &format!("HELLO Guest1 {}", guest), now);
+        assert!(
+            matches!(&said(&mut lobby, 3)[0], ServerMessage::Welcome { name, .. } if name == "Guest1")
+        );
+    }
+
+    #[test]
+    fn a_stuck_side_that_is_not_to_move_plays_on() {
+        let now = Instant::now();
+        let mut lobby = Lobby::default();
+        start(&mut lobby, 0, now);
+        // After a6 White has no move left, but Black is to move and has many.
+        lobby.games.get_mut(&2).unwrap().board =
+            CheckerBoard::from_fen("2k5/p7/8/P7/8/7p/4n2P/7K w - - 0 1").unwrap();
+        lobby.receive(1, "MOVE a5a6", now);
+        let messages = said(&mut lobby, 2);
+        assert!(matches!(messages[..], [ServerMessage::Move { .. }]));
+        assert!(lobby.games.contains_key(&2));
+        // Only once White is to move is it stalemate.
+        lobby.receive(2, "MOVE c8b8", now);
+        assert!(said(&mut lobby, 1).contains(&ServerMessage::End {
+            result: GameResult::Draw,
+            reason: "by stalemate".to_string(),
+        }));
+    }
+
+    #[test]
+    fn games_end_by_resignation_agreement_time_or_abandonment() {
+        let now = Instant::now();
+        let mut lobby = Lobby::default();
+        start(&mut lobby, 1, now);
+        lobby.receive(2, "DRAW", now);
+        assert_eq!(said(&mut lobby, 1), vec![ServerMessage::DrawOffered]);
+        lobby.receive(1, "DRAW", now);
+        assert!(said(&mut lobby, 2).contains(&ServerMessage::End {
+            result: GameResult::Draw,
+            reason: "by agreement".to_string(),
+        }));
+
+        let mut lobby = Lobby::default();
+        start(&mut lobby, 1, now);
+        lobby.tick(now + Duration::from_secs(61));
+        assert!(said(&mut lobby, 2).contains(&ServerMessage::End {
+            result: GameResult::BlackWins,
+            reason: "on time".to_string(),
+        }));
+
+        let mut lobby = Lobby::default();
+        start(&mut lobby, 0, now);
+        lobby.receive(1, "MOVE e2e4", now);
+        lobby.disconnect(2, now);
+        lobby.tick(now + ABANDON_AFTER);
+        assert!(said(&mut lobby, 1).contains(&ServerMessage::End {
+            result: GameResult::WhiteWins,
+            reason: "by abandonment".to_string(),
+        }));
+    }
+
+    #[test]
+    fn spectators_join_late_and_follow_the_game() {
+        let now = Instant::now();
+        let mut lobby = Lobby::default();
+        start(&mut lobby, 1, now);
+        lobby.receive(1, "MOVE e2e4", now + Duration::from_secs(3));
+        lobby.receive(3, "HELLO carl", now);
+        let messages = said(&mut lobby, 3);
+        assert!(messages.contains(&ServerMessage::Game(LiveGame {
+            id: 2,
+            white: "anna".to_string(),
+            black: "ben".to_string(),
+            minutes: 1,
+            increment: 0,
+        })));
+        lobby.receive(3, "WATCH 2", now + Duration::from_secs(5));
+        let messages = said(&mut lobby, 3);
+        assert!(matches!(messages[0], ServerMessage::Watching(_)));
+        assert_eq!(
+            messages[1..],
+            [
+                ServerMessage::Move {
+                    ply: 0,
+                    uci: "e2e4".to_string(),
+                    clock: None,
+                },
+                ServerMessage::Clock([Duration::from_secs(57), Duration::from_secs(58)]),
+            ]
+        );
+        lobby.receive(3, "MOVE e7e5", now);
+        assert!(matches!(said(&mut lobby, 3)[..], [ServerMessage::Error(_)]));
+        lobby.receive(2, "MOVE e7e5", now + Duration::from_secs(5));
+        assert!(matches!(
+            said(&mut lobby, 3)[..],
+            [ServerMessage::Move { ply: 1, .. }]
+        ));
+        lobby.receive(1, "RESIGN", now);
+        let messages = said(&mut lobby, 3);
+        assert!(messages.contains(&ServerMessage::GameEnded(2)));
+        assert!(messages.contains(&ServerMessage::End {
+            result: GameResult::BlackWins,
+            reason: "by resignation".to_string(),
+        }));
+        lobby.receive(3, "WATCH 2", now);
+        assert_eq!(
+            said(&mut lobby, 3),
+            vec![ServerMessage::Error("That game is over".to_string())]
+        );
+    }
+
+    fn wait_for(connection: &mut Connection, prefix: &str) -> ServerMessage {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while Instant::now() < deadline {
+            for line in connection.receive() {
+                if line.starts_with(prefix) {
+                    return ServerMessage::from_str(&line).unwrap();
+                }
+            }
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        panic!("No {} from the server", prefix);
+    }
+
+    #[test]
+    fn two_clients_play_on_localhost() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        std::thread::spawn(move || serve(listener, None));
+        let mut anna = Connection::connect(&address).unwrap();
+        let mut ben = Connection::connect(&address).unwrap();
+        anna.send("HELLO anna");
+        wait_for(&mut anna, "WELCOME");
+        ben.send("HELLO ben");
+        wait_for(&mut ben, "WELCOME");
+        ben.send("CHALLENGE anna 0 0 black");
+        let ServerMessage::Challenge(seek) = wait_for(&mut anna, "CHALLENGE") else {
+            unreachable!();
+        };
+        anna.send(format!("ACCEPT {}", seek.id));
+        wait_for(&mut ben, "START");
+        anna.send("MOVE f2f3");
+        wait_for(&mut ben, "MOVE 0 f2f3");
+        ben.send("MOVE e7e5");
+        wait_for(&mut anna, "MOVE 1 e7e5");
+        anna.send("MOVE g2g4");
+        wait_for(&mut ben, "MOVE 2 g2g4");
+        ben.send("MOVE d8h4");
+        assert_eq!(
+            wait_for(&mut anna, "END"),
+            ServerMessage::End {
+                result: GameResult::BlackWins,
+                reason: "by checkmate".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn browsers_connect_over_websocket() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let websocket = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = websocket.local_addr().unwrap();
+        std::thread::spawn(move || serve(listener, Some(websocket)));
+        let stream = TcpStream::connect(address).unwrap();
+        let (mut socket, _) = tungstenite::client(format!("ws://{}", address), stream).unwrap();
+        socket.send(Message::text("HELLO carl")).unwrap();
+        let welcome = socket.read().unwrap().into_text().unwrap();
+        assert!(welcome.starts_with("WELCOME carl "));
+    }
+
+    #[test]
+    fn overlong_lines_drop_the_client() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        std::thread::spawn(move || serve(listener, None));
+        let mut stream = TcpStream::connect(address).unwrap();
+        stream
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        let _ = stream.write_all("x".repeat(MAX_MESSAGE + 1).as_bytes());
+        // The server hangs up, cleanly or with a reset, instead of waiting
+        // for the end of the line.
+        let closed = stream
+            .read_to_end(&mut vec![])
+            .map_err(|error| error.kind());
+        assert!(!matches!(
+            closed,
+            Err(ErrorKind::WouldBlock | ErrorKind::TimedOut)
+        ));
+    }
+
+    #[test]
+    fn tokens_are_random() {
+        let token = Lobby::new_token().unwrap();
+        assert_eq!(token.len(), 32);
+        assert_ne!(token, Lobby::new_token().unwrap());
+    }
+}
@@ -0,0 +1,158 @@
+/// The connection to the game server, over plain TCP.
+#[cfg(not(target_arch = "wasm32"))]
+mod platform {
+    use rusty_chess::protocol::DEFAULT_PORT;
+
+    pub use rusty_chess::protocol::Connection;
+
+    pub fn default_address() -> String {
+        format!("127.0.0.1:{}", DEFAULT_PORT)
+    }
+}
+
+/// The connection to the game server, over a WebSocket, as browsers cannot
+/// open plain TCP connections.
+#[cfg(target_arch = "wasm32")]
+mod platform {
+    use rusty_chess::protocol::DEFAULT_WEBSOCKET_PORT;
+    use std::cell::{Cell, RefCell};
+    use std::collections::HashMap;
+    use std::fmt::Display;
+    use std::io::Error;
+    use std::rc::Rc;
+    use wasm_bindgen::closure::Closure;
+    use wasm_bindgen::JsCast;
+    use web_sys::{Event, MessageEvent, WebSocket};
+
+    /// What the browser handed over since the last poll.
+    #[derive(Default)]
+    struct Inbox {
+        lines: Vec<String>,
+        closed: bool,
+    }
+
+    struct Socket {
+        socket: WebSocket,
+        inbox: Rc<RefCell<Inbox>>,
+        /// The lines sent before the socket opened.
+        outgoing: Vec<String>,
+        _on_message: Closure<dyn FnMut(MessageEvent)>,
+        _on_close: Closure<dyn FnMut(Event)>,
+    }
+
+    thread_local! {
+        // Browser sockets cannot leave the main thread while resources must
+        // be able to, so a connection only holds the key of its socket.
+        static SOCKETS: RefCell<HashMap<u32, Socket>> = RefCell::default();
+        static NEXT_KEY: Cell<u32> = const { Cell::new(0) };
+    }
+
+    /// The server on the host the game was loaded from, unless the address
+    /// says otherwise.
+    pub fn default_address() -> String {
+        let host = web_sys::window()
+            .and_then(|window| window.location().hostname().ok())
+            .filter(|host| !host.is_empty())
+            .unwrap_or_else(|| "127.0.0.1".to_string());
+        format!("ws://{}:{}", host, DEFAULT_WEBSOCKET_PORT)
+    }
+
+    /// Works like the TCP connection: lines are sent as they are given and
+    /// polled once a frame.
+    pub struct Connection {
+        key: u32,
+    }
+
+    impl Connection {
+        pub fn connect(address: &str) -> std::io::Result<Self> {
+            let url = match address.contains("://") {
+                true => address.to_string(),
+                false => format!("ws://{}", address),
+            };
+            let socket =
+                WebSocket::new(&url).map_err(|error| Error::other(format!("{:?}", error)))?;
+            let inbox = Rc::new(RefCell::new(Inbox::default()));
+            let on_message = {
+                let inbox = inbox.clone();
+                Closure::<dyn FnMut(MessageEvent)>::new(move |event: MessageEvent| {
+                    if let Some(text) = event.data().as_string() {
+                        inbox
+                            .borrow_mut()
+                            .lines
+                            .extend(text.lines().map(str::to_string));
+                    }
+                })
+            };
+            let on_close = {
+                let inbox = inbox.clone();
+                Closure::<dyn FnMut(Event)>::new(move |_: Event| inbox.borrow_mut().closed = true)
+            };
+            socket.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+            socket.set_onclose(Some(on_close.as_ref().unchecked_ref()));
+            socket.set_onerror(Some(on_close.as_ref().unchecked_ref()));
+            let key = NEXT_KEY.with(|next| next.replace(next.get() + 1));
+            let socket = Socket {
+                socket,
+                inbox,
+                outgoing: vec![],
+                _on_message: on_message,
+                _on_close: on_close,
+            };
+            SOCKETS.with(|sockets| sockets.borrow_mut().insert(key, socket));
+            Ok(Self { key })
+        }
+
+        fn with_socket<T>(&self, action: impl FnOnce(&mut Socket) -> T) -> Option<T> {
+            SOCKETS.with(|sockets| sockets.borrow_mut().get_mut(&self.key).map(action))
+        }
+
+        pub fn is_closed(&self) -> bool {
+            self.with_socket(|socket| socket.inbox.borrow().closed)
+                .unwrap_or(true)
+        }
+
+        pub fn send(&mut self, message: impl Display) {
+            self.with_socket(|socket| {
+                socket.outgoing.push(message.to_string());
+                flush(socket);
+            });
+        }
+
+        /// The lines that arrived since the last call.
+        pub fn receive(&mut self) -> Vec<String> {
+            self.with_socket(|socket| {
+                flush(socket);
+                std::mem::take(&mut socket.inbox.borrow_mut().lines)
+                    .into_iter()
+                    .map(|line| line.trim().to_string())
+                    .filter(|line| !line.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default()
+        }
+    }
+
+    fn flush(socket: &mut Socket) {
+        if socket.socket.ready_state() != WebSocket::OPEN {
+            return;
+        }
+        for line in socket.outgoing.drain(..) {
+            if socket.socket.send_with_str(&line).is_err() {
+                socket.inbox.borrow_mut().closed = true;
+            }
+        }
+    }
+
+    impl Drop for Connection {
+        fn drop(&mut self) {
+            if let Some(socket) = SOCKETS.with(|sockets| sockets.borrow_mut().remove(&self.key)) {
+                socket.socket.set_onmessage(None);
+                socket.socket.set_onclose(None);
+                socket.socket.set_onerror(None);
+                let _ = socket.socket.close();
+            }
+        }
+    }
+}
+
+pub use platform::{default_address, Connection};
@@ -0,0 +1,103 @@
+use crate::storage;
+use bevy::log::warn;
+use bevy::prelude::Resource;
+use std::fmt::Display;
+
+pub const SETTINGS_NAME: &str = "settings.cfg";
+
+/// User preferences, stored as `key=value` lines.
+#[derive(Resource, Debug, Clone, PartialEq)]
+pub struct Settings {
+    pub volume: f32,
+    pub muted: bool,
+    /// The name of the board and piece theme.
+    pub theme: String,
+}
+
+impl Settings {
+    /// Reads what it understands and keeps the defaults for everything else.
+    pub fn parse(text: &str) -> Self {
+        let mut settings = Self::default();
+        for line in text.lines() {
+            let Some((key, value)) = line.split_once('=') else {
+                continue;
+            };
+            match key.trim() {
+                "volume" => {
+                    if let Ok(volume) = value.trim().parse::<f32>() {
+                        settings.volume = volume.clamp(0., 1.);
+                    }
+                }
+                "muted" => {
+                    if let Ok(muted) = value.trim().parse() {
+                        settings.muted = muted;
+                    }
+                }
+                "theme" => settings.theme = value.trim().to_string(),
+                _ => {}
+            }
+        }
+        settings
+    }
+
+    pub fn set_volume(&mut self, volume: f32) {
+        self.volume = volume.clamp(0., 1.);
+    }
+
+    pub fn load() -> Self {
+        storage::read(SETTINGS_NAME)
+            .map(|text| Self::parse(&text))
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) {
+        if let Err(error) = storage::write(SETTINGS_NAME, &self.to_string()) {
+            warn!("Could not save the settings: {}", error);
+        }
+    }
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            volume: 0.8,
+            muted: false,
+            theme: "Classic".to_string(),
+        }
+    }
+}
+
+impl Display for Settings {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "volume={:.2}", self.volume)?;
+        writeln!(f, "muted={}", self.muted)?;
+        writeln!(f, "theme={}", self.theme)
+    }
+}
+
+#[cfg(test)]
+mod settings_tests {
+    use crate::settings::Settings;
+
+    #[test]
+    fn it_round_trips() {
+        let settings = Settings {
+            volume: 0.35,
+            muted: true,
+            theme: "Tournament".to_string(),
+        };
+        assert_eq!(Settings::parse(&settings.to_string()), settings);
+    }
+
+    #[test]
+    fn it_keeps_defaults_for_bad_lines() {
+        let settings = Settings::parse("volume=loud\nunknown=1\nmuted=true\n");
+        assert_eq!(settings.volume, Settings::default().volume);
+        assert!(settings.muted);
+    }
+
+    #[test]
+    fn volume_is_clamped() {
+        assert_eq!(Settings::parse("volume=3").volume, 1.);
+    }
+}
@@ -0,0 +1,162 @@
+use crate::board::CheckerBoard;
+use crate::board_side_effects::BoardSideEffects;
+use crate::game_result::GameResult;
+use crate::settings::Settings;
+use bevy::audio::{AudioBundle, PlaybackSettings, Volume};
+use bevy::prelude::{AssetServer, ButtonInput, Commands, Event, EventReader, KeyCode, Res, ResMut};
+
+pub const VOLUME_STEP: f32 = 0.1;
+
+#[derive(Event, Debug, Clone, Copy, PartialEq)]
+pub enum SoundEffect {
+    Move,
+    Capture,
+    Castle,
+    Check,
+    Promotion,
+    GameEnd,
+    LowTime,
+}
+
+impl SoundEffect {
+    /// The most important thing that happened in a move, given its side
+    /// effects and the board after it.
+    pub fn from_move(side_effects: &BoardSideEffects, board: &CheckerBoard) -> Self {
+        if GameResult::from_board(board) != GameResult::Ongoing {
+            SoundEffect::GameEnd
+        } else if board.is_checked(board.active_turn()) {
+            SoundEffect::Check
+        } else if !side_effects.updates.is_empty() {
+            SoundEffect::Promotion
+        } else if !side_effects.moves.is_empty() {
+            SoundEffect::Castle
+        } else if !side_effects.takes.is_empty() {
+            SoundEffect::Capture
+        } else {
+            SoundEffect::Move
+        }
+    }
+
+    fn path(&self) -> &'static str {
+        match self {
+            SoundEffect::Move => "sounds/move.wav",
+            SoundEffect::Capture => "sounds/capture.wav",
+            SoundEffect::Castle => "sounds/castle.wav",
+            SoundEffect::Check => "sounds/check.wav",
+            SoundEffect::Promotion => "sounds/promotion.wav",
+            SoundEffect::GameEnd => "sounds/game_end.wav",
+            SoundEffect::LowTime => "sounds/low_time.wav",
+        }
+    }
+}
+
+pub fn play_sounds(
+    mut commands: Commands,
+    mut events: EventReader<SoundEffect>,
+    asset_server: Res<AssetServer>,
+    settings: Res<Settings>,
+) {
+    for sound in events.read() {
+        if settings.muted {
+            continue;
+        }
+        commands.spawn(AudioBundle {
+            source: asset_server.load(sound.path()),
+            settings: PlaybackSettings::DESPAWN.with_volume(Volume::new(settings.volume)),
+        });
+    }
+}
+
+/// M mutes, minus and equals turn the volume down and up.
+pub fn adjust_volume(keys: Res<ButtonInput<KeyCode>>, mut settings: ResMut<Settings>) {
+    if keys.just_pressed(KeyCode::KeyM) {
+        settings.muted = !settings.muted;
+    } else if keys.just_pressed(KeyCode::Minus) {
+        let volume = settings.volume - VOLUME_STEP;
+        settings.set_volume(volume);
+    } else if keys.just_pressed(KeyCode::Equal) {
+        let volume = settings.volume + VOLUME_STEP;
+        settings.set_volume(volume);
+    } else {
+        return;
+    }
+    settings.save();
+}
+
+#[cfg(test)]
+mod sound_tests {
+    use crate::board::CheckerBoard;
+    use crate::board_piece::BoardPiece;
+    use crate::board_pos;
+    use crate::pieces::color::PieceColor;
+    use crate::pieces::piece_type::PieceType;
+    use crate::sound::SoundEffect;
+    use std::str::FromStr;
+
+    #[test]
+    fn quiet_move() {
+        let mut board = CheckerBoard::default();
+        let side_effects = board.move_piece(&board_pos!("e2"), &board_pos!("e4"));
+        assert_eq!(
+            SoundEffect::from_move(&side_effects, &board),
+            SoundEffect::Move
+        );
+    }
+
+    #[test]
+    fn capture() {
+        let mut board = CheckerBoard::default();
+        board.move_piece(&board_pos!("e2"), &board_pos!("e4"));
+        board.move_piece(&board_pos!("d7"), &board_pos!("d5"));
+        let side_effects = board.move_piece(&board_pos!("e4"), &board_pos!("d5"));
+        assert_eq!(
+            SoundEffect::from_move(&side_effects, &board),
+            SoundEffect::Capture
+        );
+    }
+
+    #[test]
+    fn castle() {
+        let mut board = CheckerBoard::with_pieces(vec![
+            BoardPiece::build(PieceType::King, PieceColor::White, "e1"),
+            BoardPiece::build(PieceType::Rook, PieceColor::White, "h1"),
+            BoardPiece::build(PieceType::King, PieceColor::Black, "a8"),
+            BoardPiece::build(PieceType::Pawn, PieceColor::Black, "a7"),
+        ]);
+        let side_effects = board.move_piece(&board_pos!("e1"), &board_pos!("g1"));
+        assert_eq!(
+            SoundEffect::from_move(&side_effects, &board),
+            SoundEffect::Castle
+        );
+    }
+
+    #[test]
+    fn check_wins_over_capture() {
+        let mut board = CheckerBoard::with_pieces(vec![
+            BoardPiece::build(PieceType::King, PieceColor::White, "c1"),
+            BoardPiece::build(PieceType::Rook, PieceColor::White, "h1"),
+            BoardPiece::build(PieceType::Knight, PieceColor::Black, "h7"),
+            BoardPiece::build(PieceType::King, PieceColor::Black, "b7"),
+        ]);
+        let side_effects = board.move_piece(&board_pos!("h1"), &board_pos!("h7"));
+        assert_eq!(
+            SoundEffect::from_move(&side_effects, &board),
+            SoundEffect::Check
+        );
+    }
+
+    #[test]
+    fn mate_ends_the_game() {
+        let mut board = CheckerBoard::with_pieces(vec![
+            BoardPiece::build(PieceType::Pawn, PieceColor::White, "a7"),
+            BoardPiece::build(PieceType::Pawn, PieceColor::White, "b6"),
+            BoardPiece::build(PieceType::King, PieceColor::White, "a6"),
+            BoardPiece::build(PieceType::King, PieceColor::Black, "a8"),
+        ]);
+        let side_effects = board.move_piece(&board_pos!("b6"), &board_pos!("b7"));
+        assert_eq!(
+            SoundEffect::from_move(&side_effects, &board),
+            SoundEffect::GameEnd
+        );
+    }
+}
@@ -0,0 +1,65 @@
+/// What the app keeps between sessions, as files in the data directory.
+#[cfg(not(target_arch = "wasm32"))]
+mod platform {
+    use std::path::PathBuf;
+
+    /// Where the app keeps its data on this platform, falling back to the
+    /// working directory when the usual place is unknown.
+    fn data_dir() -> PathBuf {
+        let env = |name: &str| std::env::var_os(name).map(PathBuf::from);
+        let base = if cfg!(target_os = "windows") {
+            env("APPDATA")
+        } else if cfg!(target_os = "macos") {
+            env("HOME").map(|home| home.join("Library/Application Support"))
+        } else {
+            env("XDG_DATA_HOME").or_else(|| env("HOME").map(|home| home.join(".local/share")))
+        };
+        base.map(|base| base.join("rusty-chess"))
+            .unwrap_or_default()
+    }
+
+    pub fn read(name: &str) -> Option<String> {
+        std::fs::read_to_string(data_dir().join(name)).ok()
+    }
+
+    pub fn write(name: &str, text: &str) -> Result<(), String> {
+        let dir = data_dir();
+        std::fs::create_dir_all(&dir)
+            .and_then(|_| std::fs::write(dir.join(name), text))
+            .map_err(|error| error.to_string())
+    }
+
+    pub fn remove(name: &str) {
+        let _ = std::fs::remove_file(data_dir().join(name));
+    }
+}
+
+/// What the app keeps between sessions, as keys in the browser's local
+/// storage.
+#[cfg(target_arch = "wasm32")]
+mod platform {
+    use web_sys::Storage;
+
+    fn local_storage() -> Option<Storage> {
+        web_sys::window()?.local_storage().ok()?
+    }
+
+    pub fn read(name: &str) -> Option<String> {
+        local_storage()?.get_item(name).ok()?
+    }
+
+    pub fn write(name: &str, text: &str) -> Result<(), String> {
+        local_storage()
+            .ok_or_else(|| "No local storage".to_string())?
+            .set_item(name, text)
+            .map_err(|error| format!("{:?}", error))
+    }
+
+    pub fn remove(name: &str) {
+        if let Some(storage) = local_storage() {
+            let _ = storage.remove_item(name);
+        }
+    }
+}
+
+pub use platform::{read, remove, write};
@@ -0,0 +1,90 @@
+use bevy::asset::io::Reader;
+use bevy::asset::{AssetLoader, AsyncReadExt, LoadContext};
+use bevy::prelude::*;
+use bevy::render::render_asset::RenderAssetUsages;
+use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
+use resvg::tiny_skia::Pixmap;
+use resvg::usvg::{Options, Transform, Tree};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum SvgError {
+    #[error("Could not read SVG file: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Could not parse SVG file: {0}")]
+    Parse(#[from] resvg::usvg::Error),
+    #[error("SVG file has no size")]
+    Empty,
+}
+
+/// Turns `.svg` files into images at the size they declare, so a theme
+/// using `layout=files` should give that size.
+#[derive(Default)]
+pub struct SvgLoader;
+
+impl AssetLoader for SvgLoader {
+    type Asset = Image;
+    type Settings = ();
+    type Error = SvgError;
+
+    async fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader<'_>,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext<'_>,
+    ) -> Result<Image, SvgError> {
+        let mut bytes = vec![];
+        reader.read_to_end(&mut bytes).await?;
+        rasterize(&bytes)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["svg"]
+    }
+}
+
+pub fn rasterize(bytes: &[u8]) -> Result<Image, SvgError> {
+    let tree = Tree::from_data(bytes, &Options::default())?;
+    let size = tree.size().to_int_size();
+    let mut pixmap = Pixmap::new(size.width(), size.height()).ok_or(SvgError::Empty)?;
+    resvg::render(&tree, Transform::default(), &mut pixmap.as_mut());
+    let data = pixmap
+        .pixels()
+        .iter()
+        .flat_map(|pixel| {
+            let color = pixel.demultiply();
+            [color.red(), color.green(), color.blue(), color.alpha()]
+        })
+        .collect();
+    Ok(Image::new(
+        Extent3d {
+            width: size.width(),
+            height: size.height(),
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::default(),
+    ))
+}
+
+#[cfg(test)]
+mod svg_loader_tests {
+    use crate::svg_loader::rasterize;
+
+    #[test]
+    fn svg_is_drawn_at_its_own_size() {
+        let svg = r##"<svg xmlns="http://www.w3.org/2000/svg" width="45" height="30">
+            <rect x="0" y="0" width="45" height="30" fill="#ff0000"/>
+        </svg>"##;
+        let image = rasterize(svg.as_bytes()).unwrap();
+        assert_eq!(image.size(), bevy::math::UVec2::new(45, 30));
+        assert_eq!(&image.data[..4], &[255, 0, 0, 255]);
+    }
+
+    #[test]
+    fn broken_svg_is_an_error() {
+        assert!(rasterize(b"<svg").is_err());
+    }
+}
@@ -0,0 +1,322 @@
+use crate::board::CheckerBoard;
+use crate::board_move::BoardMove;
+use crate::pieces::color::PieceColor;
+use crate::pieces::piece_type::PieceType;
+use bevy::prelude::Resource;
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+pub const SYZYGY_PATH_ENV: &str = "RUSTY_CHESS_SYZYGY_PATH";
+
+#[cfg_attr(not(feature = "tablebase"), allow(dead_code))]
+#[derive(Debug, Clone, PartialEq)]
+pub enum Wdl {
+    Loss,
+    BlessedLoss,
+    Draw,
+    CursedWin,
+    Win,
+}
+
+impl Wdl {
+    /// Orders the results from worst to best for the side to move.
+    fn rank(&self) -> i32 {
+        match self {
+            Wdl::Loss => -2,
+            Wdl::BlessedLoss => -1,
+            Wdl::Draw => 0,
+            Wdl::CursedWin => 1,
+            Wdl::Win => 2,
+        }
+    }
+}
+
+impl Display for Wdl {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let result = match self {
+            Wdl::Loss => "Loss",
+            Wdl::BlessedLoss => "Blessed loss",
+            Wdl::Draw => "Draw",
+            Wdl::CursedWin => "Cursed win",
+            Wdl::Win => "Win",
+        };
+        write!(f, "{}", result)
+    }
+}
+
+/// Result of a probe, seen from the side to move.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TablebaseProbe {
+    pub wdl: Wdl,
+    pub dtz: Option<i32>,
+}
+
+#[cfg_attr(not(feature = "tablebase"), allow(dead_code))]
+#[derive(Error, Debug)]
+pub enum TablebaseError {
+    #[error("Tablebase support is not enabled in this build")]
+    Disabled,
+    #[error("Tablebase directory not found: {0}")]
+    MissingDirectory(PathBuf),
+    #[error("No tablebase files are loaded")]
+    NotLoaded,
+    #[error("No tablebase files for {0}")]
+    MissingTable(String),
+    #[error("Too many pieces for tablebase: {0}")]
+    TooManyPieces(usize),
+    #[error("Could not load tablebase files: {0}")]
+    Load(String),
+    #[error("Tablebase probe failed: {0}")]
+    Probe(String),
+}
+
+#[derive(Resource)]
+pub struct Tablebase {
+    directories: Vec<PathBuf>,
+    max_pieces: usize,
+    #[cfg(feature = "tablebase")]
+    tables: shakmaty_syzygy::Tablebase<shakmaty::Chess>,
+}
+
+impl Tablebase {
+    pub fn new() -> Self {
+        Self {
+            directories: vec![],
+            max_pieces: 0,
+            #[cfg(feature = "tablebase")]
+            tables: shakmaty_syzygy::Tablebase::new(),
+        }
+    }
+
+    /// Loads tables from the directory in `RUSTY_CHESS_SYZYGY_PATH`, if any.
+    /// A missing or unreadable directory leaves the tablebase empty.
+    pub fn from_env() -> Self {
+        let mut tablebase = Self::new();
+        if let Some(path) = std::env::var_os(SYZYGY_PATH_ENV) {
+            if let Err(error) = tablebase.add_directory(&path) {
+                bevy::log::warn!("{}", error);
+            }
+        }
+        tablebase
+    }
+
+    pub fn add_directory<P: AsRef<Path>>(&mut self, path: P) -> Result<usize, TablebaseError> {
+        let path = path.as_ref();
+        if !path.is_dir() {
+            return Err(TablebaseError::MissingDirectory(path.to_path_buf()));
+        }
+        let added = self.add_tables(path)?;
+        self.directories.push(path.to_path_buf());
+        Ok(added)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.max_pieces == 0
+    }
+
+    pub fn probe(&self, board: &CheckerBoard) -> Result<TablebaseProbe, TablebaseError> {
+        if self.is_empty() {
+            return Err(TablebaseError::NotLoaded);
+        }
+        let pieces = board.get_pieces().count();
+        if pieces > self.max_pieces {
+            return Err(TablebaseError::TooManyPieces(pieces));
+        }
+        // Bare kings have no table of their own.
+        if pieces == 2 {
+            return Ok(TablebaseProbe {
+                wdl: Wdl::Draw,
+                dtz: Some(0),
+            });
+        }
+        let material = Self::material_key(board);
+        if !self.has_table(&material) {
+            return Err(TablebaseError::MissingTable(material));
+        }
+        self.probe_tables(board)
+    }
+
+    /// The move that keeps the best result for the side to move: the
+    /// quickest way on to a win, or the longest way to a loss. `None` when
+    /// the position is not in the tables.
+    pub fn best_move(&self, board: &CheckerBoard) -> Option<BoardMove> {
+        self.probe(board).ok()?;
+        board
+            .get_legal_moves()
+            .into_iter()
+            .filter_map(|board_move| {
+                let mut after = board.clone();
+                after.move_piece(board_move.from(), board_move.to());
+                // The probe after the move is seen from the opponent.
+                let probe = self.probe(&after).ok()?;
+                let result = -probe.wdl.rank();
+                let distance = probe.dtz.unwrap_or_default().abs();
+                let tiebreak = if result > 0 { -distance } else { distance };
+                Some((board_move, (result, tiebreak)))
+            })
+            .max_by_key(|(_, rank)| *rank)
+            .map(|(board_move, _)| board_move)
+    }
+
+    /// Syzygy table name for the material on the board, white first, e.g. `KQvK`.
+    pub fn material_key(board: &CheckerBoard) -> String {
+        let side = |color: PieceColor| {
+            let mut pieces: Vec<PieceType> = board
+                .get_pieces()
+                .filter(|(_, piece)| piece.color() == &color)
+                .map(|(_, piece)| piece.piece_type().clone())
+                .collect();
+            pieces.sort_by_key(Self::table_order);
+            pieces
+                .iter()
+                .map(|piece| piece.symbol())
+                .collect::<String>()
+        };
+        format!("{}v{}", side(PieceColor::White), side(PieceColor::Black))
+    }
+
+    fn table_order(piece_type: &PieceType) -> u8 {
+        match piece_type {
+            PieceType::King => 0,
+            PieceType::Queen => 1,
+            PieceType::Rook => 2,
+            PieceType::Bishop => 3,
+            PieceType::Knight => 4,
+            PieceType::Pawn => 5,
+        }
+    }
+
+    fn has_table(&self, material: &str) -> bool {
+        let mirrored = material.split('v').rev().collect::<Vec<_>>().join("v");
+        self.directories.iter().any(|directory| {
+            [material, &mirrored]
+                .iter()
+                .any(|name| directory.join(format!("{}.rtbw", name)).is_file())
+        })
+    }
+
+    #[cfg(feature = "tablebase")]
+    fn add_tables(&mut self, path: &Path) -> Result<usize, TablebaseError> {
+        let added = self
+            .tables
+            .add_directory(path)
+            .map_err(|error| TablebaseError::Load(error.to_string()))?;
+        self.max_pieces = self.tables.max_pieces();
+        Ok(added)
+    }
+
+    #[cfg(not(feature = "tablebase"))]
+    fn add_tables(&mut self, _path: &Path) -> Result<usize, TablebaseError> {
+        Err(TablebaseError::Disabled)
+    }
+
+    #[cfg(feature = "tablebase")]
+    fn probe_tables(&self, board: &CheckerBoard) -> Result<TablebaseProbe, TablebaseError> {
+        use shakmaty::fen::Fen;
+        use shakmaty::{CastlingMode, Chess};
+
+        let fen: Fen = board
+            .to_fen()
+            .parse()
+            .map_err(|error| TablebaseError::Probe(error.to_string()))?;
+        let position: Chess = fen
+            .into_position(CastlingMode::Standard)
+            .map_err(|error| TablebaseError::Probe(error.to_string()))?;
+        let wdl = self
+            .tables
+            .probe_wdl_after_zeroing(&position)
+            .map_err(|error| TablebaseError::Probe(error.to_string()))?;
+        let dtz = self
+            .tables
+            .probe_dtz(&position)
+            .ok()
+            .map(|dtz| dtz.ignore_rounding().0);
+        Ok(TablebaseProbe {
+            wdl: match wdl {
+                shakmaty_syzygy::Wdl::Loss => Wdl::Loss,
+                shakmaty_syzygy::Wdl::BlessedLoss => Wdl::BlessedLoss,
+                shakmaty_syzygy::Wdl::Draw => Wdl::Draw,
+                shakmaty_syzygy::Wdl::CursedWin => Wdl::CursedWin,
+                shakmaty_syzygy::Wdl::Win => Wdl::Win,
+            },
+            dtz,
+        })
+    }
+
+    #[cfg(not(feature = "tablebase"))]
+    fn probe_tables(&self, _board: &CheckerBoard) -> Result<TablebaseProbe, TablebaseError> {
+        Err(TablebaseError::Disabled)
+    }
+}
+
+impl Default for Tablebase {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tablebase_tests {
+    use crate::board::CheckerBoard;
+    use crate::board_piece::BoardPiece;
+    use crate::pieces::color::PieceColor;
+    use crate::pieces::piece_type::PieceType;
+    use crate::tablebase::{Tablebase, TablebaseError};
+
+    #[test]
+    fn material_key_lists_white_then_black() {
+        let board = CheckerBoard::with_pieces(vec![
+            BoardPiece::build(PieceType::King, PieceColor::White, "e1"),
+            BoardPiece::build(PieceType::Pawn, PieceColor::White, "e2"),
+            BoardPiece::build(PieceType::Rook, PieceColor::White, "a1"),
+            BoardPiece::build(PieceType::King, PieceColor::Black, "e8"),
+        ]);
+        assert_eq!(Tablebase::material_key(&board), "KRPvK");
+    }
+
+    #[test]
+    fn missing_directory_is_an_error() {
+        let mut tablebase = Tablebase::new();
+        let result = tablebase.add_directory("/this/path/does/not/exist");
+        assert!(matches!(result, Err(TablebaseError::MissingDirectory(_))));
+        assert!(tablebase.is_empty());
+    }
+
+    #[test]
+    fn empty_tablebase_does_not_probe() {
+        let tablebase = Tablebase::new();
+        let board = CheckerBoard::with_pieces(vec![
+            BoardPiece::build(PieceType::King, PieceColor::White, "e1"),
+            BoardPiece::build(PieceType::King, PieceColor::Black, "e8"),
+        ]);
+        assert!(matches!(
+            tablebase.probe(&board),
+            Err(TablebaseError::NotLoaded)
+        ));
+    }
+
+    #[test]
+    fn empty_tablebase_suggests_no_move() {
+        let tablebase = Tablebase::new();
+        let board = CheckerBoard::with_pieces(vec![
+            BoardPiece::build(PieceType::King, PieceColor::White, "e1"),
+            BoardPiece::build(PieceType::Queen, PieceColor::White, "d1"),
+            BoardPiece::build(PieceType::King, PieceColor::Black, "e8"),
+        ]);
+        assert_eq!(tablebase.best_move(&board), None);
+    }
+
+    #[test]
+    fn full_board_is_never_probed() {
+        let tablebase = Tablebase {
+            max_pieces: 5,
+            ..Tablebase::new()
+        };
+        let board = CheckerBoard::default();
+        assert!(matches!(
+            tablebase.probe(&board),
+            Err(TablebaseError::TooManyPieces(32))
+        ));
+    }
+}
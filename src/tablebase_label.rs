@@ -0,0 +1,58 @@
+use crate::board_ui_factory::BoardUiFactory;
+use crate::notation::San;
+use crate::screen_layout::PanelPlacement;
+use crate::tablebase::Tablebase;
+use bevy::prelude::{
+    default, Commands, Component, PositionType, Query, Res, Style, Text, TextBundle, TextStyle,
+    Val, With,
+};
+
+#[derive(Component)]
+pub struct TablebaseLabel;
+
+pub fn spawn_tablebase_label(mut commands: Commands) {
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font_size: 20.,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(32.),
+            left: Val::Px(8.),
+            ..default()
+        }),
+        TablebaseLabel,
+        PanelPlacement::new(8., 32.).narrow(8., 32.),
+    ));
+}
+
+pub fn update_tablebase_label(
+    board_ui_factory: Res<BoardUiFactory>,
+    tablebase: Res<Tablebase>,
+    mut query: Query<&mut Text, With<TablebaseLabel>>,
+) {
+    if tablebase.is_empty() {
+        return;
+    }
+    let board = &board_ui_factory.board;
+    let label = match tablebase.probe(board) {
+        Ok(probe) => {
+            let mut label = format!("{}: {}", Tablebase::material_key(board), probe.wdl);
+            if let Some(dtz) = probe.dtz {
+                label.push_str(&format!(" (DTZ {})", dtz));
+            }
+            if let Some(best_move) = tablebase.best_move(board) {
+                label.push_str(&format!(", best {}", San::from_move(board, &best_move)));
+            }
+            label
+        }
+        Err(_) => String::new(),
+    };
+    for mut text in query.iter_mut() {
+        text.sections[0].value = label.clone();
+    }
+}
@@ -0,0 +1,463 @@
+use crate::board::CheckerBoard;
+use crate::board_move::BoardMove;
+use crate::board_position::BoardPosition;
+use crate::engine::Engine;
+use crate::game_result::GameResult;
+use crate::notation::San;
+use crate::pieces::color::PieceColor;
+use crate::pieces::piece_type::PieceType;
+use crate::protocol::parse_uci;
+use std::io::{BufRead, Write};
+
+/// The strongest level of the computer, as in the windowed game.
+const MAX_LEVEL: u8 = 4;
+
+const RESET: &str = "\x1b[0m";
+const LIGHT_SQUARE: &str = "\x1b[48;5;180m";
+const DARK_SQUARE: &str = "\x1b[48;5;94m";
+/// The last move's squares stand out the way they do in the windowed game.
+const LIGHT_HIGHLIGHT: &str = "\x1b[48;5;186m";
+const DARK_HIGHLIGHT: &str = "\x1b[48;5;136m";
+const WHITE_PIECE: &str = "\x1b[1;97m";
+const BLACK_PIECE: &str = "\x1b[1;30m";
+
+const HELP: &str = "Moves are typed in SAN (Nf3, exd5, O-O) or UCI (g1f3). Pawns promote to queens.
+Commands:
+  undo             take back the last move, or the last two against the computer
+  flip             turn the board around
+  ai white|black   let the computer play that side
+  ai off           play both sides
+  level <1-4>      how deep the computer searches
+  new [fen]        start again, from the standard position or a FEN
+  moves            list the moves so far
+  fen              show the position as FEN
+  help             show this
+  quit             leave";
+
+fn color_name(color: &PieceColor) -> &'static str {
+    match color {
+        PieceColor::White => "White",
+        PieceColor::Black => "Black",
+    }
+}
+
+/// The outlined glyphs for white and the filled ones for black, which tell
+/// the sides apart even without colours.
+fn glyph(piece_type: &PieceType, color: &PieceColor) -> char {
+    let glyphs = match color {
+        PieceColor::White => ['♔', '♕', '♖', '♗', '♘', '♙'],
+        PieceColor::Black => ['♚', '♛', '♜', '♝', '♞', '♟'],
+    };
+    match piece_type {
+        PieceType::King => glyphs[0],
+        PieceType::Queen => glyphs[1],
+        PieceType::Rook => glyphs[2],
+        PieceType::Bishop => glyphs[3],
+        PieceType::Knight => glyphs[4],
+        PieceType::Pawn => glyphs[5],
+    }
+}
+
+/// A game played at the terminal, against the computer or between two
+/// people taking turns at the keyboard.
+pub struct TerminalGame {
+    /// Where the game started, which every move is replayed from after an
+    /// undo, as the board cannot take moves back.
+    start: CheckerBoard,
+    board: CheckerBoard,
+    moves: Vec<BoardMove>,
+    flipped: bool,
+    computer: Option<PieceColor>,
+    level: u8,
+    /// Whether to draw with ANSI colours.
+    colors: bool,
+}
+
+/// What the game has to say after a line of input.
+#[derive(Debug, PartialEq)]
+pub enum Reply {
+    Text(String),
+    /// The board changed and is drawn again, after the text if any.
+    Board(String),
+    Quit,
+}
+
+impl TerminalGame {
+    pub fn new(colors: bool) -> Self {
+        Self {
+            start: CheckerBoard::default(),
+            board: CheckerBoard::default(),
+            moves: vec![],
+            flipped: false,
+            computer: Some(PieceColor::Black),
+            level: 2,
+            colors,
+        }
+    }
+
+    pub fn result(&self) -> GameResult {
+        GameResult::from_board(&self.board)
+    }
+
+    /// The board with ranks and files around it, and the last move
+    /// highlighted.
+    pub fn render(&self) -> String {
+        let mut ranks: Vec<u8> = (0..8).rev().collect();
+        let mut files: Vec<u8> = (0..8).collect();
+        if self.flipped {
+            ranks.reverse();
+            files.reverse();
+        }
+        let last_move = self.moves.last();
+        let file_letters: String = files
+            .iter()
+            .map(|x| format!(" {} ", (b'a' + x) as char))
+            .collect();
+        let mut text = String::new();
+        for y in ranks.iter() {
+            text.push_str(&format!("{} ", y + 1));
+            for x in files.iter() {
+                let pos = BoardPosition::new(*x, *y);
+                let piece = self.board.piece_at(&pos);
+                if !self.colors {
+                    let symbol =
+                        piece.map_or('·', |piece| glyph(piece.piece_type(), piece.color()));
+                    text.push_str(&format!(" {} ", symbol));
+                    continue;
+                }
+                let light = (x + y) % 2 == 1;
+                let highlighted =
+                    last_move.is_some_and(|last| last.from() == &pos || last.to() == &pos);
+                let background = match (light, highlighted) {
+                    (true, false) => LIGHT_SQUARE,
+                    (false, false) => DARK_SQUARE,
+                    (true, true) => LIGHT_HIGHLIGHT,
+                    (false, true) => DARK_HIGHLIGHT,
+                };
+                // Filled glyphs in the side's colour read best on the squares.
+                let cell = match piece {
+                    Some(piece) => {
+                        let foreground = match piece.color() {
+                            PieceColor::White => WHITE_PIECE,
+                            PieceColor::Black => BLACK_PIECE,
+                        };
+                        let filled = glyph(piece.piece_type(), &PieceColor::Black);
+                        format!("{}{} {} ", background, foreground, filled)
+                    }
+                    None => format!("{}   ", background),
+                };
+                text.push_str(&cell);
+                text.push_str(RESET);
+            }
+            text.push('\n');
+        }
+        text.push_str(&format!("  {}\n", file_letters));
+        text
+    }
+
+    /// What happens next: who is to move, or how the game ended.
+    pub fn status(&self) -> String {
+        let turn = self.board.active_turn();
+        let last = self.moves.len().checked_sub(1).map(|last| {
+            let before = self.position_before(last);
+            let number = last / 2 + 1;
+            let dots = if last % 2 == 0 { "." } else { "..." };
+            format!(
+                "{}{} {}, ",
+                number,
+                dots,
+                San::from_move(&before, &self.moves[last])
+            )
+        });
+        let last = last.unwrap_or_default();
+        match self.result() {
+            GameResult::Ongoing if self.board.is_checked(turn) => {
+                format!("{}{} to move, in check", last, color_name(turn))
+            }
+            GameResult::Ongoing => format!("{}{} to move", last, color_name(turn)),
+            GameResult::Draw => format!("{}stalemate, 1/2-1/2", last),
+            GameResult::WhiteWins => format!("{}checkmate, 1-0 White wins", last),
+            GameResult::BlackWins => format!("{}checkmate, 0-1 Black wins", last),
+        }
+    }
+
+    /// The position before move `ply` was played.
+    fn position_before(&self, ply: usize) -> CheckerBoard {
+        let mut board = self.start.clone();
+        for board_move in self.moves[..ply].iter() {
+            board.move_piece(board_move.from(), board_move.to());
+        }
+        board
+    }
+
+    /// A move typed in SAN, or in UCI with the promotion piece left out or
+    /// given as a queen.
+    fn parse_move(&self, text: &str) -> Option<BoardMove> {
+        San::parse(&self.board, text).or_else(|| {
+            let (from, to) = parse_uci(text)?;
+            self.board
+                .get_legal_moves()
+                .into_iter()
+                .find(|board_move| board_move.from() == &from && board_move.to() == &to)
+        })
+    }
+
+    fn play(&mut self, board_move: BoardMove) {
+        self.board.move_piece(board_move.from(), board_move.to());
+        self.moves.push(board_move);
+    }
+
+    /// Whether the computer is to make the next move.
+    pub fn is_computer_turn(&self) -> bool {
+        self.result() == GameResult::Ongoing
+            && self.computer.as_ref() == Some(self.board.active_turn())
+    }
+
+    /// Lets the computer move, and tells which move it chose.
+    pub fn play_computer(&mut self) -> Option<String> {
+        if !self.is_computer_turn() {
+            return None;
+        }
+        let board_move = Engine::new(self.level).best_move(&self.board)?;
+        let san = San::from_move(&self.board, &board_move);
+        self.play(board_move);
+        Some(format!("Rusty Chess plays {}", san))
+    }
+
+    fn undo(&mut self) -> Reply {
+        let plies = match &self.computer {
+            // Back to the last position where the person was to move.
+            Some(computer) if self.moves.len() >= 2 => {
+                if self.board.active_turn() == computer {
+                    1
+                } else {
+                    2
+                }
+            }
+            _ => 1,
+        };
+        if self.moves.is_empty() {
+            return Reply::Text("There is no move to take back".to_string());
+        }
+        let kept = self.moves.len().saturating_sub(plies);
+        self.board = self.position_before(kept);
+        self.moves.truncate(kept);
+        Reply::Board(String::new())
+    }
+
+    /// Handles a line typed at the prompt: a move or a command.
+    pub fn handle(&mut self, line: &str) -> Reply {
+        let line = line.trim();
+        let mut words = line.split_whitespace();
+        let command = words.next().unwrap_or_default();
+        let argument = words.collect::<Vec<&str>>().join(" ");
+        match (command, argument.as_str()) {
+            ("", _) => Reply::Text(String::new()),
+            ("quit" | "exit", _) => Reply::Quit,
+            ("help" | "?", _) => Reply::Text(HELP.to_string()),
+            ("flip", _) => {
+                self.flipped = !self.flipped;
+                Reply::Board(String::new())
+            }
+            ("undo", _) => self.undo(),
+            ("ai", "white" | "black" | "off") => {
+                self.computer = match argument.as_str() {
+                    "white" => Some(PieceColor::White),
+                    "black" => Some(PieceColor::Black),
+                    _ => None,
+                };
+                let text = match &self.computer {
+                    Some(color) => format!("The computer plays {}", color_name(color)),
+                    None => "Both sides are played at the keyboard".to_string(),
+                };
+                Reply::Text(text)
+            }
+            ("level", level) => match level.parse::<u8>() {
+                Ok(level) if (1..=MAX_LEVEL).contains(&level) => {
+                    self.level = level;
+                    Reply::Text(format!("Computer level {}", level))
+                }
+                _ => Reply::Text(format!("Levels go from 1 to {}", MAX_LEVEL)),
+            },
+            ("new", fen) => {
+                let start = if fen.is_empty() {
+                    CheckerBoard::default()
+                } else {
+                    match CheckerBoard::from_fen(fen) {
+                        Ok(board) => board,
+                        Err(error) => return Reply::Text(error.to_string()),
+                    }
+                };
+                self.start = start.clone();
+                self.board = start;
+                self.moves.clear();
+                Reply::Board("New game".to_string())
+            }
+            ("moves", _) => {
+                let sans = San::from_line(&self.start, &self.moves);
+                let text = sans
+                    .chunks(2)
+                    .enumerate()
+                    .map(|(index, pair)| format!("{}. {}", index + 1, pair.join(" ")))
+                    .collect::<Vec<String>>()
+                    .join(" ");
+                Reply::Text(if text.is_empty() {
+                    "No moves yet".to_string()
+                } else {
+                    text
+                })
+            }
+            ("fen", _) => Reply::Text(self.board.to_fen()),
+            _ if self.result() != GameResult::Ongoing => {
+                Reply::Text("The game is over: undo, or start a new one".to_string())
+            }
+            _ if self.is_computer_turn() => Reply::Text("It is the computer's move".to_string()),
+            _ => match self.parse_move(line) {
+                Some(board_move) => {
+                    self.play(board_move);
+                    Reply::Board(String::new())
+                }
+                None => Reply::Text(format!("{} is not a legal move here, try help", line)),
+            },
+        }
+    }
+}
+
+/// Plays at the terminal until the input ends or the player quits, drawing
+/// the board after every move.
+pub fn run(input: impl BufRead, mut output: impl Write, colors: bool) -> std::io::Result<()> {
+    let mut game = TerminalGame::new(colors);
+    writeln!(output, "Rusty Chess. Type help for the commands.\n")?;
+    let mut lines = input.lines();
+    let mut redraw = true;
+    loop {
+        if let Some(text) = game.play_computer() {
+            writeln!(output, "{}", text)?;
+            redraw = true;
+        }
+        if redraw {
+            write!(output, "\n{}", game.render())?;
+            redraw = false;
+        }
+        write!(output, "{}> ", game.status())?;
+        output.flush()?;
+        let Some(line) = lines.next() else {
+            writeln!(output)?;
+            return Ok(());
+        };
+        match game.handle(&line?) {
+            Reply::Quit => return Ok(()),
+            Reply::Text(text) => {
+                if !text.is_empty() {
+                    writeln!(output, "{}", text)?;
+                }
+            }
+            Reply::Board(text) => {
+                if !text.is_empty() {
+                    writeln!(output, "{}", text)?;
+                }
+                redraw = true;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod terminal_tests {
+    use crate::game_result::GameResult;
+    use crate::terminal::{run, Reply, TerminalGame};
+
+    fn two_players() -> TerminalGame {
+        let mut game = TerminalGame::new(false);
+        game.handle("ai off");
+        game
+    }
+
+    #[test]
+    fn the_board_is_drawn_with_unicode_pieces() {
+        let mut game = two_players();
+        let board = game.render();
+        let lines: Vec<&str> = board.lines().collect();
+        assert_eq!(lines[0], "8  ♜  ♞  ♝  ♛  ♚  ♝  ♞  ♜ ");
+        assert_eq!(lines[4], "4  ·  ·  ·  ·  ·  ·  ·  · ");
+        assert_eq!(lines[8], "   a  b  c  d  e  f  g  h ");
+        game.handle("flip");
+        assert!(game.render().starts_with("1  ♖  ♘  ♗  ♔  ♕"));
+        let colored = TerminalGame::new(true).render();
+        assert!(colored.contains("\x1b[48;5;180m"));
+        assert!(colored.contains("\x1b[0m"));
+    }
+
+    #[test]
+    fn moves_are_typed_in_san_or_uci() {
+        let mut game = two_players();
+        assert_eq!(game.handle("e4"), Reply::Board(String::new()));
+        assert_eq!(game.handle("e7e5"), Reply::Board(String::new()));
+        assert_eq!(game.status(), "1... e5, White to move");
+        assert!(matches!(game.handle("Ke3"), Reply::Text(_)));
+        game.handle("Qh5");
+        game.handle("Nc6");
+        game.handle("Bc4");
+        game.handle("Nf6");
+        game.handle("Qxf7");
+        assert_eq!(game.result(), GameResult::WhiteWins);
+        assert_eq!(game.status(), "4. Qxf7#, checkmate, 1-0 White wins");
+        assert_eq!(
+            game.handle("moves"),
+            Reply::Text("1. e4 e5 2. Qh5 Nc6 3. Bc4 Nf6 4. Qxf7#".to_string())
+        );
+        assert!(
+            matches!(game.handle("a3"), Reply::Text(text) if text.starts_with("The game is over"))
+        );
+    }
+
+    #[test]
+    fn a_stuck_side_that_is_not_to_move_plays_on() {
+        let mut game = two_players();
+        game.handle("new 2k5/p7/8/P7/8/7p/4n2P/7K w - - 0 1");
+        game.handle("a6");
+        assert_eq!(game.result(), GameResult::Ongoing);
+        assert_eq!(game.status(), "1. a6, Black to move");
+        assert_eq!(game.handle("Kb8"), Reply::Board(String::new()));
+        assert_eq!(game.status(), "1... Kb8, stalemate, 1/2-1/2");
+    }
+
+    #[test]
+    fn undo_takes_back_the_computers_reply_too() {
+        let mut game = TerminalGame::new(false);
+        game.handle("level 1");
+        game.handle("e4");
+        assert!(game
+            .play_computer()
+            .unwrap()
+            .starts_with("Rusty Chess plays"));
+        assert_eq!(game.handle("undo"), Reply::Board(String::new()));
+        assert_eq!(
+            game.handle("moves"),
+            Reply::Text("No moves yet".to_string())
+        );
+        let mut game = two_players();
+        game.handle("d4");
+        game.handle("undo");
+        assert!(matches!(game.handle("undo"), Reply::Text(_)));
+    }
+
+    #[test]
+    fn games_start_from_a_fen() {
+        let mut game = two_players();
+        let fen = "7k/8/8/8/8/8/8/R6K w - - 0 1";
+        game.handle(&format!("new {}", fen));
+        assert_eq!(game.handle("fen"), Reply::Text(fen.to_string()));
+        assert!(matches!(game.handle("new nonsense"), Reply::Text(_)));
+        assert!(matches!(game.handle("level 9"), Reply::Text(text) if text.contains("1 to 4")));
+    }
+
+    #[test]
+    fn the_session_reads_lines_until_quit() {
+        let mut output = vec![];
+        run("ai off\ne4\nquit\nd4\n".as_bytes(), &mut output, false).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.contains("1. e4, Black to move> "));
+        assert!(!output.contains("2. d4"));
+    }
+}
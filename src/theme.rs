@@ -0,0 +1,446 @@
+use crate::board_preview::BoardPreview;
+use crate::board_ui_factory::{BoardUiFactory, PieceSprites};
+use crate::pieces::piece_type::PieceType;
+use crate::{BoardPieceComponent, BoardPosComponent};
+use bevy::asset::AssetServer;
+use bevy::log::warn;
+use bevy::prelude::{
+    Assets, Commands, Component, DetectChangesMut, Handle, Image, Query, Res, ResMut, Resource,
+    Sprite, TextureAtlas, TextureAtlasLayout, UVec2, Vec2, With, Without,
+};
+use thiserror::Error;
+
+/// Where the manifest is read from at startup, so themes can be added
+/// without rebuilding. The copy built into the binary is used otherwise.
+pub const THEMES_PATH: &str = "assets/themes.cfg";
+const BUILTIN_THEMES: &str = include_str!("../assets/themes.cfg");
+
+/// How much of a square a piece covers when the theme does not say.
+const PIECE_SCALE: f32 = 0.8;
+
+/// The pieces in the order of `BoardUiFactory::sprite_index`, black first.
+const PIECE_TYPES: [PieceType; 6] = [
+    PieceType::Pawn,
+    PieceType::Rook,
+    PieceType::Bishop,
+    PieceType::Knight,
+    PieceType::King,
+    PieceType::Queen,
+];
+const PIECE_COUNT: usize = 12;
+
+#[derive(Error, Debug)]
+pub enum ThemeError {
+    #[error("Invalid theme manifest: {0}")]
+    Invalid(String),
+}
+
+/// The FEN letter of the piece with sprite index `piece`.
+fn fen_letter(piece: usize) -> char {
+    let symbol = PIECE_TYPES[piece % 6].symbol();
+    if piece < 6 {
+        symbol.to_ascii_lowercase()
+    } else {
+        symbol
+    }
+}
+
+/// The usual file name of the piece with sprite index `piece`, as in `wK`.
+fn file_name(piece: usize) -> String {
+    let color = if piece < 6 { 'b' } else { 'w' };
+    format!("{}{}", color, PIECE_TYPES[piece % 6].symbol())
+}
+
+fn parse_size(value: &str) -> Result<Vec2, ThemeError> {
+    let invalid = || ThemeError::Invalid(format!("bad size {}", value));
+    let (width, height) = value.split_once('x').ok_or_else(invalid)?;
+    let width = width.trim().parse::<f32>().map_err(|_| invalid())?;
+    let height = height.trim().parse::<f32>().map_err(|_| invalid())?;
+    if width <= 0. || height <= 0. {
+        return Err(invalid());
+    }
+    Ok(Vec2::new(width, height))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PieceLayout {
+    /// A single image cut into tiles, the pieces given by their FEN letters
+    /// row by row.
+    Atlas { tile: UVec2, rows: Vec<String> },
+    /// An image per piece, all of the same size.
+    Files { size: UVec2 },
+}
+
+impl PieceLayout {
+    fn parse(value: &str) -> Result<Self, ThemeError> {
+        let mut words = value.split_whitespace();
+        let kind = words.next().unwrap_or_default();
+        let size = parse_size(words.next().unwrap_or_default())?.as_uvec2();
+        let layout = match kind {
+            "atlas" => {
+                let rows: Vec<String> = words
+                    .next()
+                    .unwrap_or_default()
+                    .split('/')
+                    .map(str::to_string)
+                    .collect();
+                for piece in 0..PIECE_COUNT {
+                    if !rows.iter().any(|row| row.contains(fen_letter(piece))) {
+                        return Err(ThemeError::Invalid(format!(
+                            "no {} in the atlas",
+                            fen_letter(piece)
+                        )));
+                    }
+                }
+                PieceLayout::Atlas { tile: size, rows }
+            }
+            "files" => PieceLayout::Files { size },
+            _ => return Err(ThemeError::Invalid(format!("unknown layout {}", value))),
+        };
+        Ok(layout)
+    }
+
+    fn columns(&self) -> usize {
+        match self {
+            PieceLayout::Atlas { rows, .. } => rows.iter().map(|row| row.len()).max().unwrap_or(1),
+            PieceLayout::Files { .. } => 1,
+        }
+    }
+
+    /// The tile of the piece with sprite index `piece`.
+    fn atlas_index(&self, piece: usize) -> usize {
+        let PieceLayout::Atlas { rows, .. } = self else {
+            return 0;
+        };
+        let columns = self.columns();
+        rows.iter()
+            .enumerate()
+            .find_map(|(y, row)| row.find(fen_letter(piece)).map(|x| y * columns + x))
+            .unwrap_or(0)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Theme {
+    name: String,
+    board: String,
+    square: Vec2,
+    pieces: String,
+    layout: PieceLayout,
+    piece_size: Option<Vec2>,
+}
+
+impl Theme {
+    fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            board: String::new(),
+            square: Vec2::ZERO,
+            pieces: String::new(),
+            layout: PieceLayout::Files { size: UVec2::ZERO },
+            piece_size: None,
+        }
+    }
+
+    fn set(&mut self, key: &str, value: &str) -> Result<(), ThemeError> {
+        match key {
+            "board" => self.board = value.to_string(),
+            "square" => self.square = parse_size(value)?,
+            "pieces" => self.pieces = value.to_string(),
+            "layout" => self.layout = PieceLayout::parse(value)?,
+            "piece_size" => self.piece_size = Some(parse_size(value)?),
+            _ => {
+                return Err(ThemeError::Invalid(format!(
+                    "unknown key {} in {}",
+                    key, self.name
+                )))
+            }
+        }
+        Ok(())
+    }
+
+    fn check(&self) -> Result<(), ThemeError> {
+        let missing = if self.board.is_empty() {
+            "board"
+        } else if self.square == Vec2::ZERO {
+            "square"
+        } else if self.pieces.is_empty() {
+            "pieces"
+        } else if self.layout == (PieceLayout::Files { size: UVec2::ZERO }) {
+            "layout"
+        } else {
+            return Ok(());
+        };
+        Err(ThemeError::Invalid(format!(
+            "{} has no {}",
+            self.name, missing
+        )))
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn board(&self) -> &str {
+        &self.board
+    }
+
+    /// Size of a single square.
+    pub fn square(&self) -> Vec2 {
+        self.square
+    }
+
+    pub fn piece_size(&self) -> Vec2 {
+        self.piece_size
+            .unwrap_or(Vec2::splat(self.square.min_element() * PIECE_SCALE))
+    }
+
+    /// The image holding the piece with sprite index `piece`.
+    fn piece_path(&self, piece: usize) -> String {
+        match self.layout {
+            PieceLayout::Atlas { .. } => self.pieces.clone(),
+            PieceLayout::Files { .. } => self.pieces.replace("{piece}", &file_name(piece)),
+        }
+    }
+
+    pub fn piece_sprites(
+        &self,
+        asset_server: &AssetServer,
+        layouts: &mut Assets<TextureAtlasLayout>,
+    ) -> PieceSprites {
+        let layout = match &self.layout {
+            PieceLayout::Atlas { tile, rows } => TextureAtlasLayout::from_grid(
+                *tile,
+                self.layout.columns() as u32,
+                rows.len() as u32,
+                None,
+                None,
+            ),
+            PieceLayout::Files { size } => TextureAtlasLayout::from_grid(*size, 1, 1, None, None),
+        };
+        let textures = (0..PIECE_COUNT)
+            .map(|piece| asset_server.load(self.piece_path(piece)))
+            .collect();
+        let indices = (0..PIECE_COUNT)
+            .map(|piece| self.layout.atlas_index(piece))
+            .collect();
+        PieceSprites::new(textures, layouts.add(layout), indices, self.piece_size())
+    }
+}
+
+/// The themes listed in the manifest and the one in use.
+#[derive(Resource, Debug, Clone)]
+pub struct Themes {
+    themes: Vec<Theme>,
+    current: usize,
+}
+
+impl Themes {
+    pub fn parse(text: &str) -> Result<Self, ThemeError> {
+        let mut themes: Vec<Theme> = vec![];
+        for line in text.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                themes.push(Theme::new(name.trim()));
+                continue;
+            }
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| ThemeError::Invalid(line.to_string()))?;
+            themes
+                .last_mut()
+                .ok_or_else(|| ThemeError::Invalid(format!("{} outside a theme", line)))?
+                .set(key.trim(), value.trim())?;
+        }
+        for theme in themes.iter() {
+            theme.check()?;
+        }
+        if themes.is_empty() {
+            return Err(ThemeError::Invalid("no themes".to_string()));
+        }
+        Ok(Self { themes, current: 0 })
+    }
+
+    /// Reads the manifest next to the assets, falling back to the one built in.
+    pub fn load() -> Self {
+        match std::fs::read_to_string(THEMES_PATH).map(|text| Self::parse(&text)) {
+            Ok(Ok(themes)) => themes,
+            Ok(Err(error)) => {
+                warn!("{}", error);
+                Self::default()
+            }
+            Err(_) => Self::default(),
+        }
+    }
+
+    pub fn current(&self) -> &Theme {
+        &self.themes[self.current]
+    }
+
+    /// Switches to the theme called `name`, if there is one.
+    pub fn select(&mut self, name: &str) {
+        if let Some(index) = self.themes.iter().position(|theme| theme.name == name) {
+            self.current = index;
+        }
+    }
+
+    pub fn next(&mut self) {
+        self.current = (self.current + 1) % self.themes.len();
+    }
+}
+
+impl Default for Themes {
+    fn default() -> Self {
+        Self::parse(BUILTIN_THEMES).expect("the built in themes are valid")
+    }
+}
+
+/// The sprite showing the board texture under the squares.
+#[derive(Component)]
+pub struct BoardSprite;
+
+/// Swaps textures and square size over to the current theme.
+pub fn apply_theme(
+    mut commands: Commands,
+    themes: Res<Themes>,
+    asset_server: Res<AssetServer>,
+    mut layouts: ResMut<Assets<TextureAtlasLayout>>,
+    mut board_ui_factory: ResMut<BoardUiFactory>,
+    mut preview: ResMut<BoardPreview>,
+    mut board_query: Query<&mut Handle<Image>, With<BoardSprite>>,
+    mut square_query: Query<&mut Sprite, (With<BoardPosComponent>, Without<BoardPieceComponent>)>,
+    mut piece_query: Query<
+        (
+            &BoardPieceComponent,
+            &mut Sprite,
+            &mut Handle<Image>,
+            &mut TextureAtlas,
+        ),
+        Without<BoardSprite>,
+    >,
+) {
+    let theme = themes.current();
+    for mut texture in board_query.iter_mut() {
+        *texture = asset_server.load(theme.board().to_string());
+    }
+    if board_ui_factory.pos_size() != theme.square() {
+        board_ui_factory.set_pos_size(theme.square());
+    }
+    for mut sprite in square_query.iter_mut() {
+        sprite.custom_size = Some(theme.square());
+    }
+    let piece_sprites = theme.piece_sprites(&asset_server, &mut layouts);
+    for (piece, mut sprite, mut texture, mut atlas) in piece_query.iter_mut() {
+        let Some(index) = board_ui_factory.get_sprite_index(&piece.0) else {
+            continue;
+        };
+        *sprite = piece_sprites.sprite();
+        *texture = piece_sprites.texture(index);
+        *atlas = piece_sprites.atlas(index);
+    }
+    commands.insert_resource(piece_sprites);
+    // Redraw the previewed position with the new pieces.
+    preview.set_changed();
+}
+
+#[cfg(test)]
+mod theme_tests {
+    use crate::board_ui_factory::BoardUiFactory;
+    use crate::pieces::color::PieceColor;
+    use crate::pieces::piece_type::PieceType;
+    use crate::theme::{fen_letter, file_name, PieceLayout, Themes};
+    use bevy::prelude::{UVec2, Vec2};
+
+    const MANIFEST: &str = "
+        # comment
+        [Atlas]
+        board=board.png
+        square=60x60
+        pieces=pieces.png
+        layout=atlas 54x54 PNBRQK/pnbrqk
+
+        [Files]
+        board=wood.png
+        square=80x70
+        pieces=sets/cburnett/{piece}.svg
+        layout=files 45x45
+        piece_size=64x64
+    ";
+
+    #[test]
+    fn sprite_indices_match_the_board() {
+        for (piece, color, piece_type) in [
+            (0, PieceColor::Black, PieceType::Pawn),
+            (4, PieceColor::Black, PieceType::King),
+            (9, PieceColor::White, PieceType::Knight),
+            (11, PieceColor::White, PieceType::Queen),
+        ] {
+            assert_eq!(BoardUiFactory::sprite_index(&color, &piece_type), piece);
+        }
+        assert_eq!(fen_letter(4), 'k');
+        assert_eq!(fen_letter(9), 'N');
+        assert_eq!(file_name(0), "bP");
+        assert_eq!(file_name(10), "wK");
+    }
+
+    #[test]
+    fn it_reads_the_manifest() {
+        let mut themes = Themes::parse(MANIFEST).unwrap();
+        let atlas = themes.current();
+        assert_eq!(atlas.name(), "Atlas");
+        assert_eq!(atlas.square(), Vec2::new(60., 60.));
+        assert_eq!(atlas.piece_size(), Vec2::new(48., 48.));
+        themes.next();
+        let files = themes.current();
+        assert_eq!(files.board(), "wood.png");
+        assert_eq!(files.piece_size(), Vec2::new(64., 64.));
+        assert_eq!(files.piece_path(10), "sets/cburnett/wK.svg");
+        themes.next();
+        assert_eq!(themes.current().name(), "Atlas");
+    }
+
+    #[test]
+    fn atlas_tiles_follow_the_fen_letters() {
+        let layout = PieceLayout::parse("atlas 54x54 PNBRQK/pnbrqk").unwrap();
+        assert_eq!(layout.atlas_index(6), 0);
+        assert_eq!(layout.atlas_index(10), 5);
+        assert_eq!(layout.atlas_index(0), 6);
+        assert_eq!(layout.atlas_index(1), 9);
+        let files = PieceLayout::parse("files 45x45").unwrap();
+        assert_eq!(
+            files,
+            PieceLayout::Files {
+                size: UVec2::new(45, 45)
+            }
+        );
+        assert_eq!(files.atlas_index(3), 0);
+    }
+
+    #[test]
+    fn themes_can_be_selected_by_name() {
+        let mut themes = Themes::parse(MANIFEST).unwrap();
+        themes.select("Files");
+        assert_eq!(themes.current().name(), "Files");
+        themes.select("Missing");
+        assert_eq!(themes.current().name(), "Files");
+    }
+
+    #[test]
+    fn broken_manifests_are_rejected() {
+        assert!(Themes::parse("").is_err());
+        assert!(Themes::parse("board=board.png").is_err());
+        assert!(Themes::parse("[A]\nboard=b.png\nsquare=60\n").is_err());
+        assert!(Themes::parse("[A]\nboard=b.png\nsquare=60x60\npieces=p.png\n").is_err());
+        assert!(Themes::parse("[A]\nlayout=atlas 54x54 PNBRQ/pnbrqk\n").is_err());
+    }
+
+    #[test]
+    fn the_built_in_themes_are_valid() {
+        let themes = Themes::default();
+        assert_eq!(themes.current().name(), "Classic");
+        assert_eq!(themes.current().square(), Vec2::new(68.5, 72.));
+    }
+}
@@ -0,0 +1,927 @@
+use crate::game_result::GameResult;
+use crate::pieces::color::PieceColor;
+use std::fmt::Display;
+use std::str::FromStr;
+use thiserror::Error;
+
+/// Where the club's running tournament is kept, in the working directory.
+pub const TOURNAMENT_PATH: &str = "tournament.txt";
+
+#[derive(Error, Debug, PartialEq)]
+pub enum TournamentError {
+    #[error("Invalid tournament: {0}")]
+    Invalid(String),
+    #[error("{0}")]
+    NotAllowed(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Kind {
+    /// Everyone plays everyone, in the order of the Berger tables.
+    RoundRobin,
+    /// Players with the same score meet, paired by the Dutch system.
+    Swiss,
+}
+
+impl FromStr for Kind {
+    type Err = TournamentError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        match text {
+            "round-robin" => Ok(Kind::RoundRobin),
+            "swiss" => Ok(Kind::Swiss),
+            _ => Err(TournamentError::Invalid(format!("Unknown kind {}", text))),
+        }
+    }
+}
+
+impl Display for Kind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Kind::RoundRobin => write!(f, "round-robin"),
+            Kind::Swiss => write!(f, "swiss"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Player {
+    pub name: String,
+    pub rating: u32,
+}
+
+/// A game of a round, between players given by their place in the list of
+/// players. `GameResult::Ongoing` stands for a game not played yet.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Game {
+    pub white: usize,
+    pub black: usize,
+    pub result: GameResult,
+}
+
+impl Game {
+    fn new(white: usize, black: usize) -> Self {
+        Self {
+            white,
+            black,
+            result: GameResult::Ongoing,
+        }
+    }
+
+    /// The opponent of `player` and what `player` scored, once the game
+    /// is over.
+    fn outcome(&self, player: usize) -> Option<(usize, f32)> {
+        let (opponent, won) = if self.white == player {
+            (self.black, GameResult::WhiteWins)
+        } else if self.black == player {
+            (self.white, GameResult::BlackWins)
+        } else {
+            return None;
+        };
+        let points = match &self.result {
+            GameResult::Ongoing => return None,
+            GameResult::Draw => 0.5,
+            result if result == &won => 1.,
+            _ => 0.,
+        };
+        Some((opponent, points))
+    }
+
+    fn colour_of(&self, player: usize) -> Option<PieceColor> {
+        if self.white == player {
+            Some(PieceColor::White)
+        } else if self.black == player {
+            Some(PieceColor::Black)
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Round {
+    /// The games by board, the first board first.
+    pub games: Vec<Game>,
+    /// The player left without an opponent, if any.
+    pub bye: Option<usize>,
+}
+
+impl Round {
+    fn is_finished(&self) -> bool {
+        self.games
+            .iter()
+            .all(|game| game.result != GameResult::Ongoing)
+    }
+}
+
+/// A player's score and tiebreaks, for the standings.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Standing {
+    pub player: usize,
+    pub points: f32,
+    /// The sum of the opponents' scores.
+    pub buchholz: f32,
+    /// The sum of the scores of the opponents beaten and half those of the
+    /// opponents drawn with.
+    pub sonneborn_berger: f32,
+}
+
+/// How strongly a player should get a colour next, weakest first.
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+enum Strength {
+    Mild,
+    Strong,
+    Absolute,
+}
+
+fn opposite(colour: &PieceColor) -> PieceColor {
+    match colour {
+        PieceColor::White => PieceColor::Black,
+        PieceColor::Black => PieceColor::White,
+    }
+}
+
+/// A score with halves written as ½, as on a crosstable.
+pub fn format_points(points: f32) -> String {
+    let whole = points.trunc() as u32;
+    match (whole, points.fract() > 0.) {
+        (0, true) => "½".to_string(),
+        (whole, true) => format!("{}½", whole),
+        (whole, false) => whole.to_string(),
+    }
+}
+
+/// A result as written in PGN, `*` for a game still to be played.
+pub fn parse_result(text: &str) -> Option<GameResult> {
+    match text {
+        "1-0" => Some(GameResult::WhiteWins),
+        "0-1" => Some(GameResult::BlackWins),
+        "1/2-1/2" => Some(GameResult::Draw),
+        "*" => Some(GameResult::Ongoing),
+        _ => None,
+    }
+}
+
+/// A club event: the players, the rounds paired so far and their results.
+/// It is kept as `key=value` lines, with a `round` line before the games of
+/// each round.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tournament {
+    pub name: String,
+    pub kind: Kind,
+    /// The number of rounds of a Swiss event. Round robins take as many as
+    /// it needs for everyone to meet.
+    pub rounds: usize,
+    pub players: Vec<Player>,
+    pub played: Vec<Round>,
+}
+
+impl Tournament {
+    pub fn new(name: &str, kind: Kind, rounds: usize) -> Self {
+        Self {
+            name: name.to_string(),
+            kind,
+            rounds,
+            players: vec![],
+            played: vec![],
+        }
+    }
+
+    pub fn load(path: &str) -> Result<Self, TournamentError> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|error| TournamentError::Invalid(format!("{}: {}", path, error)))?;
+        text.parse()
+    }
+
+    pub fn save(&self, path: &str) -> std::io::Result<()> {
+        std::fs::write(path, self.to_string())
+    }
+
+    pub fn add_player(&mut self, name: &str, rating: u32) -> Result<(), TournamentError> {
+        let name = name.trim();
+        if !self.played.is_empty() {
+            return Err(TournamentError::NotAllowed(
+                "Players join before the first round".to_string(),
+            ));
+        }
+        if name.is_empty() || self.find(name).is_some() {
+            return Err(TournamentError::NotAllowed(format!(
+                "{} cannot join twice",
+                name
+            )));
+        }
+        self.players.push(Player {
+            name: name.to_string(),
+            rating,
+        });
+        Ok(())
+    }
+
+    fn find(&self, name: &str) -> Option<usize> {
+        self.players
+            .iter()
+            .position(|player| player.name.eq_ignore_ascii_case(name.trim()))
+    }
+
+    pub fn total_rounds(&self) -> usize {
+        match self.kind {
+            Kind::RoundRobin => self.players.len() - 1 + self.players.len() % 2,
+            Kind::Swiss => self.rounds,
+        }
+    }
+
+    /// The players from the highest rating down, which is the order of the
+    /// Berger numbers and of the pairing within a score group.
+    fn seeds(&self) -> Vec<usize> {
+        let mut seeds: Vec<usize> = (0..self.players.len()).collect();
+        seeds.sort_by_key(|player| std::cmp::Reverse(self.players[*player].rating));
+        seeds
+    }
+
+    /// A bye is worth a point in a Swiss event, where it is handed out by
+    /// the pairing. In a round robin it is just a round off.
+    fn bye_points(&self) -> f32 {
+        match self.kind {
+            Kind::RoundRobin => 0.,
+            Kind::Swiss => 1.,
+        }
+    }
+
+    fn games_of(&self, player: usize) -> impl Iterator<Item = &Game> {
+        self.played
+            .iter()
+            .flat_map(|round| round.games.iter())
+            .filter(move |game| game.white == player || game.black == player)
+    }
+
+    pub fn points(&self, player: usize) -> f32 {
+        let byes = self
+            .played
+            .iter()
+            .filter(|round| round.bye == Some(player))
+            .count();
+        let games: f32 = self
+            .games_of(player)
+            .filter_map(|game| game.outcome(player))
+            .map(|(_, points)| points)
+            .sum();
+        games + byes as f32 * self.bye_points()
+    }
+
+    fn have_met(&self, player: usize, opponent: usize) -> bool {
+        self.games_of(player)
+            .any(|game| game.white == opponent || game.black == opponent)
+    }
+
+    fn had_bye(&self, player: usize) -> bool {
+        self.played.iter().any(|round| round.bye == Some(player))
+    }
+
+    /// The colour a player should get next and how much it matters, by the
+    /// Dutch rules: no colour may come up three times in a row or twice more
+    /// often than the other.
+    fn colour_preference(&self, player: usize) -> Option<(Strength, PieceColor)> {
+        let colours: Vec<PieceColor> = self
+            .games_of(player)
+            .filter_map(|game| game.colour_of(player))
+            .collect();
+        let last = colours.last()?;
+        let whites = colours
+            .iter()
+            .filter(|colour| **colour == PieceColor::White)
+            .count() as i32;
+        let difference = 2 * whites - colours.len() as i32;
+        let twice = colours.len() >= 2 && &colours[colours.len() - 2] == last;
+        Some(match difference {
+            ..=-2 => (Strength::Absolute, PieceColor::White),
+            2.. => (Strength::Absolute, PieceColor::Black),
+            _ if twice => (Strength::Absolute, opposite(last)),
+            -1 => (Strength::Strong, PieceColor::White),
+            1 => (Strength::Strong, PieceColor::Black),
+            _ => (Strength::Mild, opposite(last)),
+        })
+    }
+
+    fn colours_clash(&self, player: usize, opponent: usize) -> bool {
+        match (
+            self.colour_preference(player),
+            self.colour_preference(opponent),
+        ) {
+            (Some((Strength::Absolute, a)), Some((Strength::Absolute, b))) => a == b,
+            _ => false,
+        }
+    }
+
+    /// Gives both players the colour they prefer if they can, or else the
+    /// stronger preference, with the higher ranked player winning ties. In
+    /// the first round colours alternate down the boards.
+    fn allocate(&self, higher: usize, lower: usize, board: usize) -> Game {
+        let higher_white = match (
+            self.colour_preference(higher),
+            self.colour_preference(lower),
+        ) {
+            (None, None) => board % 2 == 0,
+            (Some((_, colour)), None) => colour == PieceColor::White,
+            (None, Some((_, colour))) => colour == PieceColor::Black,
+            (Some((_, a)), Some((_, b))) if a != b => a == PieceColor::White,
+            (Some((a, colour)), Some((b, _))) if b > a => colour == PieceColor::Black,
+            (Some((_, colour)), Some(_)) => colour == PieceColor::White,
+        };
+        if higher_white {
+            Game::new(higher, lower)
+        } else {
+            Game::new(lower, higher)
+        }
+    }
+
+    /// Round `index` of the Berger tables: everyone but the last number
+    /// moves on half the table round a circle, and the last number meets
+    /// whoever comes first, changing colour every round.
+    fn berger_round(&self, index: usize) -> Round {
+        let seeds = self.seeds();
+        let numbers = seeds.len() + seeds.len() % 2;
+        let half = numbers / 2;
+        let start = index * half % (numbers - 1);
+        let circle: Vec<usize> = (0..numbers - 1)
+            .map(|place| (start + place) % (numbers - 1))
+            .collect();
+        let last = numbers - 1;
+        let mut pairs = vec![if index % 2 == 1 {
+            (last, circle[0])
+        } else {
+            (circle[0], last)
+        }];
+        for table in 1..half {
+            pairs.push((circle[table], circle[numbers - 1 - table]));
+        }
+        let mut round = Round::default();
+        // The number past the last player stands for the bye.
+        for (white, black) in pairs {
+            match (seeds.get(white), seeds.get(black)) {
+                (Some(white), Some(black)) => round.games.push(Game::new(*white, *black)),
+                (Some(player), None) | (None, Some(player)) => round.bye = Some(*player),
+                (None, None) => {}
+            }
+        }
+        round
+    }
+
+    /// Pairs `remaining`, ranked by score, from the top down. The highest
+    /// player meets the one halfway down their score group, the next the one
+    /// after, and so on, as long as they have not met and their colours
+    /// allow. When that fails the others of the group are tried, and then
+    /// the player floats down to the group below.
+    fn pair_up(&self, remaining: &[usize], points: &[f32]) -> Option<Vec<(usize, usize)>> {
+        let Some((&top, rest)) = remaining.split_first() else {
+            return Some(vec![]);
+        };
+        let group = remaining
+            .iter()
+            .take_while(|player| points[**player] == points[top])
+            .count();
+        let half = (group / 2).max(1);
+        let mut order: Vec<usize> = remaining[half..group].to_vec();
+        order.extend(remaining[1..half].iter().rev());
+        order.extend(&remaining[group..]);
+        let in_group = |player: &usize| remaining[..group].contains(player);
+        // A stable sort keeps the Dutch order within each tier.
+        order.sort_by_key(|player| (!in_group(player), self.colours_clash(top, *player)));
+        for opponent in order {
+            if self.have_met(top, opponent) {
+                continue;
+            }
+            let others: Vec<usize> = rest
+                .iter()
+                .copied()
+                .filter(|player| *player != opponent)
+                .collect();
+            if let Some(mut pairs) = self.pair_up(&others, points) {
+                pairs.insert(0, (top, opponent));
+                return Some(pairs);
+            }
+        }
+        None
+    }
+
+    /// The next Swiss round. With an odd number of players, the lowest
+    /// ranked player who has not had a bye yet gets one.
+    fn dutch_round(&self) -> Result<Round, TournamentError> {
+        let points: Vec<f32> = (0..self.players.len())
+            .map(|player| self.points(player))
+            .collect();
+        let mut ranked = self.seeds();
+        ranked.sort_by(|a, b| points[*b].total_cmp(&points[*a]));
+        let byes: Vec<Option<usize>> = if ranked.len() % 2 == 0 {
+            vec![None]
+        } else {
+            ranked
+                .iter()
+                .rev()
+                .filter(|player| !self.had_bye(**player))
+                .map(|player| Some(*player))
+                .collect()
+        };
+        for bye in byes {
+            let players: Vec<usize> = ranked
+                .iter()
+                .copied()
+                .filter(|player| Some(*player) != bye)
+                .collect();
+            if let Some(pairs) = self.pair_up(&players, &points) {
+                let games = pairs
+                    .into_iter()
+                    .enumerate()
+                    .map(|(board, (higher, lower))| self.allocate(higher, lower, board))
+                    .collect();
+                return Ok(Round { games, bye });
+            }
+        }
+        Err(TournamentError::NotAllowed(
+            "Every pairing would repeat a game".to_string(),
+        ))
+    }
+
+    /// Pairs the next round once the last one is finished.
+    pub fn pair_next_round(&mut self) -> Result<&Round, TournamentError> {
+        if self.players.len() < 2 {
+            return Err(TournamentError::NotAllowed(
+                "A tournament needs two players".to_string(),
+            ));
+        }
+        if let Some(round) = self.played.last().filter(|round| !round.is_finished()) {
+            let unfinished = round.games.len()
+                - round
+                    .games
+                    .iter()
+                    .filter(|game| game.result != GameResult::Ongoing)
+                    .count();
+            return Err(TournamentError::NotAllowed(format!(
+                "Round {} still has {} games to play",
+                self.played.len(),
+                unfinished
+            )));
+        }
+        if self.played.len() >= self.total_rounds() {
+            return Err(TournamentError::NotAllowed(
+                "Every round has been played".to_string(),
+            ));
+        }
+        let round = match self.kind {
+            Kind::RoundRobin => self.berger_round(self.played.len()),
+            Kind::Swiss => self.dutch_round()?,
+        };
+        self.played.push(round);
+        Ok(self.played.last().expect("A round was just paired"))
+    }
+
+    /// Sets the result on a board, both counted from one.
+    pub fn set_result(
+        &mut self,
+        round: usize,
+        board: usize,
+        result: GameResult,
+    ) -> Result<(), TournamentError> {
+        let game = round
+            .checked_sub(1)
+            .and_then(|round| self.played.get_mut(round))
+            .and_then(|round| round.games.get_mut(board.checked_sub(1)?))
+            .ok_or_else(|| {
+                TournamentError::NotAllowed(format!("Round {} has no board {}", round, board))
+            })?;
+        game.result = result;
+        Ok(())
+    }
+
+    /// Enters the result of a game played between two players of the round
+    /// being played, and tells on which board it was.
+    pub fn record(
+        &mut self,
+        white: &str,
+        black: &str,
+        result: GameResult,
+    ) -> Result<usize, TournamentError> {
+        let not_paired = || {
+            TournamentError::NotAllowed(format!(
+                "{} and {} do not play each other this round",
+                white, black
+            ))
+        };
+        let (Some(white), Some(black)) = (self.find(white), self.find(black)) else {
+            return Err(not_paired());
+        };
+        let round = self.played.last_mut().ok_or_else(not_paired)?;
+        let (board, game) = round
+            .games
+            .iter_mut()
+            .enumerate()
+            .find(|(_, game)| game.white == white && game.black == black)
+            .ok_or_else(not_paired)?;
+        game.result = result;
+        Ok(board + 1)
+    }
+
+    /// Everyone by score, then by the tiebreaks of the kind of event, then
+    /// by rating.
+    pub fn standings(&self) -> Vec<Standing> {
+        let points: Vec<f32> = (0..self.players.len())
+            .map(|player| self.points(player))
+            .collect();
+        let mut standings: Vec<Standing> = self
+            .seeds()
+            .into_iter()
+            .map(|player| {
+                let outcomes: Vec<(usize, f32)> = self
+                    .games_of(player)
+                    .filter_map(|game| game.outcome(player))
+                    .collect();
+                Standing {
+                    player,
+                    points: points[player],
+                    buchholz: outcomes.iter().map(|(opponent, _)| points[*opponent]).sum(),
+                    sonneborn_berger: outcomes
+                        .iter()
+                        .map(|(opponent, scored)| scored * points[*opponent])
+                        .sum(),
+                }
+            })
+            .collect();
+        let tiebreaks = |standing: &Standing| match self.kind {
+            Kind::RoundRobin => [standing.sonneborn_berger, standing.buchholz],
+            Kind::Swiss => [standing.buchholz, standing.sonneborn_berger],
+        };
+        // Sorting is stable, so equal players stay in rating order.
+        standings.sort_by(|a, b| {
+            b.points
+                .total_cmp(&a.points)
+                .then_with(|| tiebreaks(b)[0].total_cmp(&tiebreaks(a)[0]))
+                .then_with(|| tiebreaks(b)[1].total_cmp(&tiebreaks(a)[1]))
+        });
+        standings
+    }
+
+    /// The standings with a column per opponent in a round robin, or per
+    /// round in a Swiss event, as rows of cells under a header row.
+    pub fn crosstable(&self) -> Vec<Vec<String>> {
+        let standings = self.standings();
+        let rank_of = |player: usize| {
+            standings
+                .iter()
+                .position(|standing| standing.player == player)
+                .map_or(0, |rank| rank + 1)
+        };
+        let columns = match self.kind {
+            Kind::RoundRobin => standings.len(),
+            Kind::Swiss => self.played.len(),
+        };
+        let mut header: Vec<String> = ["#", "Name", "Rating", "Points", "Buchholz", "SB"]
+            .iter()
+            .map(|title| title.to_string())
+            .collect();
+        header.extend((1..=columns).map(|column| column.to_string()));
+        let mut rows = vec![header];
+        for (rank, standing) in standings.iter().enumerate() {
+            let player = &self.players[standing.player];
+            let mut row = vec![
+                (rank + 1).to_string(),
+                player.name.clone(),
+                player.rating.to_string(),
+                format_points(standing.points),
+                format_points(standing.buchholz),
+                format_points(standing.sonneborn_berger),
+            ];
+            match self.kind {
+                Kind::RoundRobin => {
+                    for opponent in standings.iter() {
+                        let cell = if opponent.player == standing.player {
+                            "x".to_string()
+                        } else {
+                            self.games_of(standing.player)
+                                .filter_map(|game| game.outcome(standing.player))
+                                .filter(|(met, _)| *met == opponent.player)
+                                .map(|(_, scored)| format_points(scored))
+                                .collect::<Vec<String>>()
+                                .join(" ")
+                        };
+                        row.push(cell);
+                    }
+                }
+                Kind::Swiss => {
+                    for round in self.played.iter() {
+                        let game = round.games.iter().find_map(|game| {
+                            let colour = game.colour_of(standing.player)?;
+                            let opponent = match colour {
+                                PieceColor::White => game.black,
+                                PieceColor::Black => game.white,
+                            };
+                            let scored = game
+                                .outcome(standing.player)
+                                .map_or("*".to_string(), |(_, scored)| format_points(scored));
+                            let colour = match colour {
+                                PieceColor::White => "w",
+                                PieceColor::Black => "b",
+                            };
+                            Some(format!("{}{}{}", rank_of(opponent), colour, scored))
+                        });
+                        let cell = match game {
+                            Some(cell) => cell,
+                            None if round.bye == Some(standing.player) => "bye".to_string(),
+                            None => "-".to_string(),
+                        };
+                        row.push(cell);
+                    }
+                }
+            }
+            rows.push(row);
+        }
+        rows
+    }
+
+    /// The crosstable lined up in columns, for printing or a notice board.
+    pub fn crosstable_text(&self) -> String {
+        let rows = self.crosstable();
+        let mut widths = vec![0; rows[0].len()];
+        for row in rows.iter() {
+            for (width, cell) in widths.iter_mut().zip(row.iter()) {
+                *width = (*width).max(cell.chars().count());
+            }
+        }
+        let mut text = format!(
+            "{}, round {} of {}\n\n",
+            self.name,
+            self.played.len(),
+            self.total_rounds()
+        );
+        for row in rows.iter() {
+            let cells: Vec<String> = row
+                .iter()
+                .zip(widths.iter())
+                .map(|(cell, width)| {
+                    let padding = width - cell.chars().count();
+                    format!("{}{}", cell, " ".repeat(padding))
+                })
+                .collect();
+            text.push_str(cells.join("  ").trim_end());
+            text.push('\n');
+        }
+        text
+    }
+
+    /// The crosstable as comma separated values, for spreadsheets.
+    pub fn crosstable_csv(&self) -> String {
+        let mut text = String::new();
+        for row in self.crosstable() {
+            let cells: Vec<String> = row
+                .iter()
+                .map(|cell| {
+                    if cell.contains([',', '"']) {
+                        format!("\"{}\"", cell.replace('"', "\"\""))
+                    } else {
+                        cell.clone()
+                    }
+                })
+                .collect();
+            text.push_str(&cells.join(","));
+            text.push('\n');
+        }
+        text
+    }
+}
+
+impl FromStr for Tournament {
+    type Err = TournamentError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let invalid = |line: &str| TournamentError::Invalid(line.to_string());
+        let mut tournament = Tournament::new("", Kind::Swiss, 0);
+        let player = |tournament: &Tournament, word: Option<&str>, line: &str| {
+            word.and_then(|word| word.parse::<usize>().ok())
+                .and_then(|number| number.checked_sub(1))
+                .filter(|player| *player < tournament.players.len())
+                .ok_or_else(|| invalid(line))
+        };
+        for line in text.lines().map(str::trim).filter(|line| !line.is_empty()) {
+            if line == "round" {
+                tournament.played.push(Round::default());
+                continue;
+            }
+            let (key, value) = line.split_once('=').ok_or_else(|| invalid(line))?;
+            match key {
+                "name" => tournament.name = value.to_string(),
+                "kind" => tournament.kind = value.parse()?,
+                "rounds" => tournament.rounds = value.parse().map_err(|_| invalid(line))?,
+                "player" => {
+                    let (rating, name) = value.split_once(' ').ok_or_else(|| invalid(line))?;
+                    tournament.players.push(Player {
+                        name: name.to_string(),
+                        rating: rating.parse().map_err(|_| invalid(line))?,
+                    });
+                }
+                "game" => {
+                    let mut words = value.split_whitespace();
+                    let white = player(&tournament, words.next(), line)?;
+                    let black = player(&tournament, words.next(), line)?;
+                    let result = words
+                        .next()
+                        .and_then(parse_result)
+                        .ok_or_else(|| invalid(line))?;
+                    let round = tournament.played.last_mut().ok_or_else(|| invalid(line))?;
+                    round.games.push(Game {
+                        white,
+                        black,
+                        result,
+                    });
+                }
+                "bye" => {
+                    let bye = player(&tournament, Some(value), line)?;
+                    let round = tournament.played.last_mut().ok_or_else(|| invalid(line))?;
+                    round.bye = Some(bye);
+                }
+                _ => return Err(invalid(line)),
+            }
+        }
+        Ok(tournament)
+    }
+}
+
+impl Display for Tournament {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "name={}", self.name)?;
+        writeln!(f, "kind={}", self.kind)?;
+        writeln!(f, "rounds={}", self.rounds)?;
+        for player in self.players.iter() {
+            writeln!(f, "player={} {}", player.rating, player.name)?;
+        }
+        for round in self.played.iter() {
+            writeln!(f, "round")?;
+            for game in round.games.iter() {
+                writeln!(
+                    f,
+                    "game={} {} {}",
+                    game.white + 1,
+                    game.black + 1,
+                    game.result
+                )?;
+            }
+            if let Some(bye) = round.bye {
+                writeln!(f, "bye={}", bye + 1)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tournament_tests {
+    use crate::game_result::GameResult;
+    use crate::tournament::{format_points, Kind, Tournament};
+    use std::str::FromStr;
+
+    /// Players named after their rating order, the strongest first.
+    fn tournament(kind: Kind, players: usize, rounds: usize) -> Tournament {
+        let mut tournament = Tournament::new("Club", kind, rounds);
+        for player in 0..players {
+            let name = format!("p{}", player + 1);
+            tournament.add_player(&name, 2000 - player as u32).unwrap();
+        }
+        tournament
+    }
+
+    fn numbers(tournament: &Tournament) -> Vec<(usize, usize)> {
+        tournament
+            .played
+            .last()
+            .unwrap()
+            .games
+            .iter()
+            .map(|game| (game.white + 1, game.black + 1))
+            .collect()
+    }
+
+    /// Lets the higher rated player win every game of the last round.
+    fn favourites_win(tournament: &mut Tournament) {
+        let round = tournament.played.last_mut().unwrap();
+        for game in round.games.iter_mut() {
+            game.result = if game.white < game.black {
+                GameResult::WhiteWins
+            } else {
+                GameResult::BlackWins
+            };
+        }
+    }
+
+    #[test]
+    fn round_robins_follow_the_berger_tables() {
+        let mut tournament = tournament(Kind::RoundRobin, 6, 0);
+        let expected = [
+            vec![(1, 6), (2, 5), (3, 4)],
+            vec![(6, 4), (5, 3), (1, 2)],
+            vec![(2, 6), (3, 1), (4, 5)],
+            vec![(6, 5), (1, 4), (2, 3)],
+            vec![(3, 6), (4, 2), (5, 1)],
+        ];
+        for pairs in expected {
+            tournament.pair_next_round().unwrap();
+            assert_eq!(numbers(&tournament), pairs);
+            favourites_win(&mut tournament);
+        }
+        assert!(tournament.pair_next_round().is_err());
+    }
+
+    #[test]
+    fn odd_round_robins_give_everyone_one_round_off() {
+        let mut tournament = tournament(Kind::RoundRobin, 5, 0);
+        let mut byes = vec![];
+        while tournament.pair_next_round().is_ok() {
+            byes.extend(tournament.played.last().unwrap().bye);
+            favourites_win(&mut tournament);
+        }
+        byes.sort();
+        assert_eq!(byes, vec![0, 1, 2, 3, 4]);
+        for player in 0..5 {
+            let opponents =
+                (0..5).filter(|other| *other != player && tournament.have_met(player, *other));
+            assert_eq!(opponents.count(), 4);
+        }
+    }
+
+    #[test]
+    fn swiss_rounds_pair_equal_scores_without_rematches() {
+        let mut tournament = tournament(Kind::Swiss, 8, 4);
+        tournament.pair_next_round().unwrap();
+        assert_eq!(numbers(&tournament), vec![(1, 5), (6, 2), (3, 7), (8, 4)]);
+        favourites_win(&mut tournament);
+        tournament.pair_next_round().unwrap();
+        // The winners meet the winners, each taking the other colour.
+        assert_eq!(numbers(&tournament), vec![(3, 1), (2, 4), (5, 7), (8, 6)]);
+        for _ in 0..2 {
+            favourites_win(&mut tournament);
+            tournament.pair_next_round().unwrap();
+        }
+        for player in 0..8 {
+            let games = tournament.games_of(player).count();
+            let opponents =
+                (0..8).filter(|other| *other != player && tournament.have_met(player, *other));
+            assert_eq!(opponents.count(), games);
+        }
+    }
+
+    #[test]
+    fn odd_swiss_events_give_the_lowest_a_bye_once() {
+        let mut tournament = tournament(Kind::Swiss, 5, 3);
+        tournament.pair_next_round().unwrap();
+        assert_eq!(tournament.played[0].bye, Some(4));
+        favourites_win(&mut tournament);
+        tournament.pair_next_round().unwrap();
+        assert_eq!(tournament.played[1].bye, Some(3));
+        assert_eq!(tournament.points(4), 1.);
+    }
+
+    #[test]
+    fn results_are_recorded_by_name_and_rounds_wait_for_them() {
+        let mut tournament = tournament(Kind::Swiss, 4, 3);
+        tournament.pair_next_round().unwrap();
+        assert!(tournament.pair_next_round().is_err());
+        assert_eq!(tournament.record("P1", "p3", GameResult::Draw), Ok(1));
+        assert!(tournament
+            .record("p3", "p1", GameResult::WhiteWins)
+            .is_err());
+        tournament.set_result(1, 2, GameResult::BlackWins).unwrap();
+        assert!(tournament.pair_next_round().is_ok());
+        assert!(tournament.add_player("late", 1500).is_err());
+    }
+
+    #[test]
+    fn standings_break_ties_by_buchholz_and_sonneborn_berger() {
+        let mut tournament = tournament(Kind::Swiss, 4, 2);
+        tournament.pair_next_round().unwrap();
+        // p1 beats p3 and p4 draws with p2.
+        tournament
+            .record("p1", "p3", GameResult::WhiteWins)
+            .unwrap();
+        tournament.record("p4", "p2", GameResult::Draw).unwrap();
+        let standings = tournament.standings();
+        let order: Vec<usize> = standings.iter().map(|standing| standing.player).collect();
+        assert_eq!(order, vec![0, 1, 3, 2]);
+        assert_eq!(standings[1].buchholz, 0.5);
+        assert_eq!(standings[1].sonneborn_berger, 0.25);
+        assert_eq!(format_points(2.5), "2½");
+        assert_eq!(format_points(0.5), "½");
+    }
+
+    #[test]
+    fn tournaments_round_trip_and_print_crosstables() {
+        let mut tournament = tournament(Kind::Swiss, 3, 2);
+        tournament.players[0].name = "Anna Smith".to_string();
+        tournament.pair_next_round().unwrap();
+        tournament.set_result(1, 1, GameResult::WhiteWins).unwrap();
+        let text = tournament.to_string();
+        assert_eq!(Tournament::from_str(&text), Ok(tournament.clone()));
+        let table = tournament.crosstable_text();
+        assert!(table.starts_with("Club, round 1 of 2\n\n#  Name"));
+        assert!(table.contains("1  Anna Smith  2000    1"));
+        assert!(table.contains("bye"));
+        let csv = tournament.crosstable_csv();
+        assert!(csv.starts_with("#,Name,Rating,Points,Buchholz,SB,1\n"));
+        assert!(Tournament::from_str("game=1 2 1-0").is_err());
+    }
+}